@@ -171,6 +171,17 @@
           echo "Creating: archive.txz"
           tar -cJf archive.txz test-data/
 
+          echo ""
+          echo "=== Creating TAR.LZ and TAR.LZMA archives ==="
+
+          # TAR.LZ (lzip)
+          echo "Creating: archive.tar.lz"
+          tar -c test-data/ | lzip -c > archive.tar.lz
+
+          # TAR.LZMA (legacy LZMA)
+          echo "Creating: archive.tar.lzma"
+          tar -c test-data/ | xz --format=lzma -c > archive.tar.lzma
+
           echo ""
           echo "=== Creating TAR.ZST archives ==="
 
@@ -193,6 +204,14 @@
           echo "Creating: hello.txt.xz"
           xz -c test-data/hello.txt > hello.txt.xz
 
+          # LZIP single file
+          echo "Creating: hello.txt.lz"
+          lzip -c test-data/hello.txt > hello.txt.lz
+
+          # LZMA single file
+          echo "Creating: hello.txt.lzma"
+          xz --format=lzma -c test-data/hello.txt > hello.txt.lzma
+
           # ZSTD single file
           echo "Creating: hello.txt.zst"
           zstd -q test-data/hello.txt -o hello.txt.zst
@@ -301,6 +320,8 @@
           - \`archive.tar.gz\` / \`archive.tgz\` - TAR with gzip compression
           - \`archive.tar.bz2\` / \`archive.tbz2\` - TAR with bzip2 compression
           - \`archive.tar.xz\` / \`archive.txz\` - TAR with xz compression
+          - \`archive.tar.lz\` - TAR with lzip compression
+          - \`archive.tar.lzma\` - TAR with legacy LZMA compression
           - \`archive.tar.zst\` - TAR with zstd compression
           - \`nested.tar.gz\` - Compressed TAR containing other archives
 
@@ -308,6 +329,8 @@
           - \`hello.txt.gz\` - gzip compressed file
           - \`hello.txt.bz2\` - bzip2 compressed file
           - \`hello.txt.xz\` - xz compressed file
+          - \`hello.txt.lz\` - lzip compressed file
+          - \`hello.txt.lzma\` - legacy LZMA compressed file
           - \`hello.txt.zst\` - zstd compressed file
           - \`hello.txt.lz4\` - lz4 compressed file
 
@@ -0,0 +1,793 @@
+//! Command-line interface to the `archive` crate.
+//!
+//! Every command goes through [`ArchiveExtractor`] with the same limits and
+//! checks as the library, so the output shows exactly what a program using
+//! the crate would see for a given file.
+
+mod write;
+
+use archive::{
+    ArchiveExtractor, ArchiveFormat, EntryLink, ExtractOptions, ExtractedFile, HashAlgorithm,
+    OverwritePolicy, SpecialFile, VerifyReport,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde_json::{Value, json};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// List, extract, test and create archives.
+#[derive(Parser)]
+#[command(name = "archive", version)]
+struct Cli {
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the entries of an archive.
+    List {
+        #[command(flatten)]
+        input: Input,
+
+        #[command(flatten)]
+        limits: Limits,
+
+        #[command(flatten)]
+        filter: Filter,
+
+        /// Compute a digest of every file; can be repeated.
+        #[arg(long = "hash", value_enum)]
+        hashes: Vec<Hash>,
+    },
+
+    /// Extract an archive into a directory.
+    Extract {
+        #[command(flatten)]
+        input: Input,
+
+        #[command(flatten)]
+        limits: Limits,
+
+        #[command(flatten)]
+        filter: Filter,
+
+        /// Directory to extract into; created if missing.
+        #[arg(short = 'o', long, default_value = ".")]
+        output_dir: PathBuf,
+
+        /// What to do when a file already exists.
+        #[arg(long, value_enum, default_value_t = Overwrite::Error)]
+        overwrite: Overwrite,
+
+        /// Restore the permission bits stored in the archive.
+        #[arg(long)]
+        permissions: bool,
+
+        /// Restore the modification times stored in the archive.
+        #[arg(long)]
+        mtime: bool,
+    },
+
+    /// Check the integrity of every entry without writing anything.
+    ///
+    /// Exits with status 1 if any check fails.
+    Test {
+        #[command(flatten)]
+        input: Input,
+
+        #[command(flatten)]
+        limits: Limits,
+    },
+
+    /// Detect the format of files from their contents.
+    ///
+    /// Exits with status 1 if any format is unknown.
+    Detect {
+        /// Files to inspect, or `-` for standard input.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Create an archive from files and directories.
+    Create {
+        /// Archive to write.
+        output: PathBuf,
+
+        /// Files and directories to add. Directories are added recursively
+        /// under their own name.
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Format of the new archive; derived from the output extension by
+        /// default. XZ, LZMA and lzip output, with or without TAR, is valid
+        /// but not compressed.
+        #[arg(short, long, value_parser = parse_format)]
+        format: Option<ArchiveFormat>,
+
+        #[command(flatten)]
+        filter: Filter,
+    },
+
+    /// Convert an archive to another format.
+    Convert {
+        #[command(flatten)]
+        input: Input,
+
+        /// Archive to write.
+        output: PathBuf,
+
+        /// Format of the new archive; derived from the output extension by
+        /// default. XZ, LZMA and lzip output, with or without TAR, is valid
+        /// but not compressed.
+        #[arg(long, value_parser = parse_format)]
+        to: Option<ArchiveFormat>,
+
+        #[command(flatten)]
+        limits: Limits,
+
+        #[command(flatten)]
+        filter: Filter,
+    },
+}
+
+#[derive(Args)]
+struct Input {
+    /// Archive to read, or `-` for standard input.
+    file: PathBuf,
+
+    /// Format of the archive; detected from its contents by default.
+    #[arg(short, long, value_parser = parse_format)]
+    format: Option<ArchiveFormat>,
+}
+
+impl Input {
+    fn read(&self) -> Result<(Vec<u8>, ArchiveFormat), Box<dyn Error>> {
+        let data = read_input(&self.file)?;
+        let format = match self.format {
+            Some(format) => format,
+            None => ArchiveFormat::detect(&data)?,
+        };
+        Ok((data, format))
+    }
+}
+
+#[derive(Args)]
+struct Limits {
+    /// Maximum size of a single entry, e.g. `100M`.
+    #[arg(long, value_parser = parse_size)]
+    max_file_size: Option<usize>,
+
+    /// Maximum total size of all entries, e.g. `1G`.
+    #[arg(long, value_parser = parse_size)]
+    max_total_size: Option<usize>,
+}
+
+impl Limits {
+    fn extractor(&self) -> ArchiveExtractor {
+        let mut extractor = ArchiveExtractor::new();
+        if let Some(size) = self.max_file_size {
+            extractor = extractor.with_max_file_size(size);
+        }
+        if let Some(size) = self.max_total_size {
+            extractor = extractor.with_max_total_size(size);
+        }
+        extractor
+    }
+}
+
+#[derive(Args)]
+struct Filter {
+    /// Only include entries matching this glob; can be repeated.
+    #[arg(long = "include", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Leave out entries matching this glob; can be repeated.
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+}
+
+impl Filter {
+    fn build(&self) -> Result<PathFilter, globset::Error> {
+        let set = |patterns: &[String]| {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(Glob::new(pattern)?);
+            }
+            builder.build()
+        };
+        Ok(PathFilter {
+            include: (!self.include.is_empty())
+                .then(|| set(&self.include))
+                .transpose()?,
+            exclude: set(&self.exclude)?,
+        })
+    }
+}
+
+/// Compiled include and exclude globs.
+struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    fn matches(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.include.as_ref().is_none_or(|set| set.is_match(path)) && !self.exclude.is_match(path)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Hash {
+    Sha256,
+    Sha1,
+    Md5,
+    Blake3,
+    Sha512,
+}
+
+impl From<Hash> for HashAlgorithm {
+    fn from(hash: Hash) -> Self {
+        match hash {
+            Hash::Sha256 => HashAlgorithm::Sha256,
+            Hash::Sha1 => HashAlgorithm::Sha1,
+            Hash::Md5 => HashAlgorithm::Md5,
+            Hash::Blake3 => HashAlgorithm::Blake3,
+            Hash::Sha512 => HashAlgorithm::Sha512,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Overwrite {
+    Error,
+    Skip,
+    Replace,
+}
+
+impl From<Overwrite> for OverwritePolicy {
+    fn from(overwrite: Overwrite) -> Self {
+        match overwrite {
+            Overwrite::Error => OverwritePolicy::Error,
+            Overwrite::Skip => OverwritePolicy::Skip,
+            Overwrite::Replace => OverwritePolicy::Replace,
+        }
+    }
+}
+
+/// File extensions and the formats they imply, compound extensions first.
+const EXTENSIONS: &[(&str, ArchiveFormat)] = &[
+    ("tar.gz", ArchiveFormat::TarGz),
+    ("tgz", ArchiveFormat::TarGz),
+    ("tar.bz2", ArchiveFormat::TarBz2),
+    ("tbz2", ArchiveFormat::TarBz2),
+    ("tar.xz", ArchiveFormat::TarXz),
+    ("txz", ArchiveFormat::TarXz),
+    ("tar.lzma", ArchiveFormat::TarLzma),
+    ("tlz", ArchiveFormat::TarLzma),
+    ("tar.lz", ArchiveFormat::TarLz),
+    ("tar.z", ArchiveFormat::TarZ),
+    ("taz", ArchiveFormat::TarZ),
+    ("tar.br", ArchiveFormat::TarBr),
+    ("tar.zst", ArchiveFormat::TarZst),
+    ("tar.lz4", ArchiveFormat::TarLz4),
+    ("zip", ArchiveFormat::Zip),
+    ("tar", ArchiveFormat::Tar),
+    ("7z", ArchiveFormat::SevenZ),
+    ("rar", ArchiveFormat::Rar),
+    ("cpio", ArchiveFormat::Cpio),
+    ("rpm", ArchiveFormat::Rpm),
+    ("iso", ArchiveFormat::Iso),
+    ("cab", ArchiveFormat::Cab),
+    ("xar", ArchiveFormat::Xar),
+    ("pkg", ArchiveFormat::Pkg),
+    ("squashfs", ArchiveFormat::SquashFs),
+    ("sqfs", ArchiveFormat::SquashFs),
+    ("ar", ArchiveFormat::Ar),
+    ("a", ArchiveFormat::Ar),
+    ("deb", ArchiveFormat::Deb),
+    ("gz", ArchiveFormat::Gz),
+    ("bz2", ArchiveFormat::Bz2),
+    ("xz", ArchiveFormat::Xz),
+    ("lzma", ArchiveFormat::Lzma),
+    ("lz", ArchiveFormat::Lz),
+    ("lz4", ArchiveFormat::Lz4),
+    ("zst", ArchiveFormat::Zst),
+    ("z", ArchiveFormat::Compress),
+    ("br", ArchiveFormat::Br),
+    ("sz", ArchiveFormat::Snappy),
+];
+
+/// Parses a format from its name (e.g. `TAR.GZ`, `GZIP`) or extension
+/// (e.g. `tgz`), ignoring case.
+fn parse_format(value: &str) -> Result<ArchiveFormat, String> {
+    value
+        .parse()
+        .ok()
+        .or_else(|| {
+            EXTENSIONS
+                .iter()
+                .find(|(extension, _)| extension.eq_ignore_ascii_case(value))
+                .map(|&(_, format)| format)
+        })
+        .ok_or_else(|| format!("unknown archive format '{}'", value))
+}
+
+fn format_from_path(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(extension, _)| {
+            name.strip_suffix(extension)
+                .is_some_and(|stem| stem.ends_with('.'))
+        })
+        .map(|&(_, format)| format)
+}
+
+/// Parses a byte count with an optional `K`, `M` or `G` suffix (powers of
+/// 1024), e.g. `512K` or `1GiB`.
+fn parse_size(value: &str) -> Result<usize, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let number = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, multiplier) = match number.char_indices().last() {
+        Some((i, 'K')) => (&number[..i], 1 << 10),
+        Some((i, 'M')) => (&number[..i], 1 << 20),
+        Some((i, 'G')) => (&number[..i], 1 << 30),
+        _ => (number, 1),
+    };
+    digits
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size '{}'", value))
+}
+
+fn read_input(path: &Path) -> io::Result<Vec<u8>> {
+    if path == Path::new("-") {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        fs::read(path)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command, cli.json) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("archive: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command, json: bool) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::List {
+            input,
+            limits,
+            filter,
+            hashes,
+        } => {
+            let (data, format) = input.read()?;
+            let filter = filter.build()?;
+            let files = limits
+                .extractor()
+                .with_hashes(hashes.into_iter().map(HashAlgorithm::from))
+                .extract(&data, format)?;
+            let files: Vec<_> = files
+                .into_iter()
+                .filter(|file| filter.matches(&file.path))
+                .collect();
+
+            if json {
+                print_json(&json!({
+                    "format": format.name(),
+                    "entries": files.iter().map(entry_json).collect::<Vec<_>>(),
+                }))?;
+            } else {
+                let mut out = io::stdout().lock();
+                for file in &files {
+                    writeln!(out, "{}", entry_line(file))?;
+                }
+            }
+        }
+
+        Command::Extract {
+            input,
+            limits,
+            filter,
+            output_dir,
+            overwrite,
+            permissions,
+            mtime,
+        } => {
+            let (data, format) = input.read()?;
+            let filter = filter.build()?;
+            let options = ExtractOptions::new()
+                .with_overwrite(overwrite.into())
+                .with_permissions(permissions)
+                .with_mtime(mtime)
+                .with_filter(move |file| filter.matches(&file.path));
+            let written =
+                limits
+                    .extractor()
+                    .extract_to_dir(&data, format, &output_dir, &options)?;
+
+            if json {
+                print_json(&json!({
+                    "format": format.name(),
+                    "output_dir": output_dir,
+                    "written": written,
+                }))?;
+            } else {
+                println!(
+                    "Extracted {} entries from {} ({}) to {}",
+                    written.len(),
+                    input.file.display(),
+                    format.name(),
+                    output_dir.display()
+                );
+            }
+        }
+
+        Command::Test { input, limits } => {
+            let (data, format) = input.read()?;
+            let report = limits.extractor().verify(&data, format)?;
+
+            if json {
+                print_json(&report_json(&report))?;
+            } else {
+                print_report(&report)?;
+            }
+            if !report.is_ok() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+
+        Command::Detect { files } => {
+            let mut results = Vec::new();
+            for path in &files {
+                let format = ArchiveFormat::detect(&read_input(path)?).ok();
+                results.push((path, format));
+            }
+
+            if json {
+                let results: Vec<_> = results
+                    .iter()
+                    .map(|(path, format)| {
+                        json!({
+                            "path": path,
+                            "format": format.map(|format| format.name()),
+                        })
+                    })
+                    .collect();
+                print_json(&Value::from(results))?;
+            } else {
+                for (path, format) in &results {
+                    let name = format.map_or("unknown", |format| format.name());
+                    println!("{}: {}", path.display(), name);
+                }
+            }
+            if results.iter().any(|(_, format)| format.is_none()) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+
+        Command::Create {
+            output,
+            inputs,
+            format,
+            filter,
+        } => {
+            let format = output_format(format, &output)?;
+            let filter = filter.build()?;
+            let mut entries = Vec::new();
+            for input in &inputs {
+                collect_entries(input, &mut entries)?;
+            }
+            entries.retain(|file| filter.matches(&file.path));
+
+            fs::write(&output, write::write_archive(format, &entries)?)?;
+            warn_if_uncompressed(format);
+
+            if json {
+                print_json(&json!({
+                    "format": format.name(),
+                    "output": output,
+                    "entries": entries.len(),
+                }))?;
+            } else {
+                println!(
+                    "Created {} ({}) with {} entries",
+                    output.display(),
+                    format.name(),
+                    entries.len()
+                );
+            }
+        }
+
+        Command::Convert {
+            input,
+            output,
+            to,
+            limits,
+            filter,
+        } => {
+            let (data, from) = input.read()?;
+            let to = output_format(to, &output)?;
+            let filter = filter.build()?;
+            let mut entries = limits.extractor().extract(&data, from)?;
+            entries.retain(|file| filter.matches(&file.path));
+
+            fs::write(&output, write::write_archive(to, &entries)?)?;
+            warn_if_uncompressed(to);
+
+            if json {
+                print_json(&json!({
+                    "from": from.name(),
+                    "to": to.name(),
+                    "output": output,
+                    "entries": entries.len(),
+                }))?;
+            } else {
+                println!(
+                    "Converted {} ({}) to {} ({}) with {} entries",
+                    input.file.display(),
+                    from.name(),
+                    output.display(),
+                    to.name(),
+                    entries.len()
+                );
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn output_format(format: Option<ArchiveFormat>, output: &Path) -> Result<ArchiveFormat, String> {
+    format.or_else(|| format_from_path(output)).ok_or_else(|| {
+        format!(
+            "can't tell the format of {} from its extension; pass it explicitly",
+            output.display()
+        )
+    })
+}
+
+/// Adds `path` and, for directories, everything beneath it under the name
+/// of `path` itself.
+fn collect_entries(path: &Path, entries: &mut Vec<ExtractedFile>) -> io::Result<()> {
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        // `.` and `/` contribute their contents only
+        None => String::new(),
+    };
+    collect_entry(path, name, entries)
+}
+
+fn collect_entry(path: &Path, name: String, entries: &mut Vec<ExtractedFile>) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    let mut file = ExtractedFile {
+        path: name,
+        data: Vec::new(),
+        digests: Vec::new(),
+        is_directory: metadata.is_dir(),
+        metadata: Default::default(),
+    };
+    file.metadata.mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.metadata.mode = Some(metadata.permissions().mode() & 0o7777);
+    }
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        file.metadata.link = Some(EntryLink::Symbolic(target.to_string_lossy().into_owned()));
+    } else if metadata.is_file() {
+        file.data = fs::read(path)?;
+    }
+
+    let is_directory = file.is_directory;
+    let prefix = file.path.clone();
+    if !prefix.is_empty() {
+        entries.push(file);
+    }
+
+    if is_directory {
+        let mut children: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            let child_name = child.file_name().to_string_lossy().into_owned();
+            let name = if prefix.is_empty() {
+                child_name
+            } else {
+                format!("{}/{}", prefix, child_name)
+            };
+            collect_entry(&child.path(), name, entries)?;
+        }
+    }
+    Ok(())
+}
+
+fn print_json(value: &Value) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, value)?;
+    writeln!(out)
+}
+
+fn entry_json(file: &ExtractedFile) -> Value {
+    let meta = &file.metadata;
+    let (link_type, link_target) = match &meta.link {
+        Some(EntryLink::Symbolic(target)) => (Some("symbolic"), Some(target)),
+        Some(EntryLink::Hard(target)) => (Some("hard"), Some(target)),
+        None => (None, None),
+    };
+    let (special_type, device) = match meta.special {
+        Some(SpecialFile::BlockDevice { major, minor }) => {
+            (Some("block_device"), Some([major, minor]))
+        }
+        Some(SpecialFile::CharacterDevice { major, minor }) => {
+            (Some("character_device"), Some([major, minor]))
+        }
+        Some(SpecialFile::Fifo) => (Some("fifo"), None),
+        Some(SpecialFile::Socket) => (Some("socket"), None),
+        None => (None, None),
+    };
+    let digests: serde_json::Map<_, _> = file
+        .digests
+        .iter()
+        .map(|digest| (digest.algorithm.name().to_string(), digest.to_hex().into()))
+        .collect();
+
+    json!({
+        "path": file.path,
+        "is_directory": file.is_directory,
+        "size": file.data.len(),
+        "compressed_size": meta.compressed_size,
+        "crc32": meta.crc32,
+        "compression_method": meta.compression_method,
+        "encrypted": meta.encrypted,
+        "mode": meta.mode,
+        "mtime": meta.mtime,
+        "link_type": link_type,
+        "link_target": link_target,
+        "special_type": special_type,
+        "device": device,
+        "digests": digests,
+    })
+}
+
+/// Formats an entry like `ls -l`: type and mode, size, modification time,
+/// digests and path.
+fn entry_line(file: &ExtractedFile) -> String {
+    let meta = &file.metadata;
+    let kind = match (&meta.link, file.is_directory, meta.special) {
+        (_, true, _) => 'd',
+        (Some(EntryLink::Symbolic(_)), _, _) => 'l',
+        (Some(EntryLink::Hard(_)), _, _) => 'h',
+        (None, false, Some(SpecialFile::BlockDevice { .. })) => 'b',
+        (None, false, Some(SpecialFile::CharacterDevice { .. })) => 'c',
+        (None, false, Some(SpecialFile::Fifo)) => 'p',
+        (None, false, Some(SpecialFile::Socket)) => 's',
+        (None, false, None) => '-',
+    };
+    let mode = meta.mode.map_or_else(
+        || "---------".to_string(),
+        |mode| {
+            (0..9)
+                .map(|bit| {
+                    if mode & (0o400 >> bit) == 0 {
+                        '-'
+                    } else {
+                        ['r', 'w', 'x'][bit % 3]
+                    }
+                })
+                .collect()
+        },
+    );
+    let mtime = meta.mtime.map_or_else(|| "-".repeat(16), format_timestamp);
+
+    let mut line = format!("{}{} {:>12} {} ", kind, mode, file.data.len(), mtime);
+    for digest in &file.digests {
+        line.push_str(&digest.to_hex());
+        line.push(' ');
+    }
+    line.push_str(&file.path);
+    match &meta.link {
+        Some(EntryLink::Symbolic(target)) => line.push_str(&format!(" -> {}", target)),
+        Some(EntryLink::Hard(target)) => line.push_str(&format!(" => {}", target)),
+        None => {}
+    }
+    line
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM` in UTC.
+/// Points out on stderr that `format` was written without actual compression.
+fn warn_if_uncompressed(format: ArchiveFormat) {
+    if write::is_stored_uncompressed(format) {
+        eprintln!(
+            "archive: warning: {} output is not compressed; the available encoder only \
+             stores literals (use gz, bz2 or zst to reduce the size)",
+            format.name()
+        );
+    }
+}
+
+fn format_timestamp(mtime: u64) -> String {
+    let (year, month, day, hour, minute, _) = write::civil_time(mtime);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+fn report_json(report: &VerifyReport) -> Value {
+    json!({
+        "format": report.format.name(),
+        "ok": report.is_ok(),
+        "archive_error": report.archive_error,
+        "entries": report
+            .entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "path": entry.path,
+                    "is_directory": entry.is_directory,
+                    "size": entry.size,
+                    "error": entry.error,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn print_report(report: &VerifyReport) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    for entry in report.failures() {
+        writeln!(
+            out,
+            "FAILED {}: {}",
+            entry.path,
+            entry.error.as_deref().unwrap_or_default()
+        )?;
+    }
+    if let Some(error) = &report.archive_error {
+        writeln!(out, "FAILED archive: {}", error)?;
+    }
+
+    let failures = report.failures().count();
+    if report.is_ok() {
+        writeln!(
+            out,
+            "OK: {} entries verified ({})",
+            report.entries.len(),
+            report.format.name()
+        )
+    } else {
+        writeln!(
+            out,
+            "FAILED: {} of {} entries failed ({})",
+            failures,
+            report.entries.len(),
+            report.format.name()
+        )
+    }
+}
@@ -0,0 +1,343 @@
+//! Writing entries into a new archive for the `create` and `convert` commands.
+
+use archive::{
+    ArBuilder, ArVariant, ArchiveError, ArchiveFormat, EntryLink, ExtractedFile, Result,
+    SevenZBuilder,
+};
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+
+/// Writes `entries` as an archive of the given format.
+///
+/// Formats without hard links get a copy of the linked file instead. Formats
+/// that can't store an entry at all, such as symbolic links in 7-Zip or
+/// directories in single-file formats, fail with
+/// [`ArchiveError::UnsupportedFormat`].
+pub fn write_archive(format: ArchiveFormat, entries: &[ExtractedFile]) -> Result<Vec<u8>> {
+    match format {
+        ArchiveFormat::Zip => write_zip(entries),
+        ArchiveFormat::Tar => write_tar(entries),
+        ArchiveFormat::TarGz => compress(ArchiveFormat::Gz, &write_tar(entries)?, None),
+        ArchiveFormat::TarBz2 => compress(ArchiveFormat::Bz2, &write_tar(entries)?, None),
+        ArchiveFormat::TarXz => compress(ArchiveFormat::Xz, &write_tar(entries)?, None),
+        ArchiveFormat::TarZst => compress(ArchiveFormat::Zst, &write_tar(entries)?, None),
+        ArchiveFormat::TarLz4 => compress(ArchiveFormat::Lz4, &write_tar(entries)?, None),
+        ArchiveFormat::TarLz => compress(ArchiveFormat::Lz, &write_tar(entries)?, None),
+        ArchiveFormat::TarLzma => compress(ArchiveFormat::Lzma, &write_tar(entries)?, None),
+        ArchiveFormat::TarBr => compress(ArchiveFormat::Br, &write_tar(entries)?, None),
+        ArchiveFormat::SevenZ => write_7z(entries),
+        ArchiveFormat::Ar => write_ar(entries),
+        ArchiveFormat::Deb => Err(ArchiveError::UnsupportedFormat(
+            "DEB packages need control metadata and can't be created from plain files".to_string(),
+        )),
+        ArchiveFormat::Rar
+        | ArchiveFormat::Cpio
+        | ArchiveFormat::Rpm
+        | ArchiveFormat::Iso
+        | ArchiveFormat::Cab
+        | ArchiveFormat::Xar
+        | ArchiveFormat::Pkg
+        | ArchiveFormat::SquashFs
+        | ArchiveFormat::Compress
+        | ArchiveFormat::TarZ => Err(ArchiveError::UnsupportedFormat(format!(
+            "{} archives can only be read",
+            format.name()
+        ))),
+        ArchiveFormat::Gz
+        | ArchiveFormat::Bz2
+        | ArchiveFormat::Xz
+        | ArchiveFormat::Lz
+        | ArchiveFormat::Lzma
+        | ArchiveFormat::Lz4
+        | ArchiveFormat::Zst
+        | ArchiveFormat::Br
+        | ArchiveFormat::Snappy => match entries {
+            [file] if !file.is_directory && file.metadata.link.is_none() => {
+                compress(format, &file.data, Some(file))
+            }
+            _ => Err(ArchiveError::UnsupportedFormat(format!(
+                "{} holds exactly one regular file, got {} entries",
+                format.name(),
+                entries.len()
+            ))),
+        },
+    }
+}
+
+fn write_zip(entries: &[ExtractedFile]) -> Result<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let mut contents = LinkResolver::default();
+
+    for file in entries {
+        let mut options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        if let Some(mode) = file.metadata.mode {
+            options = options.unix_permissions(mode);
+        }
+        if let Some(time) = file.metadata.mtime.and_then(zip_time) {
+            options = options.last_modified_time(time);
+        }
+
+        if file.is_directory {
+            writer.add_directory(file.path.as_str(), options)?;
+            continue;
+        }
+        match &file.metadata.link {
+            Some(EntryLink::Symbolic(target)) => {
+                writer.add_symlink(file.path.as_str(), target.as_str(), options)?;
+            }
+            _ => {
+                writer.start_file(file.path.as_str(), options)?;
+                writer.write_all(contents.resolve(file)?)?;
+            }
+        }
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+fn write_tar(entries: &[ExtractedFile]) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for file in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(file.metadata.mtime.unwrap_or(0));
+        header.set_size(0);
+
+        let path = file.path.trim_start_matches('/');
+        match &file.metadata.link {
+            _ if file.is_directory => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(file.metadata.mode.unwrap_or(0o755));
+                builder.append_data(&mut header, path, std::io::empty())?;
+            }
+            Some(EntryLink::Symbolic(target)) => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(file.metadata.mode.unwrap_or(0o777));
+                builder.append_link(&mut header, path, target)?;
+            }
+            Some(EntryLink::Hard(target)) => {
+                header.set_entry_type(tar::EntryType::Link);
+                header.set_mode(file.metadata.mode.unwrap_or(0o644));
+                builder.append_link(&mut header, path, target)?;
+            }
+            None => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(file.metadata.mode.unwrap_or(0o644));
+                header.set_size(file.data.len() as u64);
+                builder.append_data(&mut header, path, file.data.as_slice())?;
+            }
+        }
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+fn write_7z(entries: &[ExtractedFile]) -> Result<Vec<u8>> {
+    let mut builder = SevenZBuilder::new();
+    let mut contents = LinkResolver::default();
+
+    for file in entries {
+        if file.is_directory {
+            builder.add_directory(&file.path)?;
+        } else if let Some(EntryLink::Symbolic(_)) = file.metadata.link {
+            return Err(unsupported_entry(
+                ArchiveFormat::SevenZ,
+                "symbolic links",
+                file,
+            ));
+        } else {
+            builder.add_file(&file.path, contents.resolve(file)?.to_vec())?;
+        }
+    }
+
+    builder.finish()
+}
+
+fn write_ar(entries: &[ExtractedFile]) -> Result<Vec<u8>> {
+    let mut builder = ArBuilder::new(ArVariant::Gnu);
+    let mut contents = LinkResolver::default();
+
+    for file in entries {
+        if file.is_directory {
+            return Err(unsupported_entry(ArchiveFormat::Ar, "directories", file));
+        } else if let Some(EntryLink::Symbolic(_)) = file.metadata.link {
+            return Err(unsupported_entry(ArchiveFormat::Ar, "symbolic links", file));
+        }
+        let data = contents.resolve(file)?.to_vec();
+        builder.add_file_with_mode(
+            file.path.as_str(),
+            data,
+            file.metadata.mode.unwrap_or(0o644),
+        )?;
+    }
+
+    builder.finish()
+}
+
+/// Whether `format` is written with the `lzma-rs` encoder, which stores every
+/// byte as a literal without matching repeated input. The output is valid XZ,
+/// `.lzma` or lzip data, but slightly larger than the input.
+pub fn is_stored_uncompressed(format: ArchiveFormat) -> bool {
+    matches!(
+        format,
+        ArchiveFormat::Xz
+            | ArchiveFormat::Lzma
+            | ArchiveFormat::Lz
+            | ArchiveFormat::TarXz
+            | ArchiveFormat::TarLzma
+            | ArchiveFormat::TarLz
+    )
+}
+
+/// Compresses `data` with a single-file format. `file` provides the name and
+/// modification time stored in gzip headers.
+fn compress(format: ArchiveFormat, data: &[u8], file: Option<&ExtractedFile>) -> Result<Vec<u8>> {
+    match format {
+        ArchiveFormat::Gz => {
+            let mut builder = flate2::GzBuilder::new();
+            if let Some(file) = file {
+                let name = file.path.rsplit('/').next().unwrap_or_default();
+                if !name.is_empty() && name != "data" {
+                    builder = builder.filename(name);
+                }
+                if let Some(mtime) = file.metadata.mtime {
+                    builder = builder.mtime(u32::try_from(mtime).unwrap_or(0));
+                }
+            }
+            let mut encoder = builder.write(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        ArchiveFormat::Bz2 => {
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(data)?;
+            Ok(encoder.finish()?)
+        }
+        ArchiveFormat::Xz => {
+            let mut output = Vec::new();
+            lzma_rs::xz_compress(&mut Cursor::new(data), &mut output)?;
+            Ok(output)
+        }
+        ArchiveFormat::Lzma => {
+            let mut output = Vec::new();
+            lzma_rs::lzma_compress(&mut Cursor::new(data), &mut output)?;
+            Ok(output)
+        }
+        ArchiveFormat::Lz => write_lzip(data),
+        ArchiveFormat::Lz4 => {
+            let mut encoder = lz4::EncoderBuilder::new().build(Vec::new())?;
+            encoder.write_all(data)?;
+            let (output, result) = encoder.finish();
+            result?;
+            Ok(output)
+        }
+        ArchiveFormat::Zst => Ok(zstd::bulk::compress(data, 0)?),
+        ArchiveFormat::Br => {
+            let mut output = Vec::new();
+            let params = brotli::enc::BrotliEncoderParams::default();
+            brotli::BrotliCompress(&mut Cursor::new(data), &mut output, &params)?;
+            Ok(output)
+        }
+        ArchiveFormat::Snappy => {
+            let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+            encoder.write_all(data)?;
+            encoder.into_inner().map_err(|e| e.into_error().into())
+        }
+        _ => unreachable!("{} is not a single-file format", format.name()),
+    }
+}
+
+/// Writes `data` as a single lzip member.
+///
+/// The LZMA stream `lzma-rs` writes after its 13-byte `.lzma` header uses the
+/// properties lzip requires and ends with an end marker, so only the header
+/// and trailer need replacing.
+fn write_lzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut lzma = Vec::new();
+    lzma_rs::lzma_compress(&mut Cursor::new(data), &mut lzma)?;
+    let dictionary_size = u32::from_le_bytes([lzma[1], lzma[2], lzma[3], lzma[4]]);
+    let stream = &lzma[13..];
+
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    let member_size = (6 + stream.len() + 20) as u64;
+
+    let mut output = Vec::with_capacity(member_size as usize);
+    output.extend_from_slice(b"LZIP\x01");
+    // A power of two, which is all lzma-rs uses
+    output.push(dictionary_size.trailing_zeros() as u8);
+    output.extend_from_slice(stream);
+    output.extend_from_slice(&crc.sum().to_le_bytes());
+    output.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    output.extend_from_slice(&member_size.to_le_bytes());
+    Ok(output)
+}
+
+/// Tracks file contents by path so that hard links can be written as copies.
+#[derive(Default)]
+struct LinkResolver<'a> {
+    contents: HashMap<&'a str, &'a [u8]>,
+}
+
+impl<'a> LinkResolver<'a> {
+    fn resolve(&mut self, file: &'a ExtractedFile) -> Result<&'a [u8]> {
+        let data = match &file.metadata.link {
+            Some(EntryLink::Hard(target)) => *self
+                .contents
+                .get(target.as_str())
+                .ok_or_else(|| ArchiveError::InvalidPath(format!("{} => {}", file.path, target)))?,
+            _ => file.data.as_slice(),
+        };
+        self.contents.insert(&file.path, data);
+        Ok(data)
+    }
+}
+
+fn unsupported_entry(format: ArchiveFormat, kind: &str, file: &ExtractedFile) -> ArchiveError {
+    ArchiveError::UnsupportedFormat(format!(
+        "{} archives can't hold {}: {}",
+        format.name(),
+        kind,
+        file.path
+    ))
+}
+
+/// Converts seconds since the Unix epoch to a ZIP timestamp, if it falls
+/// within the 1980-2107 range that ZIP can store.
+fn zip_time(mtime: u64) -> Option<zip::DateTime> {
+    let (year, month, day, hour, minute, second) = civil_time(mtime);
+    zip::DateTime::from_date_and_time(u16::try_from(year).ok()?, month, day, hour, minute, second)
+        .ok()
+}
+
+/// Splits seconds since the Unix epoch into a proleptic Gregorian UTC date
+/// and time: year, month, day, hour, minute and second.
+pub fn civil_time(mtime: u64) -> (i64, u8, u8, u8, u8, u8) {
+    // Count from 0000-03-01 in 400-year eras, so that the leap day comes last
+    let days = (mtime / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    let seconds = mtime % 86400;
+    (
+        year,
+        month as u8,
+        day as u8,
+        (seconds / 3600) as u8,
+        (seconds / 60 % 60) as u8,
+        (seconds % 60) as u8,
+    )
+}
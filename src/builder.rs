@@ -0,0 +1,964 @@
+//! Archive creation.
+//!
+//! This module provides builders that assemble archives in-memory. Each builder
+//! collects entries and produces the finished archive as a `Vec<u8>` when
+//! [`finish`](ArBuilder::finish) is called, so no disk I/O is required.
+
+use crate::error::{ArchiveError, Result};
+use std::io::{Cursor, Write};
+
+/// Default permission bits for regular files added to a builder.
+const DEFAULT_FILE_MODE: u32 = 0o644;
+
+/// Default permission bits for directories added to a builder.
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// Variants of the Unix ar archive format that [`ArBuilder`] can produce.
+///
+/// The variants only differ in how identifiers that don't fit into the
+/// 16-byte header field are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArVariant {
+    /// GNU ar, as produced by binutils on Linux and used on Windows.
+    ///
+    /// Long identifiers are collected into a `//` name table at the start of
+    /// the archive and referenced from the member headers as `/<offset>`.
+    #[default]
+    Gnu,
+
+    /// BSD ar, as produced on macOS and the BSDs.
+    ///
+    /// Long identifiers (and identifiers containing spaces) are stored as
+    /// `#1/<length>` and prepended to the member data.
+    Bsd,
+}
+
+#[derive(Debug, Clone)]
+struct ArMember {
+    name: String,
+    data: Vec<u8>,
+    mode: u32,
+}
+
+/// Builder for Unix ar archives (`.ar`, `.a`).
+///
+/// Members are written in the order they are added. All members share the same
+/// modification time (0 by default, for reproducible output) and are owned by
+/// uid/gid 0.
+///
+/// # Examples
+///
+/// ```
+/// use archive::{ArBuilder, ArVariant, ArchiveExtractor, ArchiveFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut builder = ArBuilder::new(ArVariant::Gnu);
+/// builder.add_file("hello.txt", b"Hello, World!\n".to_vec())?;
+/// builder.add_file("a-rather-long-member-name.o", vec![0u8; 16])?;
+/// let data = builder.finish()?;
+///
+/// let files = ArchiveExtractor::new().extract(&data, ArchiveFormat::Ar)?;
+/// assert_eq!(files.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ArBuilder {
+    variant: ArVariant,
+    mtime: u64,
+    members: Vec<ArMember>,
+}
+
+impl ArBuilder {
+    /// Creates a new, empty ar builder producing the given variant.
+    pub fn new(variant: ArVariant) -> Self {
+        Self {
+            variant,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the modification time (seconds since the Unix epoch) stored for
+    /// every member.
+    ///
+    /// This method uses the builder pattern, allowing you to chain configuration calls.
+    pub fn with_mtime(mut self, mtime: u64) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    /// Adds a regular file with mode `0644`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] if `name` is empty or contains a
+    /// `/` or NUL byte, none of which can be represented as an ar identifier.
+    pub fn add_file(&mut self, name: impl Into<String>, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.add_file_with_mode(name, data, DEFAULT_FILE_MODE)
+    }
+
+    /// Adds a regular file with the given permission bits.
+    ///
+    /// Only the permission bits of `mode` are used; the file type bits are
+    /// always set to "regular file".
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_file`](Self::add_file).
+    pub fn add_file_with_mode(
+        &mut self,
+        name: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+        mode: u32,
+    ) -> Result<()> {
+        let name = name.into();
+        if name.is_empty() || name.contains(['/', '\0']) {
+            return Err(ArchiveError::InvalidPath(format!(
+                "invalid ar member name: {:?}",
+                name
+            )));
+        }
+
+        self.members.push(ArMember {
+            name,
+            data: data.into(),
+            mode: 0o100000 | (mode & 0o7777),
+        });
+        Ok(())
+    }
+
+    /// Writes all members and returns the finished archive.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::Io`] if the underlying ar writer fails.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let headers: Vec<ar::Header> = self
+            .members
+            .iter()
+            .map(|member| {
+                let mut header =
+                    ar::Header::new(member.name.as_bytes().to_vec(), member.data.len() as u64);
+                header.set_mtime(self.mtime);
+                header.set_mode(member.mode);
+                header
+            })
+            .collect();
+
+        match self.variant {
+            ArVariant::Gnu => {
+                let identifiers = headers.iter().map(|h| h.identifier().to_vec()).collect();
+                let mut builder = ar::GnuBuilder::new(Vec::new(), identifiers);
+                for (header, member) in headers.iter().zip(&self.members) {
+                    builder.append(header, member.data.as_slice())?;
+                }
+                Ok(finish_ar(builder.into_inner()?))
+            }
+            ArVariant::Bsd => {
+                let mut builder = ar::Builder::new(Vec::new());
+                for (header, member) in headers.iter().zip(&self.members) {
+                    builder.append(header, member.data.as_slice())?;
+                }
+                Ok(finish_ar(builder.into_inner()?))
+            }
+        }
+    }
+}
+
+/// The ar writers only emit the global header once the first member is
+/// appended, so an archive without members would otherwise be empty.
+fn finish_ar(output: Vec<u8>) -> Vec<u8> {
+    if output.is_empty() {
+        b"!<arch>\n".to_vec()
+    } else {
+        output
+    }
+}
+
+/// Compression applied to the `control.tar` and `data.tar` members of a
+/// Debian package.
+///
+/// All variants are accepted by `dpkg` for both members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebCompression {
+    /// Uncompressed tarball (`.tar`).
+    None,
+
+    /// gzip compression (`.tar.gz`), the default.
+    #[default]
+    Gzip,
+
+    /// XZ container (`.tar.xz`), the `dpkg-deb` default.
+    ///
+    /// The XZ encoder available here stores the data as LZMA literals
+    /// without matching repeated input, so the tarball is not made any
+    /// smaller. Use it when a tool expects `.tar.xz` members, and
+    /// [`Gzip`](Self::Gzip) or [`Zstd`](Self::Zstd) to reduce the size.
+    Xz,
+
+    /// Zstandard compression (`.tar.zst`).
+    Zstd,
+}
+
+impl DebCompression {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Gzip => ".gz",
+            Self::Xz => ".xz",
+            Self::Zstd => ".zst",
+        }
+    }
+
+    fn compress(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(&data)?;
+                Ok(encoder.finish()?)
+            }
+            Self::Xz => {
+                let mut output = Vec::new();
+                lzma_rs::xz_compress(&mut data.as_slice(), &mut output)?;
+                Ok(output)
+            }
+            Self::Zstd => Ok(zstd::stream::encode_all(data.as_slice(), 0)?),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum DebEntry {
+    File {
+        path: String,
+        data: Vec<u8>,
+        mode: u32,
+    },
+    Directory {
+        path: String,
+        mode: u32,
+    },
+    Symlink {
+        path: String,
+        target: String,
+    },
+}
+
+impl DebEntry {
+    fn path(&self) -> &str {
+        match self {
+            Self::File { path, .. } | Self::Directory { path, .. } | Self::Symlink { path, .. } => {
+                path
+            }
+        }
+    }
+}
+
+/// Builder for Debian binary packages (`.deb`).
+///
+/// The package is written in the member order mandated by `deb(5)`:
+/// `debian-binary` (containing `2.0`), then `control.tar[.*]`, then
+/// `data.tar[.*]`. Paths inside both tarballs are stored relative to `./`,
+/// and missing parent directories of data files are created automatically,
+/// matching what `dpkg-deb --build` produces.
+///
+/// All entries are owned by `root:root` and share a single modification time
+/// (0 by default, for reproducible output).
+///
+/// # Examples
+///
+/// ```
+/// use archive::{ArchiveExtractor, ArchiveFormat, DebBuilder, DebCompression};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let control = "Package: hello\nVersion: 1.0\nArchitecture: all\n\
+///                Maintainer: Jane Doe <jane@example.com>\nDescription: greeting\n";
+///
+/// let mut builder = DebBuilder::new(control).with_compression(DebCompression::Gzip);
+/// builder.add_file_with_mode("/usr/bin/hello", b"#!/bin/sh\necho hello\n".to_vec(), 0o755)?;
+/// builder.add_control_file_with_mode("postinst", b"#!/bin/sh\nexit 0\n".to_vec(), 0o755)?;
+/// let deb = builder.finish()?;
+///
+/// let members = ArchiveExtractor::new().extract(&deb, ArchiveFormat::Deb)?;
+/// assert_eq!(members[0].path, "debian-binary");
+/// assert_eq!(members[1].path, "control.tar.gz");
+/// assert_eq!(members[2].path, "data.tar.gz");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DebBuilder {
+    control: Vec<u8>,
+    control_files: Vec<DebEntry>,
+    data_entries: Vec<DebEntry>,
+    compression: DebCompression,
+    control_compression: DebCompression,
+    mtime: u64,
+}
+
+impl DebBuilder {
+    /// Creates a new package builder from the contents of the `control` file.
+    ///
+    /// The control file is stored as `./control` in `control.tar` and is not
+    /// validated; it is the caller's responsibility to provide the mandatory
+    /// fields (`Package`, `Version`, `Architecture`, `Maintainer`,
+    /// `Description`).
+    ///
+    /// Both tarballs default to gzip compression.
+    pub fn new(control: impl Into<Vec<u8>>) -> Self {
+        Self {
+            control: control.into(),
+            control_files: Vec::new(),
+            data_entries: Vec::new(),
+            compression: DebCompression::default(),
+            control_compression: DebCompression::default(),
+            mtime: 0,
+        }
+    }
+
+    /// Sets the compression used for both `control.tar` and `data.tar`.
+    ///
+    /// This method uses the builder pattern, allowing you to chain configuration calls.
+    pub fn with_compression(mut self, compression: DebCompression) -> Self {
+        self.compression = compression;
+        self.control_compression = compression;
+        self
+    }
+
+    /// Sets the compression used for `control.tar` only.
+    ///
+    /// Call this after [`with_compression`](Self::with_compression), which
+    /// overrides both members.
+    pub fn with_control_compression(mut self, compression: DebCompression) -> Self {
+        self.control_compression = compression;
+        self
+    }
+
+    /// Sets the modification time (seconds since the Unix epoch) stored for
+    /// every ar member and tarball entry.
+    pub fn with_mtime(mut self, mtime: u64) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    /// Adds an additional file to `control.tar` with mode `0644`, such as
+    /// `conffiles`, `md5sums` or `triggers`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] if `name` is `control`, which is
+    /// supplied through [`new`](Self::new), or is not a plain file name.
+    pub fn add_control_file(
+        &mut self,
+        name: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<()> {
+        self.add_control_file_with_mode(name, data, DEFAULT_FILE_MODE)
+    }
+
+    /// Adds an additional file to `control.tar` with the given permission
+    /// bits. Maintainer scripts such as `postinst` need to be executable.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_control_file`](Self::add_control_file).
+    pub fn add_control_file_with_mode(
+        &mut self,
+        name: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+        mode: u32,
+    ) -> Result<()> {
+        let name = name.into();
+        if name.is_empty() || name == "control" || name.contains(['/', '\0']) || name == ".." {
+            return Err(ArchiveError::InvalidPath(format!(
+                "invalid control member name: {:?}",
+                name
+            )));
+        }
+
+        self.control_files.push(DebEntry::File {
+            path: format!("./{}", name),
+            data: data.into(),
+            mode,
+        });
+        Ok(())
+    }
+
+    /// Adds a regular file to `data.tar` with mode `0644`.
+    ///
+    /// `path` is the absolute install location, e.g. `/usr/share/doc/hello/README`.
+    /// A leading `/` is optional.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] if `path` is empty or contains
+    /// `.`/`..` components.
+    pub fn add_file(&mut self, path: &str, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.add_file_with_mode(path, data, DEFAULT_FILE_MODE)
+    }
+
+    /// Adds a regular file to `data.tar` with the given permission bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_file`](Self::add_file).
+    pub fn add_file_with_mode(
+        &mut self,
+        path: &str,
+        data: impl Into<Vec<u8>>,
+        mode: u32,
+    ) -> Result<()> {
+        let path = normalize_deb_path(path)?;
+        self.data_entries.push(DebEntry::File {
+            path,
+            data: data.into(),
+            mode,
+        });
+        Ok(())
+    }
+
+    /// Adds a directory to `data.tar` with mode `0755`.
+    ///
+    /// Parent directories of files are created automatically, so this is only
+    /// needed for empty directories or to control their permissions (the
+    /// first entry for a directory wins).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_file`](Self::add_file).
+    pub fn add_directory(&mut self, path: &str) -> Result<()> {
+        self.add_directory_with_mode(path, DEFAULT_DIR_MODE)
+    }
+
+    /// Adds a directory to `data.tar` with the given permission bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_file`](Self::add_file).
+    pub fn add_directory_with_mode(&mut self, path: &str, mode: u32) -> Result<()> {
+        let path = normalize_deb_path(path)?;
+        self.data_entries.push(DebEntry::Directory { path, mode });
+        Ok(())
+    }
+
+    /// Adds a symbolic link at `path` pointing to `target` to `data.tar`.
+    ///
+    /// The target is stored verbatim and may be relative or absolute.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_file`](Self::add_file).
+    pub fn add_symlink(&mut self, path: &str, target: impl Into<String>) -> Result<()> {
+        let path = normalize_deb_path(path)?;
+        self.data_entries.push(DebEntry::Symlink {
+            path,
+            target: target.into(),
+        });
+        Ok(())
+    }
+
+    /// Assembles the package and returns the `.deb` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::Io`] if writing or compressing one of the
+    /// tarballs fails.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let mut control_entries = vec![
+            DebEntry::Directory {
+                path: "./".to_string(),
+                mode: DEFAULT_DIR_MODE,
+            },
+            DebEntry::File {
+                path: "./control".to_string(),
+                data: self.control,
+                mode: DEFAULT_FILE_MODE,
+            },
+        ];
+        control_entries.extend(self.control_files);
+
+        let control_tar = build_tar(&control_entries, self.mtime)?;
+        let data_tar = build_tar(&with_parent_directories(self.data_entries), self.mtime)?;
+
+        let mut ar = ArBuilder::new(ArVariant::Bsd).with_mtime(self.mtime);
+        ar.add_file("debian-binary", b"2.0\n".to_vec())?;
+        ar.add_file(
+            format!("control.tar{}", self.control_compression.extension()),
+            self.control_compression.compress(control_tar)?,
+        )?;
+        ar.add_file(
+            format!("data.tar{}", self.compression.extension()),
+            self.compression.compress(data_tar)?,
+        )?;
+        ar.finish()
+    }
+}
+
+/// Converts an install path into the `./`-relative form used inside
+/// `data.tar`, rejecting anything that could escape the installation root.
+fn normalize_deb_path(path: &str) -> Result<String> {
+    Ok(format!("./{}", normalize_entry_path(path)?))
+}
+
+/// Strips leading `/` and `./` and trailing `/` from an entry path, rejecting
+/// empty paths and `.`/`..` components.
+fn normalize_entry_path(path: &str) -> Result<String> {
+    let trimmed = path.trim_start_matches('/').trim_end_matches('/');
+    let trimmed = trimmed.strip_prefix("./").unwrap_or(trimmed);
+    if trimmed.is_empty()
+        || trimmed.contains('\0')
+        || trimmed
+            .split('/')
+            .any(|component| component.is_empty() || component == "." || component == "..")
+    {
+        return Err(ArchiveError::InvalidPath(format!(
+            "invalid entry path: {:?}",
+            path
+        )));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Prepends the `./` root and any missing parent directories so that every
+/// entry's parent precedes it in the tarball.
+fn with_parent_directories(entries: Vec<DebEntry>) -> Vec<DebEntry> {
+    let mut seen = std::collections::HashSet::new();
+    let mut result = vec![DebEntry::Directory {
+        path: "./".to_string(),
+        mode: DEFAULT_DIR_MODE,
+    }];
+
+    for entry in entries {
+        let path = entry.path().to_string();
+        let mut parent = String::from(".");
+        let components: Vec<&str> = path.trim_start_matches("./").split('/').collect();
+        for component in &components[..components.len() - 1] {
+            parent.push('/');
+            parent.push_str(component);
+            if seen.insert(parent.clone()) {
+                result.push(DebEntry::Directory {
+                    path: parent.clone(),
+                    mode: DEFAULT_DIR_MODE,
+                });
+            }
+        }
+
+        if matches!(entry, DebEntry::Directory { .. }) && !seen.insert(path) {
+            continue;
+        }
+        result.push(entry);
+    }
+
+    result
+}
+
+/// Writes `entries` into an uncompressed tarball with `root:root` ownership.
+fn build_tar(entries: &[DebEntry], mtime: u64) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_username("root")?;
+        header.set_groupname("root")?;
+
+        let (path, data): (String, &[u8]) = match entry {
+            DebEntry::File { path, data, mode } => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(*mode);
+                header.set_size(data.len() as u64);
+                (path.clone(), data)
+            }
+            DebEntry::Directory { path, mode } => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(*mode);
+                header.set_size(0);
+                let path = if path.ends_with('/') {
+                    path.clone()
+                } else {
+                    format!("{}/", path)
+                };
+                (path, &[])
+            }
+            DebEntry::Symlink { path, target } => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                header.set_link_name(target)?;
+                (path.clone(), &[])
+            }
+        };
+
+        append_tar_entry(&mut builder, &mut header, &path, data)?;
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+/// Appends an entry, storing `path` verbatim when it fits in the header.
+///
+/// `tar::Header::set_path` strips the leading `./` that `dpkg-deb` writes, so
+/// short names are copied into the header directly and only names that need
+/// the GNU long-name extension go through the tar crate's path handling.
+fn append_tar_entry(
+    builder: &mut tar::Builder<Vec<u8>>,
+    header: &mut tar::Header,
+    path: &str,
+    data: &[u8],
+) -> Result<()> {
+    let name = &mut header.as_old_mut().name;
+    if path.len() < name.len() {
+        name.fill(0);
+        name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_cksum();
+        builder.append(header, data)?;
+    } else {
+        builder.append_data(header, path, data)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct SevenZEntry {
+    name: String,
+    data: Vec<u8>,
+    is_directory: bool,
+}
+
+/// Builder for 7-Zip archives (`.7z`).
+///
+/// File contents are compressed with LZMA2. By default every file is stored in
+/// its own block; [`with_solid`](Self::with_solid) packs all files into a single
+/// solid block instead, which usually compresses better at the cost of random
+/// access. When a password is set, file contents are encrypted with AES-256.
+/// The archive header, and with it the file names, can be encrypted as well
+/// with [`with_header_encryption`](Self::with_header_encryption).
+///
+/// # Examples
+///
+/// ```
+/// use archive::{ArchiveExtractor, ArchiveFormat, SevenZBuilder};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut builder = SevenZBuilder::new().with_solid(true);
+/// builder.add_directory("docs")?;
+/// builder.add_file("docs/hello.txt", b"Hello, World!\n".to_vec())?;
+/// builder.add_file("docs/test.txt", b"This is a test file\n".to_vec())?;
+/// let data = builder.finish()?;
+///
+/// let files = ArchiveExtractor::new().extract(&data, ArchiveFormat::SevenZ)?;
+/// assert_eq!(files.len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SevenZBuilder {
+    entries: Vec<SevenZEntry>,
+    solid: bool,
+    level: u32,
+    password: Option<String>,
+    encrypt_header: bool,
+    mtime: Option<u64>,
+}
+
+impl Default for SevenZBuilder {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            solid: false,
+            level: 6,
+            password: None,
+            encrypt_header: false,
+            mtime: None,
+        }
+    }
+}
+
+impl SevenZBuilder {
+    /// Creates a new, empty 7z builder.
+    ///
+    /// Defaults: LZMA2 at compression level 6, one block per file, no encryption,
+    /// no modification times stored.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Packs all files into a single solid block.
+    ///
+    /// Directories and empty files carry no data and are listed after the
+    /// solid block's files.
+    ///
+    /// This method uses the builder pattern, allowing you to chain configuration calls.
+    pub fn with_solid(mut self, solid: bool) -> Self {
+        self.solid = solid;
+        self
+    }
+
+    /// Sets the LZMA2 compression level, from 0 (fastest) to 9 (best).
+    /// Values above 9 are clamped.
+    pub fn with_compression_level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Encrypts file contents with AES-256 using the given password.
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Sets whether the archive header is encrypted when a password is set.
+    ///
+    /// Header encryption is disabled by default. Enabling it hides the file
+    /// names from anyone without the password. It has no effect without a
+    /// password.
+    ///
+    /// The header is only encrypted together with being compressed, which
+    /// the writer skips when compression wouldn't save space. An archive
+    /// whose header can't be encrypted is never written with the names in
+    /// plain text; for archives with just a few short names,
+    /// [`finish`](Self::finish) fails instead.
+    pub fn with_header_encryption(mut self, enabled: bool) -> Self {
+        self.encrypt_header = enabled;
+        self
+    }
+
+    /// Sets the modification time (seconds since the Unix epoch) stored for
+    /// every entry. Without it, no modification times are written.
+    pub fn with_mtime(mut self, mtime: u64) -> Self {
+        self.mtime = Some(mtime);
+        self
+    }
+
+    /// Adds a regular file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] if `name` is empty or contains
+    /// `.`/`..` components. A leading `/` is stripped.
+    pub fn add_file(&mut self, name: &str, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.entries.push(SevenZEntry {
+            name: normalize_entry_path(name)?,
+            data: data.into(),
+            is_directory: false,
+        });
+        Ok(())
+    }
+
+    /// Adds a directory.
+    ///
+    /// Unlike [`DebBuilder`], parent directories are not created implicitly;
+    /// 7-Zip creates them on extraction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::InvalidPath`] under the same conditions as
+    /// [`add_file`](Self::add_file).
+    pub fn add_directory(&mut self, name: &str) -> Result<()> {
+        self.entries.push(SevenZEntry {
+            name: normalize_entry_path(name)?,
+            data: Vec::new(),
+            is_directory: true,
+        });
+        Ok(())
+    }
+
+    /// Compresses all entries and returns the finished archive.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::Io`] if compressing or encrypting an entry fails,
+    /// or if header encryption is enabled but the header is too small to be
+    /// encrypted.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let mut writer =
+            sevenz_rust::SevenZWriter::new(Cursor::new(Vec::new())).map_err(sevenz_error)?;
+
+        let mut methods = Vec::new();
+        if let Some(password) = &self.password {
+            methods.push(sevenz_rust::AesEncoderOptions::new(password.as_str().into()).into());
+        }
+        methods.push(sevenz_rust::lzma::LZMA2Options::with_preset(self.level).into());
+        writer.set_content_methods(methods);
+        let encrypt_header = self.password.is_some() && self.encrypt_header;
+        writer.set_encrypt_header(encrypt_header);
+
+        let archive_entry = |entry: &SevenZEntry| {
+            let mut archive_entry = sevenz_rust::SevenZArchiveEntry::new();
+            archive_entry.name = entry.name.clone();
+            archive_entry.is_directory = entry.is_directory;
+            if let Some(mtime) = self.mtime {
+                archive_entry.last_modified_date =
+                    sevenz_rust::nt_time::FileTime::from_unix_time(mtime as i64)
+                        .unwrap_or_default();
+                archive_entry.has_last_modified_date = true;
+            }
+            archive_entry
+        };
+
+        if self.solid {
+            let (streams, empty): (Vec<_>, Vec<_>) = self
+                .entries
+                .iter()
+                .partition(|entry| !entry.is_directory && !entry.data.is_empty());
+
+            if !streams.is_empty() {
+                // Unlike `push_archive_entry`, the solid variant doesn't mark
+                // the entries as having data itself.
+                let entries = streams
+                    .iter()
+                    .map(|entry| {
+                        let mut archive_entry = archive_entry(entry);
+                        archive_entry.has_stream = true;
+                        archive_entry
+                    })
+                    .collect();
+                let readers = streams
+                    .iter()
+                    .map(|entry| sevenz_rust::SourceReader::new(entry.data.as_slice()))
+                    .collect();
+                writer
+                    .push_archive_entries(entries, sevenz_rust::SeqReader::new(readers))
+                    .map_err(sevenz_error)?;
+            }
+            for entry in empty {
+                writer
+                    .push_archive_entry::<&[u8]>(archive_entry(entry), None)
+                    .map_err(sevenz_error)?;
+            }
+        } else {
+            for entry in &self.entries {
+                let reader = (!entry.is_directory && !entry.data.is_empty())
+                    .then_some(entry.data.as_slice());
+                writer
+                    .push_archive_entry(archive_entry(entry), reader)
+                    .map_err(sevenz_error)?;
+            }
+        }
+
+        let data = writer.finish()?.into_inner();
+        // The writer falls back to a plain header without reporting it
+        if encrypt_header && !has_encoded_header(&data) {
+            return Err(ArchiveError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "7z header is too small to be encrypted; disable header encryption to write \
+                 the file names in plain text",
+            )));
+        }
+        Ok(data)
+    }
+}
+
+/// Whether the header of a 7z archive is encoded, that is compressed and,
+/// with a password, encrypted, rather than stored as is.
+fn has_encoded_header(data: &[u8]) -> bool {
+    const SIGNATURE_HEADER_SIZE: usize = 32;
+    const ENCODED_HEADER: u8 = 0x17;
+
+    let Some(offset) = data
+        .get(12..20)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+    else {
+        return false;
+    };
+    usize::try_from(offset)
+        .ok()
+        .and_then(|offset| offset.checked_add(SIGNATURE_HEADER_SIZE))
+        .and_then(|position| data.get(position))
+        == Some(&ENCODED_HEADER)
+}
+
+fn sevenz_error(error: sevenz_rust::Error) -> ArchiveError {
+    ArchiveError::Io(std::io::Error::other(format!("7z error: {}", error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_7z(data: &[u8], password: &str) -> Vec<(String, Vec<u8>)> {
+        let mut cursor = Cursor::new(data);
+        let mut reader =
+            sevenz_rust::SevenZReader::new(&mut cursor, data.len() as u64, password.into())
+                .unwrap();
+        let mut entries = Vec::new();
+        reader
+            .for_each_entries(|entry, r| {
+                let mut contents = Vec::new();
+                r.read_to_end(&mut contents)?;
+                entries.push((entry.name().to_string(), contents));
+                Ok(true)
+            })
+            .unwrap();
+        entries
+    }
+
+    #[test]
+    fn test_7z_password_round_trip() {
+        for encrypt_header in [true, false] {
+            let mut builder = SevenZBuilder::new()
+                .with_password("secret")
+                .with_header_encryption(encrypt_header);
+            // Enough names for the header to be worth compressing
+            let mut expected = Vec::new();
+            for i in 0..32 {
+                let name = format!("secret-name-{:02}.txt", i);
+                builder
+                    .add_file(&name, b"Hello, World!\n".to_vec())
+                    .unwrap();
+                expected.push((name, b"Hello, World!\n".to_vec()));
+            }
+            let data = builder.finish().unwrap();
+
+            assert_eq!(read_7z(&data, "secret"), expected);
+            if encrypt_header {
+                assert!(
+                    !data
+                        .windows(b"secret-name".len())
+                        .any(|window| window == b"secret-name")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_7z_small_header_not_written_unencrypted() {
+        let mut builder = SevenZBuilder::new().with_password("secret");
+        builder
+            .add_file("hello.txt", b"Hello, World!\n".to_vec())
+            .unwrap();
+        let result = builder.clone().with_header_encryption(true).finish();
+        assert!(matches!(
+            result,
+            Err(ArchiveError::Io(error)) if error.kind() == std::io::ErrorKind::InvalidInput
+        ));
+
+        // Header encryption is opt-in, so the default builder succeeds
+        let data = builder.finish().unwrap();
+        assert_eq!(
+            read_7z(&data, "secret"),
+            [("hello.txt".to_string(), b"Hello, World!\n".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_normalize_entry_path() {
+        assert_eq!(normalize_entry_path("/usr/bin/").unwrap(), "usr/bin");
+        assert_eq!(normalize_entry_path("./a/b").unwrap(), "a/b");
+        assert!(normalize_entry_path("a/../b").is_err());
+        assert!(normalize_entry_path("a//b").is_err());
+        assert!(normalize_entry_path("/").is_err());
+    }
+}
@@ -0,0 +1,621 @@
+//! Microsoft Cabinet reading.
+//!
+//! A cabinet starts with a header, followed by a table of folders and a table
+//! of files. Each folder is a single compressed stream made of data blocks of
+//! at most 32 KiB of output, and each file is a range of its folder's
+//! uncompressed stream. Files of a folder are therefore read in order of their
+//! offsets, decompressing the folder once.
+//!
+//! Folders are stored uncompressed or compressed with MSZIP or LZX, the latter
+//! with the `lzx` feature. Quantum compression is rarely used and not
+//! supported. Only a single cabinet is read, so files continued from or into
+//! another cabinet of a set are rejected.
+//!
+//! See the Microsoft Cabinet Format specification (MS-CAB).
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::days_from_civil;
+use crate::info::{CabFolder, CabInfo};
+use std::io::{self, Read};
+
+pub(crate) const MAGIC: &[u8] = b"MSCF\0\0\0\0";
+
+const HEADER_SIZE: usize = 36;
+const FOLDER_SIZE: usize = 8;
+const FILE_SIZE: usize = 16;
+const DATA_SIZE: usize = 8;
+
+// Header flags
+const FLAG_PREV_CABINET: u16 = 0x0001;
+const FLAG_NEXT_CABINET: u16 = 0x0002;
+const FLAG_RESERVE_PRESENT: u16 = 0x0004;
+
+/// File attribute marking a name as UTF-8 rather than the system code page.
+const ATTRIBUTE_NAME_IS_UTF: u16 = 0x80;
+
+/// Folder indices of files that continue from or into another cabinet.
+const CONTINUED_FROM_PREV: u16 = 0xFFFD;
+
+/// The largest amount of output a data block may hold.
+const MAX_BLOCK_SIZE: usize = 32 * 1024;
+
+/// The history MSZIP blocks may refer back into.
+const MSZIP_WINDOW: usize = 32 * 1024;
+
+/// Whether `data` starts with a cabinet header.
+pub(crate) fn is_cab(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// How a folder is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    None,
+    MsZip,
+    Quantum,
+    /// LZX, with a window of `1 << window_bits` bytes.
+    Lzx {
+        window_bits: u8,
+    },
+}
+
+impl Compression {
+    fn from_bits(bits: u16) -> Result<Self> {
+        match bits & 0x000F {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::MsZip),
+            2 => Ok(Compression::Quantum),
+            3 => {
+                let window_bits = ((bits >> 8) & 0x1F) as u8;
+                if !(15..=25).contains(&window_bits) {
+                    return Err(invalid(format!("invalid LZX window of 2^{}", window_bits)));
+                }
+                Ok(Compression::Lzx { window_bits })
+            }
+            other => Err(invalid(format!("unknown compression type {}", other))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Compression::None => "None",
+            Compression::MsZip => "MSZIP",
+            Compression::Quantum => "Quantum",
+            Compression::Lzx { .. } => "LZX",
+        }
+    }
+
+    /// The decoder window in bytes, for LZX.
+    pub(crate) fn window_size(self) -> Option<u64> {
+        match self {
+            Compression::Lzx { window_bits } => Some(1 << window_bits),
+            _ => None,
+        }
+    }
+}
+
+/// A folder, i.e. a compressed stream holding the contents of some files.
+#[derive(Debug, Clone)]
+pub(crate) struct Folder {
+    data_offset: u32,
+    block_count: u16,
+    pub(crate) compression: Compression,
+}
+
+/// A file of a cabinet, without its contents.
+#[derive(Debug, Clone)]
+pub(crate) struct File {
+    /// The path, with `/` separating components.
+    pub(crate) path: String,
+    pub(crate) size: u32,
+    /// The offset of the contents in the folder's uncompressed stream.
+    offset: u32,
+    folder: u16,
+    /// The FAT attributes, e.g. `0x01` for read-only files.
+    pub(crate) attributes: u16,
+    /// Modification time in seconds since the Unix epoch.
+    pub(crate) mtime: Option<u64>,
+}
+
+/// The headers of a cabinet.
+#[derive(Debug, Clone)]
+pub(crate) struct Cabinet {
+    pub(crate) folders: Vec<Folder>,
+    pub(crate) files: Vec<File>,
+    /// Size of the per-block reserved area in front of each block's data.
+    data_reserve: usize,
+    set_id: u16,
+    index: u16,
+    previous: Option<String>,
+    next: Option<String>,
+}
+
+impl Cabinet {
+    /// Parses the header, folder and file tables of `data`.
+    pub(crate) fn parse(data: &[u8]) -> Result<Self> {
+        if !is_cab(data) || data.len() < HEADER_SIZE {
+            return Err(invalid("missing cabinet header"));
+        }
+
+        let files_offset = u32_at(data, 16)? as usize;
+        let folder_count = u16_at(data, 26)?;
+        let file_count = u16_at(data, 28)?;
+        let flags = u16_at(data, 30)?;
+        let set_id = u16_at(data, 32)?;
+        let index = u16_at(data, 34)?;
+
+        let mut position = HEADER_SIZE;
+        let (mut folder_reserve, mut data_reserve) = (0, 0);
+        if flags & FLAG_RESERVE_PRESENT != 0 {
+            let header_reserve = u16_at(data, position)? as usize;
+            folder_reserve = usize::from(*byte_at(data, position + 2)?);
+            data_reserve = usize::from(*byte_at(data, position + 3)?);
+            position += 4 + header_reserve;
+        }
+
+        let mut previous = None;
+        if flags & FLAG_PREV_CABINET != 0 {
+            // The cabinet name is followed by the name of the disk holding it
+            previous = Some(string_at(data, &mut position, false)?);
+            string_at(data, &mut position, false)?;
+        }
+        let mut next = None;
+        if flags & FLAG_NEXT_CABINET != 0 {
+            next = Some(string_at(data, &mut position, false)?);
+            string_at(data, &mut position, false)?;
+        }
+
+        let mut folders = Vec::with_capacity(folder_count.into());
+        for _ in 0..folder_count {
+            folders.push(Folder {
+                data_offset: u32_at(data, position)?,
+                block_count: u16_at(data, position + 4)?,
+                compression: Compression::from_bits(u16_at(data, position + 6)?)?,
+            });
+            position += FOLDER_SIZE + folder_reserve;
+        }
+
+        let mut files = Vec::with_capacity(file_count.into());
+        let mut position = files_offset;
+        for _ in 0..file_count {
+            let date = u16_at(data, position + 10)?;
+            let time = u16_at(data, position + 12)?;
+            let attributes = u16_at(data, position + 14)?;
+            let mut name_position = position + FILE_SIZE;
+            let name = string_at(
+                data,
+                &mut name_position,
+                attributes & ATTRIBUTE_NAME_IS_UTF != 0,
+            )?;
+            files.push(File {
+                path: name.replace('\\', "/"),
+                size: u32_at(data, position)?,
+                offset: u32_at(data, position + 4)?,
+                folder: u16_at(data, position + 8)?,
+                attributes: attributes & !ATTRIBUTE_NAME_IS_UTF,
+                mtime: dos_time(date, time),
+            });
+            position = name_position;
+        }
+
+        Ok(Self {
+            folders,
+            files,
+            data_reserve,
+            set_id,
+            index,
+            previous,
+            next,
+        })
+    }
+
+    pub(crate) fn info(&self) -> CabInfo {
+        CabInfo {
+            set_id: self.set_id,
+            cabinet_index: self.index,
+            previous_cabinet: self.previous.clone(),
+            next_cabinet: self.next.clone(),
+            folders: self
+                .folders
+                .iter()
+                .map(|folder| CabFolder {
+                    compression: folder.compression.name().to_string(),
+                    window_size: folder.compression.window_size(),
+                    data_blocks: folder.block_count,
+                })
+                .collect(),
+            file_count: self.files.len(),
+        }
+    }
+
+    /// The indices of the files of each folder, in order of their offsets.
+    ///
+    /// Fails for files that continue from or into another cabinet, whose
+    /// contents aren't all in this one.
+    pub(crate) fn folder_files(&self) -> Result<Vec<Vec<usize>>> {
+        let mut folder_files = vec![Vec::new(); self.folders.len()];
+        for (index, file) in self.files.iter().enumerate() {
+            if file.folder >= CONTINUED_FROM_PREV {
+                return Err(ArchiveError::UnsupportedFormat(format!(
+                    "CAB file '{}' is split across cabinets of a set",
+                    file.path
+                )));
+            }
+            folder_files
+                .get_mut(usize::from(file.folder))
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "file '{}' is in folder {}, but there are only {}",
+                        file.path,
+                        file.folder,
+                        self.folders.len()
+                    ))
+                })?
+                .push(index);
+        }
+
+        for files in &mut folder_files {
+            files.sort_by_key(|&index| self.files[index].offset);
+        }
+        Ok(folder_files)
+    }
+
+    /// The part of `data` holding the data blocks of a folder.
+    pub(crate) fn folder_data<'a>(&self, data: &'a [u8], index: usize) -> Result<&'a [u8]> {
+        data.get(self.folders[index].data_offset as usize..)
+            .ok_or_else(|| invalid(format!("folder {} starts past the end", index)))
+    }
+
+    /// Creates a reader for the uncompressed stream of a folder, from its
+    /// data blocks read through `reader`.
+    pub(crate) fn folder_reader<R: Read>(
+        &self,
+        index: usize,
+        reader: R,
+    ) -> Result<FolderReader<R>> {
+        let folder = &self.folders[index];
+        let decoder = match folder.compression {
+            Compression::None => Decoder::None,
+            Compression::MsZip => Decoder::MsZip {
+                inflater: flate2::Decompress::new(false),
+                history: Vec::with_capacity(MSZIP_WINDOW),
+            },
+            #[cfg(feature = "lzx")]
+            Compression::Lzx { window_bits } => {
+                let window = match window_bits {
+                    15 => lzxd::WindowSize::KB32,
+                    16 => lzxd::WindowSize::KB64,
+                    17 => lzxd::WindowSize::KB128,
+                    18 => lzxd::WindowSize::KB256,
+                    19 => lzxd::WindowSize::KB512,
+                    20 => lzxd::WindowSize::MB1,
+                    21 => lzxd::WindowSize::MB2,
+                    22 => lzxd::WindowSize::MB4,
+                    23 => lzxd::WindowSize::MB8,
+                    24 => lzxd::WindowSize::MB16,
+                    _ => lzxd::WindowSize::MB32,
+                };
+                Decoder::Lzx(Box::new(lzxd::Lzxd::new(window)))
+            }
+            #[cfg(not(feature = "lzx"))]
+            Compression::Lzx { .. } => {
+                return Err(ArchiveError::UnsupportedFormat(
+                    "LZX compressed CAB folders require the `lzx` feature".to_string(),
+                ));
+            }
+            Compression::Quantum => {
+                return Err(ArchiveError::UnsupportedFormat(
+                    "Quantum compressed CAB folders are not supported".to_string(),
+                ));
+            }
+        };
+
+        Ok(FolderReader {
+            reader,
+            decoder,
+            data_reserve: self.data_reserve,
+            remaining_blocks: folder.block_count,
+            block: Vec::new(),
+            block_position: 0,
+            position: 0,
+        })
+    }
+}
+
+/// Decompresses data blocks of a folder.
+enum Decoder {
+    None,
+    /// Each block is a deflate stream that may refer back into the previous
+    /// blocks' output.
+    MsZip {
+        inflater: flate2::Decompress,
+        history: Vec<u8>,
+    },
+    #[cfg(feature = "lzx")]
+    Lzx(Box<lzxd::Lzxd>),
+}
+
+/// Reads the uncompressed stream of a folder, block by block, verifying the
+/// checksum of each block.
+pub(crate) struct FolderReader<R> {
+    reader: R,
+    decoder: Decoder,
+    data_reserve: usize,
+    remaining_blocks: u16,
+    block: Vec<u8>,
+    block_position: usize,
+    /// Offset in the uncompressed stream.
+    position: u64,
+}
+
+impl<R: Read> FolderReader<R> {
+    /// Returns a reader for the contents of `file`, which must not start
+    /// before the end of the previously read file.
+    pub(crate) fn contents<'a>(&'a mut self, file: &File) -> Contents<'a, R> {
+        Contents {
+            folder: self,
+            offset: u64::from(file.offset),
+            remaining: u64::from(file.size),
+        }
+    }
+
+    fn next_block(&mut self) -> io::Result<()> {
+        let mut header = [0u8; DATA_SIZE];
+        self.reader.read_exact(&mut header)?;
+        let checksum = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let compressed_size = u16::from_le_bytes([header[4], header[5]]);
+        let size = u16::from_le_bytes([header[6], header[7]]);
+
+        let mut reserve = vec![0u8; self.data_reserve];
+        self.reader.read_exact(&mut reserve)?;
+        let mut data = vec![0u8; compressed_size.into()];
+        self.reader.read_exact(&mut data)?;
+
+        if checksum != 0 {
+            let mut actual = Checksum::default();
+            actual.update(&reserve);
+            actual.update(&data);
+            let actual = actual.value() ^ (u32::from(compressed_size) | u32::from(size) << 16);
+            if actual != checksum {
+                return Err(invalid_data(format!(
+                    "CAB data block checksum mismatch: expected {:08x}, found {:08x}",
+                    checksum, actual
+                )));
+            }
+        }
+
+        let size = usize::from(size);
+        if size > MAX_BLOCK_SIZE {
+            return Err(invalid_data(format!(
+                "CAB data block of {} bytes exceeds {} bytes",
+                size, MAX_BLOCK_SIZE
+            )));
+        }
+
+        self.block = match &mut self.decoder {
+            Decoder::None if data.len() == size => data,
+            Decoder::None => {
+                return Err(invalid_data(format!(
+                    "uncompressed CAB data block holds {} bytes instead of {}",
+                    data.len(),
+                    size
+                )));
+            }
+            Decoder::MsZip { inflater, history } => inflate(inflater, history, &data, size)?,
+            #[cfg(feature = "lzx")]
+            Decoder::Lzx(lzx) => lzx
+                .decompress_next(&data, size)
+                .map_err(|e| invalid_data(format!("LZX error: {}", e)))?
+                .to_vec(),
+        };
+        self.block_position = 0;
+        self.remaining_blocks -= 1;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for FolderReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.block_position == self.block.len() {
+            if self.remaining_blocks == 0 || buf.is_empty() {
+                return Ok(0);
+            }
+            self.next_block()?;
+        }
+
+        let read = buf.len().min(self.block.len() - self.block_position);
+        buf[..read].copy_from_slice(&self.block[self.block_position..][..read]);
+        self.block_position += read;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+/// Reads the contents of a single file from its folder.
+pub(crate) struct Contents<'a, R> {
+    folder: &'a mut FolderReader<R>,
+    offset: u64,
+    remaining: u64,
+}
+
+impl<R: Read> Read for Contents<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.folder.position > self.offset {
+            return Err(invalid_data("CAB files overlap within their folder"));
+        }
+        if self.folder.position < self.offset {
+            let gap = self.offset - self.folder.position;
+            let skipped = io::copy(&mut self.folder.by_ref().take(gap), &mut io::sink())?;
+            if skipped < gap {
+                return Err(truncated());
+            }
+        }
+
+        let limit = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let read = self.folder.read(&mut buf[..limit])?;
+        if read == 0 {
+            return Err(truncated());
+        }
+        self.offset += read as u64;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Decompresses an MSZIP block, which starts with a `CK` signature.
+///
+/// `flate2` can't be given a dictionary for raw deflate streams, so the
+/// history is fed to the inflater as a stored block first.
+fn inflate(
+    inflater: &mut flate2::Decompress,
+    history: &mut Vec<u8>,
+    data: &[u8],
+    size: usize,
+) -> io::Result<Vec<u8>> {
+    let data = data
+        .strip_prefix(b"CK")
+        .ok_or_else(|| invalid_data("MSZIP block without signature"))?;
+    let error = |e: flate2::DecompressError| invalid_data(format!("MSZIP error: {}", e));
+
+    inflater.reset(false);
+    if !history.is_empty() {
+        let length = history.len() as u16;
+        let mut stored = Vec::with_capacity(5 + history.len());
+        stored.push(0);
+        stored.extend_from_slice(&length.to_le_bytes());
+        stored.extend_from_slice(&(!length).to_le_bytes());
+        stored.extend_from_slice(history);
+        let mut discarded = vec![0u8; history.len()];
+        inflater
+            .decompress(&stored, &mut discarded, flate2::FlushDecompress::Sync)
+            .map_err(error)?;
+    }
+
+    let mut output = vec![0u8; size];
+    let start = inflater.total_out();
+    inflater
+        .decompress(data, &mut output, flate2::FlushDecompress::Finish)
+        .map_err(error)?;
+    let written = inflater.total_out() - start;
+    if written != size as u64 {
+        return Err(invalid_data(format!(
+            "MSZIP block decompressed to {} bytes instead of {}",
+            written, size
+        )));
+    }
+
+    history.extend_from_slice(&output);
+    let excess = history.len().saturating_sub(MSZIP_WINDOW);
+    history.drain(..excess);
+    Ok(output)
+}
+
+/// The checksum of a data block, XOR-ing little-endian 32-bit words.
+///
+/// Trailing bytes that don't fill a word are combined in reverse order.
+#[derive(Default)]
+struct Checksum {
+    value: u32,
+    remainder: u32,
+    remainder_bytes: u32,
+}
+
+impl Checksum {
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.remainder |= u32::from(byte) << (8 * self.remainder_bytes);
+            self.remainder_bytes += 1;
+            if self.remainder_bytes == 4 {
+                self.value ^= self.remainder;
+                self.remainder = 0;
+                self.remainder_bytes = 0;
+            }
+        }
+    }
+
+    fn value(&self) -> u32 {
+        let r = self.remainder;
+        match self.remainder_bytes {
+            1 => self.value ^ r,
+            2 => self.value ^ (r >> 8) ^ ((r & 0xFF) << 8),
+            3 => self.value ^ (r >> 16) ^ (r & 0xFF00) ^ ((r & 0xFF) << 16),
+            _ => self.value,
+        }
+    }
+}
+
+/// Converts an MS-DOS date and time, interpreted as UTC.
+fn dos_time(date: u16, time: u16) -> Option<u64> {
+    let (year, month, day) = (1980 + (date >> 9), (date >> 5) & 0x0F, date & 0x1F);
+    if !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+    let days = days_from_civil(year.into(), month.into(), day.into());
+    let seconds = i64::from(time >> 11) * 3600
+        + i64::from((time >> 5) & 0x3F) * 60
+        + i64::from(time & 0x1F) * 2;
+    u64::try_from(days * 86400 + seconds).ok()
+}
+
+/// Gathers cabinet-level information, which only needs the headers.
+pub(crate) fn inspect(data: &[u8]) -> Result<CabInfo> {
+    Ok(Cabinet::parse(data)?.info())
+}
+
+fn byte_at(data: &[u8], position: usize) -> Result<&u8> {
+    data.get(position)
+        .ok_or_else(|| invalid("truncated headers"))
+}
+
+fn u16_at(data: &[u8], position: usize) -> Result<u16> {
+    match data.get(position..position + 2) {
+        Some(bytes) => Ok(u16::from_le_bytes([bytes[0], bytes[1]])),
+        None => Err(invalid("truncated headers")),
+    }
+}
+
+fn u32_at(data: &[u8], position: usize) -> Result<u32> {
+    match data.get(position..position + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(invalid("truncated headers")),
+    }
+}
+
+/// Reads a NUL-terminated string at `position`, moving past it. Names that
+/// aren't UTF-8 are in an unspecified code page and read as Latin-1.
+fn string_at(data: &[u8], position: &mut usize, utf8: bool) -> Result<String> {
+    let bytes = data.get(*position..).unwrap_or_default();
+    let length = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| invalid("unterminated name"))?;
+    *position += length + 1;
+
+    let bytes = &bytes[..length];
+    Ok(if utf8 {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().copied().map(char::from).collect()
+    })
+}
+
+fn invalid(message: impl std::fmt::Display) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("CAB archive: {}", message))
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "CAB folder ends before the end of a file",
+    )
+}
@@ -0,0 +1,461 @@
+//! cpio archive reading.
+//!
+//! A cpio archive is a sequence of entries, each a header followed by the
+//! entry's name and contents, ending with an entry named `TRAILER!!!`. Three
+//! header formats are read:
+//!
+//! - SVR4 "newc" (`070701`), and its variant with checksums (`070702`), with
+//!   hexadecimal fields and 4-byte alignment, as written for RPM payloads and
+//!   Linux initramfs images
+//! - POSIX.1 "odc" (`070707`), with octal fields and no alignment
+//! - the old binary format, with 16-bit fields in the byte order of the
+//!   machine that wrote it and 2-byte alignment
+
+use crate::extractor::{EntryLink, ExtractedFile};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+
+/// The name of the entry that ends an archive.
+const TRAILER: &[u8] = b"TRAILER!!!";
+
+/// The longest entry name accepted, well above the limits of file systems.
+const MAX_NAME_SIZE: usize = 64 * 1024;
+
+/// File type bits of a Unix mode.
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// cpio header formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Newc,
+    NewcCrc,
+    Odc,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+impl Variant {
+    /// Identifies the format from the first bytes of a header.
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [b'0', b'7', b'0', b'7', b'0', b'1', ..] => Some(Self::Newc),
+            [b'0', b'7', b'0', b'7', b'0', b'2', ..] => Some(Self::NewcCrc),
+            [b'0', b'7', b'0', b'7', b'0', b'7', ..] => Some(Self::Odc),
+            // The octal magic number 070707 as a 16-bit integer
+            [0xC7, 0x71, ..] => Some(Self::BinaryLittleEndian),
+            [0x71, 0xC7, ..] => Some(Self::BinaryBigEndian),
+            _ => None,
+        }
+    }
+
+    /// The alignment of names and contents, relative to the archive start.
+    fn alignment(self) -> u64 {
+        match self {
+            Self::Newc | Self::NewcCrc => 4,
+            Self::Odc => 1,
+            Self::BinaryLittleEndian | Self::BinaryBigEndian => 2,
+        }
+    }
+}
+
+/// Whether `data` starts with a cpio header.
+pub(crate) fn is_cpio(data: &[u8]) -> bool {
+    match Variant::from_magic(data) {
+        Some(Variant::BinaryLittleEndian | Variant::BinaryBigEndian) => {
+            // Two bytes are a weak signature, so the name size must be
+            // plausible too
+            data.len() >= 26 && Header::parse_binary(&data[..26]).is_ok()
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// The fields of a cpio header that describe an entry.
+#[derive(Debug, Clone)]
+pub(crate) struct Header {
+    variant: Variant,
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    nlink: u32,
+    mtime: u64,
+    size: u64,
+    name_size: usize,
+    checksum: u32,
+    name: Vec<u8>,
+}
+
+impl Header {
+    fn parse_newc(header: &[u8], variant: Variant) -> io::Result<Self> {
+        let field = |index: usize| {
+            let start = 6 + index * 8;
+            parse_number(&header[start..start + 8], 16)
+        };
+        Ok(Self {
+            variant,
+            ino: field(0)?,
+            mode: field(1)? as u32,
+            nlink: field(4)? as u32,
+            mtime: field(5)?,
+            size: field(6)?,
+            dev: (field(7)? << 32) | field(8)?,
+            name_size: field(11)? as usize,
+            checksum: field(12)? as u32,
+            name: Vec::new(),
+        })
+    }
+
+    fn parse_odc(header: &[u8]) -> io::Result<Self> {
+        let field = |start: usize, len: usize| parse_number(&header[start..start + len], 8);
+        Ok(Self {
+            variant: Variant::Odc,
+            dev: field(6, 6)?,
+            ino: field(12, 6)?,
+            mode: field(18, 6)? as u32,
+            nlink: field(36, 6)? as u32,
+            mtime: field(48, 11)?,
+            name_size: field(59, 6)? as usize,
+            size: field(65, 11)?,
+            checksum: 0,
+            name: Vec::new(),
+        })
+    }
+
+    fn parse_binary(header: &[u8]) -> io::Result<Self> {
+        let variant = Variant::from_magic(header).unwrap_or(Variant::BinaryLittleEndian);
+        let field = |index: usize| {
+            let bytes = [header[index * 2], header[index * 2 + 1]];
+            u64::from(match variant {
+                Variant::BinaryBigEndian => u16::from_be_bytes(bytes),
+                _ => u16::from_le_bytes(bytes),
+            })
+        };
+        let name_size = field(10) as usize;
+        if name_size == 0 {
+            return Err(invalid("empty entry name"));
+        }
+        Ok(Self {
+            variant,
+            dev: field(1),
+            ino: field(2),
+            mode: field(3) as u32,
+            nlink: field(6) as u32,
+            // 32-bit values are stored as two 16-bit words, most significant first
+            mtime: (field(8) << 16) | field(9),
+            name_size,
+            size: (field(11) << 16) | field(12),
+            checksum: 0,
+            name: Vec::new(),
+        })
+    }
+
+    /// The entry's path, as stored.
+    pub(crate) fn path(&self) -> String {
+        String::from_utf8_lossy(&self.name).into_owned()
+    }
+
+    /// The size of the entry's contents, which is the target for symbolic
+    /// links.
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Permission bits, including the setuid, setgid and sticky bits.
+    pub(crate) fn mode(&self) -> u32 {
+        self.mode & 0o7777
+    }
+
+    /// Modification time in seconds since the Unix epoch.
+    pub(crate) fn mtime(&self) -> u64 {
+        self.mtime
+    }
+
+    pub(crate) fn is_directory(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    pub(crate) fn is_symlink(&self) -> bool {
+        self.mode & S_IFMT == S_IFLNK
+    }
+
+    /// Whether this is a regular file with several names, each stored as an
+    /// entry of its own.
+    fn is_hard_linked(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG && self.nlink > 1
+    }
+}
+
+fn parse_number(field: &[u8], radix: u32) -> io::Result<u64> {
+    std::str::from_utf8(field)
+        .ok()
+        .and_then(|digits| u64::from_str_radix(digits, radix).ok())
+        .ok_or_else(|| invalid("invalid header field"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("cpio archive: {}", message),
+    )
+}
+
+/// A streaming cpio reader, yielding entries in archive order.
+pub(crate) struct Archive<R> {
+    reader: R,
+    /// Bytes read from the start of the archive, which alignment is relative to.
+    offset: u64,
+    /// Contents of the current entry that haven't been read yet.
+    remaining: u64,
+    /// Padding after the contents of the current entry.
+    padding: u64,
+    /// The checksum of the current entry, if it has one, and the sum of the
+    /// bytes read so far.
+    checksum: Option<(u32, u32)>,
+    done: bool,
+}
+
+impl<R: Read> Archive<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            remaining: 0,
+            padding: 0,
+            checksum: None,
+            done: false,
+        }
+    }
+
+    /// Reads the next entry, skipping whatever is left of the previous one.
+    ///
+    /// Returns `None` after the trailer entry, or at the end of the input if
+    /// the trailer is missing.
+    pub(crate) fn next_entry(&mut self) -> Option<io::Result<Entry<'_, R>>> {
+        if self.done {
+            return None;
+        }
+        match self.read_header() {
+            Ok(Some(header)) => Some(Ok(Entry {
+                header,
+                archive: self,
+            })),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn read_header(&mut self) -> io::Result<Option<Header>> {
+        self.skip(self.remaining + self.padding)?;
+        self.remaining = 0;
+        self.padding = 0;
+        self.checksum = None;
+
+        let mut magic = [0u8; 6];
+        let read = self.fill(&mut magic)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < magic.len() {
+            return Err(invalid("truncated header"));
+        }
+
+        let mut header = match Variant::from_magic(&magic) {
+            Some(variant @ (Variant::Newc | Variant::NewcCrc)) => {
+                let mut header = [0u8; 110];
+                header[..6].copy_from_slice(&magic);
+                self.read_exact(&mut header[6..])?;
+                Header::parse_newc(&header, variant)?
+            }
+            Some(Variant::Odc) => {
+                let mut header = [0u8; 76];
+                header[..6].copy_from_slice(&magic);
+                self.read_exact(&mut header[6..])?;
+                Header::parse_odc(&header)?
+            }
+            Some(Variant::BinaryLittleEndian | Variant::BinaryBigEndian) => {
+                let mut header = [0u8; 26];
+                header[..6].copy_from_slice(&magic);
+                self.read_exact(&mut header[6..])?;
+                Header::parse_binary(&header)?
+            }
+            None => return Err(invalid("bad header magic")),
+        };
+
+        if header.name_size == 0 || header.name_size > MAX_NAME_SIZE {
+            return Err(invalid("bad entry name size"));
+        }
+        let mut name = vec![0u8; header.name_size];
+        self.read_exact(&mut name)?;
+        // The name size includes the terminating NUL
+        let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        name.truncate(end);
+        header.name = name;
+
+        let alignment = header.variant.alignment();
+        self.skip(padding(self.offset, alignment))?;
+        if header.name == TRAILER {
+            return Ok(None);
+        }
+
+        self.remaining = header.size;
+        self.padding = padding(self.offset.saturating_add(header.size), alignment);
+        if header.variant == Variant::NewcCrc {
+            self.checksum = Some((header.checksum, 0));
+        }
+        Ok(Some(header))
+    }
+
+    /// Reads as much of `buf` as the input holds.
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.offset += filled as u64;
+        Ok(filled)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if self.fill(buf)? < buf.len() {
+            return Err(invalid("unexpected end of archive"));
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self, len: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.reader).take(len), &mut io::sink())?;
+        self.offset += skipped;
+        if skipped < len {
+            return Err(invalid("unexpected end of archive"));
+        }
+        Ok(())
+    }
+}
+
+/// The number of bytes from `offset` to the next multiple of `alignment`.
+fn padding(offset: u64, alignment: u64) -> u64 {
+    (alignment - offset % alignment) % alignment
+}
+
+/// An entry of a cpio archive, whose reads return its contents.
+///
+/// For archives with checksums, reading to the end fails if the contents
+/// don't match.
+pub(crate) struct Entry<'a, R> {
+    header: Header,
+    archive: &'a mut Archive<R>,
+}
+
+impl<R> Entry<'_, R> {
+    pub(crate) fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl<R: Read> Read for Entry<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let archive = &mut *self.archive;
+        if archive.remaining == 0 {
+            if let Some((expected, sum)) = archive.checksum.take()
+                && expected != sum
+            {
+                return Err(invalid(&format!(
+                    "checksum mismatch for '{}'",
+                    self.header.path()
+                )));
+            }
+            return Ok(0);
+        }
+
+        let len = buf
+            .len()
+            .min(usize::try_from(archive.remaining).unwrap_or(usize::MAX));
+        let read = archive.reader.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(invalid("unexpected end of archive"));
+        }
+        archive.offset += read as u64;
+        archive.remaining -= read as u64;
+        if let Some((_, sum)) = &mut archive.checksum {
+            *sum = buf[..read]
+                .iter()
+                .fold(*sum, |sum, &byte| sum.wrapping_add(u32::from(byte)));
+        }
+        Ok(read)
+    }
+}
+
+/// Turns the entries of hard-linked files into links to a single entry that
+/// holds the contents, as TAR archives store them.
+///
+/// Archivers differ in which entries of a hard-linked file carry the
+/// contents: newc archivers store them with the last entry only, leaving the
+/// others empty, while odc and binary archivers store them with every entry.
+/// Empty entries are held back until the entry with the contents is found, so
+/// that links always point at an earlier entry.
+#[derive(Default)]
+pub(crate) struct HardLinks {
+    /// The path of the entry holding the contents, by device and inode.
+    targets: HashMap<(u64, u64), String>,
+    /// Empty entries waiting for the entry holding the contents.
+    deferred: BTreeMap<(u64, u64), Vec<ExtractedFile>>,
+}
+
+impl HardLinks {
+    /// Adds an entry, returning the entries that are ready in order.
+    pub(crate) fn add(&mut self, header: &Header, mut file: ExtractedFile) -> Vec<ExtractedFile> {
+        if !header.is_hard_linked() {
+            return vec![file];
+        }
+
+        let key = (header.dev, header.ino);
+        if file.data.is_empty() {
+            if let Some(target) = self.targets.get(&key) {
+                file.metadata.link = Some(EntryLink::Hard(target.clone()));
+                return vec![file];
+            }
+            if matches!(header.variant, Variant::Newc | Variant::NewcCrc) {
+                self.deferred.entry(key).or_default().push(file);
+                return Vec::new();
+            }
+        }
+
+        self.targets.entry(key).or_insert_with(|| file.path.clone());
+        let target = &self.targets[&key];
+        let mut ready = vec![file];
+        for mut link in self.deferred.remove(&key).unwrap_or_default() {
+            link.metadata.link = Some(EntryLink::Hard(target.clone()));
+            ready.push(link);
+        }
+        ready
+    }
+
+    /// Returns the entries still waiting at the end of the archive, which
+    /// belong to empty files.
+    pub(crate) fn finish(self) -> Vec<ExtractedFile> {
+        let mut ready = Vec::new();
+        for (_, mut files) in self.deferred {
+            let target = files[0].path.clone();
+            for link in &mut files[1..] {
+                link.metadata.link = Some(EntryLink::Hard(target.clone()));
+            }
+            ready.extend(files);
+        }
+        ready
+    }
+}
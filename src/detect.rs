@@ -0,0 +1,200 @@
+//! Archive format detection from file contents.
+//!
+//! Formats are recognized by their magic bytes. Compressed streams are
+//! partially decompressed to tell a compressed TAR archive apart from a single
+//! compressed file.
+
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+#[cfg(feature = "xar")]
+use crate::xar;
+use crate::{cab, cpio, iso, lzma, lzw, rpm, squashfs};
+use std::io::{self, Read, Write};
+
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// The stream identifier chunk that starts every framed Snappy stream.
+const SNAPPY_STREAM_IDENTIFIER: &[u8] = b"\xFF\x06\x00\x00sNaPpY";
+
+/// Detects the format of an archive from its leading bytes.
+pub(crate) fn detect(data: &[u8]) -> Result<ArchiveFormat> {
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        return Ok(ArchiveFormat::Zip);
+    }
+    if data.starts_with(b"7z\xBC\xAF\x27\x1C") {
+        return Ok(ArchiveFormat::SevenZ);
+    }
+    // RAR 1.5 to 4.x, RAR 5.0 and later, and the original RAR 1.4 format
+    if data.starts_with(b"Rar!\x1A\x07\x00")
+        || data.starts_with(b"Rar!\x1A\x07\x01\x00")
+        || data.starts_with(b"RE~^")
+    {
+        return Ok(ArchiveFormat::Rar);
+    }
+    if cab::is_cab(data) {
+        return Ok(ArchiveFormat::Cab);
+    }
+    if data.starts_with(b"xar!") {
+        // Telling installer packages apart takes parsing the table of contents
+        #[cfg(feature = "xar")]
+        if xar::is_package(data) {
+            return Ok(ArchiveFormat::Pkg);
+        }
+        return Ok(ArchiveFormat::Xar);
+    }
+    if squashfs::is_squashfs(data) {
+        return Ok(ArchiveFormat::SquashFs);
+    }
+    if data.starts_with(rpm::LEAD_MAGIC) {
+        return Ok(ArchiveFormat::Rpm);
+    }
+    if data.starts_with(b"!<arch>\n") {
+        // The first member of a Debian package is always `debian-binary`
+        return Ok(if data[8..].starts_with(b"debian-binary") {
+            ArchiveFormat::Deb
+        } else {
+            ArchiveFormat::Ar
+        });
+    }
+
+    if iso::is_iso(data) {
+        return Ok(ArchiveFormat::Iso);
+    }
+    if cpio::is_cpio(data) {
+        return Ok(ArchiveFormat::Cpio);
+    }
+
+    if data.starts_with(SNAPPY_STREAM_IDENTIFIER) {
+        return Ok(ArchiveFormat::Snappy);
+    }
+
+    let compressed = if data.starts_with(&[0x1F, 0x8B]) {
+        Some((ArchiveFormat::Gz, ArchiveFormat::TarGz))
+    } else if data.starts_with(b"BZh") {
+        Some((ArchiveFormat::Bz2, ArchiveFormat::TarBz2))
+    } else if data.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some((ArchiveFormat::Xz, ArchiveFormat::TarXz))
+    } else if data.starts_with(lzma::LZIP_MAGIC) {
+        Some((ArchiveFormat::Lz, ArchiveFormat::TarLz))
+    } else if data.starts_with(lzw::MAGIC) {
+        Some((ArchiveFormat::Compress, ArchiveFormat::TarZ))
+    } else if data.starts_with(&[0x04, 0x22, 0x4D, 0x18]) {
+        Some((ArchiveFormat::Lz4, ArchiveFormat::TarLz4))
+    } else if data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        Some((ArchiveFormat::Zst, ArchiveFormat::TarZst))
+    } else {
+        None
+    };
+
+    if let Some((single, tar)) = compressed {
+        let block = decompress_prefix(data, single);
+        return Ok(if is_tar_header(&block) { tar } else { single });
+    }
+
+    if is_tar_header(data) {
+        return Ok(ArchiveFormat::Tar);
+    }
+
+    // Without magic bytes, `.lzma` streams are only recognized by their header
+    // values once everything else has been ruled out
+    if lzma::is_lzma(data) {
+        let block = decompress_prefix(data, ArchiveFormat::Lzma);
+        return Ok(if is_tar_header(&block) {
+            ArchiveFormat::TarLzma
+        } else {
+            ArchiveFormat::Lzma
+        });
+    }
+
+    Err(ArchiveError::UnknownFormat)
+}
+
+/// Decompresses up to one TAR block from the start of a compressed stream.
+///
+/// Decoding errors only shorten the result, since a stream that can't be
+/// decompressed is still reported as its compression format.
+fn decompress_prefix(data: &[u8], format: ArchiveFormat) -> Vec<u8> {
+    let mut block = Vec::with_capacity(TAR_BLOCK_SIZE);
+    let limit = TAR_BLOCK_SIZE as u64;
+    let _ = match format {
+        ArchiveFormat::Gz => flate2::read::MultiGzDecoder::new(data)
+            .take(limit)
+            .read_to_end(&mut block),
+        ArchiveFormat::Bz2 => bzip2::read::MultiBzDecoder::new(data)
+            .take(limit)
+            .read_to_end(&mut block),
+        ArchiveFormat::Lz4 => {
+            lz4::Decoder::new(data).and_then(|d| d.take(limit).read_to_end(&mut block))
+        }
+        ArchiveFormat::Zst => zstd::stream::read::Decoder::new(data)
+            .and_then(|d| d.take(limit).read_to_end(&mut block)),
+        ArchiveFormat::Compress => {
+            lzw::Decoder::new(data).and_then(|d| d.take(limit).read_to_end(&mut block))
+        }
+        ArchiveFormat::Xz | ArchiveFormat::Lz | ArchiveFormat::Lzma => {
+            let mut output = PrefixWriter {
+                inner: &mut block,
+                remaining: TAR_BLOCK_SIZE,
+            };
+            // Stops with an error once the block is filled
+            let _ = lzma::decompress_head(format, data, &mut output);
+            Ok(0)
+        }
+        _ => Ok(0),
+    };
+    block
+}
+
+/// Checks whether `data` starts with a TAR header with a valid checksum.
+///
+/// The checksum is the sum of all header bytes with the checksum field itself
+/// counted as spaces. This recognizes V7 headers, which have no magic.
+fn is_tar_header(data: &[u8]) -> bool {
+    let Some(header) = data.get(..TAR_BLOCK_SIZE) else {
+        return false;
+    };
+    let field = &header[148..156];
+    let Some(stored) = std::str::from_utf8(field)
+        .ok()
+        .map(|s| s.trim_matches(|c| c == ' ' || c == '\0'))
+        .filter(|s| !s.is_empty())
+        .and_then(|s| u32::from_str_radix(s, 8).ok())
+    else {
+        return false;
+    };
+
+    let sum: u32 = header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if (148..156).contains(&i) {
+                0x20
+            } else {
+                b as u32
+            }
+        })
+        .sum();
+    sum == stored
+}
+
+/// A writer that keeps the first bytes written to it and then fails.
+struct PrefixWriter<'a> {
+    inner: &'a mut Vec<u8>,
+    remaining: usize,
+}
+
+impl Write for PrefixWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Err(io::Error::other("prefix complete"));
+        }
+        let len = buf.len().min(self.remaining);
+        self.inner.extend_from_slice(&buf[..len]);
+        self.remaining -= len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@
 //! various failure scenarios during archive extraction.
 
 use std::io;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias for archive operations.
@@ -66,7 +67,8 @@ pub enum ArchiveError {
 
     /// The archive format could not be determined.
     ///
-    /// This error is currently unused but reserved for future auto-detection features.
+    /// This error is returned by [`ArchiveFormat::detect`](crate::ArchiveFormat::detect)
+    /// when the data doesn't match any supported format.
     #[error("Unknown archive format")]
     UnknownFormat,
 
@@ -106,6 +108,25 @@ pub enum ArchiveError {
         limit: usize,
     },
 
+    /// A compressed stream needs a decoder window larger than the configured limit.
+    ///
+    /// This is a safety feature against streams that make the decoder reserve
+    /// huge amounts of memory regardless of how much they decompress to. The
+    /// limit can be configured using
+    /// [`ArchiveExtractor::with_max_window_size`](crate::ArchiveExtractor::with_max_window_size).
+    ///
+    /// # Fields
+    ///
+    /// - `size`: The window size declared by the stream in bytes
+    /// - `limit`: The configured maximum window size in bytes
+    #[error("Decoder window of {size} bytes exceeds limit of {limit} bytes")]
+    WindowTooLarge {
+        /// The window size declared by the stream
+        size: u64,
+        /// The configured maximum window size
+        limit: usize,
+    },
+
     /// The archive is invalid or corrupted.
     ///
     /// This error occurs when the archive data doesn't conform to the expected
@@ -124,4 +145,36 @@ pub enum ArchiveError {
     /// The string contains details about what is unsupported.
     #[error("Unsupported format: {0}")]
     UnsupportedFormat(String),
+
+    /// An entry path cannot be represented in or safely taken from an archive.
+    ///
+    /// This error occurs when a builder is given an entry name that is empty,
+    /// absolute, contains `..` components, or uses characters the target
+    /// format cannot store.
+    ///
+    /// The string contains the offending path.
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+
+    /// The extraction was stopped by a progress callback or a cancellation token.
+    ///
+    /// See [`ArchiveExtractor::with_progress`](crate::ArchiveExtractor::with_progress)
+    /// and [`ArchiveExtractor::with_cancellation`](crate::ArchiveExtractor::with_cancellation).
+    #[error("Extraction cancelled")]
+    Cancelled,
+
+    /// The extraction took longer than the configured time budget.
+    ///
+    /// This is a safety feature against inputs that decompress very slowly,
+    /// which size limits don't catch. The budget can be configured using
+    /// [`ArchiveExtractor::with_max_duration`](crate::ArchiveExtractor::with_max_duration).
+    ///
+    /// # Fields
+    ///
+    /// - `limit`: The configured maximum duration
+    #[error("Extraction exceeded time limit of {limit:?}")]
+    Timeout {
+        /// The configured maximum duration
+        limit: Duration,
+    },
 }
@@ -4,14 +4,44 @@
 //! archive formats. The main entry point is [`ArchiveExtractor`], which can
 //! extract files from any supported format into memory.
 
+use crate::cpio;
 use crate::error::{ArchiveError, Result};
 use crate::format::ArchiveFormat;
-use std::io::{Cursor, Read};
+use crate::hash::{Digest, HashAlgorithm, Hasher, HashingReader};
+use crate::info::{self, ArchiveInfo};
+use crate::limit::LimitedWriter;
+#[cfg(feature = "rayon")]
+use crate::parallel;
+use crate::progress::{
+    CancellationToken, LZMA_INPUT_BUFFER_SIZE, Progress, ProgressCallback, Tracker,
+};
+#[cfg(feature = "rar")]
+use crate::rar;
+#[cfg(feature = "unpack")]
+use crate::unpack::{self, ExtractOptions};
+use crate::verify::{self, VerifyReport};
+use crate::window;
+#[cfg(feature = "xar")]
+use crate::xar;
+use crate::{cab, iso, lzma, lzw, rpm, squashfs};
+#[cfg(feature = "rar")]
+use rars::ExtractionDecision;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::ops::ControlFlow;
+#[cfg(feature = "unpack")]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Receives each extracted entry as soon as it is complete.
+pub(crate) type Emit<'a> = dyn FnMut(ExtractedFile) -> Result<()> + 'a;
 
 /// Represents a single file extracted from an archive.
 ///
 /// This structure contains the file's path within the archive, its contents,
-/// and metadata about whether it represents a directory.
+/// whether it represents a directory, and how it was stored in the archive.
 ///
 /// # Examples
 ///
@@ -35,25 +65,184 @@ use std::io::{Cursor, Read};
 /// # }
 /// ```
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExtractedFile {
     /// The original path of the file within the archive.
     ///
-    /// For multi-file archives (ZIP, TAR, 7-Zip), this is the path as stored
-    /// in the archive. For single-file compression formats:
+    /// For multi-file archives (ZIP, TAR, 7-Zip, RAR, cpio, RPM, ISO 9660, CAB), this
+    /// is the path as stored in the archive. For single-file compression formats:
     /// - **Gzip**: The original filename from the header, or "data" if not present
     /// - **Bzip2, XZ, LZ4, Zstandard**: Always "data" as these formats don't store filenames
     pub path: String,
 
     /// The decompressed contents of the file.
     ///
-    /// For directories, this will be an empty vector.
+    /// For directories, this will be an empty vector. Serialized as a base64
+    /// string.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::base64"))]
     pub data: Vec<u8>,
 
+    /// Digests of [`data`](Self::data) for each algorithm enabled with
+    /// [`ArchiveExtractor::with_hashes`], in the order they were configured.
+    ///
+    /// Empty for directories and when no algorithms are enabled.
+    pub digests: Vec<Digest>,
+
     /// Whether this entry represents a directory.
     ///
     /// If `true`, the `data` field will be empty and `path` represents a directory.
     /// If `false`, this is a regular file with content in `data`.
     pub is_directory: bool,
+
+    /// Archive-stored information about the entry, such as its compressed size,
+    /// checksum and compression method.
+    pub metadata: EntryMetadata,
+}
+
+impl ExtractedFile {
+    /// Returns the digest computed with `algorithm`, if it was enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat, HashAlgorithm};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let data = vec![0u8; 100];
+    /// let files = ArchiveExtractor::new()
+    ///     .with_hashes([HashAlgorithm::Sha256])
+    ///     .extract(&data, ArchiveFormat::Zip)?;
+    ///
+    /// for file in files {
+    ///     if let Some(digest) = file.digest(HashAlgorithm::Sha256) {
+    ///         println!("{}  {}", digest, file.path);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn digest(&self, algorithm: HashAlgorithm) -> Option<&Digest> {
+        self.digests
+            .iter()
+            .find(|digest| digest.algorithm == algorithm)
+    }
+}
+
+/// Per-entry information as stored in the archive.
+///
+/// Which fields are available depends on the format: ZIP, 7-Zip and RAR
+/// record sizes, checksums and methods for every entry, while TAR, ar and cpio
+/// entries are stored uncompressed and only the surrounding stream (if any) is
+/// compressed.
+/// Fields the format doesn't record are `None` or empty.
+///
+/// # Examples
+///
+/// ```no_run
+/// use archive::{ArchiveExtractor, ArchiveFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let data = vec![0u8; 100];
+/// let files = ArchiveExtractor::new().extract(&data, ArchiveFormat::Zip)?;
+///
+/// for file in files {
+///     let meta = &file.metadata;
+///     println!(
+///         "{}: {} -> {} bytes ({}), crc32 {:08x?}",
+///         file.path,
+///         meta.compressed_size.unwrap_or_default(),
+///         file.data.len(),
+///         meta.compression_method.as_deref().unwrap_or("-"),
+///         meta.crc32,
+///     );
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryMetadata {
+    /// Size of the entry's data as stored in the archive, in bytes.
+    ///
+    /// For 7-Zip this is only known when the entry has a block of its own; entries
+    /// sharing a solid block have no individual compressed size. For single-file
+    /// formats it is the size of the whole compressed input.
+    pub compressed_size: Option<u64>,
+
+    /// CRC32 of the uncompressed data as recorded in the archive (ZIP, 7-Zip,
+    /// RAR).
+    pub crc32: Option<u32>,
+
+    /// Name of the compression method, e.g. `Deflated` for ZIP, `LZMA2` for
+    /// 7-Zip, `Normal` for RAR or `GZIP` for single-file formats. For 7-Zip,
+    /// the coders of the entry's block are joined with `+`, e.g. `BCJ_X86+LZMA`.
+    pub compression_method: Option<String>,
+
+    /// Whether the entry's data is encrypted.
+    pub encrypted: bool,
+
+    /// The per-entry comment (ZIP).
+    pub comment: Option<String>,
+
+    /// Header IDs of the extra fields attached to the entry (ZIP), in the order
+    /// they appear in the central directory, e.g. `0x5455` for extended timestamps.
+    pub extra_field_ids: Vec<u16>,
+
+    /// Unix permission bits, including the setuid, setgid and sticky bits
+    /// (ZIP entries created on Unix, TAR, ar, cpio, RPM, 7-Zip and RAR entries
+    /// with Unix attributes, ISO 9660 images with Rock Ridge, XAR and
+    /// SquashFS).
+    pub mode: Option<u32>,
+
+    /// Modification time in seconds since the Unix epoch.
+    ///
+    /// ZIP entries without an extended timestamp and CAB files only store a
+    /// local MS-DOS time, which is interpreted as UTC.
+    pub mtime: Option<u64>,
+
+    /// The link target if the entry is a symbolic or hard link (ZIP, TAR, 7-Zip,
+    /// RAR, cpio, RPM, ISO 9660 with Rock Ridge, XAR, SquashFS).
+    pub link: Option<EntryLink>,
+
+    /// MS-DOS file attributes (CAB), e.g. `0x01` for read-only, `0x02` for
+    /// hidden and `0x20` for files changed since the last backup.
+    pub attributes: Option<u32>,
+
+    /// The kind of special file if the entry is a device node, named pipe or
+    /// socket (SquashFS). Such entries have no contents.
+    pub special: Option<SpecialFile>,
+}
+
+/// The target of a link entry.
+///
+/// Symbolic link targets are stored as written by the archiver and are
+/// relative to the directory containing the link. Hard link targets are paths
+/// of earlier entries in the same archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryLink {
+    /// A symbolic link to the given path.
+    Symbolic(String),
+
+    /// A hard link to the entry with the given path.
+    Hard(String),
+}
+
+/// A file that is neither a regular file, a directory nor a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpecialFile {
+    /// A block device with the given device numbers.
+    BlockDevice { major: u32, minor: u32 },
+
+    /// A character device with the given device numbers.
+    CharacterDevice { major: u32, minor: u32 },
+
+    /// A named pipe.
+    Fifo,
+
+    /// A Unix domain socket.
+    Socket,
 }
 
 /// Main extractor that handles all archive formats.
@@ -131,6 +320,14 @@ pub struct ExtractedFile {
 pub struct ArchiveExtractor {
     max_file_size: usize,
     max_total_size: usize,
+    hashes: Vec<HashAlgorithm>,
+    progress: Option<ProgressCallback>,
+    cancellation: Option<CancellationToken>,
+    max_duration: Option<Duration>,
+    max_window_size: usize,
+    password: Option<String>,
+    #[cfg(feature = "rayon")]
+    parallel: bool,
 }
 
 impl Default for ArchiveExtractor {
@@ -138,6 +335,14 @@ impl Default for ArchiveExtractor {
         Self {
             max_file_size: 100 * 1024 * 1024,   // 100 MB per file
             max_total_size: 1024 * 1024 * 1024, // 1 GB total
+            hashes: Vec::new(),
+            progress: None,
+            cancellation: None,
+            max_duration: None,
+            max_window_size: 128 * 1024 * 1024, // 128 MB decoder window
+            password: None,
+            #[cfg(feature = "rayon")]
+            parallel: false,
         }
     }
 }
@@ -148,6 +353,8 @@ impl ArchiveExtractor {
     /// Default settings:
     /// - Maximum file size: 100 MB (104,857,600 bytes)
     /// - Maximum total extraction size: 1 GB (1,073,741,824 bytes)
+    /// - Maximum decoder window: 128 MB (134,217,728 bytes)
+    /// - No time limit
     ///
     /// # Examples
     ///
@@ -222,6 +429,207 @@ impl ArchiveExtractor {
         self
     }
 
+    /// Sets the hash algorithms computed over the contents of every extracted
+    /// file.
+    ///
+    /// Digests are computed while each entry is decompressed, so the data
+    /// doesn't have to be walked again afterwards. They are available in
+    /// [`ExtractedFile::digests`]. Duplicate algorithms are ignored, and an
+    /// empty list disables hashing, which is the default.
+    ///
+    /// This method uses the builder pattern, allowing you to chain configuration calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithms` - The algorithms to compute, in the order the digests are returned
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::{ArchiveExtractor, HashAlgorithm};
+    ///
+    /// let extractor = ArchiveExtractor::new()
+    ///     .with_hashes([HashAlgorithm::Sha256, HashAlgorithm::Sha512]);
+    /// ```
+    pub fn with_hashes(mut self, algorithms: impl IntoIterator<Item = HashAlgorithm>) -> Self {
+        self.hashes.clear();
+        for algorithm in algorithms {
+            if !self.hashes.contains(&algorithm) {
+                self.hashes.push(algorithm);
+            }
+        }
+        self
+    }
+
+    /// Sets a callback that is called with the [`Progress`] of every
+    /// extraction.
+    ///
+    /// The callback is called when an entry starts and finishes and each time
+    /// a chunk of data is read, from the thread running the extraction.
+    /// Returning [`ControlFlow::Break`] stops the extraction with
+    /// [`ArchiveError::Cancelled`].
+    ///
+    /// Only [`extract`](Self::extract) and `extract_to_dir` report progress.
+    /// For XZ, lzip and LZMA streams, the stream is decompressed in one go
+    /// before the entries are read, so only [`Progress::bytes_in`] advances
+    /// during that phase.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat};
+    /// use std::ops::ControlFlow;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let data = vec![0u8; 100];
+    /// let total = data.len() as u64;
+    /// let extractor = ArchiveExtractor::new().with_progress(move |progress| {
+    ///     eprint!("\r{}% {}", progress.bytes_in * 100 / total, progress.current_path);
+    ///     ControlFlow::Continue(())
+    /// });
+    ///
+    /// let files = extractor.extract(&data, ArchiveFormat::SevenZ)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_progress(
+        mut self,
+        callback: impl Fn(&Progress<'_>) -> ControlFlow<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Sets a token that cancels extractions when
+    /// [`CancellationToken::cancel`] is called.
+    ///
+    /// The token is checked between chunks of data, so cancellation takes
+    /// effect quickly even inside a large entry. A cancelled extraction fails
+    /// with [`ArchiveError::Cancelled`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Enables decompressing entries in parallel on the rayon thread pool.
+    ///
+    /// This applies to the formats whose entries are compressed
+    /// independently: ZIP entries, and the blocks of 7-Zip archives. A solid
+    /// 7-Zip archive stores everything in one block, so it only benefits if it
+    /// was created with several.
+    ///
+    /// XZ, Zstandard and gzip streams, on their own or wrapping a TAR archive,
+    /// are decompressed in parallel if they consist of independently
+    /// compressed parts: XZ blocks listed in the stream index, as written by
+    /// `xz -T`, Zstandard frames, as written by `pzstd`, and BGZF gzip members,
+    /// as written by `bgzip`. The whole stream is then held in memory, and its
+    /// decompressed size counts against the maximum file size, or the maximum
+    /// total size for TAR archives. Other streams and formats are extracted
+    /// serially.
+    ///
+    /// Entries are still returned in archive order, and the size limits apply
+    /// to the archive as a whole. Progress callbacks may be called from
+    /// several threads at once, in which case
+    /// [`Progress::current_path`] is the entry started last.
+    ///
+    /// Extraction runs on the current rayon thread pool, which is the global
+    /// pool unless called from within
+    /// [`ThreadPool::install`](rayon::ThreadPool::install).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::ArchiveExtractor;
+    ///
+    /// let extractor = ArchiveExtractor::new().with_parallel(true);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Sets the maximum decoder window for XZ, lzip, LZMA, Zstandard and
+    /// Brotli streams.
+    ///
+    /// LZMA, LZMA2, Zstandard and Brotli streams declare how much history the decoder has to
+    /// keep, the dictionary and window size respectively, and a malicious
+    /// stream can demand gigabytes of it while decompressing to almost
+    /// nothing. These declarations are checked before decoding starts, and a
+    /// stream needing more than `size` bytes is rejected with
+    /// [`ArchiveError::WindowTooLarge`].
+    ///
+    /// The default of 128 MB accepts everything `xz` and `zstd` produce with
+    /// their standard presets, including `zstd --long`.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Maximum window size in bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::ArchiveExtractor;
+    ///
+    /// // Accept up to `xz -6` and `zstd -19` streams
+    /// let extractor = ArchiveExtractor::new()
+    ///     .with_max_window_size(8 * 1024 * 1024);
+    /// ```
+    pub fn with_max_window_size(mut self, size: usize) -> Self {
+        self.max_window_size = size;
+        self
+    }
+
+    /// Sets the maximum wall-clock time a single extraction may take.
+    ///
+    /// Some inputs decompress very slowly without ever producing much output,
+    /// so size limits alone don't bound the work they cause. The budget is
+    /// checked each time a chunk of data is read, and an extraction that
+    /// exceeds it fails with [`ArchiveError::Timeout`].
+    ///
+    /// The budget applies to [`extract`](Self::extract) and `extract_to_dir`.
+    /// For the latter, only reading the archive counts, not writing files to
+    /// disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::ArchiveExtractor;
+    /// use std::time::Duration;
+    ///
+    /// let extractor = ArchiveExtractor::new()
+    ///     .with_max_file_size(10 * 1024 * 1024)
+    ///     .with_max_duration(Duration::from_secs(5));
+    /// ```
+    pub fn with_max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// Sets the password used to decrypt encrypted entries.
+    ///
+    /// ZIP entries encrypted with ZipCrypto, 7-Zip archives encrypted with
+    /// AES-256 and encrypted RAR archives are decrypted with it, and are
+    /// reported with [`EntryMetadata::encrypted`] set. Without a password,
+    /// extracting an encrypted entry fails. Entries that aren't encrypted are
+    /// read as usual.
+    ///
+    /// The password applies to [`verify`](Self::verify) and
+    /// [`inspect`](Self::inspect) as well, so encrypted entries can be
+    /// verified and encrypted 7-Zip and RAR headers can be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::ArchiveExtractor;
+    ///
+    /// let extractor = ArchiveExtractor::new().with_password("secret");
+    /// ```
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
     /// Extracts all files from an archive.
     ///
     /// This is the main extraction method that handles all supported archive formats.
@@ -245,6 +653,8 @@ impl ArchiveExtractor {
     /// - The total extracted size exceeds the limit ([`ArchiveError::TotalSizeTooLarge`])
     /// - An I/O error occurs during extraction ([`ArchiveError::Io`])
     /// - A ZIP-specific error occurs ([`ArchiveError::Zip`])
+    /// - A stream needs too large a decoder window ([`ArchiveError::WindowTooLarge`])
+    /// - The extraction takes longer than the time budget ([`ArchiveError::Timeout`])
     ///
     /// # Examples
     ///
@@ -312,175 +722,1053 @@ impl ArchiveExtractor {
     /// # }
     /// ```
     pub fn extract(&self, data: &[u8], format: ArchiveFormat) -> Result<Vec<ExtractedFile>> {
-        match format {
-            ArchiveFormat::Zip => self.extract_zip(data),
-            ArchiveFormat::Tar => self.extract_tar(data),
-            ArchiveFormat::Ar => self.extract_ar(data),
-            ArchiveFormat::Deb => self.extract_deb(data),
-            ArchiveFormat::TarGz => self.extract_tar_gz(data),
-            ArchiveFormat::TarBz2 => self.extract_tar_bz2(data),
-            ArchiveFormat::TarXz => self.extract_tar_xz(data),
-            ArchiveFormat::TarZst => self.extract_tar_zst(data),
-            ArchiveFormat::TarLz4 => self.extract_tar_lz4(data),
-            ArchiveFormat::SevenZ => self.extract_7z(data),
-            ArchiveFormat::Gz => self.extract_single_gz(data),
-            ArchiveFormat::Bz2 => self.extract_single_bz2(data),
-            ArchiveFormat::Xz => self.extract_single_xz(data),
-            ArchiveFormat::Lz4 => self.extract_single_lz4(data),
-            ArchiveFormat::Zst => self.extract_single_zst(data),
-        }
-    }
-
-    fn extract_zip(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let reader = Cursor::new(data);
-        let mut archive = zip::ZipArchive::new(reader)?;
-        let mut files = Vec::new();
-        let mut total_size = 0usize;
-
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i)?;
-            let is_directory = file.is_dir();
-
-            if !is_directory {
-                let size = file.size() as usize;
-                if size > self.max_file_size {
-                    return Err(ArchiveError::FileTooLarge {
-                        size,
-                        limit: self.max_file_size,
-                    });
-                }
-
-                total_size += size;
-                if total_size > self.max_total_size {
-                    return Err(ArchiveError::TotalSizeTooLarge {
-                        size: total_size,
-                        limit: self.max_total_size,
-                    });
-                }
-
-                let mut contents = Vec::new();
-                file.read_to_end(&mut contents)?;
-
-                files.push(ExtractedFile {
-                    path: file.name().to_string(),
-                    data: contents,
-                    is_directory,
-                });
-            } else {
-                files.push(ExtractedFile {
-                    path: file.name().to_string(),
-                    data: Vec::new(),
-                    is_directory,
-                });
+        window::check(data, format, self.max_window_size)?;
+        let tracker = Tracker::new(
+            self.progress.as_ref(),
+            self.cancellation.as_ref(),
+            self.max_duration,
+        );
+        let result = match format {
+            ArchiveFormat::Zip => self.extract_zip(&tracker, data),
+            ArchiveFormat::SevenZ => self.extract_7z(&tracker, data),
+            #[cfg(feature = "rar")]
+            ArchiveFormat::Rar => self.extract_rar(&tracker, data),
+            #[cfg(not(feature = "rar"))]
+            ArchiveFormat::Rar => Err(format.feature_disabled("rar")),
+            ArchiveFormat::Iso => self.extract_iso(&tracker, Cursor::new(data)),
+            ArchiveFormat::Cab => self.extract_cab(&tracker, data),
+            #[cfg(feature = "xar")]
+            ArchiveFormat::Xar | ArchiveFormat::Pkg => {
+                let mut files = Vec::new();
+                self.extract_xar(&tracker, data, format, &mut |file| {
+                    files.push(file);
+                    Ok(())
+                })
+                .map(|()| files)
             }
-        }
-
-        Ok(files)
-    }
-
-    fn extract_tar(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut archive = tar::Archive::new(cursor);
-        self.process_tar_entries(&mut archive)
-    }
-
-    fn extract_ar(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut archive = ar::Archive::new(cursor);
-        self.process_ar_entries(&mut archive)
-    }
-
-    fn extract_deb(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut archive = ar::Archive::new(cursor);
-        self.process_ar_entries(&mut archive)
-    }
-
-    fn extract_tar_gz(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let decoder = flate2::read::GzDecoder::new(cursor);
-        let mut archive = tar::Archive::new(decoder);
-        self.process_tar_entries(&mut archive)
+            #[cfg(not(feature = "xar"))]
+            ArchiveFormat::Xar | ArchiveFormat::Pkg => Err(format.feature_disabled("xar")),
+            ArchiveFormat::SquashFs => self.extract_squashfs(&tracker, data),
+            ArchiveFormat::Tar
+            | ArchiveFormat::Ar
+            | ArchiveFormat::Deb
+            | ArchiveFormat::Cpio
+            | ArchiveFormat::Rpm
+            | ArchiveFormat::TarGz
+            | ArchiveFormat::TarBz2
+            | ArchiveFormat::TarXz
+            | ArchiveFormat::TarZst
+            | ArchiveFormat::TarLz4
+            | ArchiveFormat::TarLz
+            | ArchiveFormat::TarLzma
+            | ArchiveFormat::TarZ
+            | ArchiveFormat::TarBr
+            | ArchiveFormat::Gz
+            | ArchiveFormat::Bz2
+            | ArchiveFormat::Xz
+            | ArchiveFormat::Lz
+            | ArchiveFormat::Lzma
+            | ArchiveFormat::Lz4
+            | ArchiveFormat::Zst
+            | ArchiveFormat::Compress
+            | ArchiveFormat::Br
+            | ArchiveFormat::Snappy => {
+                let mut files = Vec::new();
+                self.extract_sequential(&tracker, data, format, &mut |file| {
+                    files.push(file);
+                    Ok(())
+                })
+                .map(|()| files)
+            }
+        };
+        tracker.finish(result)
     }
 
-    fn extract_tar_bz2(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let decoder = bzip2::read::BzDecoder::new(cursor);
-        let mut archive = tar::Archive::new(decoder);
-        self.process_tar_entries(&mut archive)
-    }
+    /// Extracts all files from an archive read through a seekable reader, such
+    /// as a [`File`](std::fs::File).
+    ///
+    /// ISO 9660 images are read in place, so only the directories and the
+    /// files being extracted are read from `reader`. This keeps large images,
+    /// such as installer ISOs, out of memory apart from the extracted
+    /// contents. Other formats are read into memory and extracted as by
+    /// [`extract`](Self::extract).
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`extract`](Self::extract), and
+    /// [`ArchiveError::Io`] if reading from `reader` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat};
+    /// use std::fs::File;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let image = File::open("installer.iso")?;
+    /// let files = ArchiveExtractor::new()
+    ///     .with_max_total_size(8 * 1024 * 1024 * 1024)
+    ///     .extract_seekable(image, ArchiveFormat::Iso)?;
+    ///
+    /// for file in files {
+    ///     println!("{}: {} bytes", file.path, file.data.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_seekable<R: Read + Seek>(
+        &self,
+        mut reader: R,
+        format: ArchiveFormat,
+    ) -> Result<Vec<ExtractedFile>> {
+        if format != ArchiveFormat::Iso {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            return self.extract(&data, format);
+        }
 
-    fn extract_tar_xz(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut output = Vec::new();
-        lzma_rs::xz_decompress(&mut cursor.clone(), &mut output)
-            .map_err(|e| ArchiveError::InvalidArchive(e.to_string()))?;
-        let cursor = Cursor::new(output);
-        let mut archive = tar::Archive::new(cursor);
-        self.process_tar_entries(&mut archive)
+        let tracker = Tracker::new(
+            self.progress.as_ref(),
+            self.cancellation.as_ref(),
+            self.max_duration,
+        );
+        let result = self.extract_iso(&tracker, reader);
+        tracker.finish(result)
     }
 
-    fn extract_tar_zst(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let decoder = zstd::stream::read::Decoder::new(cursor)?;
-        let mut archive = tar::Archive::new(decoder);
-        self.process_tar_entries(&mut archive)
+    /// Extracts all files from an archive into a directory on disk.
+    ///
+    /// Entries are written beneath `root`, which is created if it doesn't
+    /// exist. Nothing is ever written outside of it:
+    ///
+    /// - Absolute paths and paths with `..` components are rejected with
+    ///   [`ArchiveError::InvalidPath`] before anything is written.
+    /// - Symbolic links are only created if their target stays within `root`,
+    ///   and no entry is written through a symbolic link, whether it came from
+    ///   the archive or was already on disk.
+    /// - Hard links may only point at regular files already beneath `root`.
+    /// - Device nodes, named pipes and sockets are skipped.
+    ///
+    /// Files are written to a temporary file next to their destination and
+    /// renamed into place, so a destination is never left half-written. The
+    /// archive is extracted in memory first, with the configured size limits
+    /// applied as in [`extract`](Self::extract).
+    ///
+    /// Returns the paths that were written, in archive order. Entries skipped
+    /// by [`OverwritePolicy::Skip`](crate::OverwritePolicy::Skip) are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if extraction fails, if an entry path or link target is
+    /// unsafe, if a destination exists and the policy is
+    /// [`OverwritePolicy::Error`](crate::OverwritePolicy::Error), or if writing
+    /// to disk fails. Entries written before a write error are left in place.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat, ExtractOptions, OverwritePolicy};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let data = vec![0u8; 100];
+    /// let options = ExtractOptions::new()
+    ///     .with_overwrite(OverwritePolicy::Skip)
+    ///     .with_permissions(true);
+    ///
+    /// let written = ArchiveExtractor::new().extract_to_dir(
+    ///     &data,
+    ///     ArchiveFormat::TarGz,
+    ///     Path::new("/tmp/unpacked"),
+    ///     &options,
+    /// )?;
+    /// println!("Wrote {} entries", written.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "unpack")]
+    pub fn extract_to_dir(
+        &self,
+        data: &[u8],
+        format: ArchiveFormat,
+        root: &Path,
+        options: &ExtractOptions,
+    ) -> Result<Vec<PathBuf>> {
+        let files = self.extract(data, format)?;
+        unpack::write_entries(&files, root, options)
     }
 
-    fn extract_tar_lz4(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let decoder = lz4::Decoder::new(cursor)?;
-        let mut archive = tar::Archive::new(decoder);
-        self.process_tar_entries(&mut archive)
+    /// Reads archive-level metadata without extracting entry contents.
+    ///
+    /// The returned [`ArchiveInfo`] covers what [`extract`](Self::extract)
+    /// doesn't expose per entry: the ZIP comment, the TAR header variant and
+    /// PAX global headers, 7-Zip header flags, the RAR version and volume
+    /// flags, the ar variant and symbol table, RPM header tags, ISO 9660 volume
+    /// descriptor fields, the CAB set and folder compression, gzip header
+    /// fields and Zstandard frame headers.
+    ///
+    /// Compressed TAR archives are decompressed to read their headers. Entry
+    /// data is skipped rather than buffered, except for `.tar.xz`, `.tar.lz`
+    /// and `.tar.lzma` whose stream is decompressed into memory first. The
+    /// extractor's limits apply as they do to [`extract`](Self::extract).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive or its compression stream is invalid,
+    /// if a stream needs too large a decoder window
+    /// ([`ArchiveError::WindowTooLarge`]), if a buffered `.tar.xz`, `.tar.lz`
+    /// or `.tar.lzma` stream exceeds the total size limit
+    /// ([`ArchiveError::TotalSizeTooLarge`]), or if a PAX global header
+    /// exceeds the file size limit ([`ArchiveError::FileTooLarge`]).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let data = vec![0u8; 100];
+    /// let info = ArchiveExtractor::new().inspect(&data, ArchiveFormat::Zip)?;
+    ///
+    /// if let Some(comment) = info.zip.and_then(|zip| zip.comment) {
+    ///     println!("Archive comment: {}", comment);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inspect(&self, data: &[u8], format: ArchiveFormat) -> Result<ArchiveInfo> {
+        info::inspect(
+            data,
+            format,
+            self.password.as_deref(),
+            self.max_file_size,
+            self.max_total_size,
+            self.max_window_size,
+        )
     }
 
-    fn extract_7z(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let mut cursor = Cursor::new(data);
-        let len = cursor.get_ref().len() as u64;
+    /// Tests the integrity of an archive without keeping its contents.
+    ///
+    /// Every entry is decompressed and discarded, like `unzip -t` or `7z t`.
+    /// Along the way the checks each format provides are applied: ZIP, 7-Zip
+    /// and RAR entry checksums, TAR header checksums, the checksums of gzip,
+    /// bzip2, XZ, Zstandard and LZ4 streams, and the declared size of every
+    /// entry.
+    ///
+    /// The configured size limits still apply, since decompressing a zip bomb
+    /// takes time even when nothing is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive can't be opened at all, or
+    /// [`ArchiveError::FileTooLarge`], [`ArchiveError::TotalSizeTooLarge`] or
+    /// [`ArchiveError::WindowTooLarge`] if a limit is exceeded. Corrupt
+    /// entries are reported in the returned [`VerifyReport`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let data = vec![0u8; 100];
+    /// let report = ArchiveExtractor::new().verify(&data, ArchiveFormat::TarGz)?;
+    ///
+    /// if !report.is_ok() {
+    ///     eprintln!("Rejecting corrupt upload");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify(&self, data: &[u8], format: ArchiveFormat) -> Result<VerifyReport> {
+        verify::verify(
+            data,
+            format,
+            self.password.as_deref(),
+            self.max_file_size,
+            self.max_total_size,
+            self.max_window_size,
+        )
+    }
 
-        let mut archive = sevenz_rust::SevenZReader::new(&mut cursor, len, "".into())
-            .map_err(|e| ArchiveError::InvalidArchive(format!("7z error: {}", e)))?;
+    /// The total size limit, which also bounds the input buffered for
+    /// formats that can't be read as a stream.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn max_total_size(&self) -> usize {
+        self.max_total_size
+    }
 
-        let mut files = Vec::new();
+    /// Extracts a format that is read front to back from `reader`, passing
+    /// each entry to `emit` as soon as it is complete.
+    ///
+    /// Only the head of the stream is available for the window check, which
+    /// covers the first XZ block and the Zstandard frames that fit in it. The
+    /// Zstandard decoder enforces the limit on later frames itself.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn extract_reader<R: Read>(
+        &self,
+        mut reader: R,
+        format: ArchiveFormat,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        let mut head = Vec::new();
+        (&mut reader)
+            .take(window::HEAD_SIZE as u64)
+            .read_to_end(&mut head)?;
+        window::check(&head, format, self.max_window_size)?;
+
+        let tracker = Tracker::new(
+            self.progress.as_ref(),
+            self.cancellation.as_ref(),
+            self.max_duration,
+        );
+        let reader = tracker.input(Cursor::new(head).chain(reader));
+        let result = self.read_stream(&tracker, reader, format, None, emit);
+        tracker.finish(result)
+    }
+
+    fn extract_zip(&self, tracker: &Tracker, data: &[u8]) -> Result<Vec<ExtractedFile>> {
+        let reader = tracker.input(Cursor::new(data));
+        let archive = zip::ZipArchive::new(reader)?;
+        let total_size = AtomicUsize::new(0);
+
+        // Handles to the archive are cheap to clone and have their own
+        // position, so entries can be read independently
+        self.map_entries(archive.len(), |index| {
+            self.read_zip_entry(tracker, &mut archive.clone(), index, &total_size)
+        })
+    }
+
+    /// Extracts the entries `0..count` with `extract`, in parallel if enabled,
+    /// returning the results in order.
+    fn map_entries<T: Send>(
+        &self,
+        count: usize,
+        extract: impl Fn(usize) -> Result<T> + Send + Sync,
+    ) -> Result<Vec<T>> {
+        #[cfg(feature = "rayon")]
+        if self.parallel {
+            return (0..count).into_par_iter().map(extract).collect();
+        }
+        (0..count).map(extract).collect()
+    }
+
+    fn read_zip_entry<R: Read + Seek>(
+        &self,
+        tracker: &Tracker,
+        archive: &mut zip::ZipArchive<R>,
+        index: usize,
+        total_size: &AtomicUsize,
+    ) -> Result<ExtractedFile> {
+        // The flag is in the central directory, so it is known before
+        // decrypting; the password must only be used for encrypted entries
+        let encrypted = archive.by_index_raw(index)?.encrypted();
+        let mut file = match &self.password {
+            Some(password) if encrypted => archive.by_index_decrypt(index, password.as_bytes())?,
+            _ => archive.by_index(index)?,
+        };
+        tracker.start_entry(file.name())?;
+        let is_directory = file.is_dir();
+        let mut metadata = zip_entry_metadata(&file);
+
+        let extracted = if !is_directory {
+            self.check_size(file.size() as usize, total_size)?;
+            let (contents, digests) = self.read_contents(tracker, &mut file)?;
+
+            // Symbolic links store their target as the entry's contents
+            if file.is_symlink() {
+                metadata.link = Some(EntryLink::Symbolic(
+                    String::from_utf8_lossy(&contents).to_string(),
+                ));
+            }
+
+            ExtractedFile {
+                path: file.name().to_string(),
+                data: contents,
+                digests,
+                is_directory,
+                metadata,
+            }
+        } else {
+            ExtractedFile {
+                path: file.name().to_string(),
+                data: Vec::new(),
+                digests: Vec::new(),
+                is_directory,
+                metadata,
+            }
+        };
+        tracker.finish_entry()?;
+        Ok(extracted)
+    }
+
+    /// Checks an entry's size against the limits, adding it to `total_size`.
+    ///
+    /// The total is shared between the workers of a parallel extraction, so
+    /// that the limit holds for the archive as a whole.
+    fn check_size(&self, size: usize, total_size: &AtomicUsize) -> Result<()> {
+        if size > self.max_file_size {
+            return Err(ArchiveError::FileTooLarge {
+                size,
+                limit: self.max_file_size,
+            });
+        }
+
+        let total = total_size
+            .fetch_add(size, Ordering::Relaxed)
+            .saturating_add(size);
+        if total > self.max_total_size {
+            return Err(ArchiveError::TotalSizeTooLarge {
+                size: total,
+                limit: self.max_total_size,
+            });
+        }
+        Ok(())
+    }
+
+    fn extract_iso<R: Read + Seek>(
+        &self,
+        tracker: &Tracker,
+        reader: R,
+    ) -> Result<Vec<ExtractedFile>> {
+        let mut image = iso::Image::open(tracker.input(reader))?;
         let mut total_size = 0usize;
-        let mut size_error: Option<ArchiveError> = None;
+        let mut files = Vec::new();
 
-        // Single-pass extraction: validate sizes and extract contents in one iteration
-        let result = archive.for_each_entries(|entry, reader| {
-            if entry.is_directory() {
-                files.push(ExtractedFile {
-                    path: entry.name().to_string(),
-                    data: Vec::new(),
-                    is_directory: true,
+        for entry in image.entries()? {
+            tracker.start_entry(&entry.path)?;
+
+            let size = entry.size as usize;
+            if size > self.max_file_size {
+                return Err(ArchiveError::FileTooLarge {
+                    size,
+                    limit: self.max_file_size,
                 });
+            }
+
+            total_size += size;
+            if total_size > self.max_total_size {
+                return Err(ArchiveError::TotalSizeTooLarge {
+                    size: total_size,
+                    limit: self.max_total_size,
+                });
+            }
+
+            let metadata = EntryMetadata {
+                mode: entry.mode,
+                mtime: entry.mtime,
+                link: entry.link.clone().map(EntryLink::Symbolic),
+                ..EntryMetadata::default()
+            };
+            let (data, digests) = if entry.is_directory {
+                (Vec::new(), Vec::new())
             } else {
-                let size = entry.size() as usize;
+                self.read_contents(tracker, image.contents(&entry))?
+            };
+            files.push(ExtractedFile {
+                path: entry.path,
+                data,
+                digests,
+                is_directory: entry.is_directory,
+                metadata,
+            });
+            tracker.finish_entry()?;
+        }
+
+        Ok(files)
+    }
+
+    /// Extracts the files of a cabinet folder by folder, decompressing each
+    /// folder once, and returns them in the order of the file table.
+    fn extract_cab(&self, tracker: &Tracker, data: &[u8]) -> Result<Vec<ExtractedFile>> {
+        let cabinet = cab::Cabinet::parse(data)?;
+        let mut total_size = 0usize;
+        let mut files = Vec::with_capacity(cabinet.files.len());
+
+        for (folder_index, file_indices) in cabinet.folder_files()?.into_iter().enumerate() {
+            if file_indices.is_empty() {
+                continue;
+            }
+            let folder_data = cabinet.folder_data(data, folder_index)?;
+            let mut reader = cabinet.folder_reader(folder_index, tracker.input(folder_data))?;
+            let compression = cabinet.folders[folder_index].compression;
+
+            for file_index in file_indices {
+                let file = &cabinet.files[file_index];
+                tracker.start_entry(&file.path)?;
+
+                let size = file.size as usize;
                 if size > self.max_file_size {
-                    size_error = Some(ArchiveError::FileTooLarge {
+                    return Err(ArchiveError::FileTooLarge {
                         size,
                         limit: self.max_file_size,
                     });
-                    return Ok(false); // Stop iteration
                 }
 
                 total_size += size;
                 if total_size > self.max_total_size {
-                    size_error = Some(ArchiveError::TotalSizeTooLarge {
+                    return Err(ArchiveError::TotalSizeTooLarge {
                         size: total_size,
                         limit: self.max_total_size,
                     });
+                }
+
+                let (contents, digests) = self.read_contents(tracker, reader.contents(file))?;
+                let metadata = EntryMetadata {
+                    compression_method: Some(compression.name().to_string()),
+                    mtime: file.mtime,
+                    attributes: Some(u32::from(file.attributes)),
+                    ..EntryMetadata::default()
+                };
+                files.push((
+                    file_index,
+                    ExtractedFile {
+                        path: file.path.clone(),
+                        data: contents,
+                        digests,
+                        is_directory: false,
+                        metadata,
+                    },
+                ));
+                tracker.finish_entry()?;
+            }
+        }
+
+        files.sort_by_key(|&(file_index, _)| file_index);
+        Ok(files.into_iter().map(|(_, file)| file).collect())
+    }
+
+    /// Extracts the files of a XAR archive in the order of its table of
+    /// contents.
+    ///
+    /// For [`ArchiveFormat::Pkg`], the `Payload` and `Scripts` of component
+    /// packages are reported as directories holding the files of their cpio
+    /// archives instead.
+    #[cfg(feature = "xar")]
+    fn extract_xar(
+        &self,
+        tracker: &Tracker,
+        data: &[u8],
+        format: ArchiveFormat,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        let archive = xar::Archive::parse(data)?;
+        archive.check_toc()?;
+        let mut total_size = 0usize;
+
+        for entry in &archive.entries {
+            let mut metadata = EntryMetadata {
+                mode: entry.mode,
+                mtime: entry.mtime,
+                ..EntryMetadata::default()
+            };
+            let is_directory = entry.kind == xar::Kind::Directory;
+
+            let (contents, digests) = match (&entry.kind, &entry.data) {
+                (xar::Kind::File, Some(entry_data)) => {
+                    let size = entry_data.size as usize;
+                    if format == ArchiveFormat::Pkg && entry.is_package_archive() {
+                        // Only the expanded files count towards the total size
+                        if size > self.max_total_size {
+                            return Err(ArchiveError::TotalSizeTooLarge {
+                                size,
+                                limit: self.max_total_size,
+                            });
+                        }
+                        // The reader fails as soon as the contents outgrow
+                        // their declared size, checked above
+                        let mut package = Vec::new();
+                        archive
+                            .reader(
+                                &entry.path,
+                                entry_data,
+                                self.max_window_size,
+                                self.max_total_size,
+                            )?
+                            .read_to_end(&mut package)?;
+                        self.expand_package(tracker, entry, &package, &mut total_size, emit)?;
+                        continue;
+                    }
+
+                    tracker.start_entry(&entry.path)?;
+                    if size > self.max_file_size {
+                        return Err(ArchiveError::FileTooLarge {
+                            size,
+                            limit: self.max_file_size,
+                        });
+                    }
+                    total_size += size;
+                    if total_size > self.max_total_size {
+                        return Err(ArchiveError::TotalSizeTooLarge {
+                            size: total_size,
+                            limit: self.max_total_size,
+                        });
+                    }
+
+                    metadata.compressed_size = Some(entry_data.length);
+                    metadata.compression_method = Some(entry_data.encoding.name().to_string());
+                    let reader = archive.reader(
+                        &entry.path,
+                        entry_data,
+                        self.max_window_size,
+                        self.max_file_size,
+                    )?;
+                    self.read_contents(tracker, reader)?
+                }
+                (kind, _) => {
+                    tracker.start_entry(&entry.path)?;
+                    metadata.link = match kind {
+                        xar::Kind::Symlink(target) => Some(EntryLink::Symbolic(target.clone())),
+                        xar::Kind::Hardlink(target) => Some(EntryLink::Hard(target.clone())),
+                        _ => None,
+                    };
+                    if is_directory {
+                        (Vec::new(), Vec::new())
+                    } else {
+                        (Vec::new(), self.digest_of(&[]))
+                    }
+                }
+            };
+
+            emit(ExtractedFile {
+                path: entry.path.clone(),
+                data: contents,
+                digests,
+                is_directory,
+                metadata,
+            })?;
+            tracker.finish_entry()?;
+        }
+
+        Ok(())
+    }
+
+    /// Extracts the entries of a SquashFS image in the order of a walk over
+    /// its directory tree, with each directory before its contents.
+    fn extract_squashfs(&self, tracker: &Tracker, data: &[u8]) -> Result<Vec<ExtractedFile>> {
+        let image = squashfs::Image::parse(data, self.max_window_size)?;
+        let mut total_size = 0usize;
+        let mut files = Vec::with_capacity(image.entries.len());
+
+        for entry in &image.entries {
+            tracker.start_entry(&entry.path)?;
+            let mut metadata = EntryMetadata {
+                mode: Some(entry.mode),
+                mtime: Some(entry.mtime),
+                ..EntryMetadata::default()
+            };
+
+            let (contents, digests) = match &entry.kind {
+                squashfs::Kind::File(file) => {
+                    let size = usize::try_from(file.size).unwrap_or(usize::MAX);
+                    if size > self.max_file_size {
+                        return Err(ArchiveError::FileTooLarge {
+                            size,
+                            limit: self.max_file_size,
+                        });
+                    }
+                    total_size = total_size.saturating_add(size);
+                    if total_size > self.max_total_size {
+                        return Err(ArchiveError::TotalSizeTooLarge {
+                            size: total_size,
+                            limit: self.max_total_size,
+                        });
+                    }
+                    metadata.compression_method = Some(image.compression().name().to_string());
+                    self.read_contents(tracker, image.reader(file))?
+                }
+                squashfs::Kind::Directory => (Vec::new(), Vec::new()),
+                squashfs::Kind::Symlink(target) => {
+                    metadata.link = Some(EntryLink::Symbolic(target.clone()));
+                    (Vec::new(), self.digest_of(&[]))
+                }
+                squashfs::Kind::Hardlink(target) => {
+                    metadata.link = Some(EntryLink::Hard(target.clone()));
+                    (Vec::new(), self.digest_of(&[]))
+                }
+                squashfs::Kind::Special(special) => {
+                    metadata.special = Some(*special);
+                    (Vec::new(), self.digest_of(&[]))
+                }
+            };
+
+            files.push(ExtractedFile {
+                path: entry.path.clone(),
+                data: contents,
+                digests,
+                is_directory: entry.kind == squashfs::Kind::Directory,
+                metadata,
+            });
+            tracker.finish_entry()?;
+        }
+
+        Ok(files)
+    }
+
+    /// Reports the `Payload` or `Scripts` of a component package as a
+    /// directory, followed by the files of its cpio archive beneath it.
+    #[cfg(feature = "xar")]
+    fn expand_package(
+        &self,
+        tracker: &Tracker,
+        entry: &xar::Entry,
+        package: &[u8],
+        total_size: &mut usize,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        tracker.start_entry(&entry.path)?;
+        emit(ExtractedFile {
+            path: entry.path.clone(),
+            data: Vec::new(),
+            digests: Vec::new(),
+            is_directory: true,
+            metadata: EntryMetadata {
+                mode: entry.mode,
+                mtime: entry.mtime,
+                ..EntryMetadata::default()
+            },
+        })?;
+        tracker.finish_entry()?;
+
+        let reader = xar::package_reader(package, self.max_window_size, self.max_total_size)?;
+        self.process_cpio_entries(tracker, &mut cpio::Archive::new(reader), &mut |mut file| {
+            // The root directory of the payload is the entry itself
+            let Some(path) = entry.expanded_path(&file.path) else {
+                return Ok(());
+            };
+            *total_size += file.data.len();
+            if *total_size > self.max_total_size {
+                return Err(ArchiveError::TotalSizeTooLarge {
+                    size: *total_size,
+                    limit: self.max_total_size,
+                });
+            }
+            file.path = path;
+            if let Some(EntryLink::Hard(target)) = &mut file.metadata.link
+                && let Some(path) = entry.expanded_path(target)
+            {
+                *target = path;
+            }
+            emit(file)
+        })
+    }
+
+    /// Extracts the entries of a format that is read front to back from
+    /// `data`.
+    fn extract_sequential(
+        &self,
+        tracker: &Tracker,
+        data: &[u8],
+        format: ArchiveFormat,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        let input_size = Some(data.len() as u64);
+
+        #[cfg(feature = "rayon")]
+        if self.parallel
+            && let Some(decompressed) = self.decompress_parallel(tracker, data, format)
+        {
+            return self.read_decompressed(tracker, data, decompressed?, format, emit);
+        }
+
+        let reader = tracker.input(Cursor::new(data));
+        self.read_stream(tracker, reader, format, input_size, emit)
+    }
+
+    /// Decompresses `data` on the rayon thread pool if it consists of
+    /// independently compressed parts.
+    ///
+    /// The decompressed stream is limited to the maximum file size for
+    /// single-file formats, and to the maximum total size for TAR archives.
+    #[cfg(feature = "rayon")]
+    fn decompress_parallel(
+        &self,
+        tracker: &Tracker,
+        data: &[u8],
+        format: ArchiveFormat,
+    ) -> Option<Result<Vec<u8>>> {
+        let window_limit = self.max_window_size;
+        match format {
+            ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::TarZst => {
+                let limit = self.max_total_size;
+                parallel::decompress(tracker, data, format, window_limit, limit, |size| {
+                    ArchiveError::TotalSizeTooLarge { size, limit }
+                })
+            }
+            _ => {
+                let limit = self.max_file_size;
+                parallel::decompress(tracker, data, format, window_limit, limit, |size| {
+                    ArchiveError::FileTooLarge { size, limit }
+                })
+            }
+        }
+    }
+
+    /// Extracts the entries from a stream decompressed by
+    /// [`decompress_parallel`](Self::decompress_parallel).
+    #[cfg(feature = "rayon")]
+    fn read_decompressed(
+        &self,
+        tracker: &Tracker,
+        data: &[u8],
+        decompressed: Vec<u8>,
+        format: ArchiveFormat,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        let input_size = Some(data.len() as u64);
+        let (path, metadata) = match format {
+            ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::TarZst => {
+                let mut archive = tar::Archive::new(Cursor::new(decompressed));
+                return self.process_tar_entries(tracker, &mut archive, emit);
+            }
+            ArchiveFormat::Gz => {
+                gzip_entry(flate2::read::GzDecoder::new(data).header(), input_size)
+            }
+            _ => ("data".to_string(), single_file_metadata(input_size, format)),
+        };
+
+        tracker.start_entry(&path)?;
+        let digests = self.digest_of(&decompressed);
+        emit(self.single_file(tracker, path, decompressed, digests, metadata)?)
+    }
+
+    /// Extracts the entries of a format that is read front to back, passing
+    /// each one to `emit` as soon as it is complete.
+    ///
+    /// `input_size` is the size of the archive data if it is known, which
+    /// single-file formats report as the compressed size of their entry.
+    fn read_stream<R: Read>(
+        &self,
+        tracker: &Tracker,
+        reader: R,
+        format: ArchiveFormat,
+        input_size: Option<u64>,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        match format {
+            ArchiveFormat::Tar => {
+                self.process_tar_entries(tracker, &mut tar::Archive::new(reader), emit)
+            }
+            ArchiveFormat::Ar | ArchiveFormat::Deb => {
+                self.process_ar_entries(tracker, &mut ar::Archive::new(reader), emit)
+            }
+            ArchiveFormat::Cpio => {
+                self.process_cpio_entries(tracker, &mut cpio::Archive::new(reader), emit)
+            }
+            ArchiveFormat::Rpm => {
+                let mut reader = reader;
+                let compression = rpm::Package::read(&mut reader)?.compression()?;
+                let payload = rpm::payload_reader(
+                    reader,
+                    compression,
+                    self.max_window_size,
+                    self.max_total_size,
+                )?;
+                self.process_cpio_entries(tracker, &mut cpio::Archive::new(payload), emit)
+            }
+            ArchiveFormat::TarGz => {
+                let decoder = flate2::read::MultiGzDecoder::new(reader);
+                self.process_tar_entries(tracker, &mut tar::Archive::new(decoder), emit)
+            }
+            ArchiveFormat::TarBz2 => {
+                let decoder = bzip2::read::BzDecoder::new(reader);
+                self.process_tar_entries(tracker, &mut tar::Archive::new(decoder), emit)
+            }
+            ArchiveFormat::TarXz | ArchiveFormat::TarLz | ArchiveFormat::TarLzma => {
+                // lzma-rs only decompresses into a writer, so the TAR stream
+                // is buffered, bounded by the total size limit
+                let mut output =
+                    tracker.writer(LimitedWriter::new(Vec::new(), self.max_total_size));
+                let mut input = BufReader::with_capacity(LZMA_INPUT_BUFFER_SIZE, reader);
+                let result =
+                    lzma::decompress(format, &mut input, &mut output, self.max_window_size);
+                let output = output.into_inner();
+                if let Some(size) = output.exceeded {
+                    return Err(ArchiveError::TotalSizeTooLarge {
+                        size,
+                        limit: self.max_total_size,
+                    });
+                }
+                result?;
+                let cursor = Cursor::new(output.inner);
+                self.process_tar_entries(tracker, &mut tar::Archive::new(cursor), emit)
+            }
+            ArchiveFormat::TarZst => {
+                let decoder = window::zstd_decoder(reader, self.max_window_size)?;
+                self.process_tar_entries(tracker, &mut tar::Archive::new(decoder), emit)
+            }
+            ArchiveFormat::TarLz4 => {
+                let decoder = lz4::Decoder::new(reader)?;
+                self.process_tar_entries(tracker, &mut tar::Archive::new(decoder), emit)
+            }
+            ArchiveFormat::TarZ => {
+                let decoder = lzw::Decoder::new(reader)?;
+                self.process_tar_entries(tracker, &mut tar::Archive::new(decoder), emit)
+            }
+            #[cfg(feature = "brotli")]
+            ArchiveFormat::TarBr => {
+                let decoder = brotli::Decompressor::new(reader, 4096);
+                self.process_tar_entries(tracker, &mut tar::Archive::new(decoder), emit)
+            }
+            ArchiveFormat::Gz => self.read_single_gz(tracker, reader, input_size, emit),
+            ArchiveFormat::Bz2 => {
+                let decoder = bzip2::read::BzDecoder::new(reader);
+                let metadata = single_file_metadata(input_size, format);
+                self.read_single(tracker, decoder, "data".to_string(), metadata, emit)
+            }
+            ArchiveFormat::Xz | ArchiveFormat::Lz | ArchiveFormat::Lzma => {
+                self.read_single_lzma(tracker, reader, format, input_size, emit)
+            }
+            ArchiveFormat::Lz4 => {
+                let decoder = lz4::Decoder::new(reader)?;
+                let metadata = single_file_metadata(input_size, format);
+                self.read_single(tracker, decoder, "data".to_string(), metadata, emit)
+            }
+            ArchiveFormat::Zst => {
+                let decoder = window::zstd_decoder(reader, self.max_window_size)?;
+                let metadata = single_file_metadata(input_size, format);
+                self.read_single(tracker, decoder, "data".to_string(), metadata, emit)
+            }
+            ArchiveFormat::Compress => {
+                let decoder = lzw::Decoder::new(reader)?;
+                let metadata = single_file_metadata(input_size, format);
+                self.read_single(tracker, decoder, "data".to_string(), metadata, emit)
+            }
+            #[cfg(feature = "brotli")]
+            ArchiveFormat::Br => {
+                let decoder = brotli::Decompressor::new(reader, 4096);
+                let metadata = single_file_metadata(input_size, format);
+                self.read_single(tracker, decoder, "data".to_string(), metadata, emit)
+            }
+            #[cfg(not(feature = "brotli"))]
+            ArchiveFormat::Br | ArchiveFormat::TarBr => Err(format.feature_disabled("brotli")),
+            #[cfg(feature = "snappy")]
+            ArchiveFormat::Snappy => {
+                let decoder = snap::read::FrameDecoder::new(reader);
+                let metadata = single_file_metadata(input_size, format);
+                self.read_single(tracker, decoder, "data".to_string(), metadata, emit)
+            }
+            #[cfg(not(feature = "snappy"))]
+            ArchiveFormat::Snappy => Err(format.feature_disabled("snappy")),
+            ArchiveFormat::Zip
+            | ArchiveFormat::SevenZ
+            | ArchiveFormat::Rar
+            | ArchiveFormat::Iso
+            | ArchiveFormat::Cab
+            | ArchiveFormat::Xar
+            | ArchiveFormat::Pkg
+            | ArchiveFormat::SquashFs => Err(ArchiveError::UnsupportedFormat(format!(
+                "{} archives can't be read as a stream",
+                format
+            ))),
+        }
+    }
+
+    fn extract_7z(&self, tracker: &Tracker, data: &[u8]) -> Result<Vec<ExtractedFile>> {
+        let mut cursor = tracker.input(Cursor::new(data));
+        let len = data.len() as u64;
+
+        let password = self.sevenz_password();
+        let archive = sevenz_rust::Archive::read(&mut cursor, len, password.as_slice())
+            .map_err(|e| ArchiveError::InvalidArchive(format!("7z error: {}", e)))?;
+        let total_size = AtomicUsize::new(0);
+
+        // Blocks are compressed independently, so they can be decoded
+        // independently, each through its own reader
+        let blocks = self.map_entries(archive.folders.len(), |folder_index| {
+            self.read_7z_block(tracker, &archive, data, folder_index, &total_size)
+        })?;
+        let mut files: Vec<ExtractedFile> = blocks.into_iter().flatten().collect();
+
+        // Directories and empty files are not stored in any block
+        for (file_index, entry) in archive.files.iter().enumerate() {
+            if archive.stream_map.file_folder_index[file_index].is_none() {
+                let digests = if entry.is_directory() {
+                    Vec::new()
+                } else {
+                    self.digest_of(&[])
+                };
+                tracker.start_entry(entry.name())?;
+                files.push(ExtractedFile {
+                    path: entry.name().to_string(),
+                    data: Vec::new(),
+                    digests,
+                    is_directory: entry.is_directory(),
+                    metadata: sevenz_entry_metadata(entry),
+                });
+                tracker.finish_entry()?;
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Returns the password in the UTF-16 encoding 7-Zip derives keys from.
+    fn sevenz_password(&self) -> sevenz_rust::Password {
+        self.password.as_deref().unwrap_or_default().into()
+    }
+
+    /// Decodes the entries stored in one block of a 7-Zip archive.
+    ///
+    /// Decoding block by block lets each entry be matched with the coders and
+    /// packed size of the block it is stored in.
+    fn read_7z_block(
+        &self,
+        tracker: &Tracker,
+        archive: &sevenz_rust::Archive,
+        data: &[u8],
+        folder_index: usize,
+        total_size: &AtomicUsize,
+    ) -> Result<Vec<ExtractedFile>> {
+        let mut cursor = tracker.input(Cursor::new(data));
+        let folder_metadata = sevenz_folder_metadata(archive, folder_index);
+        let password = self.sevenz_password();
+        let decoder =
+            sevenz_rust::BlockDecoder::new(folder_index, archive, password.as_slice(), &mut cursor);
+        let mut files = Vec::new();
+        let mut size_error: Option<ArchiveError> = None;
+
+        // Single-pass extraction: validate sizes and extract contents in one iteration
+        let result = decoder.for_each_entries(&mut |entry, reader| {
+            tracker.start_entry(entry.name())?;
+            let mut metadata = EntryMetadata {
+                compressed_size: folder_metadata.compressed_size,
+                compression_method: folder_metadata.compression_method.clone(),
+                encrypted: folder_metadata.encrypted,
+                ..sevenz_entry_metadata(entry)
+            };
+
+            if entry.is_directory() {
+                files.push(ExtractedFile {
+                    path: entry.name().to_string(),
+                    data: Vec::new(),
+                    digests: Vec::new(),
+                    is_directory: true,
+                    metadata,
+                });
+            } else {
+                if let Err(e) = self.check_size(entry.size() as usize, total_size) {
+                    size_error = Some(e);
                     return Ok(false); // Stop iteration
                 }
 
-                let mut contents = Vec::new();
-                reader.read_to_end(&mut contents)?;
+                let (contents, digests) = self.read_contents(tracker, reader)?;
+
+                if metadata.mode.is_some() && is_symlink_mode(entry.windows_attributes >> 16) {
+                    metadata.link = Some(EntryLink::Symbolic(
+                        String::from_utf8_lossy(&contents).to_string(),
+                    ));
+                }
 
                 files.push(ExtractedFile {
                     path: entry.name().to_string(),
                     data: contents,
+                    digests,
                     is_directory: false,
+                    metadata,
                 });
             }
+            tracker.finish_entry()?;
             Ok(true)
         });
 
@@ -491,131 +1779,245 @@ impl ArchiveExtractor {
 
         // Check for other extraction errors
         result.map_err(|e| ArchiveError::InvalidArchive(format!("7z extraction error: {}", e)))?;
+        Ok(files)
+    }
+
+    #[cfg(feature = "rar")]
+    fn extract_rar(&self, tracker: &Tracker, data: &[u8]) -> Result<Vec<ExtractedFile>> {
+        let options = rar::read_options(
+            self.password.as_deref(),
+            self.max_file_size,
+            self.max_total_size,
+            self.max_window_size,
+        );
+        let archive = rar::read(data, options)?;
+        let total_size = AtomicUsize::new(0);
+        let contents = rar::SharedBuffer::default();
+        let mut files = Vec::new();
+        let mut pending = None;
+        let mut error: Option<ArchiveError> = None;
+
+        let result = archive.extract_with_control(options, |member| {
+            let decision = self.visit_rar_member(
+                tracker,
+                member,
+                &total_size,
+                &contents,
+                &mut pending,
+                &mut files,
+            );
+            decision.or_else(|e| {
+                error = Some(e);
+                Ok(ExtractionDecision::Stop)
+            })
+        });
 
+        if let Some(err) = error {
+            return Err(err);
+        }
+        result.map_err(rar::map_error)?;
+
+        if let Some(entry) = pending {
+            files.push(self.finish_rar_entry(tracker, entry, &contents)?);
+        }
         Ok(files)
     }
 
-    // Single-file decompression methods
+    /// Decides how to extract a RAR member, completing the previous one.
+    ///
+    /// `rars` only reports the start of each member, so the member whose
+    /// contents were written last is complete once the next one is visited.
+    #[cfg(feature = "rar")]
+    fn visit_rar_member(
+        &self,
+        tracker: &Tracker,
+        member: &rars::ArchiveMember,
+        total_size: &AtomicUsize,
+        contents: &rar::SharedBuffer,
+        pending: &mut Option<PendingRarEntry>,
+        files: &mut Vec<ExtractedFile>,
+    ) -> Result<ExtractionDecision> {
+        if let Some(entry) = pending.take() {
+            files.push(self.finish_rar_entry(tracker, entry, contents)?);
+        }
 
-    fn extract_single_gz(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut decoder = flate2::read::GzDecoder::new(cursor);
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
+        let path = rar::entry_path(member);
+        tracker.start_entry(&path)?;
+        rar::check_member(member, self.password.is_some())?;
+        let metadata = rar::entry_metadata(member);
 
-        if decompressed.len() > self.max_file_size {
-            return Err(ArchiveError::FileTooLarge {
-                size: decompressed.len(),
-                limit: self.max_file_size,
+        // Directories and RAR 5.0 links have no contents
+        if member.meta.is_directory || member.meta.is_redirection {
+            files.push(ExtractedFile {
+                path,
+                data: Vec::new(),
+                digests: Vec::new(),
+                is_directory: member.meta.is_directory,
+                metadata,
             });
+            tracker.finish_entry()?;
+            return Ok(ExtractionDecision::Skip);
         }
 
-        // Try to extract original filename from gzip header
-        let path = decoder
-            .header()
-            .and_then(|h| h.filename())
-            .and_then(|f| std::str::from_utf8(f).ok())
-            .unwrap_or("data")
-            .to_string();
-
-        Ok(vec![ExtractedFile {
+        self.check_size(member.meta.unpacked_size as usize, total_size)?;
+        *pending = Some(PendingRarEntry {
+            legacy_symlink: rar::is_legacy_symlink(member),
             path,
-            data: decompressed,
-            is_directory: false,
-        }])
+            metadata,
+        });
+        Ok(ExtractionDecision::Extract(Box::new(contents.clone())))
     }
 
-    fn extract_single_bz2(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut decoder = bzip2::read::BzDecoder::new(cursor);
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
-
-        if decompressed.len() > self.max_file_size {
-            return Err(ArchiveError::FileTooLarge {
-                size: decompressed.len(),
-                limit: self.max_file_size,
-            });
+    #[cfg(feature = "rar")]
+    fn finish_rar_entry(
+        &self,
+        tracker: &Tracker,
+        entry: PendingRarEntry,
+        contents: &rar::SharedBuffer,
+    ) -> Result<ExtractedFile> {
+        let data = contents.take();
+        let mut metadata = entry.metadata;
+        tracker.advance(metadata.compressed_size.unwrap_or(0), data.len() as u64)?;
+
+        if entry.legacy_symlink {
+            metadata.link = Some(EntryLink::Symbolic(
+                String::from_utf8_lossy(&data).to_string(),
+            ));
         }
 
-        Ok(vec![ExtractedFile {
-            path: "data".to_string(),
-            data: decompressed,
+        let digests = self.digest_of(&data);
+        tracker.finish_entry()?;
+        Ok(ExtractedFile {
+            path: entry.path,
+            data,
+            digests,
             is_directory: false,
-        }])
+            metadata,
+        })
     }
 
-    fn extract_single_xz(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let mut cursor = Cursor::new(data);
-        let mut decompressed = Vec::new();
-        lzma_rs::xz_decompress(&mut cursor, &mut decompressed)
-            .map_err(|e| ArchiveError::InvalidArchive(e.to_string()))?;
+    // Single-file decompression methods
 
-        if decompressed.len() > self.max_file_size {
+    fn read_single_gz<R: Read>(
+        &self,
+        tracker: &Tracker,
+        reader: R,
+        input_size: Option<u64>,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        // Concatenated members, as written by BGZF and parallel gzip tools,
+        // are one file. The header is parsed when the decoder is created.
+        let decoder = flate2::read::MultiGzDecoder::new(reader);
+        let (path, metadata) = gzip_entry(decoder.header(), input_size);
+        self.read_single(tracker, decoder, path, metadata, emit)
+    }
+
+    /// Reads the entry of an XZ, lzip or `.lzma` file, which lzma-rs can only
+    /// decompress in full.
+    fn read_single_lzma<R: Read>(
+        &self,
+        tracker: &Tracker,
+        reader: R,
+        format: ArchiveFormat,
+        input_size: Option<u64>,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        tracker.start_entry("data")?;
+        let mut output = tracker.writer(LimitedWriter::new(Vec::new(), self.max_file_size));
+        let mut input = BufReader::with_capacity(LZMA_INPUT_BUFFER_SIZE, reader);
+        let result = lzma::decompress(format, &mut input, &mut output, self.max_window_size);
+        let output = output.into_inner();
+        if let Some(size) = output.exceeded {
             return Err(ArchiveError::FileTooLarge {
-                size: decompressed.len(),
+                size,
                 limit: self.max_file_size,
             });
         }
+        result?;
 
-        Ok(vec![ExtractedFile {
-            path: "data".to_string(),
-            data: decompressed,
-            is_directory: false,
-        }])
+        let decompressed = output.inner;
+        let digests = self.digest_of(&decompressed);
+        let metadata = single_file_metadata(input_size, format);
+        emit(self.single_file(tracker, "data".to_string(), decompressed, digests, metadata)?)
     }
 
-    fn extract_single_lz4(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut decoder = lz4::Decoder::new(cursor)?;
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
+    /// Reads the entry of a single-file format from its decoder.
+    ///
+    /// Decoding stops one byte past the maximum file size, which is enough
+    /// to reject the entry without decompressing the rest of the stream.
+    fn read_single<R: Read>(
+        &self,
+        tracker: &Tracker,
+        decoder: R,
+        path: String,
+        metadata: EntryMetadata,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        tracker.start_entry(&path)?;
+        let limit = (self.max_file_size as u64).saturating_add(1);
+        let (decompressed, digests) = self.read_contents(tracker, decoder.take(limit))?;
+        emit(self.single_file(tracker, path, decompressed, digests, metadata)?)
+    }
 
-        if decompressed.len() > self.max_file_size {
+    /// Completes the entry of a single-file format once it is decompressed.
+    fn single_file(
+        &self,
+        tracker: &Tracker,
+        path: String,
+        data: Vec<u8>,
+        digests: Vec<Digest>,
+        metadata: EntryMetadata,
+    ) -> Result<ExtractedFile> {
+        if data.len() > self.max_file_size {
             return Err(ArchiveError::FileTooLarge {
-                size: decompressed.len(),
+                size: data.len(),
                 limit: self.max_file_size,
             });
         }
 
-        Ok(vec![ExtractedFile {
-            path: "data".to_string(),
-            data: decompressed,
+        tracker.finish_entry()?;
+        Ok(ExtractedFile {
+            path,
+            data,
+            digests,
             is_directory: false,
-        }])
+            metadata,
+        })
     }
 
-    fn extract_single_zst(&self, data: &[u8]) -> Result<Vec<ExtractedFile>> {
-        let cursor = Cursor::new(data);
-        let mut decoder = zstd::stream::read::Decoder::new(cursor)?;
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
-
-        if decompressed.len() > self.max_file_size {
-            return Err(ArchiveError::FileTooLarge {
-                size: decompressed.len(),
-                limit: self.max_file_size,
-            });
-        }
+    /// Reads an entry's contents, computing the configured digests on the way.
+    fn read_contents<R: Read>(
+        &self,
+        tracker: &Tracker,
+        reader: R,
+    ) -> std::io::Result<(Vec<u8>, Vec<Digest>)> {
+        let mut reader = HashingReader::new(tracker.output(reader), &self.hashes);
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+        Ok((contents, reader.finish()))
+    }
 
-        Ok(vec![ExtractedFile {
-            path: "data".to_string(),
-            data: decompressed,
-            is_directory: false,
-        }])
+    /// Computes the configured digests of data that was decompressed in one go.
+    fn digest_of(&self, data: &[u8]) -> Vec<Digest> {
+        let mut hasher = Hasher::new(&self.hashes);
+        hasher.update(data);
+        hasher.finish()
     }
 
     fn process_tar_entries<R: Read>(
         &self,
+        tracker: &Tracker,
         archive: &mut tar::Archive<R>,
-    ) -> Result<Vec<ExtractedFile>> {
-        let mut files = Vec::new();
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
         let mut total_size = 0usize;
 
         for entry_result in archive.entries()? {
             let mut entry = entry_result?;
             let path = entry.path()?.to_string_lossy().to_string();
+            tracker.start_entry(&path)?;
             let is_directory = entry.header().entry_type().is_dir();
+            let metadata = tar_entry_metadata(&entry)?;
 
             if !is_directory {
                 let size = entry.size() as usize;
@@ -634,36 +2036,43 @@ impl ArchiveExtractor {
                     });
                 }
 
-                let mut contents = Vec::new();
-                entry.read_to_end(&mut contents)?;
+                let (contents, digests) = self.read_contents(tracker, &mut entry)?;
 
-                files.push(ExtractedFile {
+                emit(ExtractedFile {
                     path,
                     data: contents,
+                    digests,
                     is_directory,
-                });
+                    metadata,
+                })?;
             } else {
-                files.push(ExtractedFile {
+                emit(ExtractedFile {
                     path,
                     data: Vec::new(),
+                    digests: Vec::new(),
                     is_directory,
-                });
+                    metadata,
+                })?;
             }
+            tracker.finish_entry()?;
         }
 
-        Ok(files)
+        Ok(())
     }
 
     fn process_ar_entries<R: Read>(
         &self,
+        tracker: &Tracker,
         archive: &mut ar::Archive<R>,
-    ) -> Result<Vec<ExtractedFile>> {
-        let mut files = Vec::new();
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
         let mut total_size = 0usize;
 
-        while let Some(entry_result) = archive.next_entry(){
+        while let Some(entry_result) = archive.next_entry() {
             let mut entry = entry_result?;
             let path = String::from_utf8_lossy(entry.header().identifier()).to_string();
+            tracker.start_entry(&path)?;
+            let metadata = ar_entry_metadata(entry.header());
 
             let size = entry.header().size() as usize;
             if size > self.max_file_size {
@@ -681,17 +2090,299 @@ impl ArchiveExtractor {
                 });
             }
 
-            let mut contents = Vec::new();
-            entry.read_to_end(&mut contents)?;
+            let (contents, digests) = self.read_contents(tracker, &mut entry)?;
 
-            files.push(ExtractedFile {
+            emit(ExtractedFile {
                 path,
                 data: contents,
+                digests,
                 is_directory: false,
-            });
+                metadata,
+            })?;
+            tracker.finish_entry()?;
         }
 
-        Ok(files)
+        Ok(())
+    }
+
+    fn process_cpio_entries<R: Read>(
+        &self,
+        tracker: &Tracker,
+        archive: &mut cpio::Archive<R>,
+        emit: &mut Emit<'_>,
+    ) -> Result<()> {
+        let mut total_size = 0usize;
+        let mut links = cpio::HardLinks::default();
+
+        while let Some(entry_result) = archive.next_entry() {
+            let mut entry = entry_result?;
+            let header = entry.header().clone();
+            let path = header.path();
+            tracker.start_entry(&path)?;
+            let is_directory = header.is_directory();
+            let mut metadata = cpio_entry_metadata(&header);
+
+            let size = header.size() as usize;
+            if size > self.max_file_size {
+                return Err(ArchiveError::FileTooLarge {
+                    size,
+                    limit: self.max_file_size,
+                });
+            }
+
+            total_size += size;
+            if total_size > self.max_total_size {
+                return Err(ArchiveError::TotalSizeTooLarge {
+                    size: total_size,
+                    limit: self.max_total_size,
+                });
+            }
+
+            let file = if is_directory {
+                ExtractedFile {
+                    path,
+                    data: Vec::new(),
+                    digests: Vec::new(),
+                    is_directory,
+                    metadata,
+                }
+            } else if header.is_symlink() {
+                // Symbolic links store their target as the entry's contents,
+                // which is reported as a link like in TAR archives
+                let mut target = Vec::new();
+                entry.read_to_end(&mut target)?;
+                metadata.link = Some(EntryLink::Symbolic(
+                    String::from_utf8_lossy(&target).to_string(),
+                ));
+                ExtractedFile {
+                    path,
+                    data: Vec::new(),
+                    digests: self.digest_of(&[]),
+                    is_directory,
+                    metadata,
+                }
+            } else {
+                let (contents, digests) = self.read_contents(tracker, &mut entry)?;
+                ExtractedFile {
+                    path,
+                    data: contents,
+                    digests,
+                    is_directory,
+                    metadata,
+                }
+            };
+            tracker.finish_entry()?;
+
+            for file in links.add(&header, file) {
+                emit(file)?;
+            }
+        }
+
+        for file in links.finish() {
+            emit(file)?;
+        }
+        Ok(())
+    }
+}
+
+/// A RAR member whose contents are being written.
+#[cfg(feature = "rar")]
+struct PendingRarEntry {
+    path: String,
+    metadata: EntryMetadata,
+    legacy_symlink: bool,
+}
+
+/// Collects the archive-stored metadata of a ZIP entry.
+fn zip_entry_metadata<R: Read>(file: &zip::read::ZipFile<'_, R>) -> EntryMetadata {
+    EntryMetadata {
+        compressed_size: Some(file.compressed_size()),
+        crc32: Some(file.crc32()),
+        compression_method: Some(file.compression().to_string()),
+        encrypted: file.encrypted(),
+        comment: Some(file.comment())
+            .filter(|comment| !comment.is_empty())
+            .map(str::to_string),
+        extra_field_ids: file.extra_data().map(extra_field_ids).unwrap_or_default(),
+        mode: file.unix_mode().map(|mode| mode & 0o7777),
+        mtime: zip_entry_mtime(file),
+        link: None,
+        attributes: None,
+        special: None,
+    }
+}
+
+/// Returns the modification time of a ZIP entry, preferring the UTC extended
+/// timestamp over the MS-DOS time.
+fn zip_entry_mtime<R: Read>(file: &zip::read::ZipFile<'_, R>) -> Option<u64> {
+    let extended = file.extra_data_fields().find_map(|field| match field {
+        zip::extra_fields::ExtraField::ExtendedTimestamp(timestamp) => timestamp.mod_time(),
+        _ => None,
+    });
+    if let Some(mtime) = extended {
+        return Some(mtime as u64);
+    }
+
+    let time = file.last_modified()?;
+    let days = days_from_civil(time.year() as i64, time.month() as i64, time.day() as i64);
+    let seconds =
+        days * 86400 + time.hour() as i64 * 3600 + time.minute() as i64 * 60 + time.second() as i64;
+    u64::try_from(seconds).ok()
+}
+
+/// Returns the number of days between 1970-01-01 and the given proleptic
+/// Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Shift the year to start in March so that the leap day comes last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Collects the Unix attributes and modification time of a 7-Zip entry.
+fn sevenz_entry_metadata(entry: &sevenz_rust::SevenZArchiveEntry) -> EntryMetadata {
+    // 7-Zip stores Unix mode bits in the high word when bit 15 is set
+    let unix_mode = (entry.has_windows_attributes && entry.windows_attributes & 0x8000 != 0)
+        .then_some(entry.windows_attributes >> 16);
+
+    EntryMetadata {
+        crc32: entry.has_crc.then_some(entry.crc as u32),
+        mode: unix_mode.map(|mode| mode & 0o7777),
+        mtime: entry
+            .has_last_modified_date
+            .then(|| u64::try_from(entry.last_modified_date.to_unix_time()).ok())
+            .flatten(),
+        ..EntryMetadata::default()
+    }
+}
+
+/// Whether Unix mode bits, including the file type, describe a symbolic link.
+fn is_symlink_mode(mode: u32) -> bool {
+    mode & 0o170000 == 0o120000
+}
+
+/// Collects the permissions, modification time and link target of a TAR entry.
+fn tar_entry_metadata<R: Read>(entry: &tar::Entry<'_, R>) -> Result<EntryMetadata> {
+    let header = entry.header();
+    let entry_type = header.entry_type();
+
+    let link = if entry_type.is_symlink() || entry_type.is_hard_link() {
+        let target = entry
+            .link_name()?
+            .map(|target| target.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Some(if entry_type.is_symlink() {
+            EntryLink::Symbolic(target)
+        } else {
+            EntryLink::Hard(target)
+        })
+    } else {
+        None
+    };
+
+    Ok(EntryMetadata {
+        mode: header.mode().ok().map(|mode| mode & 0o7777),
+        mtime: header.mtime().ok(),
+        link,
+        ..EntryMetadata::default()
+    })
+}
+
+/// Collects the permissions and modification time of a cpio entry.
+fn cpio_entry_metadata(header: &cpio::Header) -> EntryMetadata {
+    EntryMetadata {
+        mode: Some(header.mode()),
+        mtime: Some(header.mtime()),
+        ..EntryMetadata::default()
+    }
+}
+
+/// Collects the permissions and modification time of an ar member.
+fn ar_entry_metadata(header: &ar::Header) -> EntryMetadata {
+    EntryMetadata {
+        mode: Some(header.mode() & 0o7777),
+        mtime: Some(header.mtime()),
+        ..EntryMetadata::default()
+    }
+}
+
+/// Parses the header IDs out of a raw ZIP extra field, which is a sequence of
+/// little-endian `(id: u16, size: u16, data)` records.
+fn extra_field_ids(mut extra: &[u8]) -> Vec<u16> {
+    let mut ids = Vec::new();
+    while extra.len() >= 4 {
+        ids.push(u16::from_le_bytes([extra[0], extra[1]]));
+        let size = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        extra = extra.get(4 + size..).unwrap_or_default();
+    }
+    ids
+}
+
+/// Collects the metadata shared by all entries of a 7-Zip block: the coder
+/// chain, whether it includes AES encryption and, for blocks holding a single
+/// entry, the packed size.
+fn sevenz_folder_metadata(archive: &sevenz_rust::Archive, folder_index: usize) -> EntryMetadata {
+    let folder = &archive.folders[folder_index];
+
+    let mut encrypted = false;
+    let mut methods = Vec::new();
+    for coder in &folder.coders {
+        let id = coder.decompression_method_id();
+        if id == sevenz_rust::SevenZMethod::ID_AES256SHA256 {
+            encrypted = true;
+            continue;
+        }
+        methods.push(
+            sevenz_rust::SevenZMethod::by_id(id)
+                .map(|method| method.name().to_string())
+                .unwrap_or_else(|| format!("{:02X?}", id)),
+        );
+    }
+
+    let compressed_size = (folder.num_unpack_sub_streams == 1).then(|| {
+        let first = archive.stream_map.folder_first_pack_stream_index[folder_index];
+        archive.pack_sizes[first..first + folder.packed_streams.len()]
+            .iter()
+            .sum()
+    });
+
+    EntryMetadata {
+        compressed_size,
+        compression_method: Some(methods.join("+")),
+        encrypted,
+        ..EntryMetadata::default()
+    }
+}
+
+/// Path and metadata for the entry of a gzip file, using the original file
+/// name and modification time from its header.
+fn gzip_entry(
+    header: Option<&flate2::GzHeader>,
+    compressed_size: Option<u64>,
+) -> (String, EntryMetadata) {
+    let path = header
+        .and_then(|h| h.filename())
+        .and_then(|f| std::str::from_utf8(f).ok())
+        .unwrap_or("data")
+        .to_string();
+    let mtime = header.map(|h| h.mtime()).filter(|&mtime| mtime != 0);
+
+    let metadata = EntryMetadata {
+        mtime: mtime.map(u64::from),
+        ..single_file_metadata(compressed_size, ArchiveFormat::Gz)
+    };
+    (path, metadata)
+}
+
+/// Metadata for the single entry of a compressed stream.
+fn single_file_metadata(compressed_size: Option<u64>, format: ArchiveFormat) -> EntryMetadata {
+    EntryMetadata {
+        compressed_size,
+        compression_method: Some(format.name().to_string()),
+        ..EntryMetadata::default()
     }
 }
 
@@ -3,14 +3,18 @@
 //! This module defines the supported archive and compression formats.
 
 use mime_type::MimeType;
+use std::fmt;
+use std::str::FromStr;
 
 use crate::ArchiveError;
 
 /// Supported archive and compression formats.
 ///
 /// This enum represents all archive and compression formats that can be extracted
-/// by this crate. It includes multi-file archives (ZIP, TAR, 7-Zip) and single-file
-/// compression formats (gzip, bzip2, etc.).
+/// by this crate. It includes multi-file archives and disk images (ZIP, TAR and
+/// its compressed variants, 7-Zip, RAR, ar, Debian packages, cpio, RPM, ISO 9660,
+/// CAB, XAR, macOS installer packages and SquashFS) and single-file compression
+/// formats (gzip, bzip2, XZ, Zstandard, etc.).
 ///
 /// # Examples
 ///
@@ -132,9 +136,159 @@ pub enum ArchiveFormat {
     /// 7-Zip is a high-compression archive format that supports multiple
     /// compression algorithms and can achieve excellent compression ratios.
     SevenZ,
+
+    /// RAR archive format (`.rar`).
+    ///
+    /// Archives in the RAR 1.x to 4.x and RAR 5.0 formats can be read,
+    /// including solid archives, but not created. Encrypted entries and entries split
+    /// across the volumes of a multi-volume archive can't be extracted.
+    Rar,
+
+    /// cpio archive format (`.cpio`).
+    ///
+    /// The SVR4 "newc" format, with or without checksums, the POSIX.1 "odc"
+    /// format and the old binary format can be read. cpio is the payload
+    /// format of RPM packages and Linux initramfs images.
+    Cpio,
+
+    /// RPM package format (`.rpm`).
+    ///
+    /// The files of the cpio payload are extracted, decompressing it with
+    /// gzip, bzip2, XZ, LZMA or Zstandard as recorded in the package header.
+    /// The header tags, such as the name, version and dependencies, are
+    /// available through [`inspect`](crate::ArchiveExtractor::inspect).
+    Rpm,
+
+    /// ISO 9660 disc image format (`.iso`).
+    ///
+    /// Files and directories are read from the Rock Ridge names and
+    /// attributes if present, otherwise from the Joliet tree. Images can be
+    /// read in place with
+    /// [`extract_seekable`](crate::ArchiveExtractor::extract_seekable).
+    Iso,
+
+    /// Microsoft Cabinet format (`.cab`).
+    ///
+    /// Folders compressed with MSZIP or LZX, or stored uncompressed, can be
+    /// read, and the FAT attributes of each file are kept. Cabinets are found
+    /// in Windows driver packages and MSI installers. Quantum compression and
+    /// files split across the cabinets of a set are not supported.
+    Cab,
+
+    /// Single file compressed with lzip (`.lz`).
+    ///
+    /// Files made of several concatenated members, as written by `plzip`, are
+    /// decompressed in full, and the CRC32 and sizes in each member's trailer
+    /// are checked. The extracted file will be named "data".
+    Lz,
+
+    /// Single file compressed with the legacy LZMA format (`.lzma`).
+    ///
+    /// The format that preceded XZ, as written by LZMA Utils and
+    /// `xz --format=lzma`. It has no magic bytes, so detection relies on the
+    /// header values these tools write. The extracted file will be named "data".
+    Lzma,
+
+    /// TAR archive with lzip compression (`.tar.lz`).
+    ///
+    /// The format of many GNU source releases.
+    TarLz,
+
+    /// TAR archive with legacy LZMA compression (`.tar.lzma`, `.tlz`).
+    TarLzma,
+
+    /// Single file compressed with Unix `compress` (`.Z`).
+    ///
+    /// LZW compression with 9 to 16 bit codes, with or without block mode.
+    /// The extracted file will be named "data".
+    Compress,
+
+    /// TAR archive with Unix `compress` compression (`.tar.Z`, `.taz`).
+    ///
+    /// Found on old archive mirrors and in legacy build inputs.
+    TarZ,
+
+    /// Single file compressed with Brotli (`.br`).
+    ///
+    /// Brotli streams have no magic bytes, so this format is never
+    /// [detected](Self::detect) and has to be given explicitly. The extracted
+    /// file will be named "data".
+    Br,
+
+    /// TAR archive with Brotli compression (`.tar.br`).
+    ///
+    /// Like [`Br`](Self::Br), this format is never detected.
+    TarBr,
+
+    /// Single file compressed with the Snappy framing format (`.sz`).
+    ///
+    /// The CRC32C checksum of every chunk is verified. Raw Snappy blocks
+    /// without the framing are not supported. The extracted file will be
+    /// named "data".
+    Snappy,
+
+    /// XAR archive format (`.xar`).
+    ///
+    /// Files are read from the XML table of contents and decoded from the
+    /// heap, checking the stored and extracted checksums of each file as well
+    /// as the checksum of the table of contents. Contents may be stored
+    /// uncompressed or compressed with zlib, bzip2, XZ or LZMA.
+    Xar,
+
+    /// macOS flat installer package format (`.pkg`).
+    ///
+    /// A XAR archive, extracted like [`Xar`](Self::Xar) except that the
+    /// `Payload` and `Scripts` of each component package are expanded: the
+    /// files of their gzip, bzip2 or pbzx compressed cpio archives are
+    /// extracted beneath them, e.g. `app.pkg/Payload/Applications/...`.
+    Pkg,
+
+    /// SquashFS 4.0 filesystem image (`.squashfs`, `.sqfs`).
+    ///
+    /// Files, directories, symbolic and hard links, device nodes, named pipes
+    /// and sockets are read with their permissions and modification times.
+    /// Blocks may be compressed with gzip, XZ, LZMA, LZ4 or Zstandard; LZO
+    /// images are not supported.
+    SquashFs,
 }
 
 impl ArchiveFormat {
+    /// Every format, in declaration order.
+    const ALL: [ArchiveFormat; 32] = [
+        Self::Zip,
+        Self::Tar,
+        Self::Ar,
+        Self::Deb,
+        Self::TarGz,
+        Self::TarBz2,
+        Self::TarXz,
+        Self::TarZst,
+        Self::TarLz4,
+        Self::Gz,
+        Self::Bz2,
+        Self::Xz,
+        Self::Lz4,
+        Self::Zst,
+        Self::SevenZ,
+        Self::Rar,
+        Self::Cpio,
+        Self::Rpm,
+        Self::Iso,
+        Self::Cab,
+        Self::Lz,
+        Self::Lzma,
+        Self::TarLz,
+        Self::TarLzma,
+        Self::Compress,
+        Self::TarZ,
+        Self::Br,
+        Self::TarBr,
+        Self::Snappy,
+        Self::Xar,
+        Self::Pkg,
+        Self::SquashFs,
+    ];
+
     /// Returns the human-readable name of the archive format.
     ///
     /// This method returns a string representation of the format, suitable
@@ -166,9 +320,61 @@ impl ArchiveFormat {
             Self::Lz4 => "LZ4",
             Self::Zst => "ZSTD",
             Self::SevenZ => "7Z",
+            Self::Rar => "RAR",
+            Self::Cpio => "CPIO",
+            Self::Rpm => "RPM",
+            Self::Iso => "ISO",
+            Self::Cab => "CAB",
+            Self::Lz => "LZIP",
+            Self::Lzma => "LZMA",
+            Self::TarLz => "TAR.LZ",
+            Self::TarLzma => "TAR.LZMA",
+            Self::Compress => "COMPRESS",
+            Self::TarZ => "TAR.Z",
+            Self::Br => "BROTLI",
+            Self::TarBr => "TAR.BR",
+            Self::Snappy => "SNAPPY",
+            Self::Xar => "XAR",
+            Self::Pkg => "PKG",
+            Self::SquashFs => "SQUASHFS",
         }
     }
 
+    /// Detects the format of an archive from its contents.
+    ///
+    /// Formats are recognized by their magic bytes rather than by file name.
+    /// For compressed streams, the start of the stream is decompressed to
+    /// distinguish a compressed TAR archive (e.g. [`TarGz`](Self::TarGz))
+    /// from a single compressed file (e.g. [`Gz`](Self::Gz)), and ar archives
+    /// starting with a `debian-binary` member are reported as
+    /// [`Deb`](Self::Deb). Likewise, XAR archives holding a `Distribution`,
+    /// `PackageInfo` or `Payload` file are reported as [`Pkg`](Self::Pkg) when
+    /// the `xar` feature is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ArchiveError::UnknownFormat`] if the data doesn't match any
+    /// supported format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::{ArBuilder, ArVariant, ArchiveFormat};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut builder = ArBuilder::new(ArVariant::Gnu);
+    /// builder.add_file("hello.txt", b"Hello, World!\n".to_vec())?;
+    /// let data = builder.finish()?;
+    ///
+    /// assert_eq!(ArchiveFormat::detect(&data)?, ArchiveFormat::Ar);
+    /// assert!(ArchiveFormat::detect(b"plain text").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect(data: &[u8]) -> Result<Self, ArchiveError> {
+        crate::detect::detect(data)
+    }
+
     /// Checks if a given MIME type corresponds to a supported archive format.
     ///
     /// This method attempts to convert the provided MIME type into an
@@ -191,6 +397,48 @@ impl ArchiveFormat {
     pub fn is_supported_mime(mime: &MimeType) -> bool {
         ArchiveFormat::try_from(mime).is_ok()
     }
+
+    /// The error returned for this format when the cargo `feature` it needs is
+    /// disabled.
+    #[cfg(not(all(
+        feature = "rar",
+        feature = "xar",
+        feature = "brotli",
+        feature = "snappy"
+    )))]
+    pub(crate) fn feature_disabled(self, feature: &str) -> ArchiveError {
+        ArchiveError::UnsupportedFormat(format!("{} requires the `{}` feature", self, feature))
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    /// Writes the format's [`name`](ArchiveFormat::name).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = ArchiveError;
+
+    /// Parses a format from its [`name`](ArchiveFormat::name), ignoring case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::ArchiveFormat;
+    ///
+    /// assert_eq!("TAR.GZ".parse::<ArchiveFormat>().unwrap(), ArchiveFormat::TarGz);
+    /// assert_eq!("7z".parse::<ArchiveFormat>().unwrap(), ArchiveFormat::SevenZ);
+    /// assert_eq!(ArchiveFormat::Zst.to_string(), "ZSTD");
+    /// assert!("arj".parse::<ArchiveFormat>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ArchiveError::UnsupportedFormat(s.to_string()))
+    }
 }
 
 impl TryFrom<&MimeType> for ArchiveFormat {
@@ -208,6 +456,12 @@ impl TryFrom<&MimeType> for ArchiveFormat {
             MimeType::Archive(mime_type::Archive::Lz4) => Ok(Self::Lz4),
             MimeType::Archive(mime_type::Archive::Zst) => Ok(Self::Zst),
             MimeType::Archive(mime_type::Archive::SevenZ) => Ok(Self::SevenZ),
+            MimeType::Archive(mime_type::Archive::Rar) => Ok(Self::Rar),
+            MimeType::Archive(mime_type::Archive::Cpio) => Ok(Self::Cpio),
+            MimeType::Archive(mime_type::Archive::Rpm) => Ok(Self::Rpm),
+            MimeType::Archive(mime_type::Archive::Cab) => Ok(Self::Cab),
+            MimeType::Archive(mime_type::Archive::Lz) => Ok(Self::Lz),
+            MimeType::Archive(mime_type::Archive::Z) => Ok(Self::Compress),
             _ => Err(ArchiveError::UnsupportedFormat(mime.to_string())),
         }
     }
@@ -222,6 +476,11 @@ impl TryFrom<MimeType> for ArchiveFormat {
 }
 
 impl From<&ArchiveFormat> for MimeType {
+    /// Converts a format to the MIME type of its outermost layer, e.g. gzip
+    /// for [`TarGz`](ArchiveFormat::TarGz).
+    ///
+    /// Formats that `mime_type` has no variant for, such as
+    /// [`Iso`](ArchiveFormat::Iso), become `application/octet-stream`.
     fn from(format: &ArchiveFormat) -> Self {
         match format {
             ArchiveFormat::Zip => MimeType::Archive(mime_type::Archive::Zip),
@@ -234,11 +493,29 @@ impl From<&ArchiveFormat> for MimeType {
             ArchiveFormat::Lz4 => MimeType::Archive(mime_type::Archive::Lz4),
             ArchiveFormat::Zst => MimeType::Archive(mime_type::Archive::Zst),
             ArchiveFormat::SevenZ => MimeType::Archive(mime_type::Archive::SevenZ),
+            ArchiveFormat::Rar => MimeType::Archive(mime_type::Archive::Rar),
+            ArchiveFormat::Cpio => MimeType::Archive(mime_type::Archive::Cpio),
+            ArchiveFormat::Rpm => MimeType::Archive(mime_type::Archive::Rpm),
+            ArchiveFormat::Cab => MimeType::Archive(mime_type::Archive::Cab),
+            ArchiveFormat::Lz => MimeType::Archive(mime_type::Archive::Lz),
+            ArchiveFormat::Compress => MimeType::Archive(mime_type::Archive::Z),
             ArchiveFormat::TarGz => MimeType::Archive(mime_type::Archive::Gz),
             ArchiveFormat::TarBz2 => MimeType::Archive(mime_type::Archive::Bz2),
             ArchiveFormat::TarXz => MimeType::Archive(mime_type::Archive::Xz),
             ArchiveFormat::TarZst => MimeType::Archive(mime_type::Archive::Zst),
             ArchiveFormat::TarLz4 => MimeType::Archive(mime_type::Archive::Lz4),
+            ArchiveFormat::TarLz => MimeType::Archive(mime_type::Archive::Lz),
+            ArchiveFormat::TarZ => MimeType::Archive(mime_type::Archive::Z),
+            // `mime_type` spells application/octet-stream as its EOT variant
+            ArchiveFormat::Iso
+            | ArchiveFormat::Lzma
+            | ArchiveFormat::TarLzma
+            | ArchiveFormat::Br
+            | ArchiveFormat::TarBr
+            | ArchiveFormat::Snappy
+            | ArchiveFormat::Xar
+            | ArchiveFormat::Pkg
+            | ArchiveFormat::SquashFs => MimeType::Archive(mime_type::Archive::Eot),
         }
     }
 }
@@ -0,0 +1,212 @@
+//! Content digests of extracted entries.
+//!
+//! Hash algorithms are enabled with
+//! [`ArchiveExtractor::with_hashes`](crate::ArchiveExtractor::with_hashes). The
+//! digests are computed while each entry is decompressed and returned in
+//! [`ExtractedFile::digests`](crate::ExtractedFile::digests).
+//!
+//! SHA-256 and SHA-512 are always available. SHA-1, MD5 and BLAKE3 are
+//! enabled by the `sha1`, `md5` and `blake3` features, which are on by
+//! default.
+
+use sha2::Digest as _;
+use std::fmt;
+use std::io::{self, Read};
+
+/// Hash algorithms that can be computed over entry contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HashAlgorithm {
+    /// SHA-256 (32-byte digest).
+    Sha256,
+
+    /// SHA-1 (20-byte digest).
+    #[cfg(feature = "sha1")]
+    Sha1,
+
+    /// MD5 (16-byte digest).
+    #[cfg(feature = "md5")]
+    Md5,
+
+    /// BLAKE3 (32-byte digest).
+    #[cfg(feature = "blake3")]
+    Blake3,
+
+    /// SHA-512 (64-byte digest).
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Returns the conventional name of the algorithm, e.g. `SHA-256`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::Sha1 => "SHA-1",
+            #[cfg(feature = "md5")]
+            HashAlgorithm::Md5 => "MD5",
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => "BLAKE3",
+            HashAlgorithm::Sha512 => "SHA-512",
+        }
+    }
+}
+
+/// A digest of an entry's decompressed contents.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Digest {
+    /// The algorithm that produced the digest.
+    pub algorithm: HashAlgorithm,
+
+    /// The raw digest bytes. Serialized as a lowercase hexadecimal string.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::hex"))]
+    pub bytes: Vec<u8>,
+}
+
+impl Digest {
+    /// Returns the digest as a lowercase hexadecimal string, the form used by
+    /// `sha256sum` and most hash lists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use archive::hash::{Digest, HashAlgorithm};
+    ///
+    /// let digest = Digest {
+    ///     algorithm: HashAlgorithm::Sha256,
+    ///     bytes: vec![0xe3, 0xb0, 0xc4, 0x42],
+    /// };
+    /// assert_eq!(digest.to_hex(), "e3b0c442");
+    /// ```
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.bytes {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the configured digests over data fed to it in chunks.
+pub(crate) struct Hasher {
+    states: Vec<HashState>,
+}
+
+enum HashState {
+    Sha256(sha2::Sha256),
+    #[cfg(feature = "sha1")]
+    Sha1(sha1::Sha1),
+    #[cfg(feature = "md5")]
+    Md5(md5::Md5),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+    Sha512(sha2::Sha512),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithms: &[HashAlgorithm]) -> Self {
+        let states = algorithms
+            .iter()
+            .map(|algorithm| match algorithm {
+                HashAlgorithm::Sha256 => HashState::Sha256(sha2::Sha256::new()),
+                #[cfg(feature = "sha1")]
+                HashAlgorithm::Sha1 => HashState::Sha1(sha1::Sha1::new()),
+                #[cfg(feature = "md5")]
+                HashAlgorithm::Md5 => HashState::Md5(md5::Md5::new()),
+                #[cfg(feature = "blake3")]
+                HashAlgorithm::Blake3 => HashState::Blake3(Box::default()),
+                HashAlgorithm::Sha512 => HashState::Sha512(sha2::Sha512::new()),
+            })
+            .collect();
+        Self { states }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for state in &mut self.states {
+            match state {
+                HashState::Sha256(hasher) => hasher.update(data),
+                #[cfg(feature = "sha1")]
+                HashState::Sha1(hasher) => hasher.update(data),
+                #[cfg(feature = "md5")]
+                HashState::Md5(hasher) => hasher.update(data),
+                #[cfg(feature = "blake3")]
+                HashState::Blake3(hasher) => {
+                    hasher.update(data);
+                }
+                HashState::Sha512(hasher) => hasher.update(data),
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<Digest> {
+        self.states
+            .into_iter()
+            .map(|state| match state {
+                HashState::Sha256(hasher) => Digest {
+                    algorithm: HashAlgorithm::Sha256,
+                    bytes: hasher.finalize().to_vec(),
+                },
+                #[cfg(feature = "sha1")]
+                HashState::Sha1(hasher) => Digest {
+                    algorithm: HashAlgorithm::Sha1,
+                    bytes: hasher.finalize().to_vec(),
+                },
+                #[cfg(feature = "md5")]
+                HashState::Md5(hasher) => Digest {
+                    algorithm: HashAlgorithm::Md5,
+                    bytes: hasher.finalize().to_vec(),
+                },
+                #[cfg(feature = "blake3")]
+                HashState::Blake3(hasher) => Digest {
+                    algorithm: HashAlgorithm::Blake3,
+                    bytes: hasher.finalize().as_bytes().to_vec(),
+                },
+                HashState::Sha512(hasher) => Digest {
+                    algorithm: HashAlgorithm::Sha512,
+                    bytes: hasher.finalize().to_vec(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Computes the digest of `data` with a single algorithm.
+#[cfg(feature = "xar")]
+pub(crate) fn digest(algorithm: HashAlgorithm, data: &[u8]) -> Digest {
+    let mut hasher = Hasher::new(&[algorithm]);
+    hasher.update(data);
+    hasher.finish().remove(0)
+}
+
+/// A reader that feeds everything read through it into a [`Hasher`].
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub(crate) fn new(inner: R, algorithms: &[HashAlgorithm]) -> Self {
+        Self {
+            inner,
+            hasher: Hasher::new(algorithms),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<Digest> {
+        self.hasher.finish()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
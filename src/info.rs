@@ -0,0 +1,951 @@
+//! Archive-level metadata.
+//!
+//! This module provides [`ArchiveInfo`], returned by
+//! [`ArchiveExtractor::inspect`](crate::ArchiveExtractor::inspect), which
+//! describes an archive as a whole rather than its entries: comments, the
+//! format variant that produced it, and compression stream headers.
+
+use crate::builder::ArVariant;
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+use crate::hash::HashAlgorithm;
+use crate::limit::LimitedWriter;
+#[cfg(feature = "rar")]
+use crate::rar;
+#[cfg(feature = "xar")]
+use crate::xar;
+use crate::{cab, iso, lzma, lzw, rpm, squashfs, window};
+use std::io::{Cursor, Read};
+
+/// Archive-level information gathered by
+/// [`ArchiveExtractor::inspect`](crate::ArchiveExtractor::inspect).
+///
+/// Only the sections that apply to the inspected format are filled in. A
+/// compressed TAR archive has both a [`tar`](Self::tar) section and one for its
+/// compression stream, e.g. [`gzip`](Self::gzip) for `.tar.gz`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use archive::{ArchiveExtractor, ArchiveFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let data = vec![0u8; 100];
+/// let info = ArchiveExtractor::new().inspect(&data, ArchiveFormat::TarGz)?;
+///
+/// if let Some(gzip) = &info.gzip {
+///     println!("gzip mtime: {}, OS: {}", gzip.mtime, gzip.operating_system);
+/// }
+/// if let Some(tar) = &info.tar {
+///     println!("tar variant: {:?}", tar.variant);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArchiveInfo {
+    /// The format the archive was inspected as.
+    pub format: ArchiveFormat,
+
+    /// ZIP central directory information.
+    pub zip: Option<ZipInfo>,
+
+    /// TAR header information, for plain and compressed TAR archives.
+    pub tar: Option<TarInfo>,
+
+    /// 7-Zip header information.
+    pub sevenz: Option<SevenZInfo>,
+
+    /// ar information, for ar archives and Debian packages.
+    pub ar: Option<ArInfo>,
+
+    /// RAR archive information.
+    pub rar: Option<RarInfo>,
+
+    /// RPM package header information.
+    pub rpm: Option<RpmInfo>,
+
+    /// ISO 9660 volume information.
+    pub iso: Option<IsoInfo>,
+
+    /// Microsoft Cabinet header information.
+    pub cab: Option<CabInfo>,
+
+    /// XAR table of contents information, for XAR archives and installer
+    /// packages.
+    pub xar: Option<XarInfo>,
+
+    /// SquashFS superblock information.
+    pub squashfs: Option<SquashFsInfo>,
+
+    /// gzip member header, for `.gz` and `.tar.gz`.
+    pub gzip: Option<GzipInfo>,
+
+    /// Zstandard frame headers, for `.zst` and `.tar.zst`.
+    pub zstd: Option<ZstdInfo>,
+}
+
+impl ArchiveInfo {
+    fn new(format: ArchiveFormat) -> Self {
+        Self {
+            format,
+            zip: None,
+            tar: None,
+            sevenz: None,
+            ar: None,
+            rar: None,
+            rpm: None,
+            iso: None,
+            cab: None,
+            xar: None,
+            squashfs: None,
+            gzip: None,
+            zstd: None,
+        }
+    }
+}
+
+/// ZIP archive information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZipInfo {
+    /// The archive comment from the end of central directory record, if any.
+    pub comment: Option<String>,
+
+    /// Number of entries in the central directory.
+    pub entry_count: usize,
+}
+
+/// TAR header formats, ordered from least to most capable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TarVariant {
+    /// Pre-POSIX Unix V7 headers without a magic value.
+    V7,
+
+    /// POSIX.1-1988 `ustar` headers.
+    Ustar,
+
+    /// GNU tar headers (`ustar  ` magic), possibly with GNU long name entries.
+    Gnu,
+
+    /// POSIX.1-2001 `pax` headers, i.e. `ustar` with extended header entries.
+    Pax,
+}
+
+/// TAR archive information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TarInfo {
+    /// The header format, or `None` for an archive without entries.
+    ///
+    /// Archives mixing formats are reported by their most capable one: any PAX
+    /// extended header makes the archive [`TarVariant::Pax`], otherwise any GNU
+    /// header makes it [`TarVariant::Gnu`].
+    pub variant: Option<TarVariant>,
+
+    /// Key/value records of all PAX global extended headers (`g` entries), in
+    /// archive order.
+    pub pax_global_headers: Vec<(String, String)>,
+}
+
+/// 7-Zip archive information.
+///
+/// When the header is encrypted and no password is set with
+/// [`with_password`](crate::ArchiveExtractor::with_password), it can't be
+/// read and only [`encrypted`](Self::encrypted),
+/// [`header_compressed`](Self::header_compressed) and
+/// [`header_encrypted`](Self::header_encrypted) are meaningful.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SevenZInfo {
+    /// Whether any block holds more than one entry.
+    pub solid: bool,
+
+    /// Whether any entry's data or the header is AES encrypted.
+    pub encrypted: bool,
+
+    /// Whether the header is stored as an encoded (compressed) header.
+    pub header_compressed: bool,
+
+    /// Whether the header is encrypted, which hides the file names.
+    pub header_encrypted: bool,
+
+    /// Number of blocks (folders) holding entry data.
+    pub block_count: usize,
+
+    /// Number of entries, including directories and empty files.
+    pub entry_count: usize,
+}
+
+/// Symbol table formats found at the start of ar archives of object files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArSymbolTable {
+    /// System V / GNU symbol table (`/`), with 32-bit offsets.
+    SysV,
+
+    /// System V / GNU symbol table with 64-bit offsets (`/SYM64/`).
+    SysV64,
+
+    /// BSD symbol table (`__.SYMDEF` or `__.SYMDEF SORTED`).
+    Bsd,
+}
+
+/// ar archive information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArInfo {
+    /// The variant, or `None` for the common format that only uses short
+    /// identifiers (as in Debian packages) and can't be told apart.
+    pub variant: Option<ArVariant>,
+
+    /// The symbol table, if the archive has one.
+    pub symbol_table: Option<ArSymbolTable>,
+
+    /// Number of members, excluding the symbol and name tables.
+    pub member_count: usize,
+}
+
+/// RAR format versions, named after the archiver versions that introduced
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RarVersion {
+    /// The original format of RAR 1.3 and 1.4 (`RE~^` signature).
+    Rar14,
+
+    /// The format used from RAR 1.5 to RAR 4.x.
+    Rar4,
+
+    /// The format introduced by RAR 5.0.
+    Rar5,
+}
+
+/// RAR archive information.
+///
+/// When the headers are encrypted and no password is set with
+/// [`with_password`](crate::ArchiveExtractor::with_password), they can't be
+/// read and only [`version`](Self::version), [`encrypted`](Self::encrypted) and
+/// [`header_encrypted`](Self::header_encrypted) are meaningful.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RarInfo {
+    /// The format version.
+    pub version: RarVersion,
+
+    /// Whether entries are compressed as one continuous stream, so that an
+    /// entry can only be decompressed after all entries before it.
+    pub solid: bool,
+
+    /// Whether the archive is a volume of a multi-volume archive.
+    pub multi_volume: bool,
+
+    /// The zero-based number of this volume, if the archive is a volume and
+    /// records it.
+    pub volume_number: Option<u64>,
+
+    /// Whether any entry's data or the headers are encrypted.
+    pub encrypted: bool,
+
+    /// Whether the headers are encrypted, which hides the file names.
+    pub header_encrypted: bool,
+
+    /// Number of entries, including directories.
+    pub entry_count: usize,
+}
+
+/// RPM package information, from the main header.
+///
+/// Tags that are missing from the header are left empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RpmInfo {
+    /// The package name.
+    pub name: String,
+
+    /// The upstream version.
+    pub version: String,
+
+    /// The package release.
+    pub release: String,
+
+    /// The epoch, if set.
+    pub epoch: Option<u32>,
+
+    /// The architecture the package was built for, e.g. `x86_64` or `noarch`.
+    pub arch: Option<String>,
+
+    /// The one-line summary.
+    pub summary: Option<String>,
+
+    /// The license.
+    pub license: Option<String>,
+
+    /// The capabilities the package requires, including the `rpmlib(...)`
+    /// features of the package format itself.
+    pub requires: Vec<RpmDependency>,
+
+    /// The capabilities the package provides.
+    pub provides: Vec<RpmDependency>,
+
+    /// The files of the package, in header order.
+    pub files: Vec<RpmFile>,
+
+    /// The algorithm of the file digests, or `None` if it's one this crate
+    /// doesn't compute.
+    pub file_digest_algorithm: Option<HashAlgorithm>,
+
+    /// The compression of the payload, e.g. `gzip`, `xz` or `zstd`.
+    pub payload_compressor: String,
+}
+
+/// A required or provided capability of an RPM package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RpmDependency {
+    /// The capability, e.g. a package name, `/bin/sh` or `libc.so.6()(64bit)`.
+    pub name: String,
+
+    /// The version constraint, e.g. `>= 1.2-3`, if any.
+    pub constraint: Option<String>,
+}
+
+/// A file listed in the header of an RPM package.
+///
+/// The payload stores the same files with paths relative to the root, e.g.
+/// `./usr/bin/hello` for `/usr/bin/hello`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RpmFile {
+    /// The absolute path the file is installed to.
+    pub path: String,
+
+    /// The size in bytes.
+    pub size: u64,
+
+    /// The Unix mode, including the file type bits.
+    pub mode: u32,
+
+    /// The digest of the contents as lowercase hex, computed with
+    /// [`RpmInfo::file_digest_algorithm`]. Directories, symbolic links and
+    /// other special files have none.
+    pub digest: Option<String>,
+
+    /// The target of a symbolic link.
+    pub link_target: Option<String>,
+}
+
+/// ISO 9660 volume information, from the primary volume descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IsoInfo {
+    /// The volume identifier, i.e. the label of the disc.
+    pub volume_id: String,
+
+    /// The identifier of the system that can use the boot sectors.
+    pub system_id: String,
+
+    /// The publisher identifier, if set.
+    pub publisher: Option<String>,
+
+    /// The identifier of the program that created the image, if set.
+    pub application: Option<String>,
+
+    /// The logical block size in bytes, almost always 2048.
+    pub block_size: u32,
+
+    /// The size of the volume in bytes.
+    pub volume_size: u64,
+
+    /// Whether the image has a Joliet directory tree with Unicode names.
+    pub joliet: bool,
+
+    /// Whether the primary directory tree has Rock Ridge extensions with
+    /// POSIX names, modes and symbolic links.
+    pub rock_ridge: bool,
+
+    /// Whether the image has an El Torito boot record.
+    pub bootable: bool,
+}
+
+/// Microsoft Cabinet header information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CabInfo {
+    /// The identifier shared by all cabinets of a set.
+    pub set_id: u16,
+
+    /// The position of this cabinet within its set, starting at 0.
+    pub cabinet_index: u16,
+
+    /// The file name of the previous cabinet of the set, if any.
+    pub previous_cabinet: Option<String>,
+
+    /// The file name of the next cabinet of the set, if any.
+    pub next_cabinet: Option<String>,
+
+    /// The folders, i.e. compressed streams, in order.
+    pub folders: Vec<CabFolder>,
+
+    /// The number of files, including any continued from or into another
+    /// cabinet.
+    pub file_count: usize,
+}
+
+/// A folder of a cabinet, holding the compressed contents of its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CabFolder {
+    /// The compression method: `None`, `MSZIP`, `Quantum` or `LZX`.
+    pub compression: String,
+
+    /// The LZX window size in bytes.
+    pub window_size: Option<u64>,
+
+    /// The number of data blocks in this cabinet.
+    pub data_blocks: u16,
+}
+
+/// XAR table of contents information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XarInfo {
+    /// The checksum algorithm of the table of contents, e.g. `sha1`, or
+    /// `none`.
+    pub checksum_algorithm: String,
+
+    /// The size of the zlib-compressed table of contents in bytes.
+    pub toc_compressed_size: u64,
+
+    /// The size of the table of contents XML in bytes.
+    pub toc_size: u64,
+
+    /// The number of files, directories and links.
+    pub entry_count: usize,
+}
+
+/// SquashFS superblock information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SquashFsInfo {
+    /// The compressor of the data and metadata blocks: `gzip`, `lzma`, `lzo`,
+    /// `xz`, `lz4` or `zstd`.
+    pub compression: String,
+
+    /// The size of the data blocks files are split into, in bytes.
+    pub block_size: u32,
+
+    /// The number of inodes, one per file, directory, link or special file.
+    pub inode_count: u32,
+
+    /// The number of fragment blocks holding the tails of files.
+    pub fragment_count: u32,
+
+    /// The time the image was created, in seconds since the Unix epoch.
+    pub mtime: u64,
+
+    /// The size of the image in bytes, not counting any padding.
+    pub bytes_used: u64,
+}
+
+/// gzip member header fields.
+///
+/// Only the first member's header is reported for multi-member streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GzipInfo {
+    /// Modification time of the original file in seconds since the Unix
+    /// epoch, or 0 if not recorded.
+    pub mtime: u32,
+
+    /// The operating system the stream was created on (0 = FAT, 3 = Unix,
+    /// 7 = Macintosh, 11 = NTFS, 255 = unknown).
+    pub operating_system: u8,
+
+    /// The original file name (`FNAME`).
+    pub filename: Option<String>,
+
+    /// The comment (`FCOMMENT`).
+    pub comment: Option<String>,
+
+    /// The raw extra field (`FEXTRA`).
+    pub extra: Option<Vec<u8>>,
+}
+
+/// Zstandard stream information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZstdInfo {
+    /// All frames of the stream, in order, including skippable frames.
+    pub frames: Vec<ZstdFrameInfo>,
+}
+
+/// A single Zstandard frame header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZstdFrameInfo {
+    /// Whether this is a skippable frame carrying user data instead of
+    /// compressed content. All other fields are unset for skippable frames.
+    pub skippable: bool,
+
+    /// Size of the whole frame, in bytes.
+    pub compressed_size: u64,
+
+    /// Decompressed size, if recorded in the frame header.
+    pub content_size: Option<u64>,
+
+    /// Window size the decoder needs, in bytes. Single-segment frames have no
+    /// window descriptor and use their content size instead.
+    pub window_size: Option<u64>,
+
+    /// ID of the dictionary the frame was compressed with, if any.
+    pub dictionary_id: Option<u32>,
+
+    /// Whether the frame ends with a content checksum.
+    pub has_checksum: bool,
+}
+
+/// Gathers archive-level information for `data` interpreted as `format`,
+/// enforcing the extractor's size and window limits on what is decompressed.
+/// Encrypted 7-Zip and RAR headers are decrypted with `password`.
+pub(crate) fn inspect(
+    data: &[u8],
+    format: ArchiveFormat,
+    password: Option<&str>,
+    max_file_size: usize,
+    max_total_size: usize,
+    max_window_size: usize,
+) -> Result<ArchiveInfo> {
+    window::check(data, format, max_window_size)?;
+    let mut info = ArchiveInfo::new(format);
+
+    match format {
+        ArchiveFormat::Zip => info.zip = Some(inspect_zip(data)?),
+        ArchiveFormat::Tar => info.tar = Some(inspect_tar(data, max_file_size, max_total_size)?),
+        ArchiveFormat::Ar | ArchiveFormat::Deb => info.ar = Some(inspect_ar(data)?),
+        ArchiveFormat::TarGz => {
+            info.gzip = Some(inspect_gzip(data)?);
+            info.tar = Some(inspect_tar(
+                flate2::read::MultiGzDecoder::new(data),
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        ArchiveFormat::TarBz2 => {
+            info.tar = Some(inspect_tar(
+                bzip2::read::BzDecoder::new(data),
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        ArchiveFormat::TarXz | ArchiveFormat::TarLz | ArchiveFormat::TarLzma => {
+            // lzma-rs only decompresses into a writer, so the TAR stream is
+            // buffered, bounded by the total size limit
+            let mut output = LimitedWriter::new(Vec::new(), max_total_size);
+            let result =
+                lzma::decompress(format, &mut Cursor::new(data), &mut output, max_window_size);
+            if let Some(size) = output.exceeded {
+                return Err(ArchiveError::TotalSizeTooLarge {
+                    size,
+                    limit: max_total_size,
+                });
+            }
+            result?;
+            info.tar = Some(inspect_tar(
+                output.inner.as_slice(),
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        ArchiveFormat::TarZst => {
+            info.zstd = Some(inspect_zstd(data)?);
+            info.tar = Some(inspect_tar(
+                window::zstd_decoder(data, max_window_size)?,
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        ArchiveFormat::TarLz4 => {
+            info.tar = Some(inspect_tar(
+                lz4::Decoder::new(data)?,
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        ArchiveFormat::TarZ => {
+            info.tar = Some(inspect_tar(
+                lzw::Decoder::new(data)?,
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        #[cfg(feature = "brotli")]
+        ArchiveFormat::TarBr => {
+            info.tar = Some(inspect_tar(
+                brotli::Decompressor::new(data, 4096),
+                max_file_size,
+                max_total_size,
+            )?);
+        }
+        #[cfg(not(feature = "brotli"))]
+        ArchiveFormat::TarBr => return Err(format.feature_disabled("brotli")),
+        ArchiveFormat::SevenZ => info.sevenz = Some(inspect_7z(data, password)?),
+        #[cfg(feature = "rar")]
+        ArchiveFormat::Rar => info.rar = Some(rar::inspect(data, password)?),
+        #[cfg(not(feature = "rar"))]
+        ArchiveFormat::Rar => return Err(format.feature_disabled("rar")),
+        ArchiveFormat::Rpm => info.rpm = Some(rpm::inspect(data)?),
+        ArchiveFormat::Iso => {
+            info.iso = Some(iso::Image::open(Cursor::new(data))?.info().clone());
+        }
+        ArchiveFormat::Cab => info.cab = Some(cab::inspect(data)?),
+        #[cfg(feature = "xar")]
+        ArchiveFormat::Xar | ArchiveFormat::Pkg => info.xar = Some(xar::inspect(data)?),
+        #[cfg(not(feature = "xar"))]
+        ArchiveFormat::Xar | ArchiveFormat::Pkg => return Err(format.feature_disabled("xar")),
+        ArchiveFormat::SquashFs => info.squashfs = Some(squashfs::inspect(data)?),
+        ArchiveFormat::Gz => info.gzip = Some(inspect_gzip(data)?),
+        ArchiveFormat::Zst => info.zstd = Some(inspect_zstd(data)?),
+        ArchiveFormat::Cpio
+        | ArchiveFormat::Bz2
+        | ArchiveFormat::Xz
+        | ArchiveFormat::Lz
+        | ArchiveFormat::Lzma
+        | ArchiveFormat::Lz4
+        | ArchiveFormat::Compress
+        | ArchiveFormat::Br
+        | ArchiveFormat::Snappy => {}
+    }
+
+    Ok(info)
+}
+
+fn inspect_zip(data: &[u8]) -> Result<ZipInfo> {
+    let archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let comment = Some(String::from_utf8_lossy(archive.comment()).into_owned())
+        .filter(|comment| !comment.is_empty());
+
+    Ok(ZipInfo {
+        comment,
+        entry_count: archive.len(),
+    })
+}
+
+/// Reads the TAR headers of `reader`, skipping entry data.
+///
+/// PAX global headers are read into memory, so they are limited to
+/// `max_file_size` like the contents of any other entry. The declared sizes
+/// of all entries are summed against `max_total_size` before their data is
+/// skipped, as extraction does, so a compressed bomb is rejected early.
+fn inspect_tar<R: Read>(reader: R, max_file_size: usize, max_total_size: usize) -> Result<TarInfo> {
+    let mut archive = tar::Archive::new(reader);
+    let mut variant = None;
+    let mut pax_global_headers = Vec::new();
+    let mut total_size = 0usize;
+
+    for entry_result in archive.entries()?.raw(true) {
+        let mut entry = entry_result?;
+        let entry_type = entry.header().entry_type();
+
+        let entry_variant =
+            if entry_type.is_pax_global_extensions() || entry_type.is_pax_local_extensions() {
+                TarVariant::Pax
+            } else if entry.header().as_gnu().is_some() {
+                TarVariant::Gnu
+            } else if entry.header().as_ustar().is_some() {
+                TarVariant::Ustar
+            } else {
+                TarVariant::V7
+            };
+        variant = variant.max(Some(entry_variant));
+
+        let size = entry.size() as usize;
+        if !entry_type.is_dir() {
+            total_size = total_size.saturating_add(size);
+            if total_size > max_total_size {
+                return Err(ArchiveError::TotalSizeTooLarge {
+                    size: total_size,
+                    limit: max_total_size,
+                });
+            }
+        }
+
+        if !entry_type.is_pax_global_extensions() {
+            continue;
+        }
+        if size > max_file_size {
+            return Err(ArchiveError::FileTooLarge {
+                size,
+                limit: max_file_size,
+            });
+        }
+        if let Some(extensions) = entry.pax_extensions()? {
+            for extension in extensions {
+                let extension = extension?;
+                pax_global_headers.push((
+                    extension.key().map_err(invalid_utf8)?.to_string(),
+                    String::from_utf8_lossy(extension.value_bytes()).into_owned(),
+                ));
+            }
+        }
+    }
+
+    Ok(TarInfo {
+        variant,
+        pax_global_headers,
+    })
+}
+
+fn invalid_utf8(error: std::str::Utf8Error) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("invalid PAX header key: {}", error))
+}
+
+fn inspect_ar(data: &[u8]) -> Result<ArInfo> {
+    const GLOBAL_HEADER: &[u8] = b"!<arch>\n";
+    const HEADER_LEN: usize = 60;
+
+    if !data.starts_with(GLOBAL_HEADER) {
+        return Err(ArchiveError::InvalidArchive(
+            "missing ar global header".to_string(),
+        ));
+    }
+
+    let mut info = ArInfo {
+        variant: None,
+        symbol_table: None,
+        member_count: 0,
+    };
+
+    let mut offset = GLOBAL_HEADER.len();
+    while offset + HEADER_LEN <= data.len() {
+        let header = &data[offset..offset + HEADER_LEN];
+        let identifier = &header[..16];
+        let size = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim_end().parse::<usize>().ok())
+            .ok_or_else(|| {
+                ArchiveError::InvalidArchive(format!("invalid ar member size at offset {}", offset))
+            })?;
+
+        // BSD long names are stored at the start of the member data
+        let name = if let Some(length) = identifier.strip_prefix(b"#1/") {
+            info.variant = Some(ArVariant::Bsd);
+            let length = std::str::from_utf8(length)
+                .ok()
+                .and_then(|length| length.trim_end().parse::<usize>().ok())
+                .unwrap_or(0);
+            let start = offset + HEADER_LEN;
+            let name = data.get(start..start + length).unwrap_or_default();
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            &name[..end]
+        } else {
+            let end = identifier
+                .iter()
+                .rposition(|&b| b != b' ')
+                .map_or(0, |i| i + 1);
+            &identifier[..end]
+        };
+
+        match name {
+            b"/" => info.symbol_table = Some(ArSymbolTable::SysV),
+            b"/SYM64/" => info.symbol_table = Some(ArSymbolTable::SysV64),
+            b"__.SYMDEF" | b"__.SYMDEF SORTED" => info.symbol_table = Some(ArSymbolTable::Bsd),
+            b"//" => info.variant = Some(ArVariant::Gnu),
+            _ => {
+                // GNU terminates identifiers with `/` and references long ones as `/<offset>`
+                if name.ends_with(b"/") || (name.starts_with(b"/") && name.len() > 1) {
+                    info.variant = Some(ArVariant::Gnu);
+                }
+                info.member_count += 1;
+            }
+        }
+
+        offset += HEADER_LEN + size + size % 2;
+    }
+
+    if info.variant.is_none() && info.symbol_table == Some(ArSymbolTable::Bsd) {
+        info.variant = Some(ArVariant::Bsd);
+    }
+
+    Ok(info)
+}
+
+fn inspect_7z(data: &[u8], password: Option<&str>) -> Result<SevenZInfo> {
+    const START_HEADER_LEN: usize = 32;
+    const K_ENCODED_HEADER: u8 = 0x17;
+
+    let next_header_offset = data
+        .get(12..20)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()) as usize)
+        .ok_or_else(|| ArchiveError::InvalidArchive("7z start header truncated".to_string()))?;
+    let header_compressed = START_HEADER_LEN
+        .checked_add(next_header_offset)
+        .and_then(|position| data.get(position))
+        == Some(&K_ENCODED_HEADER);
+
+    let read = |password: &str| {
+        let password = sevenz_rust::Password::from(password);
+        sevenz_rust::Archive::read(
+            &mut Cursor::new(data),
+            data.len() as u64,
+            password.as_slice(),
+        )
+    };
+    // Reading without a password first tells whether the header is encrypted
+    let (archive, header_encrypted) = match (read(""), password) {
+        (Ok(archive), _) => (archive, false),
+        (Err(sevenz_rust::Error::PasswordRequired), Some(password)) => {
+            let archive = read(password)
+                .map_err(|e| ArchiveError::InvalidArchive(format!("7z error: {}", e)))?;
+            (archive, true)
+        }
+        (Err(sevenz_rust::Error::PasswordRequired), None) => {
+            return Ok(SevenZInfo {
+                solid: false,
+                encrypted: true,
+                header_compressed,
+                header_encrypted: true,
+                block_count: 0,
+                entry_count: 0,
+            });
+        }
+        (Err(e), _) => return Err(ArchiveError::InvalidArchive(format!("7z error: {}", e))),
+    };
+
+    let encrypted = header_encrypted
+        || archive.folders.iter().any(|folder| {
+            folder.coders.iter().any(|coder| {
+                coder.decompression_method_id() == sevenz_rust::SevenZMethod::ID_AES256SHA256
+            })
+        });
+
+    Ok(SevenZInfo {
+        solid: archive
+            .folders
+            .iter()
+            .any(|folder| folder.num_unpack_sub_streams > 1),
+        encrypted,
+        header_compressed,
+        header_encrypted,
+        block_count: archive.folders.len(),
+        entry_count: archive.files.len(),
+    })
+}
+
+fn inspect_gzip(data: &[u8]) -> Result<GzipInfo> {
+    let decoder = flate2::read::GzDecoder::new(data);
+    let header = decoder
+        .header()
+        .ok_or_else(|| ArchiveError::InvalidArchive("invalid gzip header".to_string()))?;
+    let text = |bytes: Option<&[u8]>| bytes.map(|b| String::from_utf8_lossy(b).into_owned());
+
+    Ok(GzipInfo {
+        mtime: header.mtime(),
+        operating_system: header.operating_system(),
+        filename: text(header.filename()),
+        comment: text(header.comment()),
+        extra: header.extra().map(<[u8]>::to_vec),
+    })
+}
+
+fn inspect_zstd(data: &[u8]) -> Result<ZstdInfo> {
+    let mut frames = Vec::new();
+    let mut remaining = data;
+
+    while !remaining.is_empty() {
+        let frame_size =
+            zstd::zstd_safe::find_frame_compressed_size(remaining).map_err(|code| {
+                ArchiveError::InvalidArchive(format!(
+                    "invalid zstd frame at offset {}: {}",
+                    data.len() - remaining.len(),
+                    zstd::zstd_safe::get_error_name(code)
+                ))
+            })?;
+        frames.push(parse_zstd_frame_header(&remaining[..frame_size])?);
+        remaining = &remaining[frame_size..];
+    }
+
+    Ok(ZstdInfo { frames })
+}
+
+/// Parses a Zstandard frame header as described in RFC 8878, section 3.1.1.1.
+pub(crate) fn parse_zstd_frame_header(frame: &[u8]) -> Result<ZstdFrameInfo> {
+    const MAGIC: u32 = 0xFD2F_B528;
+    const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFF_FFF0;
+    const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+
+    let truncated = || ArchiveError::InvalidArchive("zstd frame header truncated".to_string());
+    let read_le = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |value, &byte| (value << 8) | byte as u64)
+    };
+
+    let magic = read_le(frame.get(..4).ok_or_else(truncated)?) as u32;
+    if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC {
+        return Ok(ZstdFrameInfo {
+            skippable: true,
+            compressed_size: frame.len() as u64,
+            content_size: None,
+            window_size: None,
+            dictionary_id: None,
+            has_checksum: false,
+        });
+    }
+    if magic != MAGIC {
+        return Err(ArchiveError::InvalidArchive(format!(
+            "invalid zstd frame magic {:#010x}",
+            magic
+        )));
+    }
+
+    let descriptor = *frame.get(4).ok_or_else(truncated)?;
+    let fcs_flag = descriptor >> 6;
+    let single_segment = descriptor & 0x20 != 0;
+    let has_checksum = descriptor & 0x04 != 0;
+    let dict_id_len = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+    let fcs_len = match fcs_flag {
+        0 if single_segment => 1,
+        0 => 0,
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+
+    let mut position = 5;
+    let mut window_size = None;
+    if !single_segment {
+        let window_descriptor = *frame.get(position).ok_or_else(truncated)?;
+        let exponent = (window_descriptor >> 3) as u32;
+        let mantissa = (window_descriptor & 0x07) as u64;
+        let base = 1u64 << (10 + exponent);
+        window_size = Some(base + (base / 8) * mantissa);
+        position += 1;
+    }
+
+    let dictionary_id = frame
+        .get(position..position + dict_id_len)
+        .ok_or_else(truncated)
+        .map(|bytes| read_le(bytes) as u32)?;
+    position += dict_id_len;
+
+    let content_size = match fcs_len {
+        0 => None,
+        len => {
+            let value = read_le(frame.get(position..position + len).ok_or_else(truncated)?);
+            Some(if len == 2 { value + 256 } else { value })
+        }
+    };
+
+    Ok(ZstdFrameInfo {
+        skippable: false,
+        compressed_size: frame.len() as u64,
+        content_size,
+        window_size: window_size.or(if single_segment { content_size } else { None }),
+        dictionary_id: (dictionary_id != 0).then_some(dictionary_id),
+        has_checksum,
+    })
+}
@@ -0,0 +1,644 @@
+//! ISO 9660 image reading.
+//!
+//! An image starts with 16 unused sectors, followed by a set of volume
+//! descriptors. The primary volume descriptor points at the root directory,
+//! whose records point at files and further directories. Names are taken from
+//! the Rock Ridge extensions when present, which also provide Unix modes,
+//! timestamps and symbolic links, and otherwise from the Joliet tree, which
+//! holds Unicode names. Images with neither use the restricted names of the
+//! primary tree.
+//!
+//! Only the parts of the image that are needed are read, through
+//! [`Read`] and [`Seek`], so images don't have to be held in memory.
+//!
+//! See ECMA-119, the Joliet specification, and IEEE P1281 (SUSP) and P1282
+//! (RRIP) for Rock Ridge.
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::days_from_civil;
+use crate::info::IsoInfo;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The size of a logical sector, which directory records never cross.
+const SECTOR_SIZE: u64 = 2048;
+const DESCRIPTORS_START: u64 = 16 * SECTOR_SIZE;
+const MAGIC: &[u8] = b"CD001";
+
+// Volume descriptor types
+const BOOT_RECORD: u8 = 0;
+const PRIMARY: u8 = 1;
+const SUPPLEMENTARY: u8 = 2;
+const TERMINATOR: u8 = 255;
+
+// Directory record flags
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+
+/// Limits guarding against malformed images.
+const MAX_DESCRIPTORS: u64 = 64;
+const MAX_DIRECTORY_SIZE: u32 = 64 * 1024 * 1024;
+const MAX_DEPTH: usize = 64;
+const MAX_CONTINUATIONS: usize = 32;
+
+/// File type bits of a Unix mode.
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Whether `data` starts with an ISO 9660 volume descriptor.
+pub(crate) fn is_iso(data: &[u8]) -> bool {
+    let start = DESCRIPTORS_START as usize + 1;
+    data.get(start..start + MAGIC.len()) == Some(MAGIC)
+}
+
+/// Where the names of an image are taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Names {
+    Primary,
+    Joliet,
+    /// Rock Ridge entries, found after skipping the given number of bytes of
+    /// each record's system use area.
+    RockRidge(usize),
+}
+
+/// An entry of an image, without its contents.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    /// The path, with `/` separating components.
+    pub(crate) path: String,
+    pub(crate) is_directory: bool,
+    /// The size of the contents in bytes.
+    pub(crate) size: u64,
+    /// Unix permission bits, from Rock Ridge.
+    pub(crate) mode: Option<u32>,
+    /// Modification time in seconds since the Unix epoch.
+    pub(crate) mtime: Option<u64>,
+    /// The target of a symbolic link, from Rock Ridge.
+    pub(crate) link: Option<String>,
+    /// Location and size of each extent holding the contents.
+    extents: Vec<(u32, u32)>,
+}
+
+/// An ISO 9660 image read through a seekable reader.
+pub(crate) struct Image<R> {
+    reader: R,
+    block_size: u64,
+    root: Record,
+    names: Names,
+    info: IsoInfo,
+}
+
+impl<R: Read + Seek> Image<R> {
+    /// Reads the volume descriptors and picks the directory tree to walk.
+    pub(crate) fn open(mut reader: R) -> Result<Self> {
+        let mut primary = None;
+        let mut joliet = None;
+        let mut bootable = false;
+
+        for index in 0..MAX_DESCRIPTORS {
+            let descriptor = read_at(&mut reader, DESCRIPTORS_START + index * SECTOR_SIZE, 2048)?;
+            if &descriptor[1..6] != MAGIC {
+                return Err(invalid("missing volume descriptor set terminator"));
+            }
+            match descriptor[0] {
+                BOOT_RECORD => bootable |= descriptor[7..].starts_with(b"EL TORITO SPECIFICATION"),
+                PRIMARY if primary.is_none() => primary = Some(descriptor),
+                SUPPLEMENTARY if is_joliet(&descriptor) => joliet = Some(descriptor),
+                TERMINATOR => break,
+                _ => {}
+            }
+        }
+        let primary = primary.ok_or_else(|| invalid("missing primary volume descriptor"))?;
+
+        let block_size = u64::from(le_u16(&primary[128..130]));
+        if !matches!(block_size, 512 | 1024 | 2048) {
+            return Err(invalid("unsupported logical block size"));
+        }
+        let root = Record::parse(&primary[156..190])
+            .ok_or_else(|| invalid("invalid root directory record"))?;
+
+        let mut image = Self {
+            reader,
+            block_size,
+            root,
+            names: Names::Primary,
+            info: IsoInfo {
+                volume_id: text(&primary[40..72]),
+                system_id: text(&primary[8..40]),
+                publisher: Some(text(&primary[318..446])).filter(|s| !s.is_empty()),
+                application: Some(text(&primary[574..702])).filter(|s| !s.is_empty()),
+                block_size: block_size as u32,
+                volume_size: u64::from(le_u32(&primary[80..84])) * block_size,
+                joliet: joliet.is_some(),
+                rock_ridge: false,
+                bootable,
+            },
+        };
+
+        // Rock Ridge is announced in the system use area of the root
+        // directory's own `.` record
+        let records = image.read_directory(image.root.extent, image.root.size)?;
+        if let Some(skip) = records.first().and_then(|dot| susp_skip(&dot.system_use)) {
+            let fields = image.rock_ridge_fields(&records[0], skip)?;
+            if fields.is_rock_ridge {
+                image.info.rock_ridge = true;
+                image.names = Names::RockRidge(skip);
+            }
+        }
+        if image.names == Names::Primary
+            && let Some(joliet) = joliet
+        {
+            image.root = Record::parse(&joliet[156..190])
+                .ok_or_else(|| invalid("invalid Joliet root directory record"))?;
+            image.names = Names::Joliet;
+        }
+
+        Ok(image)
+    }
+
+    pub(crate) fn info(&self) -> &IsoInfo {
+        &self.info
+    }
+
+    /// Lists every file and directory, each directory before its contents.
+    pub(crate) fn entries(&mut self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let root = (self.root.extent, self.root.size);
+        let mut visited = BTreeMap::from([self.byte_range(root)]);
+        self.walk(root, "", 0, &mut visited, &mut entries)?;
+        Ok(entries)
+    }
+
+    fn walk(
+        &mut self,
+        (extent, size): (u32, u32),
+        prefix: &str,
+        depth: usize,
+        visited: &mut BTreeMap<u64, u64>,
+        entries: &mut Vec<Entry>,
+    ) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("directories nested too deeply"));
+        }
+
+        let mut extents = Vec::new();
+        for record in self.read_directory(extent, size)? {
+            // Skip the `.` and `..` records
+            if matches!(record.name.as_slice(), [0] | [1]) {
+                continue;
+            }
+
+            let fields = match self.names {
+                Names::RockRidge(skip) => self.rock_ridge_fields(&record, skip)?,
+                _ => Fields::default(),
+            };
+            // Relocated directories are listed where their child link is
+            if fields.relocated {
+                continue;
+            }
+
+            // The contents of large files are split across several records
+            extents.push((record.extent, record.size));
+            if record.flags & FLAG_MULTI_EXTENT != 0 {
+                continue;
+            }
+
+            let name = match fields.name {
+                Some(name) => name,
+                None => self.decode_name(&record.name),
+            };
+            if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+                return Err(invalid(&format!("invalid file name '{}'", name)));
+            }
+            let path = format!("{}{}", prefix, name);
+
+            let mode = fields.mode;
+            let is_symlink = mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK);
+            let mut entry = Entry {
+                path,
+                is_directory: false,
+                size: 0,
+                mode: mode.map(|mode| mode & 0o7777),
+                mtime: fields.mtime.or(record.recorded),
+                link: fields.link.filter(|_| is_symlink),
+                extents: Vec::new(),
+            };
+
+            let child = match fields.child_link {
+                Some(location) => Some(self.directory_at(location)?),
+                None if record.flags & FLAG_DIRECTORY != 0
+                    || mode.is_some_and(|mode| mode & S_IFMT == S_IFDIR) =>
+                {
+                    Some((record.extent, record.size))
+                }
+                None => None,
+            };
+            let file_extents = std::mem::take(&mut extents);
+
+            if let Some(child) = child {
+                entry.is_directory = true;
+                let prefix = format!("{}/", entry.path);
+                entries.push(entry);
+                // A directory listed twice is walked once. Directories that
+                // only partly overlap would list their shared records again
+                // and again, so they are rejected.
+                let (start, end) = self.byte_range(child);
+                if start == end || visited.get(&start) == Some(&end) {
+                    continue;
+                }
+                if visited
+                    .range(..end)
+                    .next_back()
+                    .is_some_and(|(_, &other_end)| other_end > start)
+                {
+                    return Err(invalid("overlapping directories"));
+                }
+                visited.insert(start, end);
+                self.walk(child, &prefix, depth + 1, visited, entries)?;
+            } else {
+                if entry.link.is_none() {
+                    entry.size = file_extents.iter().map(|&(_, size)| u64::from(size)).sum();
+                    entry.extents = file_extents;
+                }
+                entries.push(entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the start and end offsets of the extent of a directory.
+    fn byte_range(&self, (extent, size): (u32, u32)) -> (u64, u64) {
+        let start = u64::from(extent) * self.block_size;
+        (start, start + u64::from(size))
+    }
+
+    /// Returns a reader over the contents of a file.
+    pub(crate) fn contents(&mut self, entry: &Entry) -> Contents<'_, R> {
+        Contents {
+            reader: &mut self.reader,
+            block_size: self.block_size,
+            extents: entry.extents.clone(),
+            next: 0,
+            remaining: 0,
+        }
+    }
+
+    /// Reads the records of a directory.
+    fn read_directory(&mut self, extent: u32, size: u32) -> Result<Vec<Record>> {
+        if size > MAX_DIRECTORY_SIZE {
+            return Err(invalid("directory too large"));
+        }
+        let data = read_at(
+            &mut self.reader,
+            u64::from(extent) * self.block_size,
+            size as usize,
+        )?;
+
+        let mut records = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            // Records don't cross sector boundaries, so a zero length pads the
+            // rest of the sector
+            if data[position] == 0 {
+                position = (position + 1).next_multiple_of(SECTOR_SIZE as usize);
+                continue;
+            }
+            let record = Record::parse(&data[position..])
+                .ok_or_else(|| invalid("invalid directory record"))?;
+            position += record.length;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Finds the extent and size of a directory from its `.` record.
+    fn directory_at(&mut self, location: u32) -> Result<(u32, u32)> {
+        let sector = read_at(
+            &mut self.reader,
+            u64::from(location) * self.block_size,
+            SECTOR_SIZE as usize,
+        )?;
+        let dot = Record::parse(&sector).ok_or_else(|| invalid("invalid relocated directory"))?;
+        Ok((location, dot.size))
+    }
+
+    fn decode_name(&self, identifier: &[u8]) -> String {
+        let name = match self.names {
+            Names::Joliet => {
+                let units = identifier
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            _ => String::from_utf8_lossy(identifier).into_owned(),
+        };
+
+        // File names end with a version number, and with a dot if they have no
+        // extension
+        let name = match name.rsplit_once(';') {
+            Some((name, version)) if version.bytes().all(|b| b.is_ascii_digit()) => name,
+            _ => &name,
+        };
+        match name.strip_suffix('.') {
+            Some(stem) if self.names == Names::Primary => stem.to_string(),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Collects the Rock Ridge fields of a record, following continuation
+    /// areas.
+    fn rock_ridge_fields(&mut self, record: &Record, skip: usize) -> Result<Fields> {
+        let mut fields = Fields::default();
+        let mut area = record.system_use.get(skip..).unwrap_or_default().to_vec();
+
+        for _ in 0..MAX_CONTINUATIONS {
+            let Some((block, offset, length)) = fields.parse(&area) else {
+                return Ok(fields);
+            };
+            if u64::from(length) > self.block_size {
+                return Err(invalid("invalid continuation area"));
+            }
+            let position = u64::from(block) * self.block_size + u64::from(offset);
+            area = read_at(&mut self.reader, position, length as usize)?;
+        }
+        Err(invalid("too many continuation areas"))
+    }
+}
+
+/// A reader over the extents of a file.
+pub(crate) struct Contents<'a, R> {
+    reader: &'a mut R,
+    block_size: u64,
+    extents: Vec<(u32, u32)>,
+    next: usize,
+    remaining: u64,
+}
+
+impl<R: Read + Seek> Read for Contents<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            let Some(&(extent, size)) = self.extents.get(self.next) else {
+                return Ok(0);
+            };
+            self.next += 1;
+            self.reader
+                .seek(SeekFrom::Start(u64::from(extent) * self.block_size))?;
+            self.remaining = u64::from(size);
+        }
+
+        let len = buf.len().min(self.remaining as usize);
+        let read = self.reader.read(&mut buf[..len])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "ISO 9660 image: file extends past the end of the image",
+            ));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// A directory record.
+#[derive(Debug, Clone)]
+struct Record {
+    length: usize,
+    extent: u32,
+    size: u32,
+    flags: u8,
+    recorded: Option<u64>,
+    name: Vec<u8>,
+    system_use: Vec<u8>,
+}
+
+impl Record {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let length = usize::from(*bytes.first()?);
+        let bytes = bytes.get(..length).filter(|_| length >= 34)?;
+        let name_length = usize::from(bytes[32]);
+        let name = bytes.get(33..33 + name_length)?;
+        // The name is padded to an even length
+        let system_use = bytes.get(33 + name_length + (1 - name_length % 2)..)?;
+
+        Some(Self {
+            length,
+            extent: le_u32(&bytes[2..6]),
+            size: le_u32(&bytes[10..14]),
+            flags: bytes[25],
+            recorded: short_time(&bytes[18..25]),
+            name: name.to_vec(),
+            system_use: system_use.to_vec(),
+        })
+    }
+}
+
+/// The Rock Ridge fields of a record.
+#[derive(Debug, Default)]
+struct Fields {
+    name: Option<String>,
+    mode: Option<u32>,
+    mtime: Option<u64>,
+    link: Option<String>,
+    child_link: Option<u32>,
+    relocated: bool,
+    /// Whether the last component of the link target continues in the next
+    /// one.
+    link_continues: bool,
+    /// Whether any field specific to Rock Ridge was seen.
+    is_rock_ridge: bool,
+}
+
+impl Fields {
+    /// Parses the entries of a system use area, returning the location of
+    /// the continuation area if there is one.
+    fn parse(&mut self, mut area: &[u8]) -> Option<(u32, u32, u32)> {
+        let mut continuation = None;
+        while area.len() >= 4 {
+            let length = usize::from(area[2]);
+            let Some(entry) = area.get(..length).filter(|_| length >= 4) else {
+                break;
+            };
+            let data = &entry[4..];
+            match &entry[..2] {
+                b"CE" if data.len() >= 24 => {
+                    let block = le_u32(&data[0..4]);
+                    let offset = le_u32(&data[8..12]);
+                    let length = le_u32(&data[16..20]);
+                    continuation = Some((block, offset, length));
+                }
+                b"ST" => break,
+                b"RR" | b"ER" => self.is_rock_ridge = true,
+                b"PX" if data.len() >= 8 => {
+                    self.is_rock_ridge = true;
+                    self.mode = Some(le_u32(&data[0..4]));
+                }
+                b"NM" if !data.is_empty() => {
+                    self.is_rock_ridge = true;
+                    // The current and parent directory flags only appear on
+                    // `.` and `..`, which are skipped anyway
+                    let name = self.name.get_or_insert_default();
+                    name.push_str(&String::from_utf8_lossy(&data[1..]));
+                }
+                b"SL" if !data.is_empty() => {
+                    self.is_rock_ridge = true;
+                    self.push_link_components(&data[1..]);
+                }
+                b"TF" if !data.is_empty() => {
+                    self.is_rock_ridge = true;
+                    self.mtime = self.mtime.or_else(|| modification_time(data));
+                }
+                b"CL" if data.len() >= 4 => {
+                    self.is_rock_ridge = true;
+                    self.child_link = Some(le_u32(&data[0..4]));
+                }
+                b"RE" => {
+                    self.is_rock_ridge = true;
+                    self.relocated = true;
+                }
+                _ => {}
+            }
+            area = &area[length..];
+        }
+        continuation
+    }
+
+    /// Appends the components of an `SL` entry to the symbolic link target.
+    fn push_link_components(&mut self, mut components: &[u8]) {
+        const CONTINUE: u8 = 0x01;
+        const CURRENT: u8 = 0x02;
+        const PARENT: u8 = 0x04;
+        const ROOT: u8 = 0x08;
+
+        let target = self.link.get_or_insert_default();
+        while let [flags, length, rest @ ..] = components {
+            let Some(content) = rest.get(..usize::from(*length)) else {
+                break;
+            };
+            // Components are separated by `/`, unless the previous one
+            // continues in this one
+            if !self.link_continues && !target.is_empty() && !target.ends_with('/') {
+                target.push('/');
+            }
+            if flags & ROOT != 0 {
+                target.push('/');
+            } else if flags & PARENT != 0 {
+                target.push_str("..");
+            } else if flags & CURRENT != 0 {
+                target.push('.');
+            } else {
+                target.push_str(&String::from_utf8_lossy(content));
+            }
+            self.link_continues = flags & CONTINUE != 0;
+            components = &rest[content.len()..];
+        }
+    }
+}
+
+/// Reads the modification time from the data of a `TF` entry.
+fn modification_time(data: &[u8]) -> Option<u64> {
+    const CREATION: u8 = 0x01;
+    const MODIFY: u8 = 0x02;
+    const LONG_FORM: u8 = 0x80;
+
+    let flags = data[0];
+    if flags & MODIFY == 0 {
+        return None;
+    }
+    let width = if flags & LONG_FORM != 0 { 17 } else { 7 };
+    let offset = 1 + if flags & CREATION != 0 { width } else { 0 };
+    let time = data.get(offset..offset + width)?;
+    if flags & LONG_FORM != 0 {
+        long_time(time)
+    } else {
+        short_time(time)
+    }
+}
+
+/// Converts a 7-byte directory record timestamp.
+fn short_time(time: &[u8]) -> Option<u64> {
+    let [year, month, day, hour, minute, second, offset] = *time else {
+        return None;
+    };
+    if year == 0 && month == 0 && day == 0 {
+        return None;
+    }
+    unix_time(
+        1900 + i64::from(year),
+        [month, day, hour, minute, second].map(i64::from),
+        offset as i8,
+    )
+}
+
+/// Converts a 17-byte volume descriptor timestamp, `YYYYMMDDHHMMSScc`
+/// followed by the offset.
+fn long_time(time: &[u8]) -> Option<u64> {
+    let digits = std::str::from_utf8(&time[..16]).ok()?;
+    let field = |range: std::ops::Range<usize>| digits.get(range)?.parse::<i64>().ok();
+    let year = field(0..4)?;
+    if year == 0 {
+        return None;
+    }
+    let (month, day) = (field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    unix_time(year, [month, day, hour, minute, second], time[16] as i8)
+}
+
+/// Converts a date and time with an offset from UTC in 15-minute intervals.
+fn unix_time(year: i64, [month, day, hour, minute, second]: [i64; 5], offset: i8) -> Option<u64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - i64::from(offset) * 15 * 60;
+    u64::try_from(seconds).ok()
+}
+
+/// Finds the SUSP `SP` entry that starts the system use area of the root
+/// directory's `.` record, returning the number of bytes to skip in every
+/// other record.
+fn susp_skip(system_use: &[u8]) -> Option<usize> {
+    match system_use {
+        [b'S', b'P', 7, _, 0xBE, 0xEF, skip, ..] => Some(usize::from(*skip)),
+        _ => None,
+    }
+}
+
+/// Whether a supplementary volume descriptor is a Joliet one, marked by the
+/// escape sequence of a UCS-2 level.
+fn is_joliet(descriptor: &[u8]) -> bool {
+    let escapes = &descriptor[88..120];
+    [b"%/@", b"%/C", b"%/E"]
+        .iter()
+        .any(|level| escapes.starts_with(*level))
+}
+
+/// Reads `len` bytes at `position`.
+fn read_at<R: Read + Seek>(reader: &mut R, position: u64, len: usize) -> Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(position))?;
+    let mut data = Vec::new();
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() < len {
+        return Err(invalid("truncated image"));
+    }
+    Ok(data)
+}
+
+/// Decodes a space-padded identifier.
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end().to_string()
+}
+
+fn le_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn invalid(message: &str) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("ISO 9660 image: {}", message))
+}
@@ -1,23 +1,58 @@
 //! A unified interface for extracting common archive formats in-memory.
 //!
 //! This crate provides a simple, safe API for extracting various archive formats
-//! including ZIP, TAR (with multiple compression options), 7-Zip, and single-file
-//! compression formats. All extraction happens in-memory without touching the disk.
+//! including ZIP, TAR (with multiple compression options), 7-Zip, RAR, ar, cpio,
+//! RPM, ISO 9660, CAB, XAR, SquashFS and single-file compression formats. All
+//! extraction happens in-memory without touching the disk.
 //!
 //! # Features
 //!
 //! - **Unified API**: Single interface for all archive formats
 //! - **In-memory extraction**: No disk I/O required
-//! - **Safety limits**: Protection against zip bombs and resource exhaustion
+//! - **Safe extraction to disk**: Hardened unpacking beneath a directory, with
+//!   overwrite policies
+//! - **Safety limits**: Protection against zip bombs, resource exhaustion and
+//!   slow-decompressing inputs
 //! - **Pure Rust**: Minimal C dependencies (only bzip2)
 //! - **Cross-platform**: Works on Linux, macOS, Windows (x86_64, ARM64)
+//! - **Archive creation**: In-memory builders for ar, Debian package and 7-Zip archives
+//! - **Archive inspection**: Comments, format variants and compression headers
+//! - **Integrity checks**: Verify checksums of every entry without keeping contents
+//! - **Content hashing**: SHA-256, SHA-512, SHA-1, MD5 and BLAKE3 digests computed during
+//!   extraction
+//! - **Progress and cancellation**: Callbacks with entry and byte counts, and
+//!   cancellation tokens for long extractions
+//! - **Format detection**: Identify archives from their contents, including compressed TAR archives
+//! - **Serialization**: Optional `serde` support for formats, entries, listings and reports
+//! - **Async extraction**: Optional `tokio` support for extracting from
+//!   `AsyncRead` inputs, entry by entry
+//! - **Parallel extraction**: Optional `rayon` support for decompressing ZIP
+//!   entries, 7-Zip blocks, multi-block XZ, multi-frame Zstandard and BGZF gzip
+//!   streams concurrently
+//! - **Command-line tool**: Optional `archive` binary (`cli` feature) to list,
+//!   extract, test, detect, create and convert archives
 //!
 //! # Supported Formats
 //!
 //! - **ZIP** (`.zip`)
-//! - **TAR** (`.tar`, `.tar.gz`, `.tar.bz2`, `.tar.xz`, `.tar.zst`, `.tar.lz4`)
+//! - **TAR** (`.tar`, `.tar.gz`, `.tar.bz2`, `.tar.xz`, `.tar.lz`, `.tar.lzma`,
+//!   `.tar.zst`, `.tar.lz4`, `.tar.Z`, `.tar.br`)
 //! - **7-Zip** (`.7z`)
-//! - **Single-file compression** (`.gz`, `.bz2`, `.xz`, `.lz4`, `.zst`)
+//! - **RAR** (`.rar`, RAR 4 and RAR 5, read-only)
+//! - **ar** (`.a`, `.ar`, and Debian packages `.deb`)
+//! - **cpio** (`.cpio`, newc, odc and binary)
+//! - **RPM** (`.rpm`, payload files and header tags)
+//! - **ISO 9660** (`.iso`, with Joliet and Rock Ridge)
+//! - **Microsoft Cabinet** (`.cab`, MSZIP and LZX)
+//! - **XAR** (`.xar`, and macOS installer packages `.pkg` with their payloads expanded)
+//! - **SquashFS** (`.squashfs`, `.sqfs`, gzip, XZ, LZMA, LZ4 and Zstandard images)
+//! - **Single-file compression** (`.gz`, `.bz2`, `.xz`, `.lz`, `.lzma`, `.lz4`,
+//!   `.zst`, `.Z`, `.br`, `.sz`)
+//!
+//! RAR, XAR, LZX compressed CAB folders, Brotli and Snappy need the `rar`, `xar`, `lzx`,
+//! `brotli` and `snappy` features, and the SHA-1, MD5 and BLAKE3 digests and extraction to disk
+//! need `sha1`, `md5`, `blake3` and `unpack`. All of them are enabled by default. Formats whose
+//! feature is disabled return [`ArchiveError::UnsupportedFormat`].
 //!
 //! # Examples
 //!
@@ -65,6 +100,23 @@
 //! # }
 //! ```
 //!
+//! ## Creating a Debian Package
+//!
+//! ```no_run
+//! use archive::{DebBuilder, DebCompression};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let control = "Package: hello\nVersion: 1.0\nArchitecture: all\n\
+//!                Maintainer: Jane Doe <jane@example.com>\nDescription: greeting\n";
+//!
+//! let mut builder = DebBuilder::new(control).with_compression(DebCompression::Zstd);
+//! builder.add_file_with_mode("/usr/bin/hello", b"#!/bin/sh\necho hello\n".to_vec(), 0o755)?;
+//!
+//! std::fs::write("hello_1.0_all.deb", builder.finish()?)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Multiple Archive Formats
 //!
 //! ```no_run
@@ -96,12 +148,13 @@
 //!
 //! This crate includes built-in protections against:
 //! - **Zip bombs**: Files that expand to enormous sizes
-//! - **Resource exhaustion**: Configurable size limits
+//! - **Resource exhaustion**: Configurable size limits and time budgets
 //! - **Path traversal**: Safe handling of archive paths
 //!
 //! Default limits:
 //! - Maximum file size: 100 MB
 //! - Maximum total extraction size: 1 GB
+//! - Maximum decoder window (XZ, lzip and LZMA dictionary, Zstandard and Brotli window): 128 MB
 //!
 //! # Error Handling
 //!
@@ -123,10 +176,47 @@
 //! # }
 //! ```
 
+pub mod builder;
+mod cab;
+mod cpio;
+mod detect;
 pub mod error;
 pub mod extractor;
 pub mod format;
+pub mod hash;
+pub mod info;
+mod iso;
+mod limit;
+mod lzma;
+mod lzw;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod progress;
+#[cfg(feature = "rar")]
+mod rar;
+mod rpm;
+#[cfg(feature = "serde")]
+mod serialize;
+mod squashfs;
+#[cfg(feature = "tokio")]
+pub mod stream;
+#[cfg(feature = "unpack")]
+pub mod unpack;
+pub mod verify;
+mod window;
+#[cfg(feature = "xar")]
+mod xar;
+mod xz;
 
+pub use builder::{ArBuilder, ArVariant, DebBuilder, DebCompression, SevenZBuilder};
 pub use error::{ArchiveError, Result};
-pub use extractor::{ArchiveExtractor, ExtractedFile};
+pub use extractor::{ArchiveExtractor, EntryLink, EntryMetadata, ExtractedFile, SpecialFile};
 pub use format::ArchiveFormat;
+pub use hash::{Digest, HashAlgorithm};
+pub use info::ArchiveInfo;
+pub use progress::{CancellationToken, Progress};
+#[cfg(feature = "tokio")]
+pub use stream::EntryStream;
+#[cfg(feature = "unpack")]
+pub use unpack::{ExtractOptions, OverwritePolicy};
+pub use verify::VerifyReport;
@@ -0,0 +1,45 @@
+//! Size limits for decoders that only decompress into a writer.
+//!
+//! `lzma-rs` and some other decoders can't be read from incrementally, so
+//! their output can't be bounded by reading only as much as the limits allow.
+//! Writing it through a [`LimitedWriter`] stops them as soon as a limit is
+//! exceeded instead of after the whole stream has been buffered.
+
+use std::io::{self, Write};
+
+/// A writer that fails once more than `limit` bytes have been written.
+pub(crate) struct LimitedWriter<W> {
+    pub(crate) inner: W,
+    limit: usize,
+    pub(crate) written: usize,
+    /// The size that would have been reached by the rejected write.
+    pub(crate) exceeded: Option<usize>,
+}
+
+impl<W> LimitedWriter<W> {
+    pub(crate) fn new(inner: W, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            written: 0,
+            exceeded: None,
+        }
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.written.saturating_add(buf.len());
+        if size > self.limit {
+            self.exceeded = Some(size);
+            return Err(io::Error::other("size limit exceeded"));
+        }
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,320 @@
+//! Decompression of the LZMA-based formats: XZ, lzip and legacy `.lzma`.
+//!
+//! `lzma-rs` decodes XZ and `.lzma` streams itself. lzip files are a sequence
+//! of members, each a raw LZMA stream with fixed literal and position
+//! parameters, framed by a 6-byte header and a 20-byte trailer. The trailer
+//! holds the CRC32 and size of the member's decompressed data, which are
+//! checked after each member is decoded, and the size of the whole member,
+//! which is used to find where each member starts. Data after the last member
+//! that doesn't start with the lzip magic bytes is ignored, as `lzip` does.
+//!
+//! All three only decompress into a writer, so callers buffer the output.
+//! lzip members are located from the end of the file, so the compressed
+//! stream is buffered as well.
+
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+use crate::xz;
+use lzma_rs::decompress::raw::{Lzma2Decoder, LzmaDecoder, LzmaParams, LzmaProperties};
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+
+pub(crate) const LZIP_MAGIC: &[u8] = b"LZIP";
+const LZIP_VERSION: u8 = 1;
+const LZIP_HEADER_SIZE: usize = 6;
+const LZIP_TRAILER_SIZE: usize = 20;
+/// An LZMA stream with an end marker takes at least 5 bytes.
+const MIN_MEMBER_SIZE: usize = LZIP_HEADER_SIZE + 5 + LZIP_TRAILER_SIZE;
+
+/// The properties of every lzip member: `lc = 3`, `lp = 0` and `pb = 2`.
+const LZIP_PROPERTIES: LzmaProperties = LzmaProperties {
+    lc: 3,
+    lp: 0,
+    pb: 2,
+};
+
+/// Dictionary sizes lzip accepts.
+const MIN_DICTIONARY_SIZE: u32 = 4 * 1024;
+const MAX_DICTIONARY_SIZE: u32 = 512 * 1024 * 1024;
+
+/// Size of a `.lzma` header: properties, dictionary size and unpacked size.
+const LZMA_HEADER_SIZE: usize = 13;
+
+/// Largest dictionary used by [`decompress_head`].
+const HEAD_DICTIONARY_SIZE: u32 = 64 * 1024;
+
+/// Decompresses an XZ, lzip or `.lzma` stream, or the one around a TAR
+/// archive, from `input` into `output`.
+///
+/// lzip members and `.lzma` streams declaring a dictionary larger than
+/// `max_window_size` are rejected with [`ArchiveError::WindowTooLarge`], and
+/// their decoders never grow the dictionary past it. lzma-rs takes no memory
+/// limit for XZ, so callers must check XZ block headers first with
+/// [`window::check`](crate::window::check), nested streams included.
+pub(crate) fn decompress<R: BufRead, W: Write>(
+    format: ArchiveFormat,
+    input: &mut R,
+    output: &mut W,
+    max_window_size: usize,
+) -> Result<()> {
+    match format {
+        ArchiveFormat::Lz | ArchiveFormat::TarLz => decompress_lzip(input, output, max_window_size),
+        ArchiveFormat::Lzma | ArchiveFormat::TarLzma => {
+            if let Some(size) = lzma_dictionary_size(input.fill_buf()?)
+                && size as usize > max_window_size
+            {
+                return Err(ArchiveError::WindowTooLarge {
+                    size: u64::from(size),
+                    limit: max_window_size,
+                });
+            }
+            let options = lzma_rs::decompress::Options {
+                memlimit: Some(max_window_size),
+                ..Default::default()
+            };
+            lzma_rs::lzma_decompress_with_options(input, output, &options)
+                .map_err(|e| ArchiveError::InvalidArchive(e.to_string()))
+        }
+        _ => lzma_rs::xz_decompress(input, output)
+            .map_err(|e| ArchiveError::InvalidArchive(e.to_string())),
+    }
+}
+
+/// Returns the dictionary size declared by the lzip member header at the
+/// start of `data`.
+///
+/// The size is coded in one byte as a power of two, minus up to seven
+/// sixteenths of it.
+pub(crate) fn lzip_dictionary_size(data: &[u8]) -> Option<u32> {
+    let header = data.get(..LZIP_HEADER_SIZE)?;
+    if !header.starts_with(LZIP_MAGIC) {
+        return None;
+    }
+    let base = 1u32.checked_shl(u32::from(header[5] & 0x1F))?;
+    let size = base - (base / 16) * u32::from(header[5] >> 5);
+    (MIN_DICTIONARY_SIZE..=MAX_DICTIONARY_SIZE)
+        .contains(&size)
+        .then_some(size)
+}
+
+/// Returns the dictionary size declared by the `.lzma` header at the start of
+/// `data`.
+pub(crate) fn lzma_dictionary_size(data: &[u8]) -> Option<u32> {
+    let size = data.get(1..5)?;
+    Some(u32::from_le_bytes([size[0], size[1], size[2], size[3]]))
+}
+
+/// Whether `data` starts with a plausible `.lzma` header.
+///
+/// The format has no magic bytes, so the header is checked for what `lzma`
+/// and `xz --format=lzma` write: valid properties, a dictionary size of
+/// `2^n` or `2^n + 2^(n-1)`, and an unpacked size that is either unknown or
+/// below 256 TiB.
+pub(crate) fn is_lzma(data: &[u8]) -> bool {
+    let Some(header) = data.get(..LZMA_HEADER_SIZE) else {
+        return false;
+    };
+    let Some(dictionary) = lzma_dictionary_size(header) else {
+        return false;
+    };
+    let unpacked_size = u64::from_le_bytes(header[5..13].try_into().unwrap_or_default());
+
+    let rounded = dictionary & (dictionary >> 1);
+    let standard_dictionary = dictionary >= MIN_DICTIONARY_SIZE
+        && (dictionary.is_power_of_two()
+            || (rounded.is_power_of_two() && (dictionary ^ rounded).is_power_of_two()));
+    header[0] < 9 * 5 * 5
+        && standard_dictionary
+        && (unpacked_size == u64::MAX || unpacked_size < 1 << 48)
+}
+
+/// Decodes every lzip member of `input`, checking each member's trailer.
+fn decompress_lzip<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    max_window_size: usize,
+) -> Result<()> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    for (index, range) in lzip_members(&data)?.into_iter().enumerate() {
+        let member = index + 1;
+        let header = &data[range.start..range.start + LZIP_HEADER_SIZE];
+        if header[4] != LZIP_VERSION {
+            return Err(ArchiveError::UnsupportedFormat(format!(
+                "lzip version {}",
+                header[4]
+            )));
+        }
+        let dictionary_size = lzip_dictionary_size(header)
+            .ok_or_else(|| invalid(format!("member {} has an invalid dictionary size", member)))?;
+        if dictionary_size as usize > max_window_size {
+            return Err(ArchiveError::WindowTooLarge {
+                size: u64::from(dictionary_size),
+                limit: max_window_size,
+            });
+        }
+
+        let mut checked = CheckedWriter {
+            inner: &mut *output,
+            crc: flate2::Crc::new(),
+            size: 0,
+        };
+        let trailer_start = range.end - LZIP_TRAILER_SIZE;
+        let mut stream = &data[range.start + LZIP_HEADER_SIZE..trailer_start];
+        let params = LzmaParams::new(LZIP_PROPERTIES, dictionary_size, None);
+        LzmaDecoder::new(params, Some(max_window_size))
+            .and_then(|mut decoder| decoder.decompress(&mut stream, &mut checked))
+            .map_err(|e| invalid(format!("member {}: {}", member, e)))?;
+
+        let trailer = &data[trailer_start..range.end];
+        let crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap_or_default());
+        let data_size = u64::from_le_bytes(trailer[4..12].try_into().unwrap_or_default());
+        if crc != checked.crc.sum() {
+            return Err(invalid(format!(
+                "member {} CRC mismatch: expected {:08x}, found {:08x}",
+                member,
+                crc,
+                checked.crc.sum()
+            )));
+        }
+        if data_size != checked.size {
+            return Err(invalid(format!(
+                "member {} holds {} bytes, but its trailer records {}",
+                member, checked.size, data_size
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the start of an XZ, lzip or `.lzma` stream, for format detection.
+///
+/// The decoders hold back their output until their dictionary fills up or the
+/// stream ends, so the declared dictionary size is capped at
+/// [`HEAD_DICTIONARY_SIZE`]: the bytes up to that size decode the same, and
+/// `output` is expected to fail once it has enough of them. The XZ decoder
+/// buffers each block whole whatever its dictionary, so only the chunks
+/// covering the start of the first block are decoded. Only the first lzip
+/// member is decoded, without checking its trailer, and since members are
+/// located from their trailers `data` must hold all of it.
+pub(crate) fn decompress_head<W: Write>(
+    format: ArchiveFormat,
+    data: &[u8],
+    output: &mut W,
+) -> Result<()> {
+    match format {
+        ArchiveFormat::Lz | ArchiveFormat::TarLz => {
+            let dictionary_size =
+                lzip_dictionary_size(data).ok_or_else(|| invalid("missing lzip header"))?;
+            let first = lzip_members(data)?.swap_remove(0);
+            let mut stream = &data[LZIP_HEADER_SIZE..first.end - LZIP_TRAILER_SIZE];
+            let params = LzmaParams::new(
+                LZIP_PROPERTIES,
+                dictionary_size.min(HEAD_DICTIONARY_SIZE),
+                None,
+            );
+            LzmaDecoder::new(params, None)
+                .and_then(|mut decoder| decoder.decompress(&mut stream, output))
+                .map_err(|e| invalid(e.to_string()))
+        }
+        ArchiveFormat::Lzma | ArchiveFormat::TarLzma => {
+            let (header, stream) = data
+                .split_at_checked(LZMA_HEADER_SIZE)
+                .ok_or_else(|| ArchiveError::InvalidArchive("truncated LZMA header".into()))?;
+            let mut header = header.to_vec();
+            let dictionary_size = lzma_dictionary_size(&header)
+                .unwrap_or_default()
+                .min(HEAD_DICTIONARY_SIZE);
+            header[1..5].copy_from_slice(&dictionary_size.to_le_bytes());
+            lzma_rs::lzma_decompress(&mut header.as_slice().chain(stream), output)
+                .map_err(|e| ArchiveError::InvalidArchive(e.to_string()))
+        }
+        _ => {
+            let chunks = xz::head_chunks(data, u64::from(HEAD_DICTIONARY_SIZE))
+                .ok_or_else(|| ArchiveError::InvalidArchive("truncated XZ block".into()))?;
+            // A zero control byte ends the block after the chunks taken
+            let mut stream = chunks.chain(&[0][..]);
+            Lzma2Decoder::new()
+                .decompress(&mut stream, output)
+                .map_err(|e| ArchiveError::InvalidArchive(e.to_string()))
+        }
+    }
+}
+
+/// Locates the members of an lzip file, in order.
+///
+/// The LZMA decoder needs to know where each stream ends, so members are
+/// found from the end of the file by following the member sizes in their
+/// trailers, as `lzip` does. Trailing data after the last member is skipped,
+/// unless it starts like a member, which means the last member is truncated
+/// or its trailer is corrupt.
+fn lzip_members(data: &[u8]) -> Result<Vec<Range<usize>>> {
+    if !data.starts_with(LZIP_MAGIC) {
+        return Err(invalid("missing lzip header"));
+    }
+
+    let mut end = data.len();
+    while lzip_member_size(data, end).is_none() {
+        if end <= MIN_MEMBER_SIZE {
+            return Err(invalid("truncated or corrupt member"));
+        }
+        end -= 1;
+    }
+    let trailing = &data[end..];
+    if LZIP_MAGIC.starts_with(&trailing[..trailing.len().min(LZIP_MAGIC.len())])
+        && !trailing.is_empty()
+    {
+        return Err(invalid("truncated or corrupt member"));
+    }
+
+    let mut members = Vec::new();
+    while end > 0 {
+        let size = lzip_member_size(data, end).ok_or_else(|| {
+            invalid(format!(
+                "corrupt member size in the trailer ending at {}",
+                end
+            ))
+        })?;
+        members.push(end - size..end);
+        end -= size;
+    }
+    members.reverse();
+    Ok(members)
+}
+
+/// Returns the size of the lzip member ending at `end` if its trailer records
+/// a size that leads back to a member header.
+fn lzip_member_size(data: &[u8], end: usize) -> Option<usize> {
+    let size = data.get(end.checked_sub(8)?..end)?;
+    let size = usize::try_from(u64::from_le_bytes(size.try_into().ok()?)).ok()?;
+    (MIN_MEMBER_SIZE..=end)
+        .contains(&size)
+        .then(|| data[end - size..].starts_with(LZIP_MAGIC))?
+        .then_some(size)
+}
+
+fn invalid(message: impl std::fmt::Display) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("lzip: {}", message))
+}
+
+/// Computes the CRC32 and size of the data written through it.
+struct CheckedWriter<W> {
+    inner: W,
+    crc: flate2::Crc,
+    size: u64,
+}
+
+impl<W: Write> Write for CheckedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
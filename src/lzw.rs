@@ -0,0 +1,208 @@
+//! Decompression of Unix `compress` (`.Z`) streams.
+//!
+//! A `.Z` stream is a 3-byte header followed by LZW codes packed least
+//! significant bit first. Codes start 9 bits wide and grow by one bit each time
+//! the string table fills up, up to the maximum recorded in the header, which
+//! is between 9 and 16 bits. In block mode, code 256 clears the table and
+//! returns to 9-bit codes, which `compress` emits once the compression ratio
+//! drops.
+//!
+//! `compress` writes codes in groups of eight, so that a group of `n`-bit codes
+//! fills exactly `n` bytes, and pads the current group when the code width
+//! changes. The decoder skips that padding to stay in step.
+
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The magic bytes that start every `.Z` stream.
+pub(crate) const MAGIC: &[u8] = &[0x1F, 0x9D];
+
+/// Header flag selecting block mode, in which code 256 clears the table.
+const BLOCK_MODE: u8 = 0x80;
+/// Header bits holding the maximum code width.
+const MAX_BITS_MASK: u8 = 0x1F;
+
+const INIT_BITS: u32 = 9;
+const MAX_BITS: u32 = 16;
+const CLEAR: u32 = 256;
+
+/// A reader that decompresses a `.Z` stream.
+pub(crate) struct Decoder<R> {
+    reader: BufReader<R>,
+    max_bits: u32,
+    block_mode: bool,
+    /// The current code width.
+    bits: u32,
+    bit_buffer: u32,
+    bit_count: u32,
+    /// Codes read since the last change of width, modulo 8.
+    group_position: u32,
+    prefix: Vec<u16>,
+    suffix: Vec<u8>,
+    next_code: u32,
+    previous_code: Option<u32>,
+    /// The first byte of the last string decoded.
+    first_byte: u8,
+    /// The bytes of the current string in reverse order, still to be read.
+    pending: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Reads the stream header and creates a decoder for the codes after it.
+    pub(crate) fn new(reader: R) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u8; 3];
+        reader.read_exact(&mut header)?;
+        if !header.starts_with(MAGIC) {
+            return Err(invalid("not a compress (.Z) stream".to_string()));
+        }
+        let max_bits = u32::from(header[2] & MAX_BITS_MASK);
+        if !(INIT_BITS..=MAX_BITS).contains(&max_bits) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("compress (.Z) stream with {}-bit codes", max_bits),
+            ));
+        }
+        let block_mode = header[2] & BLOCK_MODE != 0;
+
+        let table_size = 1 << max_bits;
+        Ok(Self {
+            reader,
+            max_bits,
+            block_mode,
+            bits: INIT_BITS,
+            bit_buffer: 0,
+            bit_count: 0,
+            group_position: 0,
+            prefix: vec![0; table_size],
+            suffix: vec![0; table_size],
+            next_code: Self::first_code(block_mode),
+            previous_code: None,
+            first_byte: 0,
+            pending: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// The first code added to the table, after the clear code in block
+    /// mode.
+    fn first_code(block_mode: bool) -> u32 {
+        if block_mode { CLEAR + 1 } else { CLEAR }
+    }
+
+    /// Reads the next code, or `None` at the end of the stream. Bits that
+    /// don't make up a whole code at the end are ignored.
+    fn read_code(&mut self) -> io::Result<Option<u32>> {
+        while self.bit_count < self.bits {
+            let Some(&byte) = self.reader.fill_buf()?.first() else {
+                return Ok(None);
+            };
+            self.reader.consume(1);
+            self.bit_buffer |= u32::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let code = self.bit_buffer & ((1 << self.bits) - 1);
+        self.bit_buffer >>= self.bits;
+        self.bit_count -= self.bits;
+        self.group_position = (self.group_position + 1) % 8;
+        Ok(Some(code))
+    }
+
+    /// Skips the padding that completes the current group of codes.
+    fn skip_group(&mut self) -> io::Result<()> {
+        while self.group_position != 0 {
+            if self.read_code()?.is_none() {
+                break;
+            }
+        }
+        self.group_position = 0;
+        Ok(())
+    }
+
+    /// Decodes the next string into `pending`, returning `false` at the end
+    /// of the stream.
+    fn decode_next(&mut self) -> io::Result<bool> {
+        loop {
+            if self.bits < self.max_bits && self.next_code >= 1 << self.bits {
+                self.skip_group()?;
+                self.bits += 1;
+            }
+            let Some(code) = self.read_code()? else {
+                return Ok(false);
+            };
+
+            if self.block_mode && code == CLEAR {
+                self.skip_group()?;
+                self.bits = INIT_BITS;
+                self.next_code = Self::first_code(true);
+                self.previous_code = None;
+                continue;
+            }
+
+            let Some(previous) = self.previous_code else {
+                // The first code after the start or a clear is a literal
+                let byte = u8::try_from(code).map_err(|_| {
+                    invalid(format!(
+                        "invalid first code {} in compress (.Z) stream",
+                        code
+                    ))
+                })?;
+                self.pending.push(byte);
+                self.first_byte = byte;
+                self.previous_code = Some(code);
+                return Ok(true);
+            };
+
+            let mut current = code;
+            if code >= self.next_code {
+                if code > self.next_code {
+                    return Err(invalid(format!(
+                        "invalid code {} in compress (.Z) stream",
+                        code
+                    )));
+                }
+                // The string being defined: the previous one plus its own
+                // first byte
+                self.pending.push(self.first_byte);
+                current = previous;
+            }
+            while current > 0xFF {
+                self.pending.push(self.suffix[current as usize]);
+                current = u32::from(self.prefix[current as usize]);
+            }
+            self.first_byte = current as u8;
+            self.pending.push(self.first_byte);
+
+            if self.next_code < 1 << self.max_bits {
+                self.prefix[self.next_code as usize] = previous as u16;
+                self.suffix[self.next_code as usize] = self.first_byte;
+                self.next_code += 1;
+            }
+            self.previous_code = Some(code);
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.pending.is_empty() && (self.finished || !self.decode_next()?) {
+                self.finished = true;
+                break;
+            }
+            let count = self.pending.len().min(buf.len() - written);
+            for byte in &mut buf[written..written + count] {
+                // `pending` holds the string backwards
+                *byte = self.pending.pop().unwrap_or_default();
+            }
+            written += count;
+        }
+        Ok(written)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,191 @@
+//! Block-parallel decompression of XZ, Zstandard and gzip streams.
+//!
+//! Some compressors split their output into independently compressed parts:
+//! multi-threaded `xz` writes several blocks, `pzstd` and `zstd --adapt`
+//! style tools write several frames, and `bgzip` writes BGZF files, whose
+//! gzip members record their own size. The parts of such streams are
+//! decompressed on the rayon thread pool and joined in order.
+//!
+//! Streams made of a single part, or whose parts can't be located without
+//! decompressing them, are left to the serial decoders.
+
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+use crate::progress::{LZMA_INPUT_BUFFER_SIZE, Tracker};
+use crate::{window, xz};
+use rayon::prelude::*;
+use std::io::{self, BufReader, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An independently compressed part of a stream.
+enum Part<'a> {
+    /// An XZ block, wrapped into a stream of its own.
+    Xz(Vec<u8>),
+    /// A Zstandard frame.
+    Zstd(&'a [u8]),
+    /// A gzip member.
+    Gzip(&'a [u8]),
+}
+
+/// Decompresses `data` in parallel if it consists of several parts.
+///
+/// Returns `None` if the stream can't be split, in which case nothing has
+/// been decompressed. The decompressed size is limited to `limit` across all
+/// parts, and `too_large` creates the error for a stream exceeding it.
+pub(crate) fn decompress(
+    tracker: &Tracker,
+    data: &[u8],
+    format: ArchiveFormat,
+    window_limit: usize,
+    limit: usize,
+    too_large: impl Fn(usize) -> ArchiveError,
+) -> Option<Result<Vec<u8>>> {
+    let parts = match format {
+        ArchiveFormat::Xz | ArchiveFormat::TarXz => xz_parts(data)?,
+        ArchiveFormat::Zst | ArchiveFormat::TarZst => zstd_parts(data)?,
+        ArchiveFormat::Gz | ArchiveFormat::TarGz => gzip_parts(data)?,
+        _ => return None,
+    };
+
+    let used = AtomicUsize::new(0);
+    let result = parts
+        .par_iter()
+        .map(|part| {
+            let mut output = LimitedOutput {
+                data: Vec::new(),
+                used: &used,
+                limit,
+            };
+            decode(tracker, part, window_limit, &mut output)?;
+            Ok(output.data)
+        })
+        .collect::<Result<Vec<_>>>();
+
+    // The decoders only see an I/O error when the limit is hit
+    let used = used.into_inner();
+    if used > limit {
+        return Some(Err(too_large(used)));
+    }
+    Some(result.map(|parts| parts.concat()))
+}
+
+fn decode(
+    tracker: &Tracker,
+    part: &Part<'_>,
+    window_limit: usize,
+    output: &mut LimitedOutput<'_>,
+) -> Result<()> {
+    let output = &mut tracker.writer(output);
+    match part {
+        Part::Xz(stream) => {
+            let mut reader =
+                BufReader::with_capacity(LZMA_INPUT_BUFFER_SIZE, tracker.input(stream.as_slice()));
+            lzma_rs::xz_decompress(&mut reader, output)
+                .map_err(|e| ArchiveError::InvalidArchive(e.to_string()))?;
+        }
+        Part::Zstd(frame) => {
+            let mut decoder = window::zstd_decoder(tracker.input(*frame), window_limit)?;
+            io::copy(&mut decoder, output)?;
+        }
+        Part::Gzip(member) => {
+            let mut decoder = flate2::read::GzDecoder::new(tracker.input(*member));
+            io::copy(&mut decoder, output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits XZ data into its blocks, if it is a single stream.
+///
+/// Concatenated streams and stream padding are left to the serial decoder,
+/// which rejects them.
+fn xz_parts(data: &[u8]) -> Option<Vec<Part<'_>>> {
+    let streams = xz::streams(data);
+    if streams.len() != 1 || streams[0].start != 0 || !data.ends_with(xz::FOOTER_MAGIC) {
+        return None;
+    }
+
+    let stream = &streams[0];
+    let parts: Vec<_> = stream
+        .blocks
+        .iter()
+        .map(|block| Part::Xz(xz::single_block_stream(data, stream, block)))
+        .collect();
+    (parts.len() > 1).then_some(parts)
+}
+
+/// Splits Zstandard data into its frames.
+fn zstd_parts(data: &[u8]) -> Option<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut remaining = data;
+    while !remaining.is_empty() {
+        let size = zstd::zstd_safe::find_frame_compressed_size(remaining).ok()?;
+        let (frame, rest) = remaining.split_at_checked(size)?;
+        parts.push(Part::Zstd(frame));
+        remaining = rest;
+    }
+    (parts.len() > 1).then_some(parts)
+}
+
+/// Splits BGZF data into its gzip members.
+fn gzip_parts(data: &[u8]) -> Option<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut remaining = data;
+    while !remaining.is_empty() {
+        let size = bgzf_member_size(remaining)?;
+        let (member, rest) = remaining.split_at_checked(size)?;
+        parts.push(Part::Gzip(member));
+        remaining = rest;
+    }
+    (parts.len() > 1).then_some(parts)
+}
+
+/// Reads the size of the gzip member at the start of `data` from the `BC`
+/// extra subfield that BGZF adds, as described in section 4.1 of the SAM
+/// format specification.
+fn bgzf_member_size(data: &[u8]) -> Option<usize> {
+    const FEXTRA: u8 = 0x04;
+
+    let header = data.get(..12)?;
+    if header[..3] != [0x1F, 0x8B, 0x08] || header[3] & FEXTRA == 0 {
+        return None;
+    }
+    let extra_len = u16::from_le_bytes([header[10], header[11]]) as usize;
+    let mut extra = data.get(12..12 + extra_len)?;
+
+    while let [id1, id2, len_low, len_high, rest @ ..] = extra {
+        let len = u16::from_le_bytes([*len_low, *len_high]) as usize;
+        let field = rest.get(..len)?;
+        if [*id1, *id2] == *b"BC" && len == 2 {
+            return Some(u16::from_le_bytes([field[0], field[1]]) as usize + 1);
+        }
+        extra = &rest[len..];
+    }
+    None
+}
+
+/// Collects decompressed data while counting it against a limit shared by
+/// all parts.
+struct LimitedOutput<'a> {
+    data: Vec<u8>,
+    used: &'a AtomicUsize,
+    limit: usize,
+}
+
+impl Write for LimitedOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let used = self
+            .used
+            .fetch_add(buf.len(), Ordering::Relaxed)
+            .saturating_add(buf.len());
+        if used > self.limit {
+            return Err(io::Error::other("decompressed size limit exceeded"));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
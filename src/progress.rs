@@ -0,0 +1,336 @@
+//! Progress reporting, cancellation and time budgets.
+//!
+//! A progress callback is attached with
+//! [`ArchiveExtractor::with_progress`](crate::ArchiveExtractor::with_progress),
+//! a [`CancellationToken`] with
+//! [`ArchiveExtractor::with_cancellation`](crate::ArchiveExtractor::with_cancellation)
+//! and a time budget with
+//! [`ArchiveExtractor::with_max_duration`](crate::ArchiveExtractor::with_max_duration).
+//! All of them are consulted while data is read and while decoders write
+//! their output, so a long extraction can be followed and stopped from
+//! another thread, or stopped once it takes too long.
+
+use crate::error::{ArchiveError, Result};
+use std::fmt;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Buffer size for tracked input read by lzma-rs, which holds back the output
+/// of an XZ block until the whole block is decoded. Its reads are then the
+/// only point at which it can be stopped, so they are kept small for highly
+/// compressed input not to run far past a deadline.
+pub(crate) const LZMA_INPUT_BUFFER_SIZE: usize = 256;
+
+/// A snapshot of an extraction in progress, passed to the progress callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    /// Number of entries that have been completely extracted.
+    pub entries_processed: usize,
+
+    /// Bytes read from the archive data so far. Formats that seek, like ZIP
+    /// and 7-Zip, count every byte read, including their headers.
+    pub bytes_in: u64,
+
+    /// Decompressed bytes of entry contents produced so far.
+    pub bytes_out: u64,
+
+    /// Path of the entry being extracted, or empty before the first entry.
+    pub current_path: &'a str,
+}
+
+/// A callback receiving [`Progress`] updates.
+///
+/// Returning [`ControlFlow::Break`] stops the extraction with
+/// [`ArchiveError::Cancelled`].
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Arc<ProgressFn>);
+
+type ProgressFn = dyn Fn(&Progress<'_>) -> ControlFlow<()> + Send + Sync;
+
+impl ProgressCallback {
+    pub(crate) fn new(
+        callback: impl Fn(&Progress<'_>) -> ControlFlow<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A shared flag for cancelling an extraction from another thread.
+///
+/// Clones share the same flag. Once [`cancel`](Self::cancel) is called, every
+/// extraction using the token stops with [`ArchiveError::Cancelled`] the next
+/// time it reads a chunk of data.
+///
+/// # Examples
+///
+/// ```
+/// use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, CancellationToken};
+///
+/// let token = CancellationToken::new();
+/// let extractor = ArchiveExtractor::new().with_cancellation(token.clone());
+///
+/// // E.g. from a "Cancel" button handler on the UI thread
+/// token.cancel();
+///
+/// # let data = vec![0u8; 100];
+/// let result = extractor.extract(&data, ArchiveFormat::Gz);
+/// assert!(matches!(result, Err(ArchiveError::Cancelled)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every extraction using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether [`cancel`](Self::cancel) has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracks the progress of a single extraction.
+///
+/// The counters are updated by [`TrackedInput`] and [`TrackedOutput`], which
+/// fail with an I/O error once the extraction is stopped. Since that error may
+/// be wrapped by the format libraries, [`finish`](Self::finish) replaces
+/// whatever error comes out with [`ArchiveError::Cancelled`] or
+/// [`ArchiveError::Timeout`].
+///
+/// Entries may be extracted in parallel, so the tracker can be shared between
+/// threads.
+pub(crate) struct Tracker<'a> {
+    callback: Option<&'a ProgressCallback>,
+    token: Option<&'a CancellationToken>,
+    max_duration: Option<Duration>,
+    deadline: Option<Instant>,
+    entries: AtomicUsize,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    path: Mutex<String>,
+    stopped: OnceLock<Stop>,
+}
+
+/// Why an extraction was stopped.
+#[derive(Debug, Clone, Copy)]
+enum Stop {
+    Cancelled,
+    Timeout,
+}
+
+impl<'a> Tracker<'a> {
+    /// Creates a tracker for an extraction starting now, which times out once
+    /// `max_duration` has elapsed.
+    pub(crate) fn new(
+        callback: Option<&'a ProgressCallback>,
+        token: Option<&'a CancellationToken>,
+        max_duration: Option<Duration>,
+    ) -> Self {
+        Self {
+            callback,
+            token,
+            max_duration,
+            // A deadline too far away to represent never passes
+            deadline: max_duration.and_then(|duration| Instant::now().checked_add(duration)),
+            entries: AtomicUsize::new(0),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            path: Mutex::new(String::new()),
+            stopped: OnceLock::new(),
+        }
+    }
+
+    /// Wraps the archive data so that reads from it are counted.
+    pub(crate) fn input<R>(&'a self, inner: R) -> TrackedInput<'a, R> {
+        TrackedInput {
+            inner,
+            tracker: self,
+        }
+    }
+
+    /// Wraps the reader of an entry's contents so that reads from it are counted.
+    pub(crate) fn output<R>(&'a self, inner: R) -> TrackedOutput<'a, R> {
+        TrackedOutput {
+            inner,
+            tracker: self,
+        }
+    }
+
+    /// Wraps the writer a decoder writes to, so that decoding stops once the
+    /// extraction is stopped. What is written isn't counted, since it is
+    /// counted when read back as entry contents.
+    pub(crate) fn writer<W>(&'a self, inner: W) -> TrackedWriter<'a, W> {
+        TrackedWriter {
+            inner,
+            tracker: self,
+        }
+    }
+
+    /// Counts data decompressed by a library that reads the archive from
+    /// memory and writes whole entries, so neither side can be wrapped.
+    #[cfg(feature = "rar")]
+    pub(crate) fn advance(&self, bytes_in: u64, bytes_out: u64) -> io::Result<()> {
+        self.bytes_in.fetch_add(bytes_in, Ordering::Relaxed);
+        self.bytes_out.fetch_add(bytes_out, Ordering::Relaxed);
+        self.report()
+    }
+
+    /// Records that extraction of the entry at `path` has started.
+    pub(crate) fn start_entry(&self, path: &str) -> io::Result<()> {
+        let mut current = self.path();
+        current.clear();
+        current.push_str(path);
+        drop(current);
+        self.report()
+    }
+
+    /// Records that the current entry has been completely extracted.
+    pub(crate) fn finish_entry(&self) -> io::Result<()> {
+        self.entries.fetch_add(1, Ordering::Relaxed);
+        self.report()
+    }
+
+    fn path(&self) -> MutexGuard<'_, String> {
+        self.path.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns `result`, or the reason the extraction was stopped.
+    pub(crate) fn finish<T>(&self, result: Result<T>) -> Result<T> {
+        match self.stopped.get().copied() {
+            Some(Stop::Cancelled) => Err(ArchiveError::Cancelled),
+            Some(Stop::Timeout) => Err(ArchiveError::Timeout {
+                limit: self.max_duration.unwrap_or_default(),
+            }),
+            None => result,
+        }
+    }
+
+    /// Checks for cancellation and the deadline, and passes the current
+    /// progress to the callback.
+    fn report(&self) -> io::Result<()> {
+        if self.stopped.get().is_some() {
+            return Err(cancelled());
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            let _ = self.stopped.set(Stop::Timeout);
+            return Err(cancelled());
+        }
+        let cancelled_by_token = self.token.is_some_and(CancellationToken::is_cancelled);
+        let stopped_by_callback = !cancelled_by_token
+            && self.callback.is_some_and(|callback| {
+                let path = self.path();
+                let progress = Progress {
+                    entries_processed: self.entries.load(Ordering::Relaxed),
+                    bytes_in: self.bytes_in.load(Ordering::Relaxed),
+                    bytes_out: self.bytes_out.load(Ordering::Relaxed),
+                    current_path: &path,
+                };
+                (callback.0)(&progress).is_break()
+            });
+
+        if cancelled_by_token || stopped_by_callback {
+            let _ = self.stopped.set(Stop::Cancelled);
+            return Err(cancelled());
+        }
+        Ok(())
+    }
+}
+
+fn cancelled() -> io::Error {
+    io::Error::other("extraction cancelled")
+}
+
+/// Archive data whose reads are counted as [`Progress::bytes_in`].
+#[derive(Clone)]
+pub(crate) struct TrackedInput<'a, R> {
+    inner: R,
+    tracker: &'a Tracker<'a>,
+}
+
+impl<R: Read> Read for TrackedInput<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let tracker = self.tracker;
+        tracker.bytes_in.fetch_add(read as u64, Ordering::Relaxed);
+        tracker.report()?;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for TrackedInput<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.tracker.report()?;
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let tracker = self.tracker;
+        tracker.bytes_in.fetch_add(amount as u64, Ordering::Relaxed);
+        self.inner.consume(amount);
+    }
+}
+
+impl<R: Seek> Seek for TrackedInput<'_, R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(position)
+    }
+}
+
+/// Entry contents whose reads are counted as [`Progress::bytes_out`].
+pub(crate) struct TrackedOutput<'a, R> {
+    inner: R,
+    tracker: &'a Tracker<'a>,
+}
+
+impl<R: Read> Read for TrackedOutput<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let tracker = self.tracker;
+        tracker.bytes_out.fetch_add(read as u64, Ordering::Relaxed);
+        tracker.report()?;
+        Ok(read)
+    }
+}
+
+/// A decoder's output, checked for cancellation and the deadline on every
+/// write.
+pub(crate) struct TrackedWriter<'a, W> {
+    inner: W,
+    tracker: &'a Tracker<'a>,
+}
+
+impl<W> TrackedWriter<'_, W> {
+    pub(crate) fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for TrackedWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tracker.report()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
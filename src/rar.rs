@@ -0,0 +1,267 @@
+//! RAR archive support, built on the `rars` crate.
+//!
+//! Archives are parsed from memory in one go. RAR 1.5 to 4.x and RAR 5.0 and
+//! later archives are supported, as are the rare RAR 1.3 and 1.4 ones.
+//! Encrypted entries and archives with encrypted headers are decrypted with
+//! the extractor's password and rejected without one. Only a single volume
+//! is read, so entries that continue into another volume of a multi-volume
+//! set are rejected.
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::{EntryLink, EntryMetadata};
+use crate::info::{RarInfo, RarVersion};
+use rars::{Archive, ArchiveFamily, ArchiveMember, ArchiveMemberDetail, ArchiveReadOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::UNIX_EPOCH;
+
+/// RAR 5.0 redirection types, from the file system redirection record.
+const REDIRECTION_UNIX_SYMLINK: u64 = 1;
+const REDIRECTION_WINDOWS_SYMLINK: u64 = 2;
+const REDIRECTION_HARD_LINK: u64 = 4;
+
+/// File type bits of a Unix mode.
+const S_IFMT: u64 = 0o170000;
+const S_IFLNK: u64 = 0o120000;
+
+/// Read options decrypting with `password` and enforcing the extractor's
+/// limits while decoding.
+pub(crate) fn read_options(
+    password: Option<&str>,
+    max_file_size: usize,
+    max_total_size: usize,
+    max_window_size: usize,
+) -> ArchiveReadOptions<'_> {
+    ArchiveReadOptions::with_optional_password(password.map(str::as_bytes))
+        .with_max_member_output_bytes(max_file_size as u64)
+        .with_max_total_output_bytes(max_total_size as u64)
+        .with_rar50_dictionary_size_limit(max_window_size as u64)
+}
+
+/// Parses the archive headers of `data`.
+pub(crate) fn read(data: &[u8], options: ArchiveReadOptions<'_>) -> Result<Archive> {
+    rars::ArchiveReader::read_with_options(data, options).map_err(map_error)
+}
+
+/// Rejects members that can't be extracted from a single volume, or without
+/// a password when `has_password` is false.
+pub(crate) fn check_member(member: &ArchiveMember, has_password: bool) -> Result<()> {
+    if member.meta.is_encrypted && !has_password {
+        return Err(ArchiveError::UnsupportedFormat(format!(
+            "RAR entry '{}' is encrypted and no password was given",
+            entry_path(member)
+        )));
+    }
+    if member.meta.is_split_before || member.meta.is_split_after {
+        return Err(ArchiveError::UnsupportedFormat(format!(
+            "RAR entry '{}' is split across volumes of a multi-volume archive",
+            entry_path(member)
+        )));
+    }
+    Ok(())
+}
+
+/// The path of a member, with `/` separating components.
+///
+/// RAR 5.0 always uses `/`, while older versions use `\`.
+pub(crate) fn entry_path(member: &ArchiveMember) -> String {
+    let path = String::from_utf8_lossy(&member.meta.name);
+    match member.meta.family {
+        ArchiveFamily::Rar50Plus => path.into_owned(),
+        _ => path.replace('\\', "/"),
+    }
+}
+
+/// Whether a member is a Unix symbolic link whose target is stored as its
+/// contents, as RAR versions before 5.0 store them.
+pub(crate) fn is_legacy_symlink(member: &ArchiveMember) -> bool {
+    member.meta.family != ArchiveFamily::Rar50Plus
+        && member.meta.attr_source() == rars::AttrSource::Unix
+        && member.meta.file_attr & S_IFMT == S_IFLNK
+}
+
+pub(crate) fn entry_metadata(member: &ArchiveMember) -> EntryMetadata {
+    let meta = &member.meta;
+    let (crc32, compression_method, link) = match &member.detail {
+        ArchiveMemberDetail::Rar15To40 { method, crc32, .. } => (
+            Some(*crc32),
+            method_name(u64::from(method.wrapping_sub(0x30))),
+            None,
+        ),
+        ArchiveMemberDetail::Rar50Plus {
+            compression_info,
+            crc32,
+            redirection,
+            ..
+        } => {
+            let link = redirection.as_ref().and_then(|redirection| {
+                let target = String::from_utf8_lossy(&redirection.target_name).into_owned();
+                match redirection.redirection_type {
+                    REDIRECTION_UNIX_SYMLINK | REDIRECTION_WINDOWS_SYMLINK => {
+                        Some(EntryLink::Symbolic(target))
+                    }
+                    REDIRECTION_HARD_LINK => Some(EntryLink::Hard(target)),
+                    _ => None,
+                }
+            });
+            (*crc32, method_name((compression_info >> 7) & 0x07), link)
+        }
+        _ => (None, None, None),
+    };
+
+    EntryMetadata {
+        compressed_size: Some(meta.packed_size),
+        crc32,
+        compression_method,
+        encrypted: meta.is_encrypted,
+        mode: (meta.attr_source() == rars::AttrSource::Unix)
+            .then_some((meta.file_attr & 0o7777) as u32),
+        mtime: meta
+            .modification_time()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs()),
+        link,
+        ..EntryMetadata::default()
+    }
+}
+
+/// The name RAR gives to a compression method, from fastest to best.
+fn method_name(method: u64) -> Option<String> {
+    let name = match method {
+        0 => "Store",
+        1 => "Fastest",
+        2 => "Fast",
+        3 => "Normal",
+        4 => "Good",
+        5 => "Best",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// Gathers archive-level information, which only needs the headers.
+///
+/// Encrypted headers are decrypted with `password`; without one, only the
+/// version and encryption flags are reported.
+pub(crate) fn inspect(data: &[u8], password: Option<&str>) -> Result<RarInfo> {
+    let signature = rars::ArchiveReader::detect(data).map_err(map_error)?;
+    let version = match signature.family {
+        ArchiveFamily::Rar13 => RarVersion::Rar14,
+        ArchiveFamily::Rar15To40 => RarVersion::Rar4,
+        _ => RarVersion::Rar5,
+    };
+
+    let options = ArchiveReadOptions::with_optional_password(password.map(str::as_bytes));
+    let archive = match rars::ArchiveReader::read_with_options(data, options) {
+        Ok(archive) => archive,
+        Err(e) if e.kind() == rars::ErrorKind::PasswordRequired => {
+            return Ok(RarInfo {
+                version,
+                solid: false,
+                multi_volume: false,
+                volume_number: None,
+                encrypted: true,
+                header_encrypted: true,
+                entry_count: 0,
+            });
+        }
+        Err(e) => return Err(map_error(e)),
+    };
+
+    let (multi_volume, volume_number, header_encrypted) = match &archive {
+        Archive::Rar13(archive) => (archive.main.is_volume(), None, false),
+        Archive::Rar15To40(archive) => {
+            let main = &archive.main;
+            // Only the first volume is marked, and only by newer archivers
+            (
+                main.is_volume(),
+                main.is_first_volume().then_some(0),
+                main.has_encrypted_headers(),
+            )
+        }
+        Archive::Rar50Plus(archive) => {
+            let main = &archive.main;
+            (
+                main.is_volume(),
+                Some(main.volume_number.unwrap_or(0)),
+                main.encrypted_headers,
+            )
+        }
+        _ => (false, None, false),
+    };
+
+    let members: Vec<_> = archive.members().collect();
+    Ok(RarInfo {
+        version,
+        solid: is_solid(&archive),
+        multi_volume,
+        volume_number: volume_number.filter(|_| multi_volume),
+        encrypted: header_encrypted || members.iter().any(|member| member.meta.is_encrypted),
+        header_encrypted,
+        entry_count: members.len(),
+    })
+}
+
+/// Whether the archive is solid, in which case no entry's data can be skipped.
+pub(crate) fn is_solid(archive: &Archive) -> bool {
+    let main_solid = match archive {
+        Archive::Rar13(archive) => archive.main.is_solid(),
+        Archive::Rar15To40(archive) => archive.main.is_solid(),
+        Archive::Rar50Plus(archive) => archive.main.is_solid(),
+        _ => false,
+    };
+    main_solid || archive.members().any(|member| member.is_solid())
+}
+
+/// Collects a member's contents.
+///
+/// `rars` takes ownership of the writer for each member, so the contents are
+/// shared with the caller, which takes them once the member is complete.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub(crate) fn take(&self) -> Vec<u8> {
+        mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Converts a `rars` error, keeping limit violations distinguishable.
+pub(crate) fn map_error(error: rars::Error) -> ArchiveError {
+    match error.root_cause() {
+        rars::Error::MemberOutputLimitExceeded { limit, required } => ArchiveError::FileTooLarge {
+            size: *required as usize,
+            limit: *limit as usize,
+        },
+        rars::Error::TotalOutputLimitExceeded {
+            limit, required, ..
+        } => ArchiveError::TotalSizeTooLarge {
+            size: *required as usize,
+            limit: *limit as usize,
+        },
+        rars::Error::Rar50DictionaryLimitExceeded { limit, required } => {
+            ArchiveError::WindowTooLarge {
+                size: *required,
+                limit: *limit as usize,
+            }
+        }
+        rars::Error::Cancelled => ArchiveError::Cancelled,
+        _ if error.kind() == rars::ErrorKind::PasswordRequired => ArchiveError::UnsupportedFormat(
+            "RAR archive with encrypted headers needs a password".to_string(),
+        ),
+        _ => ArchiveError::InvalidArchive(format!("RAR error: {}", error)),
+    }
+}
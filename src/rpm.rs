@@ -0,0 +1,427 @@
+//! RPM package reading.
+//!
+//! An RPM package starts with a fixed-size lead, followed by the signature
+//! header, the main header and the payload. Both headers share one structure:
+//! an index of tags pointing into a data store. The payload is a cpio archive,
+//! usually compressed with the method named in the main header.
+//!
+//! See the [RPM file format](https://rpm-software-management.github.io/rpm/manual/format.html)
+//! and [tag](https://rpm-software-management.github.io/rpm/manual/tags.html)
+//! documentation.
+
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+use crate::hash::HashAlgorithm;
+use crate::info::{RpmDependency, RpmFile, RpmInfo};
+use crate::limit::LimitedWriter;
+use crate::{lzma, window};
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read};
+
+/// The magic number at the start of the lead.
+pub(crate) const LEAD_MAGIC: &[u8] = &[0xED, 0xAB, 0xEE, 0xDB];
+const LEAD_SIZE: usize = 96;
+
+const HEADER_MAGIC: &[u8] = &[0x8E, 0xAD, 0xE8, 0x01];
+const INDEX_ENTRY_SIZE: usize = 16;
+
+/// Limits on the size of a header, as enforced by `rpm` itself.
+const MAX_INDEX_ENTRIES: usize = 0xFFFF;
+const MAX_DATA_SIZE: usize = 256 * 1024 * 1024;
+
+// Tags of the main header
+const TAG_NAME: u32 = 1000;
+const TAG_VERSION: u32 = 1001;
+const TAG_RELEASE: u32 = 1002;
+const TAG_EPOCH: u32 = 1003;
+const TAG_SUMMARY: u32 = 1004;
+const TAG_LICENSE: u32 = 1014;
+const TAG_ARCH: u32 = 1022;
+const TAG_OLDFILENAMES: u32 = 1027;
+const TAG_FILESIZES: u32 = 1028;
+const TAG_FILEMODES: u32 = 1030;
+const TAG_FILEDIGESTS: u32 = 1035;
+const TAG_FILELINKTOS: u32 = 1036;
+const TAG_PROVIDENAME: u32 = 1047;
+const TAG_REQUIREFLAGS: u32 = 1048;
+const TAG_REQUIRENAME: u32 = 1049;
+const TAG_REQUIREVERSION: u32 = 1050;
+const TAG_PROVIDEFLAGS: u32 = 1112;
+const TAG_PROVIDEVERSION: u32 = 1113;
+const TAG_DIRINDEXES: u32 = 1116;
+const TAG_BASENAMES: u32 = 1117;
+const TAG_DIRNAMES: u32 = 1118;
+const TAG_PAYLOADFORMAT: u32 = 1124;
+const TAG_PAYLOADCOMPRESSOR: u32 = 1125;
+const TAG_LONGFILESIZES: u32 = 5008;
+const TAG_FILEDIGESTALGO: u32 = 5011;
+
+// Data types of tag values
+const TYPE_INT16: u32 = 3;
+const TYPE_INT32: u32 = 4;
+const TYPE_INT64: u32 = 5;
+const TYPE_STRING: u32 = 6;
+const TYPE_STRING_ARRAY: u32 = 8;
+const TYPE_I18NSTRING: u32 = 9;
+
+// Dependency comparison flags
+const SENSE_LESS: u64 = 0x02;
+const SENSE_GREATER: u64 = 0x04;
+const SENSE_EQUAL: u64 = 0x08;
+
+/// Digest algorithms, as numbered by OpenPGP.
+const DIGEST_MD5: u64 = 1;
+#[cfg(feature = "sha1")]
+const DIGEST_SHA1: u64 = 2;
+const DIGEST_SHA256: u64 = 8;
+
+/// A signature or main header.
+pub(crate) struct Header {
+    /// Type, offset and count of each tag's value.
+    index: HashMap<u32, (u32, usize, usize)>,
+    /// Number of index entries as stored, including repeated tags.
+    entries: usize,
+    store: Vec<u8>,
+}
+
+impl Header {
+    /// Reads a header, leaving `reader` directly after it.
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut intro = [0u8; 16];
+        reader.read_exact(&mut intro)?;
+        if !intro.starts_with(HEADER_MAGIC) {
+            return Err(invalid("bad header magic"));
+        }
+        let entries = be_u32(&intro[8..12]) as usize;
+        let data_size = be_u32(&intro[12..16]) as usize;
+        if entries > MAX_INDEX_ENTRIES || data_size > MAX_DATA_SIZE {
+            return Err(invalid("header too large"));
+        }
+
+        let index_size = entries * INDEX_ENTRY_SIZE;
+        let mut bytes = Vec::new();
+        reader
+            .take((index_size + data_size) as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() < index_size + data_size {
+            return Err(invalid("truncated header"));
+        }
+        let store = bytes.split_off(index_size);
+
+        let index = bytes
+            .chunks_exact(INDEX_ENTRY_SIZE)
+            .map(|entry| {
+                let tag = be_u32(&entry[0..4]);
+                let kind = be_u32(&entry[4..8]);
+                let offset = be_u32(&entry[8..12]) as usize;
+                let count = be_u32(&entry[12..16]) as usize;
+                (tag, (kind, offset, count))
+            })
+            .collect();
+        Ok(Self {
+            index,
+            entries,
+            store,
+        })
+    }
+
+    /// The size of the header in bytes, as stored.
+    fn size(&self) -> usize {
+        16 + self.entries * INDEX_ENTRY_SIZE + self.store.len()
+    }
+
+    /// The value of a string tag, or the first one of an array.
+    fn string(&self, tag: u32) -> Option<String> {
+        self.strings(tag).into_iter().next()
+    }
+
+    /// The values of a string array tag. Missing or malformed tags have none.
+    fn strings(&self, tag: u32) -> Vec<String> {
+        let Some(&(kind, offset, count)) = self.index.get(&tag) else {
+            return Vec::new();
+        };
+        let count = match kind {
+            TYPE_STRING => 1,
+            TYPE_STRING_ARRAY | TYPE_I18NSTRING => count,
+            _ => return Vec::new(),
+        };
+        let Some(mut data) = self.store.get(offset..) else {
+            return Vec::new();
+        };
+
+        let mut strings = Vec::new();
+        while strings.len() < count {
+            let Some(end) = data.iter().position(|&b| b == 0) else {
+                break;
+            };
+            strings.push(String::from_utf8_lossy(&data[..end]).into_owned());
+            data = &data[end + 1..];
+        }
+        strings
+    }
+
+    /// The values of an integer tag. Missing or malformed tags have none.
+    fn integers(&self, tag: u32) -> Vec<u64> {
+        let Some(&(kind, offset, count)) = self.index.get(&tag) else {
+            return Vec::new();
+        };
+        let width = match kind {
+            TYPE_INT16 => 2,
+            TYPE_INT32 => 4,
+            TYPE_INT64 => 8,
+            _ => return Vec::new(),
+        };
+        let Some(data) = count
+            .checked_mul(width)
+            .and_then(|len| self.store.get(offset..offset.checked_add(len)?))
+        else {
+            return Vec::new();
+        };
+        data.chunks_exact(width)
+            .map(|value| value.iter().fold(0u64, |n, &b| (n << 8) | u64::from(b)))
+            .collect()
+    }
+
+    fn integer(&self, tag: u32) -> Option<u64> {
+        self.integers(tag).into_iter().next()
+    }
+}
+
+/// Compression methods of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Lzma,
+    Zstd,
+}
+
+/// The headers of a package, which precede its payload.
+pub(crate) struct Package {
+    /// The main header.
+    header: Header,
+}
+
+impl Package {
+    /// Reads the lead and headers, leaving `reader` at the start of the
+    /// payload.
+    pub(crate) fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut lead = [0u8; LEAD_SIZE];
+        reader.read_exact(&mut lead)?;
+        if !lead.starts_with(LEAD_MAGIC) {
+            return Err(invalid("bad lead magic"));
+        }
+
+        // The signature header is padded to a multiple of 8 bytes
+        let signature = Header::read(reader)?;
+        let padding = (8 - signature.size() % 8) % 8;
+        io::copy(&mut reader.take(padding as u64), &mut io::sink())?;
+
+        let header = Header::read(reader)?;
+        Ok(Self { header })
+    }
+
+    /// The compression of the payload, which must be a cpio archive.
+    pub(crate) fn compression(&self) -> Result<Compression> {
+        let format = self.header.string(TAG_PAYLOADFORMAT);
+        if format.as_deref().is_some_and(|format| format != "cpio") {
+            return Err(ArchiveError::UnsupportedFormat(format!(
+                "RPM payload format '{}'",
+                format.unwrap_or_default()
+            )));
+        }
+
+        // Packages predating the tag always use gzip
+        let compressor = self.header.string(TAG_PAYLOADCOMPRESSOR);
+        match compressor.as_deref().unwrap_or("gzip") {
+            "gzip" => Ok(Compression::Gzip),
+            "bzip2" => Ok(Compression::Bzip2),
+            "xz" => Ok(Compression::Xz),
+            "lzma" => Ok(Compression::Lzma),
+            "zstd" => Ok(Compression::Zstd),
+            "identity" => Ok(Compression::None),
+            other => Err(ArchiveError::UnsupportedFormat(format!(
+                "RPM payload compressor '{}'",
+                other
+            ))),
+        }
+    }
+
+    /// Collects the package metadata.
+    pub(crate) fn info(&self) -> RpmInfo {
+        let header = &self.header;
+        RpmInfo {
+            name: header.string(TAG_NAME).unwrap_or_default(),
+            version: header.string(TAG_VERSION).unwrap_or_default(),
+            release: header.string(TAG_RELEASE).unwrap_or_default(),
+            epoch: header.integer(TAG_EPOCH).map(|epoch| epoch as u32),
+            arch: header.string(TAG_ARCH),
+            summary: header.string(TAG_SUMMARY),
+            license: header.string(TAG_LICENSE),
+            requires: dependencies(
+                header,
+                TAG_REQUIRENAME,
+                TAG_REQUIREFLAGS,
+                TAG_REQUIREVERSION,
+            ),
+            provides: dependencies(
+                header,
+                TAG_PROVIDENAME,
+                TAG_PROVIDEFLAGS,
+                TAG_PROVIDEVERSION,
+            ),
+            files: self.files(),
+            // Packages predating the tag use MD5
+            file_digest_algorithm: digest_algorithm(
+                header.integer(TAG_FILEDIGESTALGO).unwrap_or(DIGEST_MD5),
+            ),
+            payload_compressor: header
+                .string(TAG_PAYLOADCOMPRESSOR)
+                .unwrap_or_else(|| "gzip".to_string()),
+        }
+    }
+
+    fn files(&self) -> Vec<RpmFile> {
+        let header = &self.header;
+
+        // File names are split into directories and base names, except in
+        // packages older than RPM 4.0
+        let dirnames = header.strings(TAG_DIRNAMES);
+        let paths: Vec<String> = if dirnames.is_empty() {
+            header.strings(TAG_OLDFILENAMES)
+        } else {
+            let dirindexes = header.integers(TAG_DIRINDEXES);
+            header
+                .strings(TAG_BASENAMES)
+                .into_iter()
+                .zip(dirindexes)
+                .map(|(basename, index)| {
+                    let dirname = dirnames.get(index as usize).map_or("", String::as_str);
+                    format!("{}{}", dirname, basename)
+                })
+                .collect()
+        };
+
+        let mut sizes = header.integers(TAG_LONGFILESIZES);
+        if sizes.is_empty() {
+            sizes = header.integers(TAG_FILESIZES);
+        }
+        let modes = header.integers(TAG_FILEMODES);
+        let digests = header.strings(TAG_FILEDIGESTS);
+        let links = header.strings(TAG_FILELINKTOS);
+
+        paths
+            .into_iter()
+            .enumerate()
+            .map(|(i, path)| RpmFile {
+                path,
+                size: sizes.get(i).copied().unwrap_or(0),
+                mode: modes.get(i).map_or(0, |&mode| mode as u32),
+                digest: digests
+                    .get(i)
+                    .filter(|digest| !digest.is_empty())
+                    .map(|digest| digest.to_ascii_lowercase()),
+                link_target: links.get(i).filter(|link| !link.is_empty()).cloned(),
+            })
+            .collect()
+    }
+}
+
+/// Wraps the payload that follows the headers in a decoder for its
+/// compression.
+///
+/// lzma-rs only decompresses into a writer, so XZ and LZMA payloads are
+/// buffered. Their window is checked before decoding, and decoding stops with
+/// [`ArchiveError::TotalSizeTooLarge`] once more than `max_size` bytes have
+/// been decompressed.
+pub(crate) fn payload_reader<'a, R: Read + 'a>(
+    mut reader: R,
+    compression: Compression,
+    max_window_size: usize,
+    max_size: usize,
+) -> Result<Box<dyn Read + 'a>> {
+    let format = match compression {
+        Compression::None => return Ok(Box::new(reader)),
+        Compression::Gzip => return Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+        Compression::Bzip2 => return Ok(Box::new(bzip2::read::BzDecoder::new(reader))),
+        Compression::Zstd => return Ok(Box::new(window::zstd_decoder(reader, max_window_size)?)),
+        Compression::Xz => ArchiveFormat::Xz,
+        Compression::Lzma => ArchiveFormat::Lzma,
+    };
+
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload)?;
+    window::check(&payload, format, max_window_size)?;
+
+    let mut output = LimitedWriter::new(Vec::new(), max_size);
+    let result = lzma::decompress(
+        format,
+        &mut payload.as_slice(),
+        &mut output,
+        max_window_size,
+    );
+    if let Some(size) = output.exceeded {
+        return Err(ArchiveError::TotalSizeTooLarge {
+            size,
+            limit: max_size,
+        });
+    }
+    result?;
+    Ok(Box::new(Cursor::new(output.inner)))
+}
+
+/// Gathers the package metadata, which only needs the headers.
+pub(crate) fn inspect(mut data: &[u8]) -> Result<RpmInfo> {
+    Ok(Package::read(&mut data)?.info())
+}
+
+/// Pairs the names of dependencies with their version constraints.
+fn dependencies(header: &Header, names: u32, flags: u32, versions: u32) -> Vec<RpmDependency> {
+    let flags = header.integers(flags);
+    let versions = header.strings(versions);
+
+    header
+        .strings(names)
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let version = versions.get(i).filter(|version| !version.is_empty());
+            let flags = flags.get(i).copied().unwrap_or(0);
+            let operator = match flags & (SENSE_LESS | SENSE_GREATER | SENSE_EQUAL) {
+                SENSE_LESS => "<",
+                f if f == SENSE_LESS | SENSE_EQUAL => "<=",
+                SENSE_EQUAL => "=",
+                f if f == SENSE_GREATER | SENSE_EQUAL => ">=",
+                SENSE_GREATER => ">",
+                _ => "",
+            };
+            RpmDependency {
+                name,
+                constraint: version
+                    .filter(|_| !operator.is_empty())
+                    .map(|version| format!("{} {}", operator, version)),
+            }
+        })
+        .collect()
+}
+
+fn digest_algorithm(id: u64) -> Option<HashAlgorithm> {
+    match id {
+        #[cfg(feature = "md5")]
+        DIGEST_MD5 => Some(HashAlgorithm::Md5),
+        #[cfg(feature = "sha1")]
+        DIGEST_SHA1 => Some(HashAlgorithm::Sha1),
+        DIGEST_SHA256 => Some(HashAlgorithm::Sha256),
+        _ => None,
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn invalid(message: &str) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("RPM package: {}", message))
+}
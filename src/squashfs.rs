@@ -0,0 +1,837 @@
+//! SquashFS image reading.
+//!
+//! A SquashFS 4.0 image starts with a little-endian superblock giving the
+//! compressor, the block size and where each of its tables starts. Inodes and
+//! directory listings are stored in tables of metadata blocks, which hold up
+//! to 8 KiB each and are compressed individually. They refer to each other by
+//! the position of a metadata block relative to the start of its table and an
+//! offset into the decompressed block, so metadata blocks are decoded on
+//! demand and cached.
+//!
+//! File contents are a sequence of data blocks of the image's block size,
+//! each compressed individually, with the stored size of every block listed in
+//! the file's inode. A block of zeros may be left out, making the file sparse.
+//! The tail of a file that doesn't fill a block may instead be packed with
+//! other tails into a fragment block, which is found through the fragment
+//! table.
+//!
+//! Hard links are directory entries sharing an inode. The first one found is
+//! reported as the file and the others as links to it.
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::SpecialFile;
+use crate::format::ArchiveFormat;
+use crate::info::SquashFsInfo;
+use crate::{lzma, window};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use std::rc::Rc;
+
+pub(crate) const MAGIC: &[u8] = b"hsqs";
+
+const SUPERBLOCK_SIZE: usize = 96;
+
+/// Block sizes `mksquashfs` accepts.
+const MIN_BLOCK_SIZE: u32 = 4 * 1024;
+const MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+
+/// The decompressed size of every metadata block but the last of a table.
+const METADATA_SIZE: usize = 8192;
+/// Flag of a metadata block header marking the block as stored uncompressed.
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+/// Flag of a data block size marking the block as stored uncompressed.
+const DATA_UNCOMPRESSED: u32 = 1 << 24;
+const DATA_SIZE_MASK: u32 = DATA_UNCOMPRESSED - 1;
+/// Fragment index of a file whose tail is stored in a data block.
+const NO_FRAGMENT: u32 = 0xFFFF_FFFF;
+/// Size of a fragment table entry: start, size and an unused field.
+const FRAGMENT_ENTRY_SIZE: u64 = 16;
+/// Directory nesting deeper than this is rejected rather than recursed into.
+const MAX_DEPTH: usize = 256;
+
+// Inode types, with the extended variants of each basic type
+const DIRECTORY: u16 = 1;
+const FILE: u16 = 2;
+const SYMLINK: u16 = 3;
+const BLOCK_DEVICE: u16 = 4;
+const CHARACTER_DEVICE: u16 = 5;
+const FIFO: u16 = 6;
+const SOCKET: u16 = 7;
+const EXTENDED_OFFSET: u16 = 7;
+
+/// Whether `data` starts with a SquashFS superblock.
+pub(crate) fn is_squashfs(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// The compressor of an image's data and metadata blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Gzip,
+    Lzma,
+    Lzo,
+    Xz,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    fn from_id(id: u16) -> Result<Self> {
+        Ok(match id {
+            1 => Self::Gzip,
+            2 => Self::Lzma,
+            3 => Self::Lzo,
+            4 => Self::Xz,
+            5 => Self::Lz4,
+            6 => Self::Zstd,
+            _ => {
+                return Err(ArchiveError::UnsupportedFormat(format!(
+                    "SquashFS compressor {}",
+                    id
+                )));
+            }
+        })
+    }
+
+    /// The name `mksquashfs` uses for this compressor.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Lzma => "lzma",
+            Self::Lzo => "lzo",
+            Self::Xz => "xz",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Decompresses a block that holds at most `max_size` bytes.
+    ///
+    /// gzip blocks are zlib streams, XZ and LZMA blocks are complete `.xz`
+    /// and `.lzma` streams, and LZ4 blocks are raw LZ4 blocks without a frame.
+    fn decompress(self, data: &[u8], max_size: usize, max_window_size: usize) -> Result<Vec<u8>> {
+        let decoded = match self {
+            Self::Gzip => read_block(flate2::read::ZlibDecoder::new(data), max_size),
+            Self::Zstd => read_block(window::zstd_decoder(data, max_window_size)?, max_size),
+            Self::Lz4 => lz4::block::decompress(data, Some(max_size as i32)),
+            Self::Xz | Self::Lzma => {
+                let format = if self == Self::Xz {
+                    ArchiveFormat::Xz
+                } else {
+                    ArchiveFormat::Lzma
+                };
+                // Writing past the end of the buffer fails the decoder
+                let mut output = vec![0; max_size + 1];
+                let mut buffer = output.as_mut_slice();
+                let mut input = data;
+                lzma::decompress(format, &mut input, &mut buffer, max_window_size)
+                    .map_err(|e| invalid(format!("{} block: {}", self.name(), e)))?;
+                let unused = buffer.len();
+                output.truncate(max_size + 1 - unused);
+                Ok(output)
+            }
+            Self::Lzo => {
+                return Err(ArchiveError::UnsupportedFormat(
+                    "LZO-compressed SquashFS images".to_string(),
+                ));
+            }
+        };
+        let decoded = decoded.map_err(|e| invalid(format!("{} block: {}", self.name(), e)))?;
+        if decoded.len() > max_size {
+            return Err(invalid(format!(
+                "block decompresses to more than {} bytes",
+                max_size
+            )));
+        }
+        Ok(decoded)
+    }
+}
+
+/// Reads up to one byte more than `max_size` from `reader`, so that blocks
+/// that are too large are noticed without decoding them completely.
+fn read_block<R: Read>(reader: R, max_size: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    reader.take(max_size as u64 + 1).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// The fields of the superblock that are needed to read an image.
+#[derive(Debug, Clone)]
+struct Superblock {
+    inode_count: u32,
+    mtime: u32,
+    block_size: u32,
+    fragment_count: u32,
+    compression: Compression,
+    root_inode: u64,
+    bytes_used: u64,
+    inode_table: u64,
+    directory_table: u64,
+    fragment_table: u64,
+}
+
+impl Superblock {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if !is_squashfs(data) {
+            return Err(invalid("missing superblock"));
+        }
+        if data.len() < SUPERBLOCK_SIZE {
+            return Err(invalid("truncated superblock"));
+        }
+        let (major, minor) = (u16_at(data, 28), u16_at(data, 30));
+        if (major, minor) != (4, 0) {
+            return Err(ArchiveError::UnsupportedFormat(format!(
+                "SquashFS version {}.{}",
+                major, minor
+            )));
+        }
+
+        let block_size = u32_at(data, 12);
+        let block_log = u16_at(data, 22);
+        if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size)
+            || !block_size.is_power_of_two()
+            || u32::from(block_log) != block_size.trailing_zeros()
+        {
+            return Err(invalid(format!("invalid block size {}", block_size)));
+        }
+
+        Ok(Self {
+            inode_count: u32_at(data, 4),
+            mtime: u32_at(data, 8),
+            block_size,
+            fragment_count: u32_at(data, 16),
+            compression: Compression::from_id(u16_at(data, 20))?,
+            root_inode: u64_at(data, 32),
+            bytes_used: u64_at(data, 40),
+            inode_table: u64_at(data, 64),
+            directory_table: u64_at(data, 72),
+            fragment_table: u64_at(data, 80),
+        })
+    }
+
+    fn info(&self) -> SquashFsInfo {
+        SquashFsInfo {
+            compression: self.compression.name().to_string(),
+            block_size: self.block_size,
+            inode_count: self.inode_count,
+            fragment_count: self.fragment_count,
+            mtime: u64::from(self.mtime),
+            bytes_used: self.bytes_used,
+        }
+    }
+}
+
+/// Where the contents of a regular file are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileData {
+    pub(crate) size: u64,
+    /// Position of the first data block in the image.
+    start: u64,
+    /// The stored size of each data block, with [`DATA_UNCOMPRESSED`] set for
+    /// blocks stored as is, and zero for sparse blocks.
+    block_sizes: Vec<u32>,
+    /// The fragment index and the offset of the tail in the fragment block.
+    fragment: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Kind {
+    File(FileData),
+    Directory,
+    Symlink(String),
+    /// Another directory entry for the inode of the entry with this path.
+    Hardlink(String),
+    Special(SpecialFile),
+}
+
+/// A file, directory, link or special file of an image.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub(crate) path: String,
+    pub(crate) kind: Kind,
+    pub(crate) mode: u32,
+    pub(crate) mtime: u64,
+}
+
+/// An inode of the inode table.
+struct Inode {
+    kind: InodeKind,
+    mode: u32,
+    mtime: u64,
+    number: u32,
+}
+
+enum InodeKind {
+    /// The position of the directory listing and its size in bytes.
+    Directory {
+        block: u32,
+        offset: u16,
+        size: u32,
+    },
+    File(FileData),
+    Symlink(String),
+    Special(SpecialFile),
+}
+
+/// A SquashFS image with the entries of its directory tree in pre-order.
+pub(crate) struct Image<'a> {
+    data: &'a [u8],
+    superblock: Superblock,
+    max_window_size: usize,
+    /// Decompressed metadata blocks by position.
+    metadata: RefCell<HashMap<u64, MetadataBlock>>,
+    pub(crate) entries: Vec<Entry>,
+}
+
+impl<'a> Image<'a> {
+    /// Parses the superblock and reads the directory tree of an image.
+    pub(crate) fn parse(data: &'a [u8], max_window_size: usize) -> Result<Self> {
+        let superblock = Superblock::parse(data)?;
+        let Some(data) = usize::try_from(superblock.bytes_used)
+            .ok()
+            .and_then(|size| data.get(..size))
+        else {
+            return Err(invalid(format!(
+                "image is truncated to {} of {} bytes",
+                data.len(),
+                superblock.bytes_used
+            )));
+        };
+
+        let mut image = Self {
+            data,
+            superblock,
+            max_window_size,
+            metadata: RefCell::new(HashMap::new()),
+            entries: Vec::new(),
+        };
+        let root = image.inode(image.superblock.root_inode)?;
+        let mut walk = Walk::default();
+        image.read_directory(&root, "", 0, &mut walk)?;
+        image.entries = walk.entries;
+        Ok(image)
+    }
+
+    pub(crate) fn compression(&self) -> Compression {
+        self.superblock.compression
+    }
+
+    /// Returns a reader for the contents of a file.
+    pub(crate) fn reader<'i>(&'i self, file: &'i FileData) -> Contents<'i, 'a> {
+        Contents {
+            image: self,
+            file,
+            block: 0,
+            position: file.start,
+            remaining: file.size,
+            buffer: Vec::new(),
+            consumed: 0,
+            fragment: None,
+        }
+    }
+
+    /// Appends the entries of a directory and its subdirectories to `walk`.
+    fn read_directory(
+        &self,
+        directory: &Inode,
+        path: &str,
+        depth: usize,
+        walk: &mut Walk,
+    ) -> Result<()> {
+        let InodeKind::Directory {
+            block,
+            offset,
+            size,
+        } = directory.kind
+        else {
+            return Err(invalid(format!("'{}' is not a directory", path)));
+        };
+        if depth > MAX_DEPTH {
+            return Err(invalid("directories nested too deeply"));
+        }
+
+        // The size counts three bytes for the `.` and `..` entries, which
+        // aren't stored
+        let mut remaining = size.saturating_sub(3) as usize;
+        // Each listing is read once, which also catches directory loops.
+        // Empty directories have no listing, so their position is arbitrary.
+        if remaining > 0 && !walk.listings.insert((block, offset)) {
+            return Err(invalid(format!(
+                "directory listing of '{}' is shared with another directory",
+                path
+            )));
+        }
+        let position = self
+            .superblock
+            .directory_table
+            .checked_add(u64::from(block))
+            .ok_or_else(|| invalid(format!("directory listing of '{}' out of range", path)))?;
+        let mut reader = MetadataReader::new(self, position, usize::from(offset))?;
+        while remaining > 0 {
+            let count = reader.u32()? as usize + 1;
+            let start = reader.u32()?;
+            let _base_number = reader.u32()?;
+            remaining = remaining
+                .checked_sub(12)
+                .ok_or_else(|| invalid(format!("truncated directory listing of '{}'", path)))?;
+
+            for _ in 0..count {
+                let offset = reader.u16()?;
+                let _number_delta = reader.u16()?;
+                let _kind = reader.u16()?;
+                let name_size = usize::from(reader.u16()?) + 1;
+                let name = reader.bytes(name_size)?;
+                remaining = remaining
+                    .checked_sub(8 + name_size)
+                    .ok_or_else(|| invalid(format!("truncated directory listing of '{}'", path)))?;
+
+                let name = String::from_utf8_lossy(&name).into_owned();
+                if name == "." || name == ".." || name.contains('/') {
+                    return Err(invalid(format!("invalid name '{}' in '{}'", name, path)));
+                }
+                let entry_path = if path.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", path, name)
+                };
+
+                let reference = (u64::from(start) << 16) | u64::from(offset);
+                let inode = self.inode(reference)?;
+                let kind = match inode.kind {
+                    InodeKind::Directory { .. } => Kind::Directory,
+                    _ if let Some(target) = walk.links.get(&inode.number) => {
+                        Kind::Hardlink(target.clone())
+                    }
+                    InodeKind::File(ref file) => Kind::File(file.clone()),
+                    InodeKind::Symlink(ref target) => Kind::Symlink(target.clone()),
+                    InodeKind::Special(special) => Kind::Special(special),
+                };
+                if !matches!(kind, Kind::Directory | Kind::Hardlink(_)) {
+                    walk.links.insert(inode.number, entry_path.clone());
+                }
+                walk.entries.push(Entry {
+                    path: entry_path.clone(),
+                    kind,
+                    mode: inode.mode,
+                    mtime: inode.mtime,
+                });
+                if matches!(inode.kind, InodeKind::Directory { .. }) {
+                    self.read_directory(&inode, &entry_path, depth + 1, walk)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the inode at `reference`, the position of its metadata block in
+    /// the inode table shifted left by 16 bits, plus its offset in the block.
+    fn inode(&self, reference: u64) -> Result<Inode> {
+        let position = self
+            .superblock
+            .inode_table
+            .checked_add(reference >> 16)
+            .ok_or_else(|| invalid("invalid inode reference"))?;
+        let mut reader = MetadataReader::new(self, position, (reference & 0xFFFF) as usize)?;
+
+        let kind = reader.u16()?;
+        let mode = u32::from(reader.u16()? & 0o7777);
+        let _uid_index = reader.u16()?;
+        let _gid_index = reader.u16()?;
+        let mtime = u64::from(reader.u32()?);
+        let number = reader.u32()?;
+
+        let kind = match kind {
+            DIRECTORY => {
+                let block = reader.u32()?;
+                let _link_count = reader.u32()?;
+                let size = u32::from(reader.u16()?);
+                let offset = reader.u16()?;
+                InodeKind::Directory {
+                    block,
+                    offset,
+                    size,
+                }
+            }
+            k if k == DIRECTORY + EXTENDED_OFFSET => {
+                let _link_count = reader.u32()?;
+                let size = reader.u32()?;
+                let block = reader.u32()?;
+                let _parent = reader.u32()?;
+                let _index_count = reader.u16()?;
+                let offset = reader.u16()?;
+                InodeKind::Directory {
+                    block,
+                    offset,
+                    size,
+                }
+            }
+            FILE => {
+                let start = u64::from(reader.u32()?);
+                let fragment = reader.u32()?;
+                let fragment_offset = reader.u32()?;
+                let size = u64::from(reader.u32()?);
+                InodeKind::File(self.file_data(
+                    &mut reader,
+                    start,
+                    size,
+                    fragment,
+                    fragment_offset,
+                )?)
+            }
+            k if k == FILE + EXTENDED_OFFSET => {
+                let start = reader.u64()?;
+                let size = reader.u64()?;
+                let _sparse = reader.u64()?;
+                let _link_count = reader.u32()?;
+                let fragment = reader.u32()?;
+                let fragment_offset = reader.u32()?;
+                let _xattr = reader.u32()?;
+                InodeKind::File(self.file_data(
+                    &mut reader,
+                    start,
+                    size,
+                    fragment,
+                    fragment_offset,
+                )?)
+            }
+            k if k == SYMLINK || k == SYMLINK + EXTENDED_OFFSET => {
+                let _link_count = reader.u32()?;
+                let size = reader.u32()? as usize;
+                let target = reader.bytes(size)?;
+                InodeKind::Symlink(String::from_utf8_lossy(&target).into_owned())
+            }
+            k if k == BLOCK_DEVICE
+                || k == CHARACTER_DEVICE
+                || k == BLOCK_DEVICE + EXTENDED_OFFSET
+                || k == CHARACTER_DEVICE + EXTENDED_OFFSET =>
+            {
+                let _link_count = reader.u32()?;
+                let device = reader.u32()?;
+                // The Linux encoding of device numbers, with the low bits of
+                // the minor number in the lowest byte
+                let major = (device >> 8) & 0xFFF;
+                let minor = (device & 0xFF) | ((device >> 12) & 0xF_FF00);
+                InodeKind::Special(
+                    if k == BLOCK_DEVICE || k == BLOCK_DEVICE + EXTENDED_OFFSET {
+                        SpecialFile::BlockDevice { major, minor }
+                    } else {
+                        SpecialFile::CharacterDevice { major, minor }
+                    },
+                )
+            }
+            k if k == FIFO || k == FIFO + EXTENDED_OFFSET => InodeKind::Special(SpecialFile::Fifo),
+            k if k == SOCKET || k == SOCKET + EXTENDED_OFFSET => {
+                InodeKind::Special(SpecialFile::Socket)
+            }
+            k => return Err(invalid(format!("unknown inode type {}", k))),
+        };
+
+        Ok(Inode {
+            kind,
+            mode,
+            mtime,
+            number,
+        })
+    }
+
+    /// Reads the list of block sizes that follows a file inode.
+    fn file_data(
+        &self,
+        reader: &mut MetadataReader<'_, 'a>,
+        start: u64,
+        size: u64,
+        fragment: u32,
+        fragment_offset: u32,
+    ) -> Result<FileData> {
+        let block_size = u64::from(self.superblock.block_size);
+        // The tail is in a fragment unless the file ends with a data block
+        let (block_count, fragment) = if fragment == NO_FRAGMENT {
+            (size.div_ceil(block_size), None)
+        } else {
+            if fragment >= self.superblock.fragment_count {
+                return Err(invalid(format!("invalid fragment index {}", fragment)));
+            }
+            (size / block_size, Some((fragment, fragment_offset)))
+        };
+        let length = usize::try_from(block_count * 4)
+            .map_err(|_| invalid(format!("invalid file size {}", size)))?;
+        let block_sizes = reader
+            .bytes(length)?
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        Ok(FileData {
+            size,
+            start,
+            block_sizes,
+            fragment,
+        })
+    }
+
+    /// Returns the decompressed metadata block at `position`.
+    fn metadata_block(&self, position: u64) -> Result<MetadataBlock> {
+        if let Some(block) = self.metadata.borrow().get(&position) {
+            return Ok(block.clone());
+        }
+
+        let header = u16_at(self.range(position, 2)?, 0);
+        let size = usize::from(header & !METADATA_UNCOMPRESSED);
+        if size == 0 {
+            return Err(invalid(format!("empty metadata block at {}", position)));
+        }
+        let stored = self.range(position + 2, size)?;
+        let data: Rc<[u8]> = if header & METADATA_UNCOMPRESSED != 0 {
+            stored.into()
+        } else {
+            self.decompress(stored, METADATA_SIZE)?.into()
+        };
+        let block = MetadataBlock {
+            data,
+            next: position + 2 + size as u64,
+        };
+        self.metadata.borrow_mut().insert(position, block.clone());
+        Ok(block)
+    }
+
+    /// Reads the data block at `position` that should decode to `expected`
+    /// bytes, and advances `position` past it.
+    fn data_block(&self, position: &mut u64, word: u32, expected: usize) -> Result<Vec<u8>> {
+        let size = (word & DATA_SIZE_MASK) as usize;
+        if size == 0 {
+            return Ok(vec![0; expected]);
+        }
+        let stored = self.range(*position, size)?;
+        let block = if word & DATA_UNCOMPRESSED != 0 {
+            stored.to_vec()
+        } else {
+            self.decompress(stored, self.superblock.block_size as usize)?
+        };
+        if block.len() != expected {
+            return Err(invalid(format!(
+                "data block at {} holds {} bytes instead of {}",
+                position,
+                block.len(),
+                expected
+            )));
+        }
+        *position += size as u64;
+        Ok(block)
+    }
+
+    /// Reads the fragment block with the given index.
+    fn fragment_block(&self, index: u32) -> Result<Vec<u8>> {
+        // The fragment table is an array of pointers to the metadata blocks
+        // holding its entries
+        let position = u64::from(index) * FRAGMENT_ENTRY_SIZE;
+        let pointer = self
+            .superblock
+            .fragment_table
+            .checked_add(position / METADATA_SIZE as u64 * 8)
+            .ok_or_else(|| invalid("invalid fragment table position"))?;
+        let pointer = u64_at(self.range(pointer, 8)?, 0);
+        let mut reader =
+            MetadataReader::new(self, pointer, (position % METADATA_SIZE as u64) as usize)?;
+        let start = reader.u64()?;
+        let word = reader.u32()?;
+        if word & DATA_SIZE_MASK == 0 {
+            return Err(invalid(format!("empty fragment block {}", index)));
+        }
+
+        let size = (word & DATA_SIZE_MASK) as usize;
+        let stored = self.range(start, size)?;
+        if word & DATA_UNCOMPRESSED != 0 {
+            Ok(stored.to_vec())
+        } else {
+            self.decompress(stored, self.superblock.block_size as usize)
+        }
+    }
+
+    fn decompress(&self, data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        self.superblock
+            .compression
+            .decompress(data, max_size, self.max_window_size)
+    }
+
+    fn range(&self, position: u64, length: usize) -> Result<&'a [u8]> {
+        usize::try_from(position)
+            .ok()
+            .and_then(|start| self.data.get(start..start.checked_add(length)?))
+            .ok_or_else(|| {
+                invalid(format!(
+                    "{} bytes at {} are outside of the image",
+                    length, position
+                ))
+            })
+    }
+}
+
+/// State of the walk over the directory tree.
+#[derive(Default)]
+struct Walk {
+    entries: Vec<Entry>,
+    /// Positions of the directory listings read so far.
+    listings: HashSet<(u32, u16)>,
+    /// Paths of the other inodes seen so far, by inode number.
+    links: HashMap<u32, String>,
+}
+
+/// A decompressed metadata block.
+#[derive(Clone)]
+struct MetadataBlock {
+    data: Rc<[u8]>,
+    /// The position of the block that follows it in its table.
+    next: u64,
+}
+
+/// Reads consecutive bytes from a table of metadata blocks, across block
+/// boundaries.
+struct MetadataReader<'i, 'a> {
+    image: &'i Image<'a>,
+    block: MetadataBlock,
+    offset: usize,
+}
+
+impl<'i, 'a> MetadataReader<'i, 'a> {
+    fn new(image: &'i Image<'a>, position: u64, offset: usize) -> Result<Self> {
+        let block = image.metadata_block(position)?;
+        if offset > block.data.len() {
+            return Err(invalid(format!(
+                "offset {} is outside of the metadata block at {}",
+                offset, position
+            )));
+        }
+        Ok(Self {
+            image,
+            block,
+            offset,
+        })
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(length.min(METADATA_SIZE));
+        while bytes.len() < length {
+            if self.offset == self.block.data.len() {
+                self.block = self.image.metadata_block(self.block.next)?;
+                self.offset = 0;
+            }
+            let data = &self.block.data;
+            let available = (length - bytes.len()).min(data.len() - self.offset);
+            bytes.extend_from_slice(&data[self.offset..self.offset + available]);
+            self.offset += available;
+        }
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16_at(&self.bytes(2)?, 0))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32_at(&self.bytes(4)?, 0))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64_at(&self.bytes(8)?, 0))
+    }
+}
+
+/// Reads the contents of a file block by block.
+///
+/// Decoded blocks must hold exactly the number of bytes the file size calls
+/// for, so corrupt blocks fail with [`io::ErrorKind::InvalidData`].
+pub(crate) struct Contents<'i, 'a> {
+    image: &'i Image<'a>,
+    file: &'i FileData,
+    /// Index of the next data block.
+    block: usize,
+    /// Position of the next stored data block in the image.
+    position: u64,
+    remaining: u64,
+    buffer: Vec<u8>,
+    consumed: usize,
+    /// The last fragment block read, by index.
+    fragment: Option<(u32, Vec<u8>)>,
+}
+
+impl Contents<'_, '_> {
+    fn next_block(&mut self) -> Result<Vec<u8>> {
+        let expected = self
+            .remaining
+            .min(u64::from(self.image.superblock.block_size)) as usize;
+        let block = if let Some(&word) = self.file.block_sizes.get(self.block) {
+            self.block += 1;
+            self.image.data_block(&mut self.position, word, expected)?
+        } else if let Some((index, offset)) = self.file.fragment {
+            if self
+                .fragment
+                .as_ref()
+                .is_none_or(|(cached, _)| *cached != index)
+            {
+                self.fragment = Some((index, self.image.fragment_block(index)?));
+            }
+            let fragment = &self.fragment.as_ref().expect("fragment was just read").1;
+            let offset = offset as usize;
+            fragment
+                .get(offset..offset + expected)
+                .ok_or_else(|| invalid(format!("tail is outside of fragment block {}", index)))?
+                .to_vec()
+        } else {
+            return Err(invalid("file has fewer blocks than its size calls for"));
+        };
+        self.remaining -= expected as u64;
+        Ok(block)
+    }
+}
+
+impl Read for Contents<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.consumed == self.buffer.len() {
+            if self.remaining == 0 || buf.is_empty() {
+                return Ok(0);
+            }
+            self.buffer = self.next_block().map_err(|e| match e {
+                ArchiveError::Io(e) => e,
+                e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+            })?;
+            self.consumed = 0;
+        }
+        let length = buf.len().min(self.buffer.len() - self.consumed);
+        buf[..length].copy_from_slice(&self.buffer[self.consumed..self.consumed + length]);
+        self.consumed += length;
+        Ok(length)
+    }
+}
+
+/// Reads the superblock of an image.
+pub(crate) fn inspect(data: &[u8]) -> Result<SquashFsInfo> {
+    Ok(Superblock::parse(data)?.info())
+}
+
+/// Returns the block size of an image, which bounds the history the decoder
+/// of any block needs.
+pub(crate) fn block_size(data: &[u8]) -> Option<u32> {
+    Superblock::parse(data)
+        .ok()
+        .map(|superblock| superblock.block_size)
+}
+
+fn u16_at(data: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([data[position], data[position + 1]])
+}
+
+fn u32_at(data: &[u8], position: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[position..position + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn u64_at(data: &[u8], position: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[position..position + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn invalid(message: impl std::fmt::Display) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("SquashFS image: {}", message))
+}
@@ -0,0 +1,307 @@
+//! Async extraction from [`AsyncRead`] inputs.
+//!
+//! Decompression is CPU-bound, so it runs on Tokio's blocking thread pool
+//! while the input is read on the async side and handed over in chunks. The
+//! formats that are read front to back (TAR and its compressed variants, ar,
+//! Debian packages, cpio, RPM packages and the single-file formats) are
+//! decompressed as the input arrives. The other formats keep their index at
+//! the end or are read out of order, so their input is buffered completely
+//! first, up to the total size limit.
+//!
+//! All limits, hashes, progress callbacks and cancellation tokens configured
+//! on the [`ArchiveExtractor`] apply as they do for
+//! [`extract`](ArchiveExtractor::extract).
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::{ArchiveExtractor, ExtractedFile};
+use crate::format::ArchiveFormat;
+use std::io::{self, Read};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Size of the chunks read from the input.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of input chunks that may be read ahead of the decompressor.
+const CHUNK_BUFFER: usize = 4;
+
+/// Number of extracted entries that may wait for the consumer.
+const ENTRY_BUFFER: usize = 4;
+
+impl ArchiveExtractor {
+    /// Extracts all files from an archive read from `reader`.
+    ///
+    /// This is the async counterpart of [`extract`](Self::extract). Entries
+    /// are decompressed on Tokio's blocking thread pool, so the calling task
+    /// only waits for input and results.
+    ///
+    /// Use [`extract_stream`](Self::extract_stream) to handle entries one by
+    /// one as they are extracted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`extract`](Self::extract),
+    /// [`ArchiveError::Io`] if reading from `reader` fails, and
+    /// [`ArchiveError::TotalSizeTooLarge`] if the input of a format that
+    /// can't be read as a stream, such as ZIP, exceeds the total size limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat};
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = tokio::fs::File::open("upload.tar.gz").await?;
+    /// let files = ArchiveExtractor::new()
+    ///     .extract_async(file, ArchiveFormat::TarGz)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn extract_async<R>(
+        &self,
+        reader: R,
+        format: ArchiveFormat,
+    ) -> Result<Vec<ExtractedFile>>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let mut entries = self.extract_stream(reader, format);
+        let mut files = Vec::new();
+        while let Some(file) = entries.next().await {
+            files.push(file?);
+        }
+        Ok(files)
+    }
+
+    /// Starts extracting an archive read from `reader`, returning a stream of
+    /// its entries in archive order.
+    ///
+    /// Each entry is yielded as soon as it has been decompressed. For formats
+    /// that are read front to back, only a few entries and input chunks are
+    /// held at a time, so a slow consumer slows down reading instead of
+    /// buffering the archive. Dropping the stream stops the extraction.
+    ///
+    /// After an error, the stream ends. The time budget set with
+    /// [`with_max_duration`](Self::with_max_duration) includes time spent
+    /// waiting for input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use archive::{ArchiveExtractor, ArchiveFormat};
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = tokio::fs::File::open("upload.tar.zst").await?;
+    /// let mut entries = ArchiveExtractor::new()
+    ///     .with_max_file_size(10 * 1024 * 1024)
+    ///     .extract_stream(file, ArchiveFormat::TarZst);
+    ///
+    /// while let Some(entry) = entries.next().await {
+    ///     let entry = entry?;
+    ///     println!("{}: {} bytes", entry.path, entry.data.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_stream<R>(&self, reader: R, format: ArchiveFormat) -> EntryStream
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(ENTRY_BUFFER);
+        let extractor = self.clone();
+
+        match format {
+            ArchiveFormat::Zip
+            | ArchiveFormat::SevenZ
+            | ArchiveFormat::Rar
+            | ArchiveFormat::Iso
+            | ArchiveFormat::Cab
+            | ArchiveFormat::Xar
+            | ArchiveFormat::Pkg
+            | ArchiveFormat::SquashFs => {
+                tokio::spawn(extract_buffered(extractor, reader, format, sender));
+            }
+            ArchiveFormat::Tar
+            | ArchiveFormat::Ar
+            | ArchiveFormat::Deb
+            | ArchiveFormat::Cpio
+            | ArchiveFormat::Rpm
+            | ArchiveFormat::TarGz
+            | ArchiveFormat::TarBz2
+            | ArchiveFormat::TarXz
+            | ArchiveFormat::TarZst
+            | ArchiveFormat::TarLz4
+            | ArchiveFormat::TarLz
+            | ArchiveFormat::TarLzma
+            | ArchiveFormat::TarZ
+            | ArchiveFormat::TarBr
+            | ArchiveFormat::Gz
+            | ArchiveFormat::Bz2
+            | ArchiveFormat::Xz
+            | ArchiveFormat::Lz
+            | ArchiveFormat::Lzma
+            | ArchiveFormat::Lz4
+            | ArchiveFormat::Zst
+            | ArchiveFormat::Compress
+            | ArchiveFormat::Br
+            | ArchiveFormat::Snappy => {
+                let (chunk_sender, chunk_receiver) = mpsc::channel(CHUNK_BUFFER);
+                tokio::spawn(read_chunks(reader, chunk_sender));
+                tokio::task::spawn_blocking(move || {
+                    let reader = ChunkReader::new(chunk_receiver);
+                    let result = extractor.extract_reader(reader, format, &mut |file| {
+                        // Fails once the stream is dropped, which stops the extraction
+                        sender
+                            .blocking_send(Ok(file))
+                            .map_err(|_| ArchiveError::Cancelled)
+                    });
+                    if let Err(e) = result {
+                        let _ = sender.blocking_send(Err(e));
+                    }
+                });
+            }
+        }
+
+        EntryStream { receiver }
+    }
+}
+
+/// A stream of entries extracted by
+/// [`ArchiveExtractor::extract_stream`].
+///
+/// Entries can be awaited with [`next`](Self::next), or consumed through the
+/// [`Stream`](futures_core::Stream) implementation.
+#[derive(Debug)]
+pub struct EntryStream {
+    receiver: mpsc::Receiver<Result<ExtractedFile>>,
+}
+
+impl EntryStream {
+    /// Waits for the next entry, returning `None` once all entries have been
+    /// extracted or after an error.
+    pub async fn next(&mut self) -> Option<Result<ExtractedFile>> {
+        self.receiver.recv().await
+    }
+}
+
+impl futures_core::Stream for EntryStream {
+    type Item = Result<ExtractedFile>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Reads the whole input, then extracts it on the blocking thread pool.
+///
+/// An input larger than the total size limit is rejected without being read
+/// any further, since it couldn't be buffered within the limit.
+async fn extract_buffered<R: AsyncRead + Unpin>(
+    extractor: ArchiveExtractor,
+    reader: R,
+    format: ArchiveFormat,
+    sender: mpsc::Sender<Result<ExtractedFile>>,
+) {
+    let limit = extractor.max_total_size();
+    let mut data = Vec::new();
+    let mut reader = reader.take(limit as u64 + 1);
+    if let Err(e) = reader.read_to_end(&mut data).await {
+        let _ = sender.send(Err(e.into())).await;
+        return;
+    }
+    if data.len() > limit {
+        let error = ArchiveError::TotalSizeTooLarge {
+            size: data.len(),
+            limit,
+        };
+        let _ = sender.send(Err(error)).await;
+        return;
+    }
+
+    tokio::task::spawn_blocking(move || match extractor.extract(&data, format) {
+        Ok(files) => {
+            for file in files {
+                if sender.blocking_send(Ok(file)).is_err() {
+                    return;
+                }
+            }
+        }
+        Err(e) => {
+            let _ = sender.blocking_send(Err(e));
+        }
+    });
+}
+
+/// Forwards the input in chunks until it ends, fails or the extraction stops
+/// listening.
+async fn read_chunks<R: AsyncRead + Unpin>(
+    mut reader: R,
+    sender: mpsc::Sender<io::Result<Vec<u8>>>,
+) {
+    loop {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let result = match reader.read(&mut chunk).await {
+            Ok(0) => return,
+            Ok(read) => {
+                chunk.truncate(read);
+                Ok(chunk)
+            }
+            Err(e) => Err(e),
+        };
+        let failed = result.is_err();
+        if sender.send(result).await.is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// The receiving end of [`read_chunks`], read from the blocking thread pool.
+struct ChunkReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl ChunkReader {
+    fn new(receiver: mpsc::Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.position == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let available = &self.chunk[self.position..];
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.position += read;
+        Ok(read)
+    }
+}
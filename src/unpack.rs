@@ -0,0 +1,382 @@
+//! Safe extraction to a directory on disk.
+//!
+//! This module provides the options for
+//! [`ArchiveExtractor::extract_to_dir`](crate::ArchiveExtractor::extract_to_dir),
+//! which writes extracted entries beneath a root directory without letting any
+//! of them escape it.
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::{EntryLink, ExtractedFile};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// What to do when an entry's destination already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Fail with an [`io::ErrorKind::AlreadyExists`] error.
+    #[default]
+    Error,
+
+    /// Leave the existing file in place and continue with the next entry.
+    Skip,
+
+    /// Replace the existing file. Existing directories are never replaced.
+    Replace,
+}
+
+type EntryFilter = Arc<dyn Fn(&ExtractedFile) -> bool + Send + Sync>;
+
+/// Options for [`ArchiveExtractor::extract_to_dir`](crate::ArchiveExtractor::extract_to_dir).
+///
+/// # Examples
+///
+/// ```
+/// use archive::{ExtractOptions, OverwritePolicy};
+///
+/// let options = ExtractOptions::new()
+///     .with_overwrite(OverwritePolicy::Replace)
+///     .with_permissions(true)
+///     .with_mtime(true)
+///     .with_filter(|file| file.path.ends_with(".txt"));
+/// ```
+#[derive(Clone, Default)]
+pub struct ExtractOptions {
+    overwrite: OverwritePolicy,
+    permissions: bool,
+    mtime: bool,
+    filter: Option<EntryFilter>,
+}
+
+impl ExtractOptions {
+    /// Creates options that fail on existing files and restore neither
+    /// permissions nor modification times.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what happens when a destination already exists.
+    pub fn with_overwrite(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite = policy;
+        self
+    }
+
+    /// Sets whether the permission bits stored in the archive are applied.
+    ///
+    /// Only the read, write and execute bits are restored; setuid, setgid and
+    /// sticky bits are dropped. Without this, files and directories are
+    /// created with the default permissions, subject to the umask. This has
+    /// no effect on platforms other than Unix.
+    pub fn with_permissions(mut self, enabled: bool) -> Self {
+        self.permissions = enabled;
+        self
+    }
+
+    /// Sets whether the modification times stored in the archive are applied.
+    pub fn with_mtime(mut self, enabled: bool) -> Self {
+        self.mtime = enabled;
+        self
+    }
+
+    /// Only writes the entries for which `filter` returns `true`.
+    ///
+    /// Entries that are filtered out are neither validated nor written. A
+    /// hard link whose target was filtered out fails to extract.
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(&ExtractedFile) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+}
+
+impl fmt::Debug for ExtractOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractOptions")
+            .field("overwrite", &self.overwrite)
+            .field("permissions", &self.permissions)
+            .field("mtime", &self.mtime)
+            .field("filter", &self.filter.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+/// Writes extracted entries beneath `root`, which is created if missing.
+///
+/// Every entry path and link target is validated before anything is written,
+/// so an archive with an escaping path leaves `root` untouched.
+pub(crate) fn write_entries(
+    files: &[ExtractedFile],
+    root: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<PathBuf>> {
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        if let Some(filter) = &options.filter
+            && !filter(file)
+        {
+            continue;
+        }
+        // Special files would need privileges to create and carry no contents
+        if file.metadata.special.is_some() {
+            continue;
+        }
+        let Some(relative) = sanitize_path(&file.path)? else {
+            // The root itself, e.g. `./` in TAR archives
+            continue;
+        };
+        if let Some(EntryLink::Symbolic(target)) = &file.metadata.link {
+            check_symlink_target(&file.path, &relative, target)?;
+        }
+        entries.push((relative, file));
+    }
+
+    fs::create_dir_all(root)?;
+    let root = root.canonicalize()?;
+
+    let mut written = Vec::new();
+    let mut directories = Vec::new();
+
+    for (relative, file) in entries {
+        let destination = root.join(&relative);
+
+        if file.is_directory {
+            create_directories(&root, &relative)?;
+            directories.push((destination.clone(), file));
+            written.push(destination);
+            continue;
+        }
+
+        if let Some(parent) = relative.parent() {
+            create_directories(&root, parent)?;
+        }
+
+        match fs::symlink_metadata(&destination) {
+            Ok(existing) if existing.is_dir() => {
+                return Err(already_exists(&destination));
+            }
+            Ok(_) => match options.overwrite {
+                OverwritePolicy::Error => return Err(already_exists(&destination)),
+                OverwritePolicy::Skip => continue,
+                OverwritePolicy::Replace => {}
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let replace = options.overwrite == OverwritePolicy::Replace;
+
+        match &file.metadata.link {
+            Some(EntryLink::Symbolic(target)) => {
+                write_symlink(&destination, target, replace)?;
+                if options.mtime
+                    && let Some(mtime) = file.metadata.mtime
+                {
+                    let mtime = filetime::FileTime::from_unix_time(mtime as i64, 0);
+                    filetime::set_symlink_file_times(&destination, mtime, mtime)?;
+                }
+            }
+            Some(EntryLink::Hard(target)) => {
+                let source = resolve_hard_link(&root, &file.path, target)?;
+                if replace {
+                    remove_if_exists(&destination)?;
+                }
+                fs::hard_link(source, &destination)?;
+            }
+            None => {
+                write_file(&destination, file, options, replace)?;
+            }
+        }
+
+        written.push(destination);
+    }
+
+    // Directories are finalized last so that read-only modes don't prevent
+    // writing their contents and writing doesn't bump their times
+    for (destination, file) in directories.iter().rev() {
+        apply_metadata(destination, file, options)?;
+    }
+
+    Ok(written)
+}
+
+/// Turns an entry path into a relative path with only normal components.
+///
+/// Returns `None` for paths that refer to the root itself.
+fn sanitize_path(path: &str) -> Result<Option<PathBuf>> {
+    if path.contains('\0') {
+        return Err(ArchiveError::InvalidPath(path.to_string()));
+    }
+
+    let mut sanitized = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(ArchiveError::InvalidPath(path.to_string()));
+            }
+        }
+    }
+
+    Ok((!sanitized.as_os_str().is_empty()).then_some(sanitized))
+}
+
+/// Rejects symbolic link targets that could resolve outside the root.
+///
+/// Targets must be relative, and `..` components may only lead the target
+/// without climbing above the root. A `..` after a normal component could
+/// step out of another symbolic link and is rejected.
+fn check_symlink_target(path: &str, relative: &Path, target: &str) -> Result<()> {
+    let escaping = || ArchiveError::InvalidPath(format!("{} -> {}", path, target));
+
+    let mut depth = relative.components().count() - 1;
+    let mut descended = false;
+    for component in Path::new(target).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if !descended && depth > 0 => depth -= 1,
+            Component::Normal(_) => descended = true,
+            _ => return Err(escaping()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the directories of `relative` beneath `root`, refusing to follow
+/// symbolic links that are already on disk.
+fn create_directories(root: &Path, relative: &Path) -> Result<()> {
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(existing) if existing.is_dir() => {}
+            Ok(existing) if existing.file_type().is_symlink() => {
+                return Err(ArchiveError::InvalidPath(format!(
+                    "{} is a symbolic link",
+                    current.display()
+                )));
+            }
+            Ok(_) => return Err(already_exists(&current)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => match fs::create_dir(&current) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && current.is_dir() => {}
+                Err(e) => return Err(e.into()),
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Writes a regular file through a temporary file in the same directory, so
+/// that the destination is either absent or complete.
+fn write_file(
+    destination: &Path,
+    file: &ExtractedFile,
+    options: &ExtractOptions,
+    replace: bool,
+) -> Result<()> {
+    let parent = destination.parent().unwrap_or(Path::new("."));
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".archive-");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // Regular default permissions rather than the private 0o600 of
+        // temporary files; the umask still applies
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+
+    let mut temp = builder.tempfile_in(parent)?;
+    temp.write_all(&file.data)?;
+    apply_metadata(temp.path(), file, options)?;
+
+    let persisted = if replace {
+        temp.persist(destination)
+    } else {
+        temp.persist_noclobber(destination)
+    };
+    persisted.map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_symlink(destination: &Path, target: &str, replace: bool) -> Result<()> {
+    let parent = destination.parent().unwrap_or(Path::new("."));
+    let temp = tempfile::Builder::new()
+        .prefix(".archive-")
+        .make_in(parent, |path| std::os::unix::fs::symlink(target, path))?;
+
+    let persisted = if replace {
+        temp.persist(destination)
+    } else {
+        temp.persist_noclobber(destination)
+    };
+    persisted.map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_symlink(_destination: &Path, _target: &str, _replace: bool) -> Result<()> {
+    Err(ArchiveError::UnsupportedFormat(
+        "symbolic links can only be extracted on Unix".to_string(),
+    ))
+}
+
+/// Resolves the target of a hard link to a regular file that was already
+/// extracted beneath the root.
+fn resolve_hard_link(root: &Path, path: &str, target: &str) -> Result<PathBuf> {
+    let invalid = || ArchiveError::InvalidPath(format!("{} => {}", path, target));
+
+    let relative = sanitize_path(target)?.ok_or_else(invalid)?;
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        let metadata = fs::symlink_metadata(&current).map_err(|_| invalid())?;
+        if metadata.file_type().is_symlink() {
+            return Err(invalid());
+        }
+    }
+
+    if !current.is_file() {
+        return Err(invalid());
+    }
+    Ok(current)
+}
+
+fn apply_metadata(path: &Path, file: &ExtractedFile, options: &ExtractOptions) -> Result<()> {
+    #[cfg(unix)]
+    if options.permissions
+        && let Some(mode) = file.metadata.mode
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+
+    if options.mtime
+        && let Some(mtime) = file.metadata.mtime
+    {
+        filetime::set_file_mtime(path, filetime::FileTime::from_unix_time(mtime as i64, 0))?;
+    }
+
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn already_exists(path: &Path) -> ArchiveError {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+    .into()
+}
@@ -0,0 +1,871 @@
+//! Archive integrity verification.
+//!
+//! This module provides [`VerifyReport`], returned by
+//! [`ArchiveExtractor::verify`](crate::ArchiveExtractor::verify), which records
+//! whether every entry of an archive decompresses cleanly and matches its
+//! stored checksums. Entry contents are discarded as they are read.
+
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+use crate::limit::LimitedWriter;
+#[cfg(feature = "rar")]
+use crate::rar;
+#[cfg(feature = "xar")]
+use crate::xar;
+use crate::{cab, cpio, iso, lzma, lzw, rpm, squashfs, window};
+#[cfg(feature = "rar")]
+use rars::{ExtractionDecision, ExtractionErrorAction};
+#[cfg(feature = "rar")]
+use std::cell::Cell;
+use std::io::{self, Cursor, Read};
+#[cfg(feature = "rar")]
+use std::sync::Arc;
+#[cfg(feature = "rar")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The outcome of verifying an archive.
+///
+/// Structural problems that prevent reading the archive at all, such as a
+/// missing ZIP central directory, are returned as errors by
+/// [`verify`](crate::ArchiveExtractor::verify) instead. Everything else is
+/// reported here so that all corrupt entries can be listed at once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use archive::{ArchiveExtractor, ArchiveFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let data = vec![0u8; 100];
+/// let report = ArchiveExtractor::new().verify(&data, ArchiveFormat::Zip)?;
+///
+/// if !report.is_ok() {
+///     for entry in report.failures() {
+///         eprintln!("{}: {}", entry.path, entry.error.as_deref().unwrap_or_default());
+///     }
+///     if let Some(error) = &report.archive_error {
+///         eprintln!("archive: {}", error);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyReport {
+    /// The format the archive was verified as.
+    pub format: ArchiveFormat,
+
+    /// Every entry that was reached, in archive order.
+    pub entries: Vec<VerifiedEntry>,
+
+    /// Corruption that can't be attributed to a single entry, e.g. a TAR header
+    /// checksum mismatch or a bad checksum in the trailer of the compression
+    /// stream around a TAR archive. Entries after this point are not reported.
+    pub archive_error: Option<String>,
+}
+
+impl VerifyReport {
+    fn new(format: ArchiveFormat) -> Self {
+        Self {
+            format,
+            entries: Vec::new(),
+            archive_error: None,
+        }
+    }
+
+    /// Returns `true` if the archive and all of its entries passed verification.
+    pub fn is_ok(&self) -> bool {
+        self.archive_error.is_none() && self.entries.iter().all(VerifiedEntry::is_ok)
+    }
+
+    /// Returns the entries that failed verification.
+    pub fn failures(&self) -> impl Iterator<Item = &VerifiedEntry> {
+        self.entries.iter().filter(|entry| !entry.is_ok())
+    }
+}
+
+/// The verification result of a single entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifiedEntry {
+    /// The path of the entry within the archive, as reported by
+    /// [`extract`](crate::ArchiveExtractor::extract).
+    pub path: String,
+
+    /// Whether this entry represents a directory.
+    pub is_directory: bool,
+
+    /// Number of bytes decompressed before the entry ended or failed.
+    pub size: u64,
+
+    /// Why the entry failed, or `None` if it passed.
+    pub error: Option<String>,
+}
+
+impl VerifiedEntry {
+    /// Returns `true` if the entry passed verification.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Decompresses every entry of `data`, checking stored checksums and sizes
+/// while enforcing the extractor's size and window limits. Encrypted ZIP,
+/// 7-Zip and RAR entries are decrypted with `password`.
+pub(crate) fn verify(
+    data: &[u8],
+    format: ArchiveFormat,
+    password: Option<&str>,
+    max_file_size: usize,
+    max_total_size: usize,
+    max_window_size: usize,
+) -> Result<VerifyReport> {
+    window::check(data, format, max_window_size)?;
+    let mut verifier = Verifier {
+        password,
+        max_file_size,
+        max_total_size,
+        total_size: 0,
+        report: VerifyReport::new(format),
+    };
+
+    match format {
+        ArchiveFormat::Zip => verifier.verify_zip(data)?,
+        ArchiveFormat::Tar => verifier.verify_tar(data)?,
+        ArchiveFormat::Ar | ArchiveFormat::Deb => verifier.verify_ar(data)?,
+        ArchiveFormat::Cpio => verifier.verify_cpio(data)?,
+        ArchiveFormat::Rpm => verifier.verify_rpm(data, max_window_size)?,
+        ArchiveFormat::Iso => verifier.verify_iso(data)?,
+        ArchiveFormat::Cab => verifier.verify_cab(data)?,
+        #[cfg(feature = "xar")]
+        ArchiveFormat::Xar | ArchiveFormat::Pkg => {
+            verifier.verify_xar(data, format, max_window_size)?;
+        }
+        #[cfg(not(feature = "xar"))]
+        ArchiveFormat::Xar | ArchiveFormat::Pkg => return Err(format.feature_disabled("xar")),
+        ArchiveFormat::SquashFs => verifier.verify_squashfs(data, max_window_size)?,
+        ArchiveFormat::TarGz => verifier.verify_tar(flate2::read::MultiGzDecoder::new(data))?,
+        ArchiveFormat::TarBz2 => verifier.verify_tar(bzip2::read::BzDecoder::new(data))?,
+        ArchiveFormat::TarXz | ArchiveFormat::TarLz | ArchiveFormat::TarLzma => {
+            // lzma-rs only decompresses into a writer, so the TAR stream is
+            // buffered, bounded by the total size limit
+            let mut output = LimitedWriter::new(Vec::new(), max_total_size);
+            let mut input = Cursor::new(data);
+            match lzma::decompress(format, &mut input, &mut output, max_window_size) {
+                Ok(()) => verifier.verify_tar(output.inner.as_slice())?,
+                Err(_) if let Some(size) = output.exceeded => {
+                    return Err(ArchiveError::TotalSizeTooLarge {
+                        size,
+                        limit: max_total_size,
+                    });
+                }
+                Err(ArchiveError::InvalidArchive(e)) => verifier.report.archive_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        ArchiveFormat::TarZst => {
+            verifier.verify_tar(window::zstd_decoder(data, max_window_size)?)?;
+        }
+        ArchiveFormat::TarLz4 => verifier.verify_tar(lz4::Decoder::new(data)?)?,
+        ArchiveFormat::TarZ => verifier.verify_tar(lzw::Decoder::new(data)?)?,
+        #[cfg(feature = "brotli")]
+        ArchiveFormat::TarBr => verifier.verify_tar(brotli::Decompressor::new(data, 4096))?,
+        ArchiveFormat::SevenZ => verifier.verify_7z(data)?,
+        #[cfg(feature = "rar")]
+        ArchiveFormat::Rar => verifier.verify_rar(data, max_window_size)?,
+        #[cfg(not(feature = "rar"))]
+        ArchiveFormat::Rar => return Err(format.feature_disabled("rar")),
+        ArchiveFormat::Gz => {
+            let mut decoder = flate2::read::MultiGzDecoder::new(data);
+            let path = decoder
+                .header()
+                .and_then(|h| h.filename())
+                .and_then(|f| std::str::from_utf8(f).ok())
+                .unwrap_or("data")
+                .to_string();
+            verifier.read_entry(path, &mut decoder, None)?;
+        }
+        ArchiveFormat::Bz2 => {
+            verifier.read_entry(
+                "data".to_string(),
+                &mut bzip2::read::BzDecoder::new(data),
+                None,
+            )?;
+        }
+        ArchiveFormat::Xz | ArchiveFormat::Lz | ArchiveFormat::Lzma => {
+            let mut output = LimitedWriter::new(io::sink(), max_file_size);
+            let mut input = Cursor::new(data);
+            let result = lzma::decompress(format, &mut input, &mut output, max_window_size);
+            if let Some(size) = output.exceeded {
+                return Err(ArchiveError::FileTooLarge {
+                    size,
+                    limit: max_file_size,
+                });
+            }
+            let error = match result {
+                Ok(()) => None,
+                Err(ArchiveError::InvalidArchive(e)) => Some(e),
+                Err(e) => return Err(e),
+            };
+            verifier.report.entries.push(VerifiedEntry {
+                path: "data".to_string(),
+                is_directory: false,
+                size: output.written as u64,
+                error,
+            });
+        }
+        ArchiveFormat::Lz4 => match lz4::Decoder::new(data) {
+            Ok(mut decoder) => {
+                verifier.read_entry("data".to_string(), &mut decoder, None)?;
+            }
+            Err(e) => verifier.fail("data".to_string(), e.to_string()),
+        },
+        ArchiveFormat::Zst => match window::zstd_decoder(data, max_window_size) {
+            Ok(mut decoder) => {
+                verifier.read_entry("data".to_string(), &mut decoder, None)?;
+            }
+            Err(e) => verifier.fail("data".to_string(), e.to_string()),
+        },
+        ArchiveFormat::Compress => match lzw::Decoder::new(data) {
+            Ok(mut decoder) => {
+                verifier.read_entry("data".to_string(), &mut decoder, None)?;
+            }
+            Err(e) => verifier.fail("data".to_string(), e.to_string()),
+        },
+        #[cfg(feature = "brotli")]
+        ArchiveFormat::Br => {
+            verifier.read_entry(
+                "data".to_string(),
+                &mut brotli::Decompressor::new(data, 4096),
+                None,
+            )?;
+        }
+        #[cfg(not(feature = "brotli"))]
+        ArchiveFormat::Br | ArchiveFormat::TarBr => return Err(format.feature_disabled("brotli")),
+        #[cfg(feature = "snappy")]
+        ArchiveFormat::Snappy => {
+            verifier.read_entry(
+                "data".to_string(),
+                &mut snap::read::FrameDecoder::new(data),
+                None,
+            )?;
+        }
+        #[cfg(not(feature = "snappy"))]
+        ArchiveFormat::Snappy => return Err(format.feature_disabled("snappy")),
+    }
+
+    Ok(verifier.report)
+}
+
+struct Verifier<'a> {
+    password: Option<&'a str>,
+    max_file_size: usize,
+    max_total_size: usize,
+    total_size: usize,
+    report: VerifyReport,
+}
+
+impl Verifier<'_> {
+    fn verify_zip(&mut self, data: &[u8]) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+
+        for i in 0..archive.len() {
+            // Reading the raw entry first keeps its name available when the
+            // entry can't be decompressed, e.g. because it is encrypted
+            let (path, is_directory, size, encrypted) = {
+                let file = archive.by_index_raw(i)?;
+                (
+                    file.name().to_string(),
+                    file.is_dir(),
+                    file.size(),
+                    file.encrypted(),
+                )
+            };
+
+            if is_directory {
+                self.push_directory(path);
+                continue;
+            }
+
+            self.check_declared_size(size)?;
+            let file = match self.password {
+                Some(password) if encrypted => archive.by_index_decrypt(i, password.as_bytes()),
+                _ => archive.by_index(i),
+            };
+            match file {
+                Ok(mut file) => {
+                    self.read_entry(path, &mut file, Some(size))?;
+                }
+                Err(e) => self.fail(path, e.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_tar<R: Read>(&mut self, reader: R) -> Result<()> {
+        let mut archive = tar::Archive::new(reader);
+
+        for entry_result in archive.entries()? {
+            let mut entry = match entry_result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.report.archive_error = Some(e.to_string());
+                    return Ok(());
+                }
+            };
+            let path = entry.path()?.to_string_lossy().to_string();
+
+            if entry.header().entry_type().is_dir() {
+                self.push_directory(path);
+                continue;
+            }
+
+            let size = entry.size();
+            self.check_declared_size(size)?;
+            if !self.read_entry(path, &mut entry, Some(size))? {
+                // The stream position is unknown after a failed read
+                return Ok(());
+            }
+        }
+
+        // The end-of-archive marker may be followed by padding and, for
+        // compressed archives, the stream trailer holding its checksum
+        if let Err(e) = io::copy(&mut archive.into_inner(), &mut io::sink()) {
+            self.report.archive_error = Some(e.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn verify_ar(&mut self, data: &[u8]) -> Result<()> {
+        let mut archive = ar::Archive::new(data);
+
+        while let Some(entry_result) = archive.next_entry() {
+            let mut entry = match entry_result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.report.archive_error = Some(e.to_string());
+                    return Ok(());
+                }
+            };
+            let path = String::from_utf8_lossy(entry.header().identifier()).to_string();
+
+            let size = entry.header().size();
+            self.check_declared_size(size)?;
+            if !self.read_entry(path, &mut entry, Some(size))? {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_rpm(&mut self, mut data: &[u8], max_window_size: usize) -> Result<()> {
+        let compression = rpm::Package::read(&mut data)?.compression()?;
+        match rpm::payload_reader(data, compression, max_window_size, self.max_total_size) {
+            Ok(payload) => self.verify_cpio(payload),
+            Err(
+                e @ (ArchiveError::TotalSizeTooLarge { .. } | ArchiveError::WindowTooLarge { .. }),
+            ) => Err(e),
+            Err(e) => {
+                self.report.archive_error = Some(e.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    fn verify_cpio<R: Read>(&mut self, reader: R) -> Result<()> {
+        let mut archive = cpio::Archive::new(reader);
+
+        while let Some(entry_result) = archive.next_entry() {
+            let mut entry = match entry_result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.report.archive_error = Some(e.to_string());
+                    return Ok(());
+                }
+            };
+            let path = entry.header().path();
+
+            if entry.header().is_directory() {
+                self.push_directory(path);
+                continue;
+            }
+
+            let size = entry.header().size();
+            self.check_declared_size(size)?;
+            if !self.read_entry(path, &mut entry, Some(size))? {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_iso(&mut self, data: &[u8]) -> Result<()> {
+        let mut image = iso::Image::open(Cursor::new(data))?;
+
+        for entry in image.entries()? {
+            if entry.is_directory {
+                self.push_directory(entry.path);
+                continue;
+            }
+
+            // Images have no checksums, but every file must lie within them
+            self.check_declared_size(entry.size)?;
+            let mut contents = image.contents(&entry);
+            self.read_entry(entry.path, &mut contents, Some(entry.size))?;
+        }
+
+        Ok(())
+    }
+
+    fn verify_cab(&mut self, data: &[u8]) -> Result<()> {
+        let cabinet = cab::Cabinet::parse(data)?;
+
+        for (folder_index, file_indices) in cabinet.folder_files()?.into_iter().enumerate() {
+            if file_indices.is_empty() {
+                continue;
+            }
+            let folder_data = cabinet.folder_data(data, folder_index)?;
+            let mut reader = cabinet.folder_reader(folder_index, folder_data)?;
+
+            for (position, &file_index) in file_indices.iter().enumerate() {
+                let file = &cabinet.files[file_index];
+                let size = u64::from(file.size);
+                self.check_declared_size(size)?;
+                if self.read_entry(file.path.clone(), &mut reader.contents(file), Some(size))? {
+                    continue;
+                }
+
+                // A folder can't be decoded past a failed block, so the rest
+                // of its files fail along with it
+                for &file_index in &file_indices[position + 1..] {
+                    let path = cabinet.files[file_index].path.clone();
+                    self.fail(path, "not decoded after an earlier error".to_string());
+                }
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_squashfs(&mut self, data: &[u8], max_window_size: usize) -> Result<()> {
+        let image = squashfs::Image::parse(data, max_window_size)?;
+
+        for entry in &image.entries {
+            match &entry.kind {
+                squashfs::Kind::Directory => self.push_directory(entry.path.clone()),
+                squashfs::Kind::File(file) => {
+                    self.check_declared_size(file.size)?;
+                    self.read_entry(entry.path.clone(), &mut image.reader(file), Some(file.size))?;
+                }
+                _ => {
+                    self.read_entry(entry.path.clone(), &mut io::empty(), Some(0))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "xar")]
+    fn verify_xar(
+        &mut self,
+        data: &[u8],
+        format: ArchiveFormat,
+        max_window_size: usize,
+    ) -> Result<()> {
+        let archive = xar::Archive::parse(data)?;
+        match archive.check_toc() {
+            Ok(()) => {}
+            Err(ArchiveError::InvalidArchive(e)) => {
+                self.report.archive_error = Some(e);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+
+        for entry in &archive.entries {
+            let entry_data = match (&entry.kind, &entry.data) {
+                (xar::Kind::Directory, _) => {
+                    self.push_directory(entry.path.clone());
+                    continue;
+                }
+                (xar::Kind::File, Some(entry_data)) => entry_data,
+                _ => {
+                    self.read_entry(entry.path.clone(), &mut io::empty(), Some(0))?;
+                    continue;
+                }
+            };
+
+            let is_package = format == ArchiveFormat::Pkg && entry.is_package_archive();
+            if is_package {
+                if entry_data.size > self.max_total_size as u64 {
+                    return Err(ArchiveError::TotalSizeTooLarge {
+                        size: entry_data.size as usize,
+                        limit: self.max_total_size,
+                    });
+                }
+            } else {
+                self.check_declared_size(entry_data.size)?;
+            }
+
+            // Stored checksum mismatches only fail the entry
+            let max_size = if is_package {
+                self.max_total_size
+            } else {
+                self.max_file_size
+            };
+            let mut reader =
+                match archive.reader(&entry.path, entry_data, max_window_size, max_size) {
+                    Ok(reader) => reader,
+                    Err(ArchiveError::InvalidArchive(e)) => {
+                        self.fail(entry.path.clone(), e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+            if !is_package {
+                self.read_entry(entry.path.clone(), &mut reader, Some(entry_data.size))?;
+                continue;
+            }
+
+            let mut package = Vec::new();
+            if let Err(e) = reader.read_to_end(&mut package) {
+                self.fail(entry.path.clone(), e.to_string());
+                continue;
+            }
+            self.verify_package(entry, &package, max_window_size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the cpio archive in the `Payload` or `Scripts` of a component
+    /// package, reporting its files beneath the entry.
+    #[cfg(feature = "xar")]
+    fn verify_package(
+        &mut self,
+        entry: &xar::Entry,
+        package: &[u8],
+        max_window_size: usize,
+    ) -> Result<()> {
+        self.push_directory(entry.path.clone());
+        let start = self.report.entries.len();
+        match xar::package_reader(package, max_window_size, self.max_total_size) {
+            Ok(reader) => self.verify_cpio(reader)?,
+            Err(ArchiveError::InvalidArchive(e)) => self.report.archive_error = Some(e),
+            Err(e) => return Err(e),
+        }
+
+        let expanded = self.report.entries.split_off(start);
+        self.report
+            .entries
+            .extend(expanded.into_iter().filter_map(|mut verified| {
+                verified.path = entry.expanded_path(&verified.path)?;
+                Some(verified)
+            }));
+        // A corrupt payload fails the package rather than the whole archive
+        if let Some(e) = self.report.archive_error.take() {
+            self.fail(entry.path.clone(), e);
+        }
+        Ok(())
+    }
+
+    fn verify_7z(&mut self, data: &[u8]) -> Result<()> {
+        let mut cursor = Cursor::new(data);
+        let password = sevenz_rust::Password::from(self.password.unwrap_or_default());
+        let archive =
+            sevenz_rust::Archive::read(&mut cursor, data.len() as u64, password.as_slice())
+                .map_err(|e| ArchiveError::InvalidArchive(format!("7z error: {}", e)))?;
+
+        for folder_index in 0..archive.folders.len() {
+            let first_file = archive.stream_map.folder_first_file_index[folder_index];
+            let file_count = archive.folders[folder_index].num_unpack_sub_streams;
+            let reported = self.report.entries.len();
+            let mut limit_error = None;
+
+            let decoder = sevenz_rust::BlockDecoder::new(
+                folder_index,
+                &archive,
+                password.as_slice(),
+                &mut cursor,
+            );
+            let result = decoder.for_each_entries(&mut |entry, reader| {
+                let path = entry.name().to_string();
+                let size = entry.size();
+                let result = self
+                    .check_declared_size(size)
+                    .and_then(|()| self.read_entry(path, reader, Some(size)));
+                match result {
+                    Ok(passed) => Ok(passed),
+                    Err(e) => {
+                        limit_error = Some(e);
+                        Ok(false)
+                    }
+                }
+            });
+
+            if let Some(e) = limit_error {
+                return Err(e);
+            }
+
+            // A failed block can't be decoded past the point of failure, so
+            // the entries that weren't reached fail along with it
+            let error = result.err().map(|e| format!("7z error: {}", e));
+            let reached = self.report.entries.len() - reported;
+            if reached < file_count {
+                let error = error.unwrap_or_else(|| "not decoded after an earlier error".into());
+                for file in &archive.files[first_file + reached..first_file + file_count] {
+                    self.fail(file.name().to_string(), error.clone());
+                }
+            }
+        }
+
+        // Directories and empty files are not stored in any block
+        for (file_index, entry) in archive.files.iter().enumerate() {
+            if archive.stream_map.file_folder_index[file_index].is_none() {
+                if entry.is_directory() {
+                    self.push_directory(entry.name().to_string());
+                } else {
+                    self.read_entry(entry.name().to_string(), &mut io::empty(), Some(0))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rar")]
+    fn verify_rar(&mut self, data: &[u8], max_window_size: usize) -> Result<()> {
+        let options = rar::read_options(
+            self.password,
+            self.max_file_size,
+            self.max_total_size,
+            max_window_size,
+        );
+        let has_password = self.password.is_some();
+        let archive = rar::read(data, options)?;
+        let solid = rar::is_solid(&archive);
+        let written = SharedCounter::default();
+        let failure = Cell::new(None);
+        let mut pending = None;
+        let mut visited = 0;
+        let mut stopped = false;
+        let mut limit_error = None;
+
+        // A member is complete once the next one is visited, and independent
+        // members that fail to decode are reported through `failure`
+        let result = archive.extract_with_control_and_errors(
+            options,
+            |member| {
+                if let Some((path, size)) = pending.take() {
+                    self.finish_rar_entry(path, size, written.take(), failure.take());
+                }
+                visited += 1;
+
+                let path = rar::entry_path(member);
+                if let Err(e) = rar::check_member(member, has_password) {
+                    self.fail(path, e.to_string());
+                    // Solid archives can't skip the data of an entry
+                    if solid {
+                        stopped = true;
+                        return Ok(ExtractionDecision::Stop);
+                    }
+                    return Ok(ExtractionDecision::Skip);
+                }
+                if member.meta.is_directory {
+                    self.push_directory(path);
+                    return Ok(ExtractionDecision::Skip);
+                }
+                if member.meta.is_redirection {
+                    self.finish_rar_entry(path, 0, 0, None);
+                    return Ok(ExtractionDecision::Skip);
+                }
+
+                let size = member.meta.unpacked_size;
+                if let Err(e) = self.check_declared_size(size) {
+                    limit_error = Some(e);
+                    return Ok(ExtractionDecision::Stop);
+                }
+                pending = Some((path, size));
+                Ok(ExtractionDecision::Extract(Box::new(written.clone())))
+            },
+            |_, error| {
+                failure.set(Some(error.to_string()));
+                Ok(ExtractionErrorAction::Continue)
+            },
+        );
+
+        if let Some(e) = limit_error {
+            return Err(e);
+        }
+
+        let error = match result.map_err(rar::map_error) {
+            Ok(_) => None,
+            Err(
+                e @ (ArchiveError::FileTooLarge { .. }
+                | ArchiveError::TotalSizeTooLarge { .. }
+                | ArchiveError::WindowTooLarge { .. }),
+            ) => return Err(e),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some((path, size)) = pending {
+            let error = error.clone().or(failure.take());
+            self.finish_rar_entry(path, size, written.take(), error);
+        } else if let Some(error) = &error {
+            self.report.archive_error = Some(error.clone());
+        }
+
+        // Solid archives can't be decoded past the point of failure, so the
+        // entries that weren't reached fail along with it
+        if stopped || error.is_some() {
+            for member in archive.members().skip(visited) {
+                self.fail(
+                    rar::entry_path(&member),
+                    "not decoded after an earlier error".to_string(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a RAR entry once all of its contents were written.
+    #[cfg(feature = "rar")]
+    fn finish_rar_entry(&mut self, path: String, expected: u64, size: u64, error: Option<String>) {
+        self.total_size = self.total_size.saturating_add(size as usize);
+        let error = error.or_else(|| {
+            (expected != size).then(|| format!("expected {} bytes, got {}", expected, size))
+        });
+        self.report.entries.push(VerifiedEntry {
+            path,
+            is_directory: false,
+            size,
+            error,
+        });
+    }
+
+    /// Rejects an entry up front if its declared size exceeds the limits.
+    fn check_declared_size(&self, size: u64) -> Result<()> {
+        let size = size as usize;
+        if size > self.max_file_size {
+            return Err(ArchiveError::FileTooLarge {
+                size,
+                limit: self.max_file_size,
+            });
+        }
+
+        let total_size = self.total_size.saturating_add(size);
+        if total_size > self.max_total_size {
+            return Err(ArchiveError::TotalSizeTooLarge {
+                size: total_size,
+                limit: self.max_total_size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reads an entry to its end, recording whether it passed. Returns
+    /// `Ok(false)` if it failed and an error if the size limits were exceeded.
+    fn read_entry(
+        &mut self,
+        path: String,
+        reader: &mut dyn Read,
+        expected_size: Option<u64>,
+    ) -> Result<bool> {
+        let mut buffer = [0u8; 64 * 1024];
+        let mut size = 0usize;
+
+        let error = loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break None,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => break Some(e.to_string()),
+            };
+
+            size += read;
+            if size > self.max_file_size {
+                return Err(ArchiveError::FileTooLarge {
+                    size,
+                    limit: self.max_file_size,
+                });
+            }
+
+            self.total_size += read;
+            if self.total_size > self.max_total_size {
+                return Err(ArchiveError::TotalSizeTooLarge {
+                    size: self.total_size,
+                    limit: self.max_total_size,
+                });
+            }
+        };
+
+        let error = error.or_else(|| {
+            expected_size
+                .filter(|&expected| expected != size as u64)
+                .map(|expected| format!("expected {} bytes, got {}", expected, size))
+        });
+        let passed = error.is_none();
+
+        self.report.entries.push(VerifiedEntry {
+            path,
+            is_directory: false,
+            size: size as u64,
+            error,
+        });
+
+        Ok(passed)
+    }
+
+    fn push_directory(&mut self, path: String) {
+        self.report.entries.push(VerifiedEntry {
+            path,
+            is_directory: true,
+            size: 0,
+            error: None,
+        });
+    }
+
+    fn fail(&mut self, path: String, error: String) {
+        self.report.entries.push(VerifiedEntry {
+            path,
+            is_directory: false,
+            size: 0,
+            error: Some(error),
+        });
+    }
+}
+
+/// Counts the bytes written through any of its clones.
+///
+/// `rars` takes ownership of the writer for each member, so the count is
+/// shared with the verifier.
+#[cfg(feature = "rar")]
+#[derive(Clone, Default)]
+struct SharedCounter(Arc<AtomicU64>);
+
+#[cfg(feature = "rar")]
+impl SharedCounter {
+    fn take(&self) -> u64 {
+        self.0.swap(0, Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "rar")]
+impl io::Write for SharedCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! Decoder window limits for XZ, lzip, LZMA, Zstandard and Brotli streams, CAB
+//! folders and SquashFS images.
+//!
+//! These formats declare in their headers how much history the decoder must
+//! keep: the LZMA2 dictionary size in each XZ block header, the dictionary
+//! size in the lzip and `.lzma` headers, the window size in each Zstandard
+//! frame header and at the start of a Brotli stream, the LZX window of each
+//! CAB folder, and the block size of a SquashFS image, which bounds the
+//! history of every block in it. A stream can demand a huge window while
+//! producing little output, so these declarations are checked against
+//! [`ArchiveExtractor::with_max_window_size`](crate::ArchiveExtractor::with_max_window_size)
+//! before any decoding starts.
+//!
+//! Headers that can't be parsed are left for the decoder to report, so
+//! corrupt streams fail the same way with or without these checks.
+
+use crate::error::{ArchiveError, Result};
+use crate::format::ArchiveFormat;
+use crate::{cab, lzma, squashfs, xz};
+use std::io::{BufReader, Read};
+
+/// Number of bytes at the start of a stream that [`check`] needs in order to
+/// see the first XZ block header, which is at most 1024 bytes long.
+#[cfg(feature = "tokio")]
+pub(crate) const HEAD_SIZE: usize = xz::STREAM_HEADER_SIZE + 1024;
+
+/// Checks the windows declared by `data` against `limit`, for the formats
+/// that declare one.
+pub(crate) fn check(data: &[u8], format: ArchiveFormat, limit: usize) -> Result<()> {
+    match format {
+        ArchiveFormat::Xz | ArchiveFormat::TarXz => check_xz(data, limit),
+        ArchiveFormat::Lz | ArchiveFormat::TarLz => {
+            lzma::lzip_dictionary_size(data).map_or(Ok(()), |size| too_large(size.into(), limit))
+        }
+        ArchiveFormat::Lzma | ArchiveFormat::TarLzma => {
+            lzma::lzma_dictionary_size(data).map_or(Ok(()), |size| too_large(size.into(), limit))
+        }
+        ArchiveFormat::Zst | ArchiveFormat::TarZst => check_zstd(data, limit),
+        ArchiveFormat::Br | ArchiveFormat::TarBr => check_brotli(data, limit),
+        ArchiveFormat::Cab => check_cab(data, limit),
+        ArchiveFormat::SquashFs => {
+            squashfs::block_size(data).map_or(Ok(()), |size| too_large(size.into(), limit))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Creates a Zstandard decoder that refuses windows larger than `limit`.
+///
+/// This backs up [`check`] for frames it couldn't parse. The decoder's limit
+/// is a power of two, rounded up, so [`check`] remains the exact one.
+pub(crate) fn zstd_decoder<R: Read>(
+    reader: R,
+    limit: usize,
+) -> Result<zstd::stream::read::Decoder<'static, BufReader<R>>> {
+    let mut decoder = zstd::stream::read::Decoder::new(reader)?;
+    let window_log = limit
+        .checked_next_power_of_two()
+        .map_or(usize::BITS, usize::trailing_zeros);
+    decoder.window_log_max(window_log.clamp(10, 31))?;
+    Ok(decoder)
+}
+
+fn too_large(size: u64, limit: usize) -> Result<()> {
+    if size > limit as u64 {
+        return Err(ArchiveError::WindowTooLarge { size, limit });
+    }
+    Ok(())
+}
+
+/// Checks the window size coded in the first bits of a Brotli stream
+/// (RFC 7932, section 9.1), including the large-window extension that allows
+/// up to 1 GiB.
+fn check_brotli(data: &[u8], limit: usize) -> Result<()> {
+    let Some(&first) = data.first() else {
+        return Ok(());
+    };
+    let window_bits = if first & 1 == 0 {
+        16
+    } else if (first >> 1) & 7 != 0 {
+        17 + ((first >> 1) & 7)
+    } else {
+        match (first >> 4) & 7 {
+            0 => 17,
+            // Large window, with the size in the next six bits
+            1 => match data.get(1) {
+                Some(second) => second & 0x3F,
+                None => return Ok(()),
+            },
+            m => 8 + m,
+        }
+    };
+    too_large((1u64 << window_bits).saturating_sub(16), limit)
+}
+
+fn check_zstd(data: &[u8], limit: usize) -> Result<()> {
+    let mut remaining = data;
+    while !remaining.is_empty() {
+        let Ok(frame_size) = zstd::zstd_safe::find_frame_compressed_size(remaining) else {
+            break;
+        };
+        let Ok(frame) = crate::info::parse_zstd_frame_header(&remaining[..frame_size]) else {
+            break;
+        };
+        if let Some(window_size) = frame.window_size {
+            too_large(window_size, limit)?;
+        }
+        remaining = &remaining[frame_size..];
+    }
+    Ok(())
+}
+
+fn check_cab(data: &[u8], limit: usize) -> Result<()> {
+    let Ok(cabinet) = cab::Cabinet::parse(data) else {
+        return Ok(());
+    };
+    for folder in &cabinet.folders {
+        if let Some(window_size) = folder.compression.window_size() {
+            too_large(window_size, limit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks the LZMA2 dictionary size of every block in every stream.
+///
+/// The first block is checked directly as well, so that a damaged index can't
+/// hide it.
+fn check_xz(data: &[u8], limit: usize) -> Result<()> {
+    if data.starts_with(xz::MAGIC)
+        && let Some(header) = data.get(xz::STREAM_HEADER_SIZE..)
+    {
+        check_xz_block_header(header, limit)?;
+    }
+
+    for stream in xz::streams(data) {
+        for block in &stream.blocks {
+            check_xz_block_header(&data[block.offset..], limit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks the dictionary size in the block header at the start of `data`.
+fn check_xz_block_header(data: &[u8], limit: usize) -> Result<()> {
+    match xz::dictionary_size(data) {
+        Some(size) => too_large(size, limit),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,755 @@
+//! XAR archive and macOS installer package reading.
+//!
+//! A XAR archive starts with a big-endian header, followed by a
+//! zlib-compressed XML table of contents and a heap. The table of contents
+//! describes every file as a nested `<file>` element, whose `<data>` element
+//! gives the offset and length of its contents in the heap, how they are
+//! encoded, and checksums of both the stored and the decoded contents. The
+//! checksum of the compressed table of contents is stored in the heap too.
+//!
+//! macOS flat packages (`.pkg`) are XAR archives holding a `Distribution`
+//! file and one `.pkg` directory per component package, or the files of a
+//! single component package directly. Each component package has a `Payload`
+//! with the files it installs and optionally `Scripts` with its install
+//! scripts. Both are cpio archives, compressed with gzip or with `pbzx`,
+//! Apple's sequence of XZ-compressed chunks.
+
+use crate::error::{ArchiveError, Result};
+use crate::extractor::days_from_civil;
+use crate::format::ArchiveFormat;
+use crate::hash::{self, HashAlgorithm, Hasher};
+use crate::info::XarInfo;
+use crate::limit::LimitedWriter;
+use crate::{lzma, window, xz};
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read, Write};
+
+pub(crate) const MAGIC: &[u8] = b"xar!";
+
+const HEADER_SIZE: usize = 28;
+
+/// The largest table of contents that is decompressed. Even installers with
+/// hundreds of thousands of files stay well below it.
+const MAX_TOC_SIZE: u64 = 256 * 1024 * 1024;
+/// Elements of the table of contents nested deeper than this are rejected,
+/// since both `roxmltree` and the walk over the files recurse per level.
+const MAX_DEPTH: usize = 256;
+
+// Checksum algorithms of the header
+const CHECKSUM_NONE: u32 = 0;
+const CHECKSUM_SHA1: u32 = 1;
+const CHECKSUM_MD5: u32 = 2;
+const CHECKSUM_OTHER: u32 = 3;
+
+const PBZX_MAGIC: &[u8] = b"pbzx";
+/// Flag of a pbzx header announcing another chunk.
+const PBZX_MORE_CHUNKS: u64 = 1 << 24;
+
+/// Whether `data` starts with a XAR header.
+fn is_xar(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Looks up a checksum algorithm by the name XAR uses, returning `None` for
+/// `none`.
+fn algorithm_from_name(name: &str) -> Result<Option<HashAlgorithm>> {
+    match name.to_ascii_lowercase().as_str() {
+        "none" => Ok(None),
+        "sha1" => Ok(Some(HashAlgorithm::Sha1)),
+        "md5" => Ok(Some(HashAlgorithm::Md5)),
+        "sha256" => Ok(Some(HashAlgorithm::Sha256)),
+        "sha512" => Ok(Some(HashAlgorithm::Sha512)),
+        _ => Err(ArchiveError::UnsupportedFormat(format!(
+            "XAR checksum algorithm '{}'",
+            name
+        ))),
+    }
+}
+
+/// The name XAR uses for a checksum algorithm.
+fn algorithm_name(algorithm: HashAlgorithm) -> &'static str {
+    match algorithm {
+        HashAlgorithm::Sha1 => "sha1",
+        HashAlgorithm::Md5 => "md5",
+        HashAlgorithm::Sha256 => "sha256",
+        HashAlgorithm::Sha512 => "sha512",
+        #[cfg(feature = "blake3")]
+        HashAlgorithm::Blake3 => "blake3",
+    }
+}
+
+/// A checksum recorded in the table of contents.
+#[derive(Debug, Clone)]
+struct Checksum {
+    algorithm: HashAlgorithm,
+    /// The digest as lowercase hexadecimal.
+    hex: String,
+}
+
+impl Checksum {
+    /// Reads a checksum element, returning `None` for the `none` style.
+    fn parse(node: roxmltree::Node<'_, '_>) -> Result<Option<Self>> {
+        let style = node.attribute("style").unwrap_or("none");
+        Ok(algorithm_from_name(style)?.map(|algorithm| Checksum {
+            algorithm,
+            hex: node.text().unwrap_or_default().trim().to_ascii_lowercase(),
+        }))
+    }
+}
+
+/// How the contents of a file are encoded in the heap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    None,
+    /// A zlib stream, despite the `application/x-gzip` name.
+    Zlib,
+    Bzip2,
+    /// XZ, or legacy LZMA in archives written by old versions of `xar`.
+    Lzma,
+}
+
+impl Encoding {
+    fn from_style(style: &str) -> Result<Self> {
+        match style {
+            "application/octet-stream" => Ok(Encoding::None),
+            "application/x-gzip" => Ok(Encoding::Zlib),
+            "application/x-bzip2" => Ok(Encoding::Bzip2),
+            "application/x-lzma" | "application/x-xz" => Ok(Encoding::Lzma),
+            _ => Err(ArchiveError::UnsupportedFormat(format!(
+                "XAR encoding '{}'",
+                style
+            ))),
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Encoding::None => "None",
+            Encoding::Zlib => "ZLIB",
+            Encoding::Bzip2 => "BZIP2",
+            Encoding::Lzma => "LZMA",
+        }
+    }
+}
+
+/// Where the contents of a file are in the heap, and how to decode them.
+#[derive(Debug, Clone)]
+pub(crate) struct Data {
+    offset: u64,
+    /// The size of the stored contents.
+    pub(crate) length: u64,
+    /// The size of the decoded contents.
+    pub(crate) size: u64,
+    pub(crate) encoding: Encoding,
+    archived_checksum: Option<Checksum>,
+    extracted_checksum: Option<Checksum>,
+}
+
+/// The type of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// A regular file, or a device node, FIFO or socket, which have no
+    /// contents.
+    File,
+    Directory,
+    Symlink(String),
+    /// A hard link to the file with the given path.
+    Hardlink(String),
+}
+
+/// A file of the table of contents, without its contents.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    /// The path, with `/` separating components.
+    pub(crate) path: String,
+    pub(crate) kind: Kind,
+    pub(crate) data: Option<Data>,
+    pub(crate) mode: Option<u32>,
+    /// Modification time in seconds since the Unix epoch.
+    pub(crate) mtime: Option<u64>,
+}
+
+impl Entry {
+    /// Whether this is the payload or the scripts of a component package,
+    /// which [`package_reader`] expands.
+    pub(crate) fn is_package_archive(&self) -> bool {
+        let name = self.path.rsplit('/').next().unwrap_or_default();
+        self.kind == Kind::File && matches!(name, "Payload" | "Scripts")
+    }
+
+    /// The path of a file of the payload or scripts beneath this entry, or
+    /// `None` for the root directory of their cpio archive.
+    pub(crate) fn expanded_path(&self, path: &str) -> Option<String> {
+        let path = path.trim_start_matches("./");
+        (!path.is_empty() && path != ".").then(|| format!("{}/{}", self.path, path))
+    }
+}
+
+/// A parsed XAR archive.
+#[derive(Debug, Clone)]
+pub(crate) struct Archive<'a> {
+    pub(crate) entries: Vec<Entry>,
+    heap: &'a [u8],
+    /// The compressed table of contents, which the checksum covers.
+    toc: &'a [u8],
+    toc_size: u64,
+    toc_checksum: Option<(HashAlgorithm, u64, u64)>,
+    /// The checksum algorithm named by the header, for the table of
+    /// contents.
+    checksum_name: String,
+}
+
+impl<'a> Archive<'a> {
+    /// Parses the header and table of contents of `data`.
+    ///
+    /// The checksum of the table of contents isn't checked here, but by
+    /// [`check_toc`](Self::check_toc).
+    pub(crate) fn parse(data: &'a [u8]) -> Result<Self> {
+        if !is_xar(data) || data.len() < HEADER_SIZE {
+            return Err(invalid("missing XAR header"));
+        }
+
+        let header_size = usize::from(u16_at(data, 4));
+        let version = u16_at(data, 6);
+        if version != 1 {
+            return Err(ArchiveError::UnsupportedFormat(format!(
+                "XAR version {}",
+                version
+            )));
+        }
+        let toc_length = u64_at(data, 8);
+        let toc_size = u64_at(data, 16);
+        let algorithm = match u32_at(data, 24) {
+            CHECKSUM_NONE => None,
+            CHECKSUM_SHA1 => Some(HashAlgorithm::Sha1),
+            CHECKSUM_MD5 => Some(HashAlgorithm::Md5),
+            CHECKSUM_OTHER => {
+                // The name of the algorithm follows the fixed header
+                let name = data
+                    .get(HEADER_SIZE..header_size)
+                    .ok_or_else(|| invalid("truncated header"))?;
+                let name = name.split(|&byte| byte == 0).next().unwrap_or_default();
+                algorithm_from_name(&String::from_utf8_lossy(name))?
+            }
+            other => return Err(invalid(format!("unknown checksum algorithm {}", other))),
+        };
+
+        let toc_end = usize::try_from(toc_length)
+            .ok()
+            .and_then(|length| header_size.checked_add(length))
+            .filter(|&end| header_size >= HEADER_SIZE && end <= data.len())
+            .ok_or_else(|| invalid("table of contents extends past the end"))?;
+        let toc = &data[header_size..toc_end];
+        let heap = &data[toc_end..];
+
+        if toc_size > MAX_TOC_SIZE {
+            return Err(invalid(format!(
+                "table of contents of {} bytes exceeds {} bytes",
+                toc_size, MAX_TOC_SIZE
+            )));
+        }
+        let mut xml = String::new();
+        flate2::read::ZlibDecoder::new(toc)
+            .take(toc_size)
+            .read_to_string(&mut xml)
+            .map_err(|e| invalid(format!("table of contents: {}", e)))?;
+        if xml.len() as u64 != toc_size {
+            return Err(invalid(format!(
+                "table of contents holds {} bytes instead of {}",
+                xml.len(),
+                toc_size
+            )));
+        }
+
+        if element_depth(&xml) > MAX_DEPTH {
+            return Err(invalid("table of contents nested too deeply"));
+        }
+        let document = roxmltree::Document::parse(&xml)
+            .map_err(|e| invalid(format!("table of contents: {}", e)))?;
+        let toc_node = document
+            .root_element()
+            .children()
+            .find(|node| node.has_tag_name("toc"))
+            .ok_or_else(|| invalid("missing <toc> element"))?;
+
+        let toc_checksum = match (algorithm, child(toc_node, "checksum")) {
+            (Some(algorithm), Some(node)) => Some((
+                algorithm,
+                number(node, "offset")?.unwrap_or(0),
+                number(node, "size")?.unwrap_or(0),
+            )),
+            (Some(_), None) => return Err(invalid("missing table of contents checksum")),
+            (None, _) => None,
+        };
+
+        let mut entries = Vec::new();
+        let mut ids = HashMap::new();
+        let mut links = Vec::new();
+        read_files(toc_node, "", &mut entries, &mut ids, &mut links)?;
+
+        // Hard links refer to the ID of the file they share contents with
+        for (index, id) in links {
+            let target = ids.get(&id).ok_or_else(|| {
+                invalid(format!(
+                    "hard link '{}' refers to unknown file {}",
+                    entries[index].path, id
+                ))
+            })?;
+            entries[index].kind = Kind::Hardlink(entries[*target].path.clone());
+        }
+
+        Ok(Self {
+            entries,
+            heap,
+            toc,
+            toc_size,
+            toc_checksum,
+            checksum_name: algorithm.map_or("none", algorithm_name).to_string(),
+        })
+    }
+
+    /// Compares the checksum stored in the heap with that of the compressed
+    /// table of contents.
+    pub(crate) fn check_toc(&self) -> Result<()> {
+        let Some((algorithm, offset, size)) = self.toc_checksum else {
+            return Ok(());
+        };
+        let stored = self.heap_range(offset, size)?;
+        if hash::digest(algorithm, self.toc).bytes != stored {
+            return Err(invalid(format!(
+                "table of contents {} checksum mismatch",
+                algorithm_name(algorithm)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns a reader for the decoded contents of a file.
+    ///
+    /// The checksum of the stored contents is checked up front, and the size
+    /// and checksum of the decoded contents once the reader reaches their
+    /// end. XZ and LZMA windows larger than `max_window_size` are rejected.
+    ///
+    /// XZ and LZMA contents are decoded up front, stopping as soon as they
+    /// outgrow their declared size or `max_size`.
+    pub(crate) fn reader(
+        &self,
+        path: &str,
+        data: &Data,
+        max_window_size: usize,
+        max_size: usize,
+    ) -> Result<Contents<Box<dyn Read + 'a>>> {
+        let stored = self.heap_range(data.offset, data.length)?;
+        if let Some(checksum) = &data.archived_checksum
+            && hash::digest(checksum.algorithm, stored).to_hex() != checksum.hex
+        {
+            return Err(invalid(format!(
+                "archived {} checksum mismatch for '{}'",
+                algorithm_name(checksum.algorithm),
+                path
+            )));
+        }
+
+        let decoder: Box<dyn Read + 'a> = match data.encoding {
+            Encoding::None => Box::new(stored),
+            Encoding::Zlib => Box::new(flate2::read::ZlibDecoder::new(stored)),
+            Encoding::Bzip2 => Box::new(bzip2::read::BzDecoder::new(stored)),
+            Encoding::Lzma => {
+                let format = if stored.starts_with(xz::MAGIC) {
+                    ArchiveFormat::Xz
+                } else {
+                    ArchiveFormat::Lzma
+                };
+                window::check(stored, format, max_window_size)?;
+                let expected_size = usize::try_from(data.size).unwrap_or(usize::MAX);
+                let mut output = LimitedWriter::new(Vec::new(), expected_size.min(max_size));
+                let result =
+                    lzma::decompress(format, &mut &stored[..], &mut output, max_window_size);
+                if output.exceeded.is_some() {
+                    if expected_size > max_size {
+                        return Err(ArchiveError::FileTooLarge {
+                            size: expected_size,
+                            limit: max_size,
+                        });
+                    }
+                    return Err(invalid(format!(
+                        "XAR file '{}' holds more than {} bytes",
+                        path, data.size
+                    )));
+                }
+                result?;
+                Box::new(Cursor::new(output.inner))
+            }
+        };
+
+        Ok(Contents {
+            inner: decoder,
+            path: path.to_string(),
+            expected_size: data.size,
+            size: 0,
+            checksum: data
+                .extracted_checksum
+                .clone()
+                .map(|checksum| (Hasher::new(&[checksum.algorithm]), checksum)),
+        })
+    }
+
+    pub(crate) fn info(&self) -> XarInfo {
+        XarInfo {
+            checksum_algorithm: self.checksum_name.clone(),
+            toc_compressed_size: self.toc.len() as u64,
+            toc_size: self.toc_size,
+            entry_count: self.entries.len(),
+        }
+    }
+
+    fn heap_range(&self, offset: u64, length: u64) -> Result<&'a [u8]> {
+        usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(length).ok())
+            .and_then(|(offset, length)| self.heap.get(offset..offset.checked_add(length)?))
+            .ok_or_else(|| invalid("heap data extends past the end"))
+    }
+}
+
+/// Reads the decoded contents of a file, checking their size and checksum
+/// at the end.
+pub(crate) struct Contents<R> {
+    inner: R,
+    path: String,
+    expected_size: u64,
+    size: u64,
+    checksum: Option<(Hasher, Checksum)>,
+}
+
+impl<R: Read> Contents<R> {
+    fn finish(&mut self) -> io::Result<()> {
+        if self.size != self.expected_size {
+            return Err(invalid_data(format!(
+                "XAR file '{}' holds {} bytes instead of {}",
+                self.path, self.size, self.expected_size
+            )));
+        }
+        if let Some((hasher, checksum)) = self.checksum.take()
+            && hasher.finish()[0].to_hex() != checksum.hex
+        {
+            return Err(invalid_data(format!(
+                "XAR extracted {} checksum mismatch for '{}'",
+                algorithm_name(checksum.algorithm),
+                self.path
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Contents<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            self.finish()?;
+            return Ok(0);
+        }
+
+        self.size += read as u64;
+        if self.size > self.expected_size {
+            return Err(invalid_data(format!(
+                "XAR file '{}' holds more than {} bytes",
+                self.path, self.expected_size
+            )));
+        }
+        if let Some((hasher, _)) = &mut self.checksum {
+            hasher.update(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+/// Returns a reader for the cpio archive in the `Payload` or `Scripts` of a
+/// component package, decompressing it with gzip, bzip2 or pbzx.
+///
+/// pbzx streams are decompressed up front, failing once they exceed
+/// `max_size`.
+pub(crate) fn package_reader<'b>(
+    data: &'b [u8],
+    max_window_size: usize,
+    max_size: usize,
+) -> Result<Box<dyn Read + 'b>> {
+    Ok(if data.starts_with(&[0x1F, 0x8B]) {
+        Box::new(flate2::read::MultiGzDecoder::new(data))
+    } else if data.starts_with(b"BZh") {
+        Box::new(bzip2::read::MultiBzDecoder::new(data))
+    } else if data.starts_with(PBZX_MAGIC) {
+        Box::new(Cursor::new(decode_pbzx(data, max_window_size, max_size)?))
+    } else {
+        Box::new(data)
+    })
+}
+
+/// Decodes a pbzx stream: a header with flags, followed by chunks that each
+/// start with flags and their length, and are XZ streams or stored as is.
+fn decode_pbzx(data: &[u8], max_window_size: usize, max_size: usize) -> Result<Vec<u8>> {
+    let truncated = || invalid("truncated pbzx stream");
+    if data.len() < 12 {
+        return Err(truncated());
+    }
+    let mut flags = u64_at(data, 4);
+    let mut position = 12usize;
+    let mut output = LimitedWriter::new(Vec::new(), max_size);
+
+    while flags & PBZX_MORE_CHUNKS != 0 {
+        let header = data.get(position..position + 16).ok_or_else(truncated)?;
+        flags = u64_at(header, 0);
+        let length = usize::try_from(u64_at(header, 8)).map_err(|_| truncated())?;
+        position += 16;
+        let chunk = position
+            .checked_add(length)
+            .and_then(|end| data.get(position..end))
+            .ok_or_else(truncated)?;
+        position += length;
+
+        let result = if chunk.starts_with(xz::MAGIC) {
+            window::check(chunk, ArchiveFormat::Xz, max_window_size)?;
+            lzma::decompress(
+                ArchiveFormat::Xz,
+                &mut &chunk[..],
+                &mut output,
+                max_window_size,
+            )
+        } else {
+            output.write_all(chunk).map_err(ArchiveError::from)
+        };
+        if let Some(size) = output.exceeded {
+            return Err(ArchiveError::TotalSizeTooLarge {
+                size,
+                limit: max_size,
+            });
+        }
+        result?;
+    }
+
+    Ok(output.inner)
+}
+
+/// Whether the table of contents describes an installer package, i.e. has a
+/// `Distribution` or `PackageInfo` file, or a component package `Payload`.
+pub(crate) fn is_package(data: &[u8]) -> bool {
+    Archive::parse(data).is_ok_and(|archive| {
+        archive.entries.iter().any(|entry| {
+            entry.path == "Distribution"
+                || entry.path.rsplit('/').next() == Some("PackageInfo")
+                || entry.is_package_archive()
+        })
+    })
+}
+
+/// Gathers archive-level information, which only needs the table of
+/// contents.
+pub(crate) fn inspect(data: &[u8]) -> Result<XarInfo> {
+    Ok(Archive::parse(data)?.info())
+}
+
+/// Collects the `<file>` children of `parent` and their descendants, in
+/// document order.
+///
+/// The IDs of files are recorded in `ids`, and hard links that refer to
+/// another file's ID in `links`, to be resolved once all files are known.
+fn read_files(
+    parent: roxmltree::Node<'_, '_>,
+    directory: &str,
+    entries: &mut Vec<Entry>,
+    ids: &mut HashMap<String, usize>,
+    links: &mut Vec<(usize, String)>,
+) -> Result<()> {
+    for node in parent.children().filter(|node| node.has_tag_name("file")) {
+        let name = child(node, "name")
+            .and_then(|name| name.text())
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .ok_or_else(|| invalid("file without a valid name"))?;
+        let path = if directory.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", directory, name)
+        };
+
+        let type_node = child(node, "type");
+        let kind = match type_node.and_then(|node| node.text()).unwrap_or("file") {
+            "directory" => Kind::Directory,
+            "symlink" => Kind::Symlink(
+                child(node, "link")
+                    .and_then(|link| link.text())
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            _ => Kind::File,
+        };
+        if let Some(link) = type_node
+            .filter(|node| node.text() == Some("hardlink"))
+            .and_then(|node| node.attribute("link"))
+            .filter(|&link| link != "original")
+        {
+            links.push((entries.len(), link.to_string()));
+        }
+
+        let data = match child(node, "data") {
+            Some(data) if kind == Kind::File => Some(read_data(data, &path)?),
+            _ => None,
+        };
+        let mode = child(node, "mode")
+            .and_then(|mode| mode.text())
+            .map(|mode| {
+                u32::from_str_radix(mode.trim(), 8)
+                    .map_err(|_| invalid(format!("invalid mode of '{}'", path)))
+            })
+            .transpose()?;
+        let mtime = child(node, "mtime")
+            .and_then(|mtime| mtime.text())
+            .and_then(parse_time);
+
+        if let Some(id) = node.attribute("id") {
+            ids.insert(id.to_string(), entries.len());
+        }
+        entries.push(Entry {
+            path: path.clone(),
+            kind,
+            data,
+            mode: mode.map(|mode| mode & 0o7777),
+            mtime,
+        });
+        read_files(node, &path, entries, ids, links)?;
+    }
+    Ok(())
+}
+
+/// Returns how deeply the elements of an XML document are nested, without
+/// checking that it is well-formed.
+///
+/// Comments, CDATA sections, processing instructions and declarations are
+/// skipped, as are quoted attribute values, so that they can't hide or fake
+/// tags.
+fn element_depth(xml: &str) -> usize {
+    let xml = xml.as_bytes();
+    let skip_past = |from: usize, end: &[u8]| {
+        xml[from..]
+            .windows(end.len())
+            .position(|window| window == end)
+            .map_or(xml.len(), |found| from + found + end.len())
+    };
+
+    let (mut depth, mut max_depth) = (0usize, 0);
+    let mut position = 0;
+    while let Some(found) = xml[position..].iter().position(|&byte| byte == b'<') {
+        let start = position + found;
+        let rest = &xml[start..];
+        position = if rest.starts_with(b"<!--") {
+            skip_past(start, b"-->")
+        } else if rest.starts_with(b"<![CDATA[") {
+            skip_past(start, b"]]>")
+        } else if rest.starts_with(b"<?") {
+            skip_past(start, b"?>")
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") {
+            depth = depth.saturating_sub(usize::from(rest.starts_with(b"</")));
+            skip_past(start, b">")
+        } else {
+            // A start tag, which ends at the first `>` outside of quotes
+            let mut quote = None;
+            let mut end = start + 1;
+            while let Some(&byte) = xml.get(end) {
+                match (quote, byte) {
+                    (None, b'>') => break,
+                    (None, b'"' | b'\'') => quote = Some(byte),
+                    (Some(open), _) if byte == open => quote = None,
+                    _ => {}
+                }
+                end += 1;
+            }
+            if xml.get(end.wrapping_sub(1)) != Some(&b'/') {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            end + 1
+        };
+        if position >= xml.len() {
+            break;
+        }
+    }
+    max_depth
+}
+
+fn read_data(node: roxmltree::Node<'_, '_>, path: &str) -> Result<Data> {
+    let missing = || invalid(format!("incomplete data of '{}'", path));
+    let encoding = match child(node, "encoding").and_then(|node| node.attribute("style")) {
+        Some(style) => Encoding::from_style(style)?,
+        None => Encoding::None,
+    };
+    let archived_checksum = child(node, "archived-checksum")
+        .map(Checksum::parse)
+        .transpose()?
+        .flatten();
+    let extracted_checksum = child(node, "extracted-checksum")
+        .or_else(|| child(node, "unarchived-checksum"))
+        .map(Checksum::parse)
+        .transpose()?
+        .flatten();
+
+    Ok(Data {
+        offset: number(node, "offset")?.ok_or_else(missing)?,
+        length: number(node, "length")?.ok_or_else(missing)?,
+        size: number(node, "size")?.ok_or_else(missing)?,
+        encoding,
+        archived_checksum,
+        extracted_checksum,
+    })
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Reads the decimal number in the child element `name`, if present.
+fn number(node: roxmltree::Node<'_, '_>, name: &str) -> Result<Option<u64>> {
+    child(node, name)
+        .map(|child| {
+            let text = child.text().unwrap_or_default().trim();
+            text.parse()
+                .map_err(|_| invalid(format!("invalid <{}> value '{}'", name, text)))
+        })
+        .transpose()
+}
+
+/// Parses a UTC time such as `2023-04-01T12:00:00Z`, ignoring fractional
+/// seconds.
+fn parse_time(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let field = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let days = days_from_civil(field(0..4)?, field(5..7)?, field(8..10)?);
+    let seconds = days * 86400 + field(11..13)? * 3600 + field(14..16)? * 60 + field(17..19)?;
+    u64::try_from(seconds).ok()
+}
+
+fn u16_at(data: &[u8], position: usize) -> u16 {
+    u16::from_be_bytes([data[position], data[position + 1]])
+}
+
+fn u32_at(data: &[u8], position: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[position..position + 4]);
+    u32::from_be_bytes(bytes)
+}
+
+fn u64_at(data: &[u8], position: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[position..position + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+fn invalid(message: impl std::fmt::Display) -> ArchiveError {
+    ArchiveError::InvalidArchive(format!("XAR archive: {}", message))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
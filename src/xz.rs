@@ -0,0 +1,243 @@
+//! XZ container parsing, as described in the XZ file format specification.
+//!
+//! Only the container is parsed here: streams, their blocks and indexes. The
+//! compressed data itself is left to `lzma-rs`.
+
+pub(crate) const MAGIC: &[u8] = b"\xFD7zXZ\x00";
+pub(crate) const STREAM_HEADER_SIZE: usize = 12;
+pub(crate) const FOOTER_MAGIC: &[u8] = b"YZ";
+const FILTER_LZMA2: u64 = 0x21;
+
+/// An XZ stream located through its footer and index.
+pub(crate) struct Stream {
+    /// Offset of the stream header.
+    pub(crate) start: usize,
+    /// The blocks of the stream, in order.
+    pub(crate) blocks: Vec<Block>,
+}
+
+/// A block of an XZ stream, as recorded in the stream's index.
+// The sizes are only needed to split streams for parallel decoding
+#[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+pub(crate) struct Block {
+    /// Offset of the block header.
+    pub(crate) offset: usize,
+    /// Size of the block without its padding.
+    pub(crate) unpadded_size: u64,
+    /// Size of the block's data once decompressed.
+    pub(crate) uncompressed_size: u64,
+    /// Size of the block including its padding.
+    pub(crate) size: usize,
+}
+
+/// Locates the streams in `data` and their blocks.
+///
+/// Block sizes are only known from the index at the end of each stream, so
+/// the streams are found from the end of the data. The walk stops at the
+/// first stream that can't be parsed, so the streams cover all of `data` only
+/// if the first one starts at offset zero.
+pub(crate) fn streams(data: &[u8]) -> Vec<Stream> {
+    let mut streams = Vec::new();
+    let mut end = data.len();
+    while let Some(stream) = stream_before(data, end) {
+        end = stream.start;
+        streams.push(stream);
+    }
+    streams.reverse();
+    streams
+}
+
+/// Locates the stream that ends at `end`, skipping stream padding.
+fn stream_before(data: &[u8], mut end: usize) -> Option<Stream> {
+    while end >= 4 && data[end - 4..end] == [0; 4] {
+        end -= 4;
+    }
+    let footer = data.get(end.checked_sub(STREAM_HEADER_SIZE)?..end)?;
+    if &footer[10..] != FOOTER_MAGIC {
+        return None;
+    }
+    let backward_size = (u32::from_le_bytes(footer[4..8].try_into().ok()?) as usize + 1) * 4;
+    let index_start = (end - STREAM_HEADER_SIZE).checked_sub(backward_size)?;
+
+    let mut index = data.get(index_start..end - STREAM_HEADER_SIZE)?;
+    if read_u8(&mut index)? != 0 {
+        return None;
+    }
+    let count = read_varint(&mut index)?;
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let unpadded_size = read_varint(&mut index)?;
+        let uncompressed_size = read_varint(&mut index)?;
+        let size = usize::try_from(unpadded_size)
+            .ok()?
+            .checked_next_multiple_of(4)?;
+        records.push((unpadded_size, uncompressed_size, size));
+    }
+
+    let blocks_size = records
+        .iter()
+        .try_fold(0usize, |total, &(_, _, size)| total.checked_add(size))?;
+    let start = index_start
+        .checked_sub(blocks_size)?
+        .checked_sub(STREAM_HEADER_SIZE)?;
+    if !data[start..].starts_with(MAGIC) {
+        return None;
+    }
+
+    let mut offset = start + STREAM_HEADER_SIZE;
+    let blocks = records
+        .into_iter()
+        .map(|(unpadded_size, uncompressed_size, size)| {
+            let block = Block {
+                offset,
+                unpadded_size,
+                uncompressed_size,
+                size,
+            };
+            offset += size;
+            block
+        })
+        .collect();
+    Some(Stream { start, blocks })
+}
+
+/// Reads the LZMA2 dictionary size from the block header at the start of
+/// `data`, as described in sections 3.1 and 5.3.1 of the specification.
+pub(crate) fn dictionary_size(data: &[u8]) -> Option<u64> {
+    let header_size = (*data.first()? as usize + 1) * 4;
+    // A zero size byte marks the index rather than a block
+    if header_size == 4 {
+        return None;
+    }
+    let mut header = data.get(1..header_size)?;
+    let flags = read_u8(&mut header)?;
+    if flags & 0x40 != 0 {
+        read_varint(&mut header)?;
+    }
+    if flags & 0x80 != 0 {
+        read_varint(&mut header)?;
+    }
+
+    let mut dictionary_size = None;
+    for _ in 0..=(flags & 0x03) {
+        let id = read_varint(&mut header)?;
+        let properties_size = usize::try_from(read_varint(&mut header)?).ok()?;
+        let properties = header.get(..properties_size)?;
+        header = &header[properties_size..];
+        if id == FILTER_LZMA2 && properties_size == 1 {
+            let bits = u32::from(properties[0] & 0x3F);
+            dictionary_size = match bits {
+                0..40 => Some(u64::from(2 | (bits & 1)) << (bits / 2 + 11)),
+                40 => Some(u64::from(u32::MAX)),
+                _ => None,
+            };
+        }
+    }
+    dictionary_size
+}
+
+/// Returns the leading LZMA2 chunks of the first block in the stream at the
+/// start of `data`, enough of them to decompress to at least `size` bytes.
+///
+/// Each chunk starts with a control byte: 0 ends the block, 1 and 2 mark a
+/// stored chunk followed by its size, and the other values with the high bit
+/// set mark an LZMA chunk followed by its unpacked and packed sizes and, when
+/// the properties are reset, a properties byte. Only blocks with LZMA2 as
+/// their single filter are read.
+pub(crate) fn head_chunks(data: &[u8], size: u64) -> Option<&[u8]> {
+    let header = data.get(STREAM_HEADER_SIZE..)?;
+    if !data.starts_with(MAGIC) || header.get(1)? & 0x03 != 0 {
+        return None;
+    }
+    dictionary_size(header)?;
+
+    let start = STREAM_HEADER_SIZE + (usize::from(header[0]) + 1) * 4;
+    let mut end = start;
+    let mut unpacked = 0u64;
+    while unpacked < size {
+        let chunk = data.get(end..)?;
+        let control = *chunk.first()?;
+        let field = |at: usize| {
+            chunk
+                .get(at..at + 2)
+                .map(|bytes| u64::from(u16::from_be_bytes([bytes[0], bytes[1]])) + 1)
+        };
+        let (header_size, unpacked_size, packed_size) = match control {
+            0 => {
+                end += 1;
+                break;
+            }
+            1 | 2 => (3, field(1)?, field(1)?),
+            0x80.. => {
+                let unpacked_size = (u64::from(control & 0x1F) << 16) + field(1)?;
+                (5 + usize::from(control >= 0xC0), unpacked_size, field(3)?)
+            }
+            _ => return None,
+        };
+        end = end
+            .checked_add(header_size)?
+            .checked_add(usize::try_from(packed_size).ok()?)?;
+        unpacked += unpacked_size;
+    }
+    data.get(start..end)
+}
+
+/// Wraps a single block of `stream` into a stream of its own, with the same
+/// header and a matching index, so that it can be decoded independently.
+#[cfg(feature = "rayon")]
+pub(crate) fn single_block_stream(data: &[u8], stream: &Stream, block: &Block) -> Vec<u8> {
+    let header = &data[stream.start..stream.start + STREAM_HEADER_SIZE];
+    let mut output = header.to_vec();
+    output.extend_from_slice(&data[block.offset..block.offset + block.size]);
+
+    let mut index = vec![0, 1];
+    write_varint(&mut index, block.unpadded_size);
+    write_varint(&mut index, block.uncompressed_size);
+    index.resize(index.len().next_multiple_of(4), 0);
+    index.extend_from_slice(&crc32(&index).to_le_bytes());
+    output.extend_from_slice(&index);
+
+    // The footer repeats the stream flags from the header
+    let mut fields = ((index.len() / 4 - 1) as u32).to_le_bytes().to_vec();
+    fields.extend_from_slice(&header[6..8]);
+    output.extend_from_slice(&crc32(&fields).to_le_bytes());
+    output.extend_from_slice(&fields);
+    output.extend_from_slice(FOOTER_MAGIC);
+    output
+}
+
+#[cfg(feature = "rayon")]
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+fn read_u8(data: &mut &[u8]) -> Option<u8> {
+    let (&byte, rest) = data.split_first()?;
+    *data = rest;
+    Some(byte)
+}
+
+/// Reads a variable-length integer of up to nine bytes, least significant
+/// seven bits first.
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = read_u8(data)?;
+        value |= u64::from(byte & 0x7F) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(feature = "rayon")]
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
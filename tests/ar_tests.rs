@@ -2,7 +2,7 @@
 
 mod common;
 
-use archive::{ArchiveExtractor, ArchiveFormat};
+use archive::{ArBuilder, ArVariant, ArchiveError, ArchiveExtractor, ArchiveFormat};
 use common::{assert_contains_file, read_test_archive};
 
 #[test]
@@ -17,3 +17,62 @@ fn test_ar() {
     assert!(!files.is_empty(), "Expected non-empty archive");
     assert_contains_file(&files, "hello.txt");
 }
+
+#[test]
+fn test_ar_builder_gnu_long_names() {
+    let mut builder = ArBuilder::new(ArVariant::Gnu);
+    builder.add_file("short.o", b"short".to_vec()).unwrap();
+    builder
+        .add_file("a-very-long-object-file-name.o", b"long".to_vec())
+        .unwrap();
+    let data = builder.finish().expect("Failed to build GNU ar archive");
+
+    assert!(data.starts_with(b"!<arch>\n//"), "Expected GNU name table");
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Ar)
+        .expect("Failed to extract GNU ar archive");
+
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, "short.o");
+    assert_eq!(files[0].data, b"short");
+    assert_eq!(files[1].path, "a-very-long-object-file-name.o");
+    assert_eq!(files[1].data, b"long");
+}
+
+#[test]
+fn test_ar_builder_bsd_long_names() {
+    let mut builder = ArBuilder::new(ArVariant::Bsd);
+    builder
+        .add_file("a-very-long-object-file-name.o", b"odd".to_vec())
+        .unwrap();
+    builder
+        .add_file("name with spaces", b"even".to_vec())
+        .unwrap();
+    let data = builder.finish().expect("Failed to build BSD ar archive");
+
+    assert!(data.starts_with(b"!<arch>\n#1/"), "Expected BSD long name");
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Ar)
+        .expect("Failed to extract BSD ar archive");
+
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, "a-very-long-object-file-name.o");
+    assert_eq!(files[0].data, b"odd");
+    assert_eq!(files[1].path, "name with spaces");
+    assert_eq!(files[1].data, b"even");
+}
+
+#[test]
+fn test_ar_builder_rejects_invalid_names() {
+    let mut builder = ArBuilder::new(ArVariant::Gnu);
+    assert!(matches!(
+        builder.add_file("dir/file.o", Vec::new()),
+        Err(ArchiveError::InvalidPath(_))
+    ));
+    assert!(matches!(
+        builder.add_file("", Vec::new()),
+        Err(ArchiveError::InvalidPath(_))
+    ));
+}
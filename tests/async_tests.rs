@@ -0,0 +1,167 @@
+//! Tests for async extraction with the `tokio` feature
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat};
+use common::{TEST_ARCHIVES_DIR, read_test_archive};
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+async fn open_test_archive(filename: &str) -> tokio::fs::File {
+    tokio::fs::File::open(Path::new(TEST_ARCHIVES_DIR).join(filename))
+        .await
+        .unwrap_or_else(|e| panic!("Failed to open test archive '{}': {}", filename, e))
+}
+
+/// Yields the wrapped data in small pieces, then fails.
+struct FailingReader {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl AsyncRead for FailingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.position == self.data.len() {
+            return Poll::Ready(Err(io::Error::other("connection reset")));
+        }
+        let end = (self.position + 100).min(self.data.len());
+        buf.put_slice(&self.data[self.position..end]);
+        self.position = end;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_extract_async_matches_extract() {
+    let fixtures = [
+        ("basic.zip", ArchiveFormat::Zip),
+        ("archive.tar", ArchiveFormat::Tar),
+        ("archive.tar.gz", ArchiveFormat::TarGz),
+        ("archive.tar.bz2", ArchiveFormat::TarBz2),
+        ("archive.tar.xz", ArchiveFormat::TarXz),
+        ("archive.tar.zst", ArchiveFormat::TarZst),
+        ("archive.7z", ArchiveFormat::SevenZ),
+        ("archive.ar", ArchiveFormat::Ar),
+        ("hello.txt.gz", ArchiveFormat::Gz),
+        ("hello.txt.xz", ArchiveFormat::Xz),
+        ("hello.txt.lz4", ArchiveFormat::Lz4),
+    ];
+    let extractor = ArchiveExtractor::new();
+
+    for (name, format) in fixtures {
+        let expected = extractor.extract(&read_test_archive(name), format).unwrap();
+
+        let files = extractor
+            .extract_async(open_test_archive(name).await, format)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to extract {}: {}", name, e));
+
+        assert_eq!(files.len(), expected.len(), "{}", name);
+        for (file, expected) in files.iter().zip(&expected) {
+            assert_eq!(file.path, expected.path, "{}", name);
+            assert_eq!(file.data, expected.data, "{}: {}", name, file.path);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_extract_stream_yields_entries_in_order() {
+    let expected = ArchiveExtractor::new()
+        .extract(&read_test_archive("archive.tar.gz"), ArchiveFormat::TarGz)
+        .unwrap();
+
+    let mut entries = ArchiveExtractor::new().extract_stream(
+        open_test_archive("archive.tar.gz").await,
+        ArchiveFormat::TarGz,
+    );
+    let mut paths = Vec::new();
+    while let Some(entry) = entries.next().await {
+        paths.push(entry.unwrap().path);
+    }
+
+    let expected: Vec<_> = expected.into_iter().map(|file| file.path).collect();
+    assert_eq!(paths, expected);
+}
+
+#[tokio::test]
+async fn test_extract_stream_enforces_limits() {
+    let extractor = ArchiveExtractor::new().with_max_file_size(1024);
+    for (name, format) in [
+        ("basic.zip", ArchiveFormat::Zip),
+        ("archive.tar.gz", ArchiveFormat::TarGz),
+    ] {
+        let mut entries = extractor.extract_stream(open_test_archive(name).await, format);
+
+        let mut error = None;
+        while let Some(entry) = entries.next().await {
+            if let Err(e) = entry {
+                error = Some(e);
+            }
+        }
+        assert!(
+            matches!(error, Some(ArchiveError::FileTooLarge { .. })),
+            "{}",
+            name
+        );
+    }
+
+    let extractor = ArchiveExtractor::new().with_max_window_size(1024 * 1024);
+    let result = extractor
+        .extract_async(
+            open_test_archive("archive.tar.xz").await,
+            ArchiveFormat::TarXz,
+        )
+        .await;
+    assert!(matches!(result, Err(ArchiveError::WindowTooLarge { .. })));
+}
+
+#[tokio::test]
+async fn test_extract_async_bounds_buffered_input() {
+    // An endless input is only read up to the total size limit
+    for format in [
+        ArchiveFormat::Zip,
+        ArchiveFormat::SevenZ,
+        ArchiveFormat::Iso,
+    ] {
+        let result = ArchiveExtractor::new()
+            .with_max_total_size(64 * 1024)
+            .extract_async(tokio::io::repeat(0), format)
+            .await;
+        assert!(
+            matches!(
+                result,
+                Err(ArchiveError::TotalSizeTooLarge { limit: 65536, .. })
+            ),
+            "{:?}",
+            format
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_extract_async_reports_read_errors() {
+    // The connection drops halfway through the archive
+    let data = read_test_archive("archive.tar.gz");
+    let reader = FailingReader {
+        data: data[..data.len() / 2].to_vec(),
+        position: 0,
+    };
+
+    let result = ArchiveExtractor::new()
+        .extract_async(reader, ArchiveFormat::TarGz)
+        .await;
+
+    assert!(
+        matches!(&result, Err(ArchiveError::Io(e)) if e.to_string() == "connection reset"),
+        "{:?}",
+        result.map(|files| files.len())
+    );
+}
@@ -0,0 +1,132 @@
+//! Tests for Brotli extraction
+#![cfg(feature = "brotli")]
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat};
+use common::find;
+use std::io::Write;
+
+/// Compresses `data` with a window of `2^window_bits - 16` bytes.
+fn brotli(data: &[u8], window_bits: u32) -> Vec<u8> {
+    let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 9, window_bits);
+    writer.write_all(data).unwrap();
+    writer.into_inner()
+}
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_brotli_single_file() {
+    let data = brotli(b"Hello, World!\n", 22);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Br)
+        .expect("Failed to extract Brotli file");
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "data");
+    assert_eq!(files[0].data, b"Hello, World!\n");
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Br)
+        .expect("Failed to verify Brotli file");
+    assert!(report.is_ok());
+}
+
+#[test]
+fn test_tar_br() {
+    let archive = tar(&[("index.html", b"<h1>hi</h1>"), ("app.js", b"main();")]);
+    let data = brotli(&archive, 22);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::TarBr)
+        .expect("Failed to extract tar.br archive");
+    assert_eq!(files.len(), 2);
+    assert_eq!(find(&files, "index.html").data, b"<h1>hi</h1>");
+    assert_eq!(find(&files, "app.js").data, b"main();");
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::TarBr)
+        .expect("Failed to verify tar.br archive");
+    assert!(report.is_ok());
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::TarBr)
+        .expect("Failed to inspect tar.br archive");
+    assert!(info.tar.is_some());
+}
+
+#[test]
+fn test_brotli_corrupt() {
+    let mut data = brotli(&[b'a'; 1000], 22);
+    data.truncate(data.len() / 2);
+    data.extend_from_slice(&[0xFF; 8]);
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Br);
+    assert!(matches!(result, Err(ArchiveError::Io(_))));
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Br)
+        .expect("Failed to verify Brotli file");
+    assert_eq!(report.failures().count(), 1);
+}
+
+#[test]
+fn test_brotli_window_limit() {
+    let data = brotli(b"Hello, World!\n", 24);
+    for format in [ArchiveFormat::Br, ArchiveFormat::TarBr] {
+        let result = ArchiveExtractor::new()
+            .with_max_window_size(1024 * 1024)
+            .extract(&data, format);
+        assert!(matches!(
+            result,
+            Err(ArchiveError::WindowTooLarge { size: 16777200, .. })
+        ));
+    }
+
+    let result = ArchiveExtractor::new()
+        .with_max_window_size(16 * 1024 * 1024)
+        .extract(&data, ArchiveFormat::Br);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_brotli_size_limits() {
+    let data = brotli(&[0u8; 1000], 22);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Br);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    // Decoding stops as soon as the limit is exceeded
+    let data = brotli(&vec![0u8; 16 * 1024 * 1024], 22);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(1024)
+        .extract(&data, ArchiveFormat::Br);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::FileTooLarge { size: 1025, .. })
+    ));
+
+    let data = brotli(
+        &tar(&[("a.bin", &[0u8; 1000]), ("b.bin", &[0u8; 1000])]),
+        22,
+    );
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .extract(&data, ArchiveFormat::TarBr);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
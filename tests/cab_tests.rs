@@ -0,0 +1,345 @@
+//! Tests for Microsoft Cabinet extraction
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat};
+use cab::{CabinetBuilder, CompressionType};
+use common::find;
+use std::io::{Cursor, Write};
+use time::{Date, Month, PrimitiveDateTime};
+
+/// 2024-03-01 12:30:10, as MS-DOS date and time and in seconds since the epoch.
+const DOS_DATE: u16 = (44 << 9) | (3 << 5) | 1;
+const DOS_TIME: u16 = (12 << 11) | (30 << 5) | 5;
+const MTIME: u64 = 1_709_296_210;
+
+const README: &[u8] = b"Driver package for the example device.\r\n";
+
+fn datetime() -> PrimitiveDateTime {
+    Date::from_calendar_date(2024, Month::March, 1)
+        .unwrap()
+        .with_hms(12, 30, 10)
+        .unwrap()
+}
+
+/// Text spanning several data blocks, with matches reaching back into
+/// earlier blocks.
+fn large_text() -> Vec<u8> {
+    (0..20_000u32)
+        .flat_map(|i| format!("line {} of the setup log\n", i % 977).into_bytes())
+        .collect()
+}
+
+/// The `(name, contents)` of the files of a folder.
+type Files<'a> = &'a [(&'a str, &'a [u8])];
+
+/// Writes a cabinet with a folder for each `(compression, files)` pair.
+/// Files ending in `.sys` are marked read-only.
+fn cabinet(folders: &[(CompressionType, Files<'_>)]) -> Vec<u8> {
+    let mut builder = CabinetBuilder::new();
+    for (compression, files) in folders {
+        let folder = builder.add_folder(*compression);
+        for (name, _) in *files {
+            let file = folder.add_file(*name);
+            file.set_datetime(datetime());
+            file.set_is_read_only(name.ends_with(".sys"));
+        }
+    }
+
+    let mut contents = folders
+        .iter()
+        .flat_map(|(_, files)| files.iter().map(|(_, data)| *data));
+    let mut writer = builder.build(Cursor::new(Vec::new())).unwrap();
+    while let Some(mut file) = writer.next_file().unwrap() {
+        file.write_all(contents.next().unwrap()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Writes a cabinet with a single folder of already compressed data blocks,
+/// for compression methods `cab` can't write. Files are `(name, size,
+/// offset)` triples.
+fn raw_cabinet(compression: u16, blocks: &[(Vec<u8>, u16)], files: &[(&str, u32, u32)]) -> Vec<u8> {
+    let files_offset = 36 + 8;
+    let mut file_table = Vec::new();
+    for (name, size, offset) in files {
+        file_table.extend_from_slice(&size.to_le_bytes());
+        file_table.extend_from_slice(&offset.to_le_bytes());
+        file_table.extend_from_slice(&0u16.to_le_bytes());
+        file_table.extend_from_slice(&DOS_DATE.to_le_bytes());
+        file_table.extend_from_slice(&DOS_TIME.to_le_bytes());
+        file_table.extend_from_slice(&0x20u16.to_le_bytes());
+        file_table.extend_from_slice(name.as_bytes());
+        file_table.push(0);
+    }
+    let data_offset = files_offset + file_table.len();
+
+    let mut data = Vec::new();
+    for (block, size) in blocks {
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        data.extend_from_slice(&size.to_le_bytes());
+        data.extend_from_slice(block);
+    }
+
+    let mut cabinet = b"MSCF\0\0\0\0".to_vec();
+    cabinet.extend_from_slice(&((data_offset + data.len()) as u32).to_le_bytes());
+    cabinet.extend_from_slice(&0u32.to_le_bytes());
+    cabinet.extend_from_slice(&(files_offset as u32).to_le_bytes());
+    cabinet.extend_from_slice(&0u32.to_le_bytes());
+    cabinet.extend_from_slice(&[3, 1]);
+    cabinet.extend_from_slice(&1u16.to_le_bytes());
+    cabinet.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    cabinet.extend_from_slice(&0u16.to_le_bytes());
+    cabinet.extend_from_slice(&0x1234u16.to_le_bytes());
+    cabinet.extend_from_slice(&0u16.to_le_bytes());
+
+    cabinet.extend_from_slice(&(data_offset as u32).to_le_bytes());
+    cabinet.extend_from_slice(&(blocks.len() as u16).to_le_bytes());
+    cabinet.extend_from_slice(&compression.to_le_bytes());
+
+    cabinet.extend(file_table);
+    cabinet.extend(data);
+    cabinet
+}
+
+/// Encodes `data` as a single uncompressed LZX block, split into data blocks
+/// of 32 KiB of output.
+fn lzx_uncompressed(data: &[u8]) -> Vec<(Vec<u8>, u16)> {
+    // No E8 translation, block type 3 and a 24-bit size, padded to 32 bits
+    let header = (3u32 << 28) | ((data.len() as u32) << 4);
+    let mut first = Vec::new();
+    for word in [(header >> 16) as u16, header as u16] {
+        first.extend_from_slice(&word.to_le_bytes());
+    }
+    // The repeated match offsets R0, R1 and R2
+    for offset in [1u32, 1, 1] {
+        first.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    data.chunks(32 * 1024)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut block = if index == 0 {
+                first.clone()
+            } else {
+                Vec::new()
+            };
+            block.extend_from_slice(chunk);
+            (block, chunk.len() as u16)
+        })
+        .collect()
+}
+
+#[test]
+fn test_cab_mszip() {
+    let large = large_text();
+    let data = cabinet(&[(
+        CompressionType::MsZip,
+        &[
+            ("readme.txt", README),
+            ("drivers\\example.sys", &large),
+            ("empty.inf", b""),
+        ],
+    )]);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Cab);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Cab)
+        .expect("Failed to extract CAB archive");
+
+    let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(names, ["readme.txt", "drivers/example.sys", "empty.inf"]);
+    assert_eq!(find(&files, "readme.txt").data, README);
+    assert!(find(&files, "empty.inf").data.is_empty());
+
+    let driver = find(&files, "drivers/example.sys");
+    assert_eq!(driver.data, large);
+    assert_eq!(driver.metadata.compression_method.as_deref(), Some("MSZIP"));
+    assert_eq!(driver.metadata.mtime, Some(MTIME));
+    // Read-only and archive
+    assert_eq!(driver.metadata.attributes, Some(0x21));
+    assert_eq!(find(&files, "readme.txt").metadata.attributes, Some(0x20));
+}
+
+#[test]
+fn test_cab_multiple_folders() {
+    let large = large_text();
+    let data = cabinet(&[
+        (
+            CompressionType::None,
+            &[("a.txt", b"first"), ("b.txt", &large)],
+        ),
+        (
+            CompressionType::MsZip,
+            &[("c.txt", b"third"), ("d.txt", &large)],
+        ),
+    ]);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Cab)
+        .expect("Failed to extract CAB archive");
+
+    let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(names, ["a.txt", "b.txt", "c.txt", "d.txt"]);
+    assert_eq!(find(&files, "a.txt").data, b"first");
+    assert_eq!(find(&files, "b.txt").data, large);
+    assert_eq!(find(&files, "c.txt").data, b"third");
+    assert_eq!(find(&files, "d.txt").data, large);
+    assert_eq!(
+        find(&files, "a.txt").metadata.compression_method.as_deref(),
+        Some("None")
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Cab)
+        .expect("Failed to verify CAB archive");
+    assert!(report.is_ok());
+    assert_eq!(report.entries.len(), 4);
+}
+
+#[test]
+#[cfg(not(feature = "lzx"))]
+fn test_cab_lzx_requires_feature() {
+    let data = raw_cabinet(3 | (16 << 8), &lzx_uncompressed(README), &[("a", 1, 0)]);
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Cab);
+    assert!(matches!(result, Err(ArchiveError::UnsupportedFormat(msg)) if msg.contains("`lzx`")));
+}
+
+#[test]
+#[cfg(feature = "lzx")]
+fn test_cab_lzx() {
+    let large = large_text();
+    let contents = &large[..40_000];
+    let data = raw_cabinet(
+        3 | (16 << 8),
+        &lzx_uncompressed(contents),
+        &[("setup.exe", 30_000, 0), ("setup.ini", 10_000, 30_000)],
+    );
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Cab)
+        .expect("Failed to extract CAB archive");
+
+    assert_eq!(files.len(), 2);
+    assert_eq!(find(&files, "setup.exe").data, &contents[..30_000]);
+    assert_eq!(find(&files, "setup.ini").data, &contents[30_000..]);
+    assert_eq!(files[0].metadata.compression_method.as_deref(), Some("LZX"));
+    assert_eq!(files[0].metadata.mtime, Some(MTIME));
+
+    // The window of 64 KiB is checked before decoding
+    let result = ArchiveExtractor::new()
+        .with_max_window_size(32 * 1024)
+        .extract(&data, ArchiveFormat::Cab);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::WindowTooLarge {
+            size: 65536,
+            limit: 32768
+        })
+    ));
+}
+
+#[test]
+fn test_cab_inspect() {
+    let data = cabinet(&[
+        (CompressionType::None, &[("a.txt", b"first")]),
+        (CompressionType::MsZip, &[("b.txt", b"second")]),
+    ]);
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Cab)
+        .expect("Failed to inspect CAB archive");
+    let cab = info.cab.expect("Missing CAB information");
+
+    assert_eq!(cab.cabinet_index, 0);
+    assert!(cab.previous_cabinet.is_none());
+    assert!(cab.next_cabinet.is_none());
+    assert_eq!(cab.file_count, 2);
+    let methods: Vec<_> = cab.folders.iter().map(|f| f.compression.as_str()).collect();
+    assert_eq!(methods, ["None", "MSZIP"]);
+
+    let data = raw_cabinet(3 | (21 << 8), &lzx_uncompressed(README), &[("a", 1, 0)]);
+    let cab = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Cab)
+        .unwrap()
+        .cab
+        .unwrap();
+    assert_eq!(cab.set_id, 0x1234);
+    assert_eq!(cab.folders[0].compression, "LZX");
+    assert_eq!(cab.folders[0].window_size, Some(2 * 1024 * 1024));
+    assert_eq!(cab.folders[0].data_blocks, 1);
+}
+
+#[test]
+fn test_cab_checksum_mismatch() {
+    let data = cabinet(&[
+        (CompressionType::None, &[("hello.txt", b"Hello, World!")]),
+        (CompressionType::None, &[("other.txt", b"other")]),
+    ]);
+    let mut corrupt = data.clone();
+    let position = corrupt.windows(5).position(|w| w == b"Hello").unwrap();
+    corrupt[position] = b'J';
+
+    let result = ArchiveExtractor::new().extract(&corrupt, ArchiveFormat::Cab);
+    assert!(
+        matches!(&result, Err(ArchiveError::Io(e)) if e.to_string().contains("checksum")),
+        "Expected a checksum error, got {:?}",
+        result
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&corrupt, ArchiveFormat::Cab)
+        .expect("Failed to verify CAB archive");
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, "hello.txt");
+}
+
+#[test]
+fn test_cab_truncated() {
+    let data = cabinet(&[(CompressionType::MsZip, &[("readme.txt", README)])]);
+
+    let result = ArchiveExtractor::new().extract(&data[..data.len() - 4], ArchiveFormat::Cab);
+    assert!(result.is_err());
+
+    let report = ArchiveExtractor::new()
+        .verify(&data[..data.len() - 4], ArchiveFormat::Cab)
+        .expect("Failed to verify CAB archive");
+    assert!(!report.is_ok());
+
+    let result = ArchiveExtractor::new().extract(&data[..40], ArchiveFormat::Cab);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+}
+
+#[test]
+fn test_cab_quantum_unsupported() {
+    let data = raw_cabinet(2 | (4 << 4) | (21 << 8), &[(vec![0; 8], 8)], &[("a", 8, 0)]);
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Cab);
+    assert!(matches!(result, Err(ArchiveError::UnsupportedFormat(_))));
+
+    // The headers can still be inspected
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Cab)
+        .unwrap();
+    assert_eq!(info.cab.unwrap().folders[0].compression, "Quantum");
+}
+
+#[test]
+fn test_cab_size_limits() {
+    let big = vec![0u8; 1000];
+    let data = cabinet(&[(CompressionType::MsZip, &[("a.bin", &big), ("b.bin", &big)])]);
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Cab);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .extract(&data, ArchiveFormat::Cab);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
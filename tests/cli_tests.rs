@@ -0,0 +1,266 @@
+//! Tests for the `archive` command-line tool
+#![cfg(feature = "cli")]
+
+mod common;
+
+use common::{TEST_ARCHIVES_DIR, read_test_archive};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn archive(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_archive"))
+        .args(args)
+        .output()
+        .expect("Failed to run archive")
+}
+
+fn archive_json(args: &[&str]) -> Value {
+    let output = archive(&[&["--json"], args].concat());
+    assert!(
+        output.status.success(),
+        "archive {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("Invalid JSON output")
+}
+
+fn fixture(name: &str) -> String {
+    Path::new(TEST_ARCHIVES_DIR)
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn paths(entries: &Value) -> Vec<&str> {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["path"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_detect() {
+    let output = archive(&["detect", &fixture("archive.tar.xz"), &fixture("basic.zip")]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("archive.tar.xz: TAR.XZ"));
+    assert!(stdout.contains("basic.zip: ZIP"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let text = dir.path().join("notes.txt");
+    fs::write(&text, "not an archive").unwrap();
+    let output = archive(&["detect", text.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_list_json_with_filter_and_hash() {
+    let listing = archive_json(&[
+        "list",
+        &fixture("archive.tar.gz"),
+        "--include",
+        "**/hello.txt",
+        "--hash",
+        "sha256",
+    ]);
+
+    assert_eq!(listing["format"], "TAR.GZ");
+    assert_eq!(paths(&listing["entries"]), ["test-data/hello.txt"]);
+    let hello = &listing["entries"][0];
+    assert_eq!(hello["size"], 14);
+    assert_eq!(
+        hello["digests"]["SHA-256"],
+        "c98c24b677eff44860afea6f493bbaec5bb1c4cbb209c6fc2bbb47f66ff2ad31"
+    );
+}
+
+#[test]
+fn test_list_escapes_control_characters() {
+    let name = "odd\tname\u{1}\n\"quoted\".txt";
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(4);
+    header.set_mode(0o644);
+    // 2000-02-29 12:34:56 UTC
+    header.set_mtime(951_827_696);
+    builder
+        .append_data(&mut header, name, &b"data"[..])
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let tarball = dir.path().join("odd.tar");
+    fs::write(&tarball, builder.into_inner().unwrap()).unwrap();
+
+    let output = archive(&["--json", "list", tarball.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""odd\tname\u0001\n\"quoted\".txt""#));
+    let listing: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(paths(&listing["entries"]), [name]);
+    assert_eq!(listing["entries"][0]["mtime"], 951_827_696);
+
+    let output = archive(&["list", tarball.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2000-02-29 12:34"));
+}
+
+#[test]
+fn test_list_respects_size_limits() {
+    let output = archive(&["list", &fixture("archive.tar.gz"), "--max-file-size", "1K"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File too large"));
+}
+
+#[test]
+fn test_extract_to_output_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let output_dir = dir.path().join("out");
+
+    let result = archive_json(&[
+        "extract",
+        &fixture("basic.zip"),
+        "-o",
+        output_dir.to_str().unwrap(),
+        "--exclude",
+        "*.bin",
+    ]);
+
+    assert_eq!(result["format"], "ZIP");
+    assert_eq!(
+        fs::read(output_dir.join("test-data/hello.txt")).unwrap(),
+        b"Hello, World!\n"
+    );
+    assert!(!output_dir.join("test-data/binary.bin").exists());
+
+    // Existing files are kept unless asked otherwise
+    let output = archive(&[
+        "extract",
+        &fixture("basic.zip"),
+        "-o",
+        output_dir.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    let output = archive(&[
+        "extract",
+        &fixture("basic.zip"),
+        "-o",
+        output_dir.to_str().unwrap(),
+        "--overwrite",
+        "replace",
+    ]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_test_reports_corruption() {
+    let report = archive_json(&["test", &fixture("archive.7z")]);
+    assert_eq!(report["ok"], true);
+
+    let dir = tempfile::tempdir().unwrap();
+    let corrupt = dir.path().join("corrupt.gz");
+    let mut data = read_test_archive("hello.txt.gz");
+    let crc_offset = data.len() - 8;
+    data[crc_offset] ^= 0xFF;
+    fs::write(&corrupt, data).unwrap();
+
+    let output = archive(&["test", corrupt.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("FAILED"));
+}
+
+#[test]
+fn test_create_and_convert() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("site");
+    fs::create_dir_all(input.join("css")).unwrap();
+    fs::write(input.join("index.html"), "<h1>hi</h1>").unwrap();
+    fs::write(input.join("css/style.css"), "h1 {}").unwrap();
+
+    let tarball = dir.path().join("site.tar.zst");
+    let created = archive_json(&["create", tarball.to_str().unwrap(), input.to_str().unwrap()]);
+    assert_eq!(created["format"], "TAR.ZST");
+    assert_eq!(created["entries"], 4);
+
+    let zip = dir.path().join("site.zip");
+    let converted = archive_json(&[
+        "convert",
+        tarball.to_str().unwrap(),
+        zip.to_str().unwrap(),
+        "--exclude",
+        "*.css",
+    ]);
+    assert_eq!(converted["from"], "TAR.ZST");
+    assert_eq!(converted["to"], "ZIP");
+
+    let listing = archive_json(&["list", zip.to_str().unwrap()]);
+    assert_eq!(
+        paths(&listing["entries"]),
+        ["site/", "site/css/", "site/index.html"]
+    );
+}
+
+#[test]
+fn test_create_and_convert_lzip_and_lzma() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("hello-2.12");
+    fs::create_dir_all(&input).unwrap();
+    fs::write(input.join("README"), "GNU hello").unwrap();
+
+    let tarball = dir.path().join("hello-2.12.tar.lz");
+    let created = archive_json(&["create", tarball.to_str().unwrap(), input.to_str().unwrap()]);
+    assert_eq!(created["format"], "TAR.LZ");
+
+    let legacy = dir.path().join("hello-2.12.tar.lzma");
+    let converted = archive_json(&[
+        "convert",
+        tarball.to_str().unwrap(),
+        legacy.to_str().unwrap(),
+    ]);
+    assert_eq!(converted["from"], "TAR.LZ");
+    assert_eq!(converted["to"], "TAR.LZMA");
+
+    let listing = archive_json(&["list", legacy.to_str().unwrap()]);
+    assert_eq!(
+        paths(&listing["entries"]),
+        ["hello-2.12", "hello-2.12/README"]
+    );
+}
+
+#[test]
+fn test_create_warns_about_uncompressed_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("notes.txt");
+    fs::write(&input, "notes").unwrap();
+
+    for (name, warns) in [
+        ("notes.tar.xz", true),
+        ("notes.xz", true),
+        ("notes.tar.gz", false),
+    ] {
+        let output = dir.path().join(name);
+        let result = archive(&["create", output.to_str().unwrap(), input.to_str().unwrap()]);
+        assert!(result.status.success());
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert_eq!(
+            stderr.contains("not compressed"),
+            warns,
+            "{}: {}",
+            name,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn test_convert_rejects_unrepresentable_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("out.gz");
+
+    let result = archive(&["convert", &fixture("basic.zip"), output.to_str().unwrap()]);
+
+    assert!(!result.status.success());
+    assert!(!output.exists());
+}
@@ -4,9 +4,11 @@ use archive::ExtractedFile;
 use std::fs;
 use std::path::Path;
 
+#[allow(dead_code)]
 pub const TEST_ARCHIVES_DIR: &str = "test-archives";
 
 /// Helper to read a test archive file
+#[allow(dead_code)]
 pub fn read_test_archive(filename: &str) -> Vec<u8> {
     let path = Path::new(TEST_ARCHIVES_DIR).join(filename);
     fs::read(&path).unwrap_or_else(|e| {
@@ -33,3 +35,12 @@ pub fn assert_contains_file<'a>(
             )
         })
 }
+
+/// Helper to find the extracted entry with exactly the given path
+#[allow(dead_code)]
+pub fn find<'a>(files: &'a [ExtractedFile], path: &str) -> &'a ExtractedFile {
+    files
+        .iter()
+        .find(|file| file.path == path)
+        .unwrap_or_else(|| panic!("Missing entry '{}'", path))
+}
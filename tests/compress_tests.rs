@@ -0,0 +1,276 @@
+//! Tests for Unix compress (`.Z`) extraction
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, HashAlgorithm};
+use common::find;
+use std::collections::HashMap;
+
+const CLEAR: u32 = 256;
+
+/// Writes LZW codes the way `compress` does: least significant bit first, in
+/// groups of eight that are padded whenever the code width changes.
+struct CodeWriter {
+    output: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+    bits: u32,
+    group_position: u32,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u32) {
+        self.bit_buffer |= u64::from(code) << self.bit_count;
+        self.bit_count += self.bits;
+        while self.bit_count >= 8 {
+            self.output.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+        self.group_position = (self.group_position + 1) % 8;
+    }
+
+    fn set_bits(&mut self, bits: u32) {
+        while self.group_position != 0 {
+            self.write(0);
+        }
+        self.bits = bits;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output.push(self.bit_buffer as u8);
+        }
+        self.output
+    }
+}
+
+/// Compresses `data` into a `.Z` stream. In block mode, the table is cleared
+/// each time it fills up.
+fn compress(data: &[u8], max_bits: u32, block_mode: bool) -> Vec<u8> {
+    let first_code = if block_mode { CLEAR + 1 } else { CLEAR };
+    let flags = max_bits as u8 | if block_mode { 0x80 } else { 0 };
+    let mut writer = CodeWriter {
+        output: vec![0x1F, 0x9D, flags],
+        bit_buffer: 0,
+        bit_count: 0,
+        bits: 9,
+        group_position: 0,
+    };
+
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut next_code = first_code;
+    // The decoder adds its entries one code later than the encoder, and
+    // widens its codes based on its own table size
+    let mut decoder_next_code = first_code;
+    let mut decoder_has_previous = false;
+    let mut emit = |writer: &mut CodeWriter, code: u32| {
+        if writer.bits < max_bits && decoder_next_code >= 1 << writer.bits {
+            writer.set_bits(writer.bits + 1);
+        }
+        writer.write(code);
+        if code == CLEAR && block_mode {
+            writer.set_bits(9);
+            decoder_next_code = first_code;
+            decoder_has_previous = false;
+        } else {
+            if decoder_has_previous && decoder_next_code < 1 << max_bits {
+                decoder_next_code += 1;
+            }
+            decoder_has_previous = true;
+        }
+    };
+
+    let Some((&first, rest)) = data.split_first() else {
+        return writer.finish();
+    };
+    let mut current = u32::from(first);
+    for &byte in rest {
+        if let Some(&code) = table.get(&(current, byte)) {
+            current = code;
+            continue;
+        }
+        emit(&mut writer, current);
+        if next_code < 1 << max_bits {
+            table.insert((current, byte), next_code);
+            next_code += 1;
+        } else if block_mode {
+            emit(&mut writer, CLEAR);
+            table.clear();
+            next_code = first_code;
+        }
+        current = u32::from(byte);
+    }
+    emit(&mut writer, current);
+    writer.finish()
+}
+
+/// Text with enough repetition to grow the string table to its maximum.
+fn sample(size: usize) -> Vec<u8> {
+    let words = [
+        "archive", "compress", "lzw", "table", "code", "mirror", "\n",
+    ];
+    let mut state = 12345u32;
+    let mut data = Vec::with_capacity(size);
+    while data.len() < size {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        data.extend_from_slice(words[(state >> 16) as usize % words.len()].as_bytes());
+        data.push((state >> 8) as u8);
+    }
+    data.truncate(size);
+    data
+}
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_compress_code_widths() {
+    let data = sample(300_000);
+    for (max_bits, block_mode) in [(10, false), (12, true), (16, false), (16, true)] {
+        let compressed = compress(&data, max_bits, block_mode);
+        assert_eq!(
+            ArchiveFormat::detect(&compressed).unwrap(),
+            ArchiveFormat::Compress
+        );
+
+        let files = ArchiveExtractor::new()
+            .extract(&compressed, ArchiveFormat::Compress)
+            .expect("Failed to extract .Z file");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "data");
+        assert!(
+            files[0].data == data,
+            "Mismatch with {}-bit codes, block mode {}",
+            max_bits,
+            block_mode
+        );
+    }
+}
+
+#[test]
+fn test_compress_empty_and_repetitive() {
+    let compressed = compress(b"", 16, true);
+    let files = ArchiveExtractor::new()
+        .extract(&compressed, ArchiveFormat::Compress)
+        .expect("Failed to extract empty .Z file");
+    assert!(files[0].data.is_empty());
+
+    // Long runs decode through codes that are defined by their own use
+    let data = vec![b'a'; 100_000];
+    let files = ArchiveExtractor::new()
+        .extract(&compress(&data, 16, true), ArchiveFormat::Compress)
+        .expect("Failed to extract .Z file");
+    assert_eq!(files[0].data, data);
+}
+
+#[test]
+fn test_tar_z() {
+    let archive = tar(&[("README", b"old mirror\n"), ("src/main.c", &sample(50_000))]);
+    let data = compress(&archive, 16, true);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::TarZ);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::TarZ)
+        .expect("Failed to extract tar.Z archive");
+    assert_eq!(files.len(), 2);
+    assert_eq!(find(&files, "README").data, b"old mirror\n");
+    assert_eq!(find(&files, "src/main.c").data, sample(50_000));
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::TarZ)
+        .expect("Failed to verify tar.Z archive");
+    assert!(report.is_ok());
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::TarZ)
+        .expect("Failed to inspect tar.Z archive");
+    assert!(info.tar.is_some());
+}
+
+#[test]
+fn test_compress_invalid_code() {
+    // A first code that isn't a literal
+    let data = [0x1F, 0x9D, 0x90, 0xFF, 0x01];
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Compress);
+    assert!(
+        matches!(&result, Err(ArchiveError::Io(e)) if e.to_string().contains("invalid")),
+        "Expected an invalid code error, got {:?}",
+        result
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Compress)
+        .expect("Failed to verify .Z file");
+    assert_eq!(report.failures().count(), 1);
+
+    // 17-bit codes
+    let result =
+        ArchiveExtractor::new().extract(&[0x1F, 0x9D, 0x91, 0x00], ArchiveFormat::Compress);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_compress_size_limits() {
+    let data = compress(&[0u8; 1000], 16, true);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Compress);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    // Decoding stops as soon as the limit is exceeded
+    let data = compress(&vec![0u8; 1024 * 1024], 16, true);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(1024)
+        .extract(&data, ArchiveFormat::Compress);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::FileTooLarge { size: 1025, .. })
+    ));
+
+    let data = compress(
+        &tar(&[("a.bin", &[0u8; 1000]), ("b.bin", &[0u8; 1000])]),
+        16,
+        true,
+    );
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .extract(&data, ArchiveFormat::TarZ);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
+
+/// A stream encoded the way ncompress 4.2 encodes with `-b 10`: the codes
+/// widen to 10 bits, the full table is kept until the compression ratio drops
+/// at a 10000-byte checkpoint, and is then cleared, after which the codes
+/// widen again. `gzip -d` decodes it to data with the digest below.
+const RATIO_CLEAR_B10: &[u8] = include_bytes!("fixtures/ratio-clear-b10.Z");
+const RATIO_CLEAR_B10_SIZE: usize = 22538;
+const RATIO_CLEAR_B10_SHA256: &str =
+    "d0a73c4d363a421fb63bbad6cc392c782438b96a9882aae269c71dc400299b0a";
+
+#[test]
+fn test_compress_ratio_clear_fixture() {
+    let files = ArchiveExtractor::new()
+        .with_hashes([HashAlgorithm::Sha256])
+        .extract(RATIO_CLEAR_B10, ArchiveFormat::Compress)
+        .expect("Failed to extract fixture");
+
+    let data = find(&files, "data");
+    assert_eq!(data.data.len(), RATIO_CLEAR_B10_SIZE);
+    assert_eq!(
+        data.digest(HashAlgorithm::Sha256).unwrap().to_hex(),
+        RATIO_CLEAR_B10_SHA256
+    );
+}
@@ -0,0 +1,328 @@
+//! Tests for cpio archive extraction
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, EntryLink, ExtractedFile};
+use common::find;
+
+const MTIME: u32 = 1_700_000_000;
+
+/// An entry to write into a test archive.
+struct Entry<'a> {
+    name: &'a str,
+    mode: u32,
+    ino: u32,
+    nlink: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Entry<'a> {
+    fn file(name: &'a str, ino: u32, data: &'a [u8]) -> Self {
+        Self {
+            name,
+            mode: 0o100644,
+            ino,
+            nlink: 1,
+            data,
+        }
+    }
+
+    fn directory(name: &'a str, ino: u32) -> Self {
+        Self {
+            name,
+            mode: 0o040755,
+            ino,
+            nlink: 2,
+            data: b"",
+        }
+    }
+
+    fn symlink(name: &'a str, ino: u32, target: &'a str) -> Self {
+        Self {
+            name,
+            mode: 0o120777,
+            ino,
+            nlink: 1,
+            data: target.as_bytes(),
+        }
+    }
+
+    fn hard_link(name: &'a str, ino: u32, data: &'a [u8]) -> Self {
+        Self {
+            nlink: 2,
+            ..Self::file(name, ino, data)
+        }
+    }
+}
+
+fn trailer<'a>() -> Entry<'a> {
+    Entry {
+        mode: 0,
+        nlink: 1,
+        ..Entry::file("TRAILER!!!", 0, b"")
+    }
+}
+
+fn pad(archive: &mut Vec<u8>, alignment: usize) {
+    archive.resize(archive.len().next_multiple_of(alignment), 0);
+}
+
+/// Writes a newc archive, with checksums if `crc` is set.
+fn newc(entries: &[Entry<'_>], crc: bool) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for entry in entries.iter().chain([&trailer()]) {
+        let checksum = if crc {
+            entry
+                .data
+                .iter()
+                .fold(0u32, |sum, &byte| sum.wrapping_add(u32::from(byte)))
+        } else {
+            0
+        };
+        let fields = [
+            entry.ino,
+            entry.mode,
+            0,
+            0,
+            entry.nlink,
+            MTIME,
+            entry.data.len() as u32,
+            0,
+            0,
+            0,
+            0,
+            entry.name.len() as u32 + 1,
+            checksum,
+        ];
+        archive.extend_from_slice(if crc { b"070702" } else { b"070701" });
+        for field in fields {
+            archive.extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        archive.extend_from_slice(entry.name.as_bytes());
+        archive.push(0);
+        pad(&mut archive, 4);
+        archive.extend_from_slice(entry.data);
+        pad(&mut archive, 4);
+    }
+    archive
+}
+
+fn odc(entries: &[Entry<'_>]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for entry in entries.iter().chain([&trailer()]) {
+        let header = format!(
+            "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+            0,
+            entry.ino,
+            entry.mode,
+            0,
+            0,
+            entry.nlink,
+            0,
+            MTIME,
+            entry.name.len() + 1,
+            entry.data.len()
+        );
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(entry.name.as_bytes());
+        archive.push(0);
+        archive.extend_from_slice(entry.data);
+    }
+    archive
+}
+
+fn binary(entries: &[Entry<'_>], big_endian: bool) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for entry in entries.iter().chain([&trailer()]) {
+        let size = entry.data.len() as u32;
+        let fields = [
+            0o070707,
+            0,
+            entry.ino as u16,
+            entry.mode as u16,
+            0,
+            0,
+            entry.nlink as u16,
+            0,
+            (MTIME >> 16) as u16,
+            MTIME as u16,
+            entry.name.len() as u16 + 1,
+            (size >> 16) as u16,
+            size as u16,
+        ];
+        for field in fields {
+            archive.extend_from_slice(&if big_endian {
+                field.to_be_bytes()
+            } else {
+                field.to_le_bytes()
+            });
+        }
+        archive.extend_from_slice(entry.name.as_bytes());
+        archive.push(0);
+        pad(&mut archive, 2);
+        archive.extend_from_slice(entry.data);
+        pad(&mut archive, 2);
+    }
+    archive
+}
+
+fn sample_entries() -> Vec<Entry<'static>> {
+    vec![
+        Entry::directory("usr", 1),
+        Entry::directory("usr/bin", 2),
+        Entry {
+            mode: 0o100755,
+            ..Entry::file("usr/bin/hello", 3, b"#!/bin/sh\necho hello\n")
+        },
+        Entry::symlink("usr/bin/hi", 4, "hello"),
+        Entry::file("usr/empty", 5, b""),
+    ]
+}
+
+fn extract(data: &[u8]) -> Vec<ExtractedFile> {
+    ArchiveExtractor::new()
+        .extract(data, ArchiveFormat::Cpio)
+        .expect("Failed to extract cpio archive")
+}
+
+#[test]
+fn test_cpio_variants() {
+    let entries = sample_entries();
+    for data in [
+        newc(&entries, false),
+        newc(&entries, true),
+        odc(&entries),
+        binary(&entries, false),
+        binary(&entries, true),
+    ] {
+        assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Cpio);
+        let files = extract(&data);
+
+        let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            names,
+            ["usr", "usr/bin", "usr/bin/hello", "usr/bin/hi", "usr/empty"]
+        );
+        assert!(find(&files, "usr/bin").is_directory);
+
+        let hello = find(&files, "usr/bin/hello");
+        assert_eq!(hello.data, b"#!/bin/sh\necho hello\n");
+        assert_eq!(hello.metadata.mode, Some(0o755));
+        assert_eq!(hello.metadata.mtime, Some(u64::from(MTIME)));
+
+        let link = find(&files, "usr/bin/hi");
+        assert!(link.data.is_empty());
+        assert_eq!(
+            link.metadata.link,
+            Some(EntryLink::Symbolic("hello".to_string()))
+        );
+        assert!(find(&files, "usr/empty").data.is_empty());
+    }
+}
+
+#[test]
+fn test_cpio_newc_hard_links() {
+    // newc archivers store the contents with the last link only
+    let data = newc(
+        &[
+            Entry::hard_link("a", 7, b""),
+            Entry::file("other", 8, b"other"),
+            Entry::hard_link("b", 7, b"shared"),
+            Entry::hard_link("empty1", 9, b""),
+            Entry::hard_link("empty2", 9, b""),
+        ],
+        false,
+    );
+    let files = extract(&data);
+
+    let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(names, ["other", "b", "a", "empty1", "empty2"]);
+    assert_eq!(find(&files, "b").data, b"shared");
+    assert!(find(&files, "b").metadata.link.is_none());
+    assert_eq!(
+        find(&files, "a").metadata.link,
+        Some(EntryLink::Hard("b".to_string()))
+    );
+    assert!(find(&files, "empty1").metadata.link.is_none());
+    assert_eq!(
+        find(&files, "empty2").metadata.link,
+        Some(EntryLink::Hard("empty1".to_string()))
+    );
+}
+
+#[test]
+fn test_cpio_odc_hard_links() {
+    // odc archivers store the contents with every link
+    let data = odc(&[
+        Entry::hard_link("a", 7, b"shared"),
+        Entry::hard_link("b", 7, b"shared"),
+    ]);
+    let files = extract(&data);
+
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().all(|file| file.data == b"shared"));
+}
+
+#[test]
+fn test_cpio_checksum_mismatch() {
+    let mut data = newc(&[Entry::file("hello.txt", 1, b"Hello, World!")], true);
+    let position = data.windows(5).position(|w| w == b"Hello").unwrap();
+    data[position] = b'J';
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Cpio);
+    assert!(
+        matches!(&result, Err(ArchiveError::Io(e)) if e.to_string().contains("checksum")),
+        "Expected a checksum error, got {:?}",
+        result
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Cpio)
+        .expect("Failed to verify cpio archive");
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, "hello.txt");
+}
+
+#[test]
+fn test_cpio_truncated() {
+    let data = newc(&[Entry::file("hello.txt", 1, b"Hello, World!")], false);
+
+    let result = ArchiveExtractor::new().extract(&data[..120], ArchiveFormat::Cpio);
+    assert!(result.is_err());
+
+    let report = ArchiveExtractor::new()
+        .verify(&data[..120], ArchiveFormat::Cpio)
+        .expect("Failed to verify cpio archive");
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn test_cpio_size_limits() {
+    let big = vec![0u8; 1000];
+    let data = newc(
+        &[Entry::file("a.bin", 1, &big), Entry::file("b.bin", 2, &big)],
+        false,
+    );
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Cpio);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .extract(&data, ArchiveFormat::Cpio);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .verify(&data, ArchiveFormat::Cpio);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
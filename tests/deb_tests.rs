@@ -0,0 +1,99 @@
+//! Tests for Debian package creation and extraction
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, DebBuilder, DebCompression};
+
+const CONTROL: &str = "Package: hello\nVersion: 1.0\nArchitecture: all\n\
+                       Maintainer: Jane Doe <jane@example.com>\nDescription: greeting\n";
+
+fn build_hello(compression: DebCompression) -> Vec<u8> {
+    let mut builder = DebBuilder::new(CONTROL).with_compression(compression);
+    builder
+        .add_file_with_mode("/usr/bin/hello", b"#!/bin/sh\necho hello\n".to_vec(), 0o755)
+        .unwrap();
+    builder
+        .add_file("/usr/share/doc/hello/README", b"Hello, World!\n".to_vec())
+        .unwrap();
+    builder.add_symlink("/usr/bin/hi", "hello").unwrap();
+    builder
+        .add_control_file_with_mode("postinst", b"#!/bin/sh\nexit 0\n".to_vec(), 0o755)
+        .unwrap();
+    builder.finish().expect("Failed to build package")
+}
+
+#[test]
+fn test_deb_member_order() {
+    let deb = build_hello(DebCompression::Xz);
+    let members = ArchiveExtractor::new()
+        .extract(&deb, ArchiveFormat::Deb)
+        .expect("Failed to extract package");
+
+    let names: Vec<_> = members.iter().map(|m| m.path.as_str()).collect();
+    assert_eq!(names, ["debian-binary", "control.tar.xz", "data.tar.xz"]);
+    assert_eq!(members[0].data, b"2.0\n");
+}
+
+#[test]
+fn test_deb_default_compression() {
+    let deb = DebBuilder::new(CONTROL).finish().unwrap();
+    let members = ArchiveExtractor::new()
+        .extract(&deb, ArchiveFormat::Deb)
+        .expect("Failed to extract package");
+
+    let names: Vec<_> = members.iter().map(|m| m.path.as_str()).collect();
+    assert_eq!(names, ["debian-binary", "control.tar.gz", "data.tar.gz"]);
+}
+
+#[test]
+fn test_deb_compressions_round_trip() {
+    let extractor = ArchiveExtractor::new();
+
+    for (compression, format, suffix) in [
+        (DebCompression::None, ArchiveFormat::Tar, ""),
+        (DebCompression::Gzip, ArchiveFormat::TarGz, ".gz"),
+        (DebCompression::Xz, ArchiveFormat::TarXz, ".xz"),
+        (DebCompression::Zstd, ArchiveFormat::TarZst, ".zst"),
+    ] {
+        let deb = build_hello(compression);
+        let members = extractor.extract(&deb, ArchiveFormat::Deb).unwrap();
+
+        let control = &members[1];
+        assert_eq!(control.path, format!("control.tar{}", suffix));
+        let control_files = extractor.extract(&control.data, format).unwrap();
+        let paths: Vec<_> = control_files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["./", "./control", "./postinst"]);
+        assert_eq!(control_files[1].data, CONTROL.as_bytes());
+
+        let data = &members[2];
+        assert_eq!(data.path, format!("data.tar{}", suffix));
+        let data_files = extractor.extract(&data.data, format).unwrap();
+        let paths: Vec<_> = data_files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "./",
+                "./usr/",
+                "./usr/bin/",
+                "./usr/bin/hello",
+                "./usr/share/",
+                "./usr/share/doc/",
+                "./usr/share/doc/hello/",
+                "./usr/share/doc/hello/README",
+                "./usr/bin/hi",
+            ]
+        );
+        assert_eq!(data_files[7].data, b"Hello, World!\n");
+    }
+}
+
+#[test]
+fn test_deb_rejects_escaping_paths() {
+    let mut builder = DebBuilder::new(CONTROL);
+    assert!(matches!(
+        builder.add_file("/usr/../../etc/passwd", Vec::new()),
+        Err(ArchiveError::InvalidPath(_))
+    ));
+    assert!(matches!(
+        builder.add_control_file("control", Vec::new()),
+        Err(ArchiveError::InvalidPath(_))
+    ));
+}
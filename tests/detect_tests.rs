@@ -0,0 +1,64 @@
+//! Tests for format detection with `ArchiveFormat::detect`
+
+mod common;
+
+use archive::{ArchiveError, ArchiveFormat, DebBuilder};
+use common::read_test_archive;
+
+#[test]
+fn test_detect_fixtures() {
+    let fixtures = [
+        ("basic.zip", ArchiveFormat::Zip),
+        ("empty.zip", ArchiveFormat::Zip),
+        ("archive.tar", ArchiveFormat::Tar),
+        ("archive.ar", ArchiveFormat::Ar),
+        ("archive.tar.gz", ArchiveFormat::TarGz),
+        ("archive.tgz", ArchiveFormat::TarGz),
+        ("archive.tar.bz2", ArchiveFormat::TarBz2),
+        ("archive.tar.xz", ArchiveFormat::TarXz),
+        ("archive.tar.zst", ArchiveFormat::TarZst),
+        ("archive.7z", ArchiveFormat::SevenZ),
+        ("hello.txt.gz", ArchiveFormat::Gz),
+        ("hello.txt.bz2", ArchiveFormat::Bz2),
+        ("hello.txt.xz", ArchiveFormat::Xz),
+        ("hello.txt.lz4", ArchiveFormat::Lz4),
+        ("hello.txt.zst", ArchiveFormat::Zst),
+    ];
+
+    for (name, expected) in fixtures {
+        let data = read_test_archive(name);
+        let format = ArchiveFormat::detect(&data)
+            .unwrap_or_else(|e| panic!("Failed to detect {}: {}", name, e));
+        assert_eq!(format, expected, "{}", name);
+    }
+}
+
+#[test]
+fn test_detect_tar_lz4() {
+    let tar = read_test_archive("archive.tar");
+    let mut encoder = lz4::EncoderBuilder::new().build(Vec::new()).unwrap();
+    std::io::copy(&mut tar.as_slice(), &mut encoder).unwrap();
+    let (data, result) = encoder.finish();
+    result.unwrap();
+
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::TarLz4);
+}
+
+#[test]
+fn test_detect_deb() {
+    let control = "Package: hello\nVersion: 1.0\nArchitecture: all\n\
+                   Maintainer: Jane Doe <jane@example.com>\nDescription: greeting\n";
+    let data = DebBuilder::new(control).finish().unwrap();
+
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Deb);
+}
+
+#[test]
+fn test_detect_unknown() {
+    for data in [&b""[..], b"plain text", &[0u8; 1024]] {
+        assert!(matches!(
+            ArchiveFormat::detect(data),
+            Err(ArchiveError::UnknownFormat)
+        ));
+    }
+}
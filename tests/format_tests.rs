@@ -0,0 +1,123 @@
+//! Tests for parsing and displaying `ArchiveFormat`
+
+use archive::{ArchiveError, ArchiveFormat};
+use mime_type::MimeType;
+
+const FORMATS: [ArchiveFormat; 32] = [
+    ArchiveFormat::Zip,
+    ArchiveFormat::Tar,
+    ArchiveFormat::Ar,
+    ArchiveFormat::Deb,
+    ArchiveFormat::TarGz,
+    ArchiveFormat::TarBz2,
+    ArchiveFormat::TarXz,
+    ArchiveFormat::TarZst,
+    ArchiveFormat::TarLz4,
+    ArchiveFormat::Gz,
+    ArchiveFormat::Bz2,
+    ArchiveFormat::Xz,
+    ArchiveFormat::Lz4,
+    ArchiveFormat::Zst,
+    ArchiveFormat::SevenZ,
+    ArchiveFormat::Rar,
+    ArchiveFormat::Cpio,
+    ArchiveFormat::Rpm,
+    ArchiveFormat::Iso,
+    ArchiveFormat::Cab,
+    ArchiveFormat::Lz,
+    ArchiveFormat::Lzma,
+    ArchiveFormat::TarLz,
+    ArchiveFormat::TarLzma,
+    ArchiveFormat::Compress,
+    ArchiveFormat::TarZ,
+    ArchiveFormat::Br,
+    ArchiveFormat::TarBr,
+    ArchiveFormat::Snappy,
+    ArchiveFormat::Xar,
+    ArchiveFormat::Pkg,
+    ArchiveFormat::SquashFs,
+];
+
+#[test]
+fn test_name_round_trip() {
+    for format in FORMATS {
+        assert_eq!(format.to_string(), format.name());
+        assert_eq!(format.name().parse::<ArchiveFormat>().unwrap(), format);
+        assert_eq!(
+            format
+                .name()
+                .to_lowercase()
+                .parse::<ArchiveFormat>()
+                .unwrap(),
+            format
+        );
+    }
+}
+
+#[test]
+fn test_parse_unknown_format() {
+    for name in ["", "arj", "tar.gz.gz", "tgz"] {
+        assert!(matches!(
+            name.parse::<ArchiveFormat>(),
+            Err(ArchiveError::UnsupportedFormat(_))
+        ));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_uses_names() {
+    for format in FORMATS {
+        let json = serde_json::to_string(&format).unwrap();
+        assert_eq!(json, format!("\"{}\"", format.name()));
+        assert_eq!(
+            serde_json::from_str::<ArchiveFormat>(&json).unwrap(),
+            format
+        );
+    }
+    assert!(serde_json::from_str::<ArchiveFormat>("\"ARJ\"").is_err());
+}
+
+#[test]
+fn test_mime_type_conversion() {
+    for format in FORMATS {
+        // Every format converts, if only to application/octet-stream
+        assert!(matches!(MimeType::from(format), MimeType::Archive(_)));
+    }
+    assert_eq!(
+        MimeType::from(ArchiveFormat::Zip).to_string(),
+        "application/zip"
+    );
+    assert_eq!(
+        MimeType::from(&ArchiveFormat::TarGz).to_string(),
+        "application/gzip"
+    );
+    for format in [
+        ArchiveFormat::Iso,
+        ArchiveFormat::TarBr,
+        ArchiveFormat::SquashFs,
+    ] {
+        assert_eq!(
+            MimeType::from(format).to_string(),
+            "application/octet-stream"
+        );
+    }
+}
+
+#[test]
+#[cfg(not(feature = "rar"))]
+fn test_disabled_format_names_its_feature() {
+    let extractor = archive::ArchiveExtractor::new();
+    let data = b"Rar!\x1A\x07\x01\x00";
+    for result in [
+        extractor.extract(data, ArchiveFormat::Rar).map(drop),
+        extractor.verify(data, ArchiveFormat::Rar).map(drop),
+        extractor.inspect(data, ArchiveFormat::Rar).map(drop),
+    ] {
+        assert!(
+            matches!(result, Err(ArchiveError::UnsupportedFormat(ref msg)) if msg.contains("`rar`")),
+            "{:?}",
+            result
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Tests for per-entry content hashing during extraction
+#![cfg(all(feature = "sha1", feature = "md5", feature = "blake3"))]
+
+mod common;
+
+use archive::{ArchiveExtractor, ArchiveFormat, HashAlgorithm, SevenZBuilder};
+use common::{assert_contains_file, read_test_archive};
+
+const HELLO_SHA256: &str = "c98c24b677eff44860afea6f493bbaec5bb1c4cbb209c6fc2bbb47f66ff2ad31";
+const HELLO_SHA1: &str = "60fde9c2310b0d4cad4dab8d126b04387efba289";
+const HELLO_MD5: &str = "bea8252ff4e80f41719ea13cdf007273";
+const HELLO_SHA512: &str = "921618bc6d9f8059437c5e0397b13f973ab7c7a7b81f0ca31b70bf448fd800a4\
+                            60b67efda0020088bc97bf7d9da97a9e2ce7b20d46e066462ec44cf60284f9a7";
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+const EMPTY_BLAKE3: &str = "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262";
+
+const ALL_ALGORITHMS: [HashAlgorithm; 5] = [
+    HashAlgorithm::Sha256,
+    HashAlgorithm::Sha1,
+    HashAlgorithm::Md5,
+    HashAlgorithm::Blake3,
+    HashAlgorithm::Sha512,
+];
+
+#[test]
+fn test_hashes_across_formats() {
+    let fixtures = [
+        ("basic.zip", ArchiveFormat::Zip, "hello.txt"),
+        ("archive.tar", ArchiveFormat::Tar, "test-data/hello.txt"),
+        (
+            "archive.tar.gz",
+            ArchiveFormat::TarGz,
+            "test-data/hello.txt",
+        ),
+        (
+            "archive.tar.xz",
+            ArchiveFormat::TarXz,
+            "test-data/hello.txt",
+        ),
+        ("archive.7z", ArchiveFormat::SevenZ, "hello.txt"),
+        ("archive.ar", ArchiveFormat::Ar, "hello.txt"),
+        ("hello.txt.gz", ArchiveFormat::Gz, "hello.txt"),
+        ("hello.txt.bz2", ArchiveFormat::Bz2, "data"),
+        ("hello.txt.xz", ArchiveFormat::Xz, "data"),
+        ("hello.txt.lz4", ArchiveFormat::Lz4, "data"),
+        ("hello.txt.zst", ArchiveFormat::Zst, "data"),
+    ];
+    let extractor = ArchiveExtractor::new().with_hashes(ALL_ALGORITHMS);
+
+    for (name, format, path) in fixtures {
+        let data = read_test_archive(name);
+        let files = extractor
+            .extract(&data, format)
+            .unwrap_or_else(|e| panic!("Failed to extract {}: {}", name, e));
+        let hello = assert_contains_file(&files, path);
+
+        let algorithms: Vec<_> = hello.digests.iter().map(|d| d.algorithm).collect();
+        assert_eq!(algorithms, ALL_ALGORITHMS, "{}", name);
+
+        let hex = |algorithm| hello.digest(algorithm).unwrap().to_hex();
+        assert_eq!(hex(HashAlgorithm::Sha256), HELLO_SHA256, "{}", name);
+        assert_eq!(hex(HashAlgorithm::Sha1), HELLO_SHA1, "{}", name);
+        assert_eq!(hex(HashAlgorithm::Md5), HELLO_MD5, "{}", name);
+        assert_eq!(hex(HashAlgorithm::Sha512), HELLO_SHA512, "{}", name);
+        assert_eq!(
+            hex(HashAlgorithm::Blake3),
+            blake3::hash(&hello.data).to_hex().as_str(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_no_hashes_by_default() {
+    let data = read_test_archive("basic.zip");
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Zip)
+        .unwrap();
+
+    assert!(files.iter().all(|file| file.digests.is_empty()));
+}
+
+#[test]
+fn test_duplicate_algorithms_and_order() {
+    let data = read_test_archive("basic.zip");
+    let files = ArchiveExtractor::new()
+        .with_hashes([
+            HashAlgorithm::Md5,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Md5,
+        ])
+        .extract(&data, ArchiveFormat::Zip)
+        .unwrap();
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    let algorithms: Vec<_> = hello.digests.iter().map(|d| d.algorithm).collect();
+    assert_eq!(algorithms, [HashAlgorithm::Md5, HashAlgorithm::Sha256]);
+    assert_eq!(hello.digest(HashAlgorithm::Sha1), None);
+}
+
+#[test]
+fn test_hashes_of_empty_files_and_directories() {
+    let mut builder = SevenZBuilder::new();
+    builder.add_file("empty.txt", Vec::new()).unwrap();
+    builder.add_directory("docs").unwrap();
+    let data = builder.finish().unwrap();
+
+    let files = ArchiveExtractor::new()
+        .with_hashes([HashAlgorithm::Sha256, HashAlgorithm::Blake3])
+        .extract(&data, ArchiveFormat::SevenZ)
+        .unwrap();
+
+    let empty = assert_contains_file(&files, "empty.txt");
+    assert_eq!(
+        empty.digest(HashAlgorithm::Sha256).unwrap().to_hex(),
+        EMPTY_SHA256
+    );
+    assert_eq!(
+        empty.digest(HashAlgorithm::Blake3).unwrap().to_hex(),
+        EMPTY_BLAKE3
+    );
+
+    let docs = files.iter().find(|file| file.path == "docs").unwrap();
+    assert!(docs.is_directory);
+    assert!(docs.digests.is_empty());
+}
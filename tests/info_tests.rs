@@ -0,0 +1,452 @@
+//! Tests for archive-level metadata returned by `ArchiveExtractor::inspect`
+
+mod common;
+
+use archive::info::{ArSymbolTable, TarVariant};
+use archive::{ArBuilder, ArVariant, ArchiveError, ArchiveExtractor, ArchiveFormat, SevenZBuilder};
+use common::read_test_archive;
+use std::io::{Cursor, Read, Write};
+
+#[test]
+fn test_inspect_zip_comment() {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer.set_comment("forensic copy").unwrap();
+    writer
+        .start_file("hello.txt", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(b"Hello").unwrap();
+    let data = writer.finish().unwrap().into_inner();
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Zip)
+        .expect("Failed to inspect zip");
+
+    let zip = info.zip.expect("Missing zip info");
+    assert_eq!(zip.comment.as_deref(), Some("forensic copy"));
+    assert_eq!(zip.entry_count, 1);
+    assert!(info.tar.is_none());
+}
+
+#[test]
+fn test_inspect_zip_without_comment() {
+    let data = read_test_archive("basic.zip");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Zip)
+        .expect("Failed to inspect basic.zip");
+
+    assert_eq!(info.format, ArchiveFormat::Zip);
+    assert_eq!(info.zip.expect("Missing zip info").comment, None);
+}
+
+#[test]
+fn test_inspect_gnu_tar() {
+    let data = read_test_archive("archive.tar");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Tar)
+        .expect("Failed to inspect archive.tar");
+
+    let tar = info.tar.expect("Missing tar info");
+    assert_eq!(tar.variant, Some(TarVariant::Gnu));
+    assert!(tar.pax_global_headers.is_empty());
+}
+
+#[test]
+fn test_inspect_tar_variants() {
+    let ustar = build_tar(|builder| {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(5);
+        header.set_cksum();
+        builder.append_data(&mut header, "hello.txt", &b"Hello"[..])
+    });
+    let v7 = build_tar(|builder| {
+        let mut header = tar::Header::new_old();
+        header.set_size(5);
+        header.set_path("hello.txt")?;
+        header.set_cksum();
+        builder.append(&header, &b"Hello"[..])
+    });
+
+    let extractor = ArchiveExtractor::new();
+    for (data, expected) in [(ustar, TarVariant::Ustar), (v7, TarVariant::V7)] {
+        let info = extractor
+            .inspect(&data, ArchiveFormat::Tar)
+            .expect("Failed to inspect tar");
+        assert_eq!(info.tar.unwrap().variant, Some(expected));
+    }
+}
+
+#[test]
+fn test_inspect_pax_global_headers() {
+    let data = build_tar(|builder| {
+        let records = pax_record("comment", "release build") + &pax_record("SCHILY.xattr.k", "v");
+        let mut global = tar::Header::new_ustar();
+        global.set_entry_type(tar::EntryType::XGlobalHeader);
+        global.set_size(records.len() as u64);
+        global.set_cksum();
+        builder.append_data(&mut global, "pax_global_header", records.as_bytes())?;
+
+        let mut header = tar::Header::new_ustar();
+        header.set_size(5);
+        header.set_cksum();
+        builder.append_data(&mut header, "hello.txt", &b"Hello"[..])
+    });
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Tar)
+        .expect("Failed to inspect pax tar");
+
+    let tar = info.tar.expect("Missing tar info");
+    assert_eq!(tar.variant, Some(TarVariant::Pax));
+    assert_eq!(
+        tar.pax_global_headers,
+        vec![
+            ("comment".to_string(), "release build".to_string()),
+            ("SCHILY.xattr.k".to_string(), "v".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_inspect_pax_global_header_limit() {
+    let records = pax_record("comment", &"x".repeat(4096));
+    let data = build_tar(|builder| {
+        let mut global = tar::Header::new_ustar();
+        global.set_entry_type(tar::EntryType::XGlobalHeader);
+        global.set_size(records.len() as u64);
+        global.set_cksum();
+        builder.append_data(&mut global, "pax_global_header", records.as_bytes())
+    });
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(1024)
+        .inspect(&data, ArchiveFormat::Tar);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+}
+
+#[test]
+fn test_inspect_tar_xz_bomb() {
+    let data = build_tar(|builder| {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(1024 * 1024);
+        header.set_cksum();
+        builder.append_data(
+            &mut header,
+            "zeros.bin",
+            std::io::repeat(0).take(1024 * 1024),
+        )
+    });
+    let mut compressed = Vec::new();
+    lzma_rs::xz_compress(&mut data.as_slice(), &mut compressed).unwrap();
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(64 * 1024)
+        .inspect(&compressed, ArchiveFormat::TarXz);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { limit: 65536, .. })
+    ));
+
+    // Within the limits, the headers are read as usual
+    let info = ArchiveExtractor::new()
+        .inspect(&compressed, ArchiveFormat::TarXz)
+        .expect("Failed to inspect tar.xz");
+    assert!(info.tar.is_some());
+}
+
+#[test]
+fn test_inspect_tar_gz_bomb() {
+    let data = build_tar(|builder| {
+        for name in ["a.bin", "b.bin", "c.bin"] {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(48 * 1024);
+            header.set_cksum();
+            builder.append_data(&mut header, name, std::io::repeat(0).take(48 * 1024))?;
+        }
+        Ok(())
+    });
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&data).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    // Each entry fits within the file limit, but together they exceed the
+    // total limit
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(64 * 1024)
+        .with_max_total_size(64 * 1024)
+        .inspect(&compressed, ArchiveFormat::TarGz);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { limit: 65536, .. })
+    ));
+}
+
+#[test]
+fn test_inspect_tar_gz() {
+    let data = read_test_archive("archive.tar.gz");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::TarGz)
+        .expect("Failed to inspect archive.tar.gz");
+
+    assert!(info.gzip.is_some());
+    assert_eq!(info.tar.unwrap().variant, Some(TarVariant::Gnu));
+}
+
+#[test]
+fn test_inspect_gzip_header() {
+    let mut encoder = flate2::GzBuilder::new()
+        .filename("report.txt")
+        .comment("nightly")
+        .extra(vec![b'A', b'B', 2, 0, 1, 2])
+        .mtime(1_700_000_000)
+        .operating_system(3)
+        .write(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"Hello").unwrap();
+    let data = encoder.finish().unwrap();
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Gz)
+        .expect("Failed to inspect gzip");
+
+    let gzip = info.gzip.expect("Missing gzip info");
+    assert_eq!(gzip.filename.as_deref(), Some("report.txt"));
+    assert_eq!(gzip.comment.as_deref(), Some("nightly"));
+    assert_eq!(gzip.extra, Some(vec![b'A', b'B', 2, 0, 1, 2]));
+    assert_eq!(gzip.mtime, 1_700_000_000);
+    assert_eq!(gzip.operating_system, 3);
+}
+
+#[test]
+fn test_inspect_gzip_fixture() {
+    let data = read_test_archive("hello.txt.gz");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Gz)
+        .expect("Failed to inspect hello.txt.gz");
+
+    let gzip = info.gzip.expect("Missing gzip info");
+    assert_eq!(gzip.filename.as_deref(), Some("hello.txt"));
+    assert_eq!(gzip.comment, None);
+}
+
+#[test]
+fn test_inspect_zstd_frames() {
+    let first = zstd::bulk::compress(b"Hello", 3).unwrap();
+    let mut encoder = zstd::stream::Encoder::new(Vec::new(), 3).unwrap();
+    encoder.include_checksum(true).unwrap();
+    encoder.write_all(b", World!").unwrap();
+    let second = encoder.finish().unwrap();
+
+    // Skippable frame: magic, little-endian length, user data
+    let mut data = vec![0x50, 0x2A, 0x4D, 0x18, 4, 0, 0, 0, 1, 2, 3, 4];
+    data.extend_from_slice(&first);
+    data.extend_from_slice(&second);
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Zst)
+        .expect("Failed to inspect zstd");
+
+    let frames = info.zstd.expect("Missing zstd info").frames;
+    assert_eq!(frames.len(), 3);
+
+    assert!(frames[0].skippable);
+    assert_eq!(frames[0].compressed_size, 12);
+
+    assert!(!frames[1].skippable);
+    assert_eq!(frames[1].compressed_size, first.len() as u64);
+    assert_eq!(frames[1].content_size, Some(5));
+    assert!(frames[1].window_size.is_some());
+    assert_eq!(frames[1].dictionary_id, None);
+
+    assert!(frames[2].has_checksum);
+    assert_eq!(frames[2].compressed_size, second.len() as u64);
+}
+
+#[test]
+fn test_inspect_tar_zst_fixture() {
+    let data = read_test_archive("archive.tar.zst");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::TarZst)
+        .expect("Failed to inspect archive.tar.zst");
+
+    let frames = info.zstd.expect("Missing zstd info").frames;
+    assert!(!frames.is_empty());
+    assert_eq!(
+        frames.iter().map(|f| f.compressed_size).sum::<u64>(),
+        data.len() as u64
+    );
+    assert!(info.tar.is_some());
+}
+
+#[test]
+fn test_inspect_7z_flags() {
+    let build = |solid: bool, password: Option<&str>, encrypt_header: bool| {
+        let mut builder = SevenZBuilder::new()
+            .with_solid(solid)
+            .with_header_encryption(encrypt_header);
+        if let Some(password) = password {
+            builder = builder.with_password(password);
+        }
+        // Enough entries for the header to be worth compressing (and encrypting)
+        for i in 0..32 {
+            builder
+                .add_file(format!("dir/file-{:02}.txt", i).as_str(), vec![b'a'; i + 1])
+                .unwrap();
+        }
+        builder.finish().unwrap()
+    };
+    let extractor = ArchiveExtractor::new();
+
+    let solid = extractor
+        .inspect(&build(true, None, false), ArchiveFormat::SevenZ)
+        .unwrap()
+        .sevenz
+        .expect("Missing 7z info");
+    assert!(solid.solid);
+    assert!(!solid.encrypted);
+    assert!(!solid.header_encrypted);
+    assert!(solid.header_compressed);
+    assert_eq!(solid.block_count, 1);
+    assert_eq!(solid.entry_count, 32);
+
+    let non_solid = extractor
+        .inspect(&build(false, None, false), ArchiveFormat::SevenZ)
+        .unwrap()
+        .sevenz
+        .unwrap();
+    assert!(!non_solid.solid);
+    assert_eq!(non_solid.block_count, 32);
+
+    let encrypted = extractor
+        .inspect(&build(true, Some("secret"), false), ArchiveFormat::SevenZ)
+        .unwrap()
+        .sevenz
+        .unwrap();
+    assert!(encrypted.encrypted);
+    assert!(!encrypted.header_encrypted);
+    assert_eq!(encrypted.entry_count, 32);
+
+    let hidden = extractor
+        .inspect(&build(true, Some("secret"), true), ArchiveFormat::SevenZ)
+        .unwrap()
+        .sevenz
+        .unwrap();
+    assert!(hidden.encrypted);
+    assert!(hidden.header_encrypted);
+    assert!(hidden.header_compressed);
+}
+
+#[test]
+fn test_inspect_ar_variants() {
+    let build = |variant: ArVariant, name: &str| {
+        let mut builder = ArBuilder::new(variant);
+        builder.add_file(name, b"data".to_vec()).unwrap();
+        builder.finish().unwrap()
+    };
+    let long_name = "a-very-long-member-name.txt";
+    let extractor = ArchiveExtractor::new();
+
+    let gnu = extractor
+        .inspect(&build(ArVariant::Gnu, long_name), ArchiveFormat::Ar)
+        .unwrap()
+        .ar
+        .expect("Missing ar info");
+    assert_eq!(gnu.variant, Some(ArVariant::Gnu));
+    assert_eq!(gnu.member_count, 1);
+    assert_eq!(gnu.symbol_table, None);
+
+    let bsd = extractor
+        .inspect(&build(ArVariant::Bsd, long_name), ArchiveFormat::Ar)
+        .unwrap()
+        .ar
+        .unwrap();
+    assert_eq!(bsd.variant, Some(ArVariant::Bsd));
+    assert_eq!(bsd.member_count, 1);
+
+    // Short names in BSD mode use the common format
+    let common = extractor
+        .inspect(&build(ArVariant::Bsd, "short"), ArchiveFormat::Ar)
+        .unwrap()
+        .ar
+        .unwrap();
+    assert_eq!(common.variant, None);
+}
+
+#[test]
+fn test_inspect_ar_symbol_table() {
+    // GNU ar archive with an empty System V symbol table
+    let mut data = b"!<arch>\n".to_vec();
+    data.extend_from_slice(
+        format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", "/", 0, 0, 0, 0, 4).as_bytes(),
+    );
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(
+        format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            "a.o/", 0, 0, 0, 644, 2
+        )
+        .as_bytes(),
+    );
+    data.extend_from_slice(b"ab");
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Ar)
+        .expect("Failed to inspect ar")
+        .ar
+        .expect("Missing ar info");
+
+    assert_eq!(info.symbol_table, Some(ArSymbolTable::SysV));
+    assert_eq!(info.variant, Some(ArVariant::Gnu));
+    assert_eq!(info.member_count, 1);
+}
+
+#[test]
+fn test_inspect_ar_fixture() {
+    let data = read_test_archive("archive.ar");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Ar)
+        .expect("Failed to inspect archive.ar");
+
+    let ar = info.ar.expect("Missing ar info");
+    assert_eq!(ar.variant, Some(ArVariant::Gnu));
+    assert!(ar.member_count > 0);
+}
+
+#[test]
+fn test_inspect_invalid_data() {
+    let extractor = ArchiveExtractor::new();
+    assert!(
+        extractor
+            .inspect(b"not an archive", ArchiveFormat::Ar)
+            .is_err()
+    );
+    assert!(
+        extractor
+            .inspect(b"not an archive", ArchiveFormat::Gz)
+            .is_err()
+    );
+    assert!(
+        extractor
+            .inspect(b"not an archive", ArchiveFormat::Zst)
+            .is_err()
+    );
+    assert!(
+        extractor
+            .inspect(b"not an archive", ArchiveFormat::SevenZ)
+            .is_err()
+    );
+}
+
+fn build_tar(append: impl FnOnce(&mut tar::Builder<Vec<u8>>) -> std::io::Result<()>) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    append(&mut builder).unwrap();
+    builder.into_inner().unwrap()
+}
+
+/// Formats a PAX record, whose length prefix counts itself.
+fn pax_record(key: &str, value: &str) -> String {
+    let body = format!(" {}={}\n", key, value);
+    let mut len = body.len() + 1;
+    while len.to_string().len() + body.len() != len {
+        len += 1;
+    }
+    format!("{}{}", len, body)
+}
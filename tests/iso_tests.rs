@@ -0,0 +1,449 @@
+//! Tests for ISO 9660 image extraction
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, EntryLink, ExtractedFile};
+use common::find;
+use std::io::Cursor;
+
+const SECTOR: usize = 2048;
+
+/// 2024-01-02 03:04:05 UTC, as stored in directory records.
+const RECORDED: [u8; 7] = [124, 1, 2, 3, 4, 5, 0];
+const RECORDED_MTIME: u64 = 1_704_164_645;
+
+/// 2023-11-14 22:13:20 UTC, as stored in Rock Ridge `TF` entries.
+const MODIFIED: [u8; 7] = [123, 11, 14, 22, 13, 20, 0];
+const MODIFIED_MTIME: u64 = 1_700_000_000;
+
+/// A file or directory to write into a test image, with its primary
+/// (ISO 9660) and long names.
+enum Node {
+    Dir(&'static str, &'static str, Vec<Node>),
+    File(&'static str, &'static str, &'static [u8]),
+    /// A file whose contents are split across two extents.
+    Split(&'static str, &'static str, &'static [u8], &'static [u8]),
+    Link(&'static str, &'static str, &'static str),
+}
+
+/// Which names and extensions a directory tree is written with.
+#[derive(Clone, Copy, PartialEq)]
+enum Tree {
+    Primary,
+    RockRidge,
+    Joliet,
+}
+
+/// Writes an image of `nodes`, with Rock Ridge entries in the primary tree
+/// and an additional Joliet tree as requested.
+fn image(nodes: &[Node], rock_ridge: bool, joliet: bool) -> Vec<u8> {
+    let mut image = vec![0u8; 19 * SECTOR];
+    let primary = if rock_ridge {
+        Tree::RockRidge
+    } else {
+        Tree::Primary
+    };
+    let primary_root = write_dir(&mut image, nodes, primary, None);
+    let joliet_root = joliet.then(|| write_dir(&mut image, nodes, Tree::Joliet, None));
+
+    let total = (image.len() / SECTOR) as u32;
+    image[16 * SECTOR..17 * SECTOR].copy_from_slice(&descriptor(1, primary_root, total));
+    match joliet_root {
+        Some(root) => {
+            image[17 * SECTOR..18 * SECTOR].copy_from_slice(&descriptor(2, root, total));
+            image[18 * SECTOR..19 * SECTOR].copy_from_slice(&descriptor(255, (0, 0), 0));
+        }
+        None => image[17 * SECTOR..18 * SECTOR].copy_from_slice(&descriptor(255, (0, 0), 0)),
+    }
+    image
+}
+
+fn descriptor(kind: u8, (extent, size): (u32, u32), total: u32) -> Vec<u8> {
+    let mut descriptor = vec![0u8; SECTOR];
+    descriptor[0] = kind;
+    descriptor[1..6].copy_from_slice(b"CD001");
+    descriptor[6] = 1;
+    if kind == 255 {
+        return descriptor;
+    }
+    descriptor[8..72].fill(b' ');
+    descriptor[8..13].copy_from_slice(b"LINUX");
+    descriptor[40..47].copy_from_slice(b"INSTALL");
+    descriptor[80..84].copy_from_slice(&total.to_le_bytes());
+    descriptor[84..88].copy_from_slice(&total.to_be_bytes());
+    if kind == 2 {
+        descriptor[88..91].copy_from_slice(b"%/E");
+    }
+    descriptor[128..130].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+    descriptor[130..132].copy_from_slice(&(SECTOR as u16).to_be_bytes());
+    descriptor[156..190].copy_from_slice(&record(&[0], extent, size, 0x02, &[]));
+    descriptor[318..446].fill(b' ');
+    descriptor[574..702].fill(b' ');
+    descriptor[574..580].copy_from_slice(b"TESTER");
+    descriptor
+}
+
+/// Writes a directory and everything below it, returning its extent and size.
+fn write_dir(image: &mut Vec<u8>, nodes: &[Node], tree: Tree, parent: Option<u32>) -> (u32, u32) {
+    let extent = (image.len() / SECTOR) as u32;
+    image.resize(image.len() + SECTOR, 0);
+    let parent = parent.unwrap_or(extent);
+
+    // The root's `.` record announces the system use sharing protocol
+    let mut dot_system_use = Vec::new();
+    if tree == Tree::RockRidge {
+        if parent == extent {
+            dot_system_use.extend_from_slice(&[b'S', b'P', 7, 1, 0xBE, 0xEF, 0]);
+        }
+        dot_system_use.extend(px(0o040755));
+    }
+    let mut records = record(&[0], extent, SECTOR as u32, 0x02, &dot_system_use);
+    records.extend(record(&[1], parent, SECTOR as u32, 0x02, &[]));
+
+    for node in nodes {
+        let (primary, long) = match node {
+            Node::Dir(primary, long, _)
+            | Node::File(primary, long, _)
+            | Node::Split(primary, long, _, _)
+            | Node::Link(primary, long, _) => (*primary, *long),
+        };
+        let identifier = match (tree, node) {
+            (Tree::Joliet, Node::Dir(..)) => utf16(long),
+            (Tree::Joliet, _) => utf16(&format!("{};1", long)),
+            (_, Node::Dir(..)) => primary.as_bytes().to_vec(),
+            _ => format!("{};1", primary).into_bytes(),
+        };
+
+        let mode = match node {
+            Node::Dir(..) => 0o040755,
+            Node::Link(..) => 0o120777,
+            _ => 0o100644,
+        };
+        let mut system_use = Vec::new();
+        if tree == Tree::RockRidge {
+            system_use.extend(px(mode));
+            system_use.extend([b'N', b'M', 5 + long.len() as u8, 1, 0]);
+            system_use.extend_from_slice(long.as_bytes());
+            system_use.extend([b'T', b'F', 12, 1, 0x02]);
+            system_use.extend(MODIFIED);
+        }
+
+        match node {
+            Node::Dir(_, _, children) => {
+                let (child, size) = write_dir(image, children, tree, Some(extent));
+                records.extend(record(&identifier, child, size, 0x02, &system_use));
+            }
+            Node::File(_, _, data) => {
+                let child = write_data(image, data);
+                records.extend(record(
+                    &identifier,
+                    child,
+                    data.len() as u32,
+                    0,
+                    &system_use,
+                ));
+            }
+            Node::Split(_, _, first, second) => {
+                let child = write_data(image, first);
+                records.extend(record(&identifier, child, first.len() as u32, 0x80, &[]));
+                let child = write_data(image, second);
+                let size = second.len() as u32;
+                records.extend(record(&identifier, child, size, 0, &system_use));
+            }
+            Node::Link(_, _, target) => {
+                if tree == Tree::RockRidge {
+                    let mut components = Vec::new();
+                    for component in target.split('/') {
+                        match component {
+                            ".." => components.extend([0x04, 0]),
+                            _ => {
+                                components.extend([0, component.len() as u8]);
+                                components.extend_from_slice(component.as_bytes());
+                            }
+                        }
+                    }
+                    system_use.extend([b'S', b'L', 5 + components.len() as u8, 1, 0]);
+                    system_use.extend(components);
+                }
+                records.extend(record(&identifier, 0, 0, 0, &system_use));
+            }
+        }
+    }
+
+    assert!(records.len() <= SECTOR, "Test directory too large");
+    let start = extent as usize * SECTOR;
+    image[start..start + records.len()].copy_from_slice(&records);
+    (extent, SECTOR as u32)
+}
+
+fn write_data(image: &mut Vec<u8>, data: &[u8]) -> u32 {
+    let extent = (image.len() / SECTOR) as u32;
+    image.extend_from_slice(data);
+    image.resize(image.len().next_multiple_of(SECTOR), 0);
+    extent
+}
+
+fn record(identifier: &[u8], extent: u32, size: u32, flags: u8, system_use: &[u8]) -> Vec<u8> {
+    let mut record = vec![0u8; 33];
+    record[2..6].copy_from_slice(&extent.to_le_bytes());
+    record[6..10].copy_from_slice(&extent.to_be_bytes());
+    record[10..14].copy_from_slice(&size.to_le_bytes());
+    record[14..18].copy_from_slice(&size.to_be_bytes());
+    record[18..25].copy_from_slice(&RECORDED);
+    record[25] = flags;
+    record[28] = 1;
+    record[31] = 1;
+    record[32] = identifier.len() as u8;
+    record.extend_from_slice(identifier);
+    if identifier.len().is_multiple_of(2) {
+        record.push(0);
+    }
+    record.extend_from_slice(system_use);
+    if record.len() % 2 == 1 {
+        record.push(0);
+    }
+    record[0] = record.len() as u8;
+    record
+}
+
+/// A Rock Ridge `PX` entry with the given mode.
+fn px(mode: u32) -> Vec<u8> {
+    let mut entry = vec![b'P', b'X', 36, 1];
+    for value in [mode, 1, 0, 0] {
+        entry.extend_from_slice(&value.to_le_bytes());
+        entry.extend_from_slice(&value.to_be_bytes());
+    }
+    entry
+}
+
+fn utf16(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+fn sample() -> Vec<Node> {
+    vec![
+        Node::Dir(
+            "DOCS",
+            "Documentation",
+            vec![Node::File("README.TXT", "Read me.txt", b"Read me first\n")],
+        ),
+        Node::File("HELLO.SH", "hello.sh", b"#!/bin/sh\necho hello\n"),
+        Node::File("GRUSSE.TXT", "grüße.txt", b"Hallo\n"),
+        Node::File("NOEXT.", "noext", b""),
+        Node::Link("LINK", "readme", "../Documentation/Read me.txt"),
+    ]
+}
+
+fn extract(data: &[u8]) -> Vec<ExtractedFile> {
+    ArchiveExtractor::new()
+        .extract(data, ArchiveFormat::Iso)
+        .expect("Failed to extract ISO image")
+}
+
+#[test]
+fn test_iso_rock_ridge() {
+    let data = image(&sample(), true, true);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Iso);
+    let files = extract(&data);
+
+    let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Documentation",
+            "Documentation/Read me.txt",
+            "hello.sh",
+            "grüße.txt",
+            "noext",
+            "readme"
+        ]
+    );
+
+    let docs = find(&files, "Documentation");
+    assert!(docs.is_directory);
+    assert_eq!(docs.metadata.mode, Some(0o755));
+
+    let hello = find(&files, "hello.sh");
+    assert_eq!(hello.data, b"#!/bin/sh\necho hello\n");
+    assert_eq!(hello.metadata.mode, Some(0o644));
+    assert_eq!(hello.metadata.mtime, Some(MODIFIED_MTIME));
+    assert_eq!(
+        find(&files, "Documentation/Read me.txt").data,
+        b"Read me first\n"
+    );
+
+    let link = find(&files, "readme");
+    assert!(link.data.is_empty());
+    assert_eq!(
+        link.metadata.link,
+        Some(EntryLink::Symbolic(
+            "../Documentation/Read me.txt".to_string()
+        ))
+    );
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Iso)
+        .expect("Failed to inspect ISO image")
+        .iso
+        .expect("Missing ISO information");
+    assert_eq!(info.volume_id, "INSTALL");
+    assert_eq!(info.system_id, "LINUX");
+    assert_eq!(info.application.as_deref(), Some("TESTER"));
+    assert_eq!(info.publisher, None);
+    assert_eq!(info.block_size, 2048);
+    assert_eq!(info.volume_size, data.len() as u64);
+    assert!(info.rock_ridge);
+    assert!(info.joliet);
+    assert!(!info.bootable);
+}
+
+#[test]
+fn test_iso_joliet() {
+    let data = image(&sample(), false, true);
+    let files = extract(&data);
+
+    let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Documentation",
+            "Documentation/Read me.txt",
+            "hello.sh",
+            "grüße.txt",
+            "noext",
+            "readme"
+        ]
+    );
+    let hello = find(&files, "hello.sh");
+    assert_eq!(hello.data, b"#!/bin/sh\necho hello\n");
+    assert_eq!(hello.metadata.mode, None);
+    assert_eq!(hello.metadata.mtime, Some(RECORDED_MTIME));
+    assert!(find(&files, "readme").metadata.link.is_none());
+}
+
+#[test]
+fn test_iso_primary_names() {
+    let mut nodes = sample();
+    nodes.push(Node::Split("BIG.BIN", "big.bin", &[1; 4096], &[2; 100]));
+    let data = image(&nodes, false, false);
+    let files = extract(&data);
+
+    let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "DOCS",
+            "DOCS/README.TXT",
+            "HELLO.SH",
+            "GRUSSE.TXT",
+            "NOEXT",
+            "LINK",
+            "BIG.BIN"
+        ]
+    );
+
+    let big = find(&files, "BIG.BIN");
+    assert_eq!(big.data.len(), 4196);
+    assert!(big.data[..4096].iter().all(|&b| b == 1));
+    assert!(big.data[4096..].iter().all(|&b| b == 2));
+}
+
+#[test]
+fn test_iso_extract_seekable() {
+    let data = image(&sample(), true, false);
+    let files = ArchiveExtractor::new()
+        .extract_seekable(Cursor::new(&data), ArchiveFormat::Iso)
+        .expect("Failed to extract ISO image");
+
+    let expected = extract(&data);
+    assert_eq!(files.len(), expected.len());
+    for (file, expected) in files.iter().zip(&expected) {
+        assert_eq!(file.path, expected.path);
+        assert_eq!(file.data, expected.data);
+    }
+}
+
+#[test]
+fn test_iso_verify() {
+    let data = image(&sample(), true, true);
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Iso)
+        .expect("Failed to verify ISO image");
+    assert!(report.is_ok());
+    assert_eq!(report.entries.len(), 6);
+
+    // The contents of the last file lie past the end of a truncated image
+    let mut nodes = sample();
+    nodes.push(Node::File("LAST.BIN", "last.bin", &[7; 3000]));
+    let data = image(&nodes, false, false);
+    let truncated = &data[..data.len() - SECTOR];
+
+    let report = ArchiveExtractor::new()
+        .verify(truncated, ArchiveFormat::Iso)
+        .expect("Failed to verify ISO image");
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, "LAST.BIN");
+    assert!(
+        ArchiveExtractor::new()
+            .extract(truncated, ArchiveFormat::Iso)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_iso_invalid() {
+    let mut data = image(&sample(), false, false);
+    // Without a terminator the descriptor set runs into the directories
+    data[17 * SECTOR] = 3;
+    data[17 * SECTOR + 1..18 * SECTOR].fill(0);
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Iso);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+
+    let result = ArchiveExtractor::new().extract(&data[..17 * SECTOR], ArchiveFormat::Iso);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_iso_size_limits() {
+    let data = image(&sample(), true, false);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(20)
+        .extract(&data, ArchiveFormat::Iso);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(30)
+        .extract(&data, ArchiveFormat::Iso);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_iso_overlapping_directories() {
+    let mut data = image(&sample(), false, false);
+    // Point DOCS at the root directory, extended by one sector, so that the
+    // root's records would be listed again below it
+    let root = 19 * SECTOR;
+    let name = data[root..root + SECTOR]
+        .windows(4)
+        .position(|window| window == b"DOCS")
+        .unwrap();
+    let record = root + name - 33;
+    data[record + 2..record + 6].copy_from_slice(&19u32.to_le_bytes());
+    data[record + 6..record + 10].copy_from_slice(&19u32.to_be_bytes());
+    let size = 2 * SECTOR as u32;
+    data[record + 10..record + 14].copy_from_slice(&size.to_le_bytes());
+    data[record + 14..record + 18].copy_from_slice(&size.to_be_bytes());
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Iso);
+    assert!(
+        matches!(&result, Err(ArchiveError::InvalidArchive(message)) if message.contains("overlapping")),
+        "{:?}",
+        result.map(|files| files.len())
+    );
+    let result = ArchiveExtractor::new().verify(&data, ArchiveFormat::Iso);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+}
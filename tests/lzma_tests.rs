@@ -0,0 +1,235 @@
+//! Tests for lzip and legacy LZMA extraction
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat};
+use common::find;
+use std::io::Cursor;
+
+/// Size of the `.lzma` header that lzma-rs writes before the LZMA stream.
+const LZMA_HEADER_SIZE: usize = 13;
+
+fn lzma(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    lzma_rs::lzma_compress(&mut Cursor::new(data), &mut output).unwrap();
+    output
+}
+
+/// Builds an lzip member from the LZMA stream lzma-rs writes, whose 8 MiB
+/// dictionary is coded as 23.
+fn lzip_member(data: &[u8]) -> Vec<u8> {
+    let compressed = lzma(data);
+    let stream = &compressed[LZMA_HEADER_SIZE..];
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+
+    let mut member = b"LZIP\x01\x17".to_vec();
+    member.extend_from_slice(stream);
+    member.extend_from_slice(&crc.sum().to_le_bytes());
+    member.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    member.extend_from_slice(&((6 + stream.len() + 20) as u64).to_le_bytes());
+    member
+}
+
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_lzip_single_file() {
+    let data = lzip_member(b"Hello, World!\n");
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Lz);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Lz)
+        .expect("Failed to extract lzip file");
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "data");
+    assert_eq!(files[0].data, b"Hello, World!\n");
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Lz)
+        .expect("Failed to verify lzip file");
+    assert!(report.is_ok());
+}
+
+#[test]
+fn test_lzip_multiple_members() {
+    let mut data = lzip_member(b"first member, ");
+    data.extend(lzip_member(b""));
+    data.extend(lzip_member(b"second member"));
+    // Trailing data after the last member is ignored
+    data.extend_from_slice(&[0u8; 16]);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Lz)
+        .expect("Failed to extract multi-member lzip file");
+    assert_eq!(files[0].data, b"first member, second member");
+}
+
+#[test]
+fn test_tar_lz() {
+    let archive = tar(&[("README", b"GNU hello\n"), ("src/hello.c", b"int main;\n")]);
+    let data = lzip_member(&archive);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::TarLz);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::TarLz)
+        .expect("Failed to extract tar.lz archive");
+    assert_eq!(files.len(), 2);
+    assert_eq!(find(&files, "README").data, b"GNU hello\n");
+    assert_eq!(find(&files, "src/hello.c").data, b"int main;\n");
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::TarLz)
+        .expect("Failed to inspect tar.lz archive");
+    assert!(info.tar.is_some());
+}
+
+#[test]
+fn test_lzma_and_tar_lzma() {
+    let data = lzma(b"Hello, World!\n");
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Lzma);
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Lzma)
+        .expect("Failed to extract lzma file");
+    assert_eq!(files[0].data, b"Hello, World!\n");
+
+    let data = lzma(&tar(&[("hello.txt", b"Hello, World!\n")]));
+    assert_eq!(
+        ArchiveFormat::detect(&data).unwrap(),
+        ArchiveFormat::TarLzma
+    );
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::TarLzma)
+        .expect("Failed to extract tar.lzma archive");
+    assert_eq!(find(&files, "hello.txt").data, b"Hello, World!\n");
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::TarLzma)
+        .expect("Failed to verify tar.lzma archive");
+    assert!(report.is_ok());
+}
+
+#[test]
+fn test_detect_past_the_first_dictionary_window() {
+    // Detection decodes with a smaller dictionary than the 8 MiB the streams
+    // declare and stops once it fills, long before the end of the payload
+    let payload: Vec<u8> = (0..1024 * 1024)
+        .map(|i: usize| (i * 7 + i / 251) as u8)
+        .collect();
+    let archive = tar(&[("large.bin", &payload)]);
+
+    let data = lzip_member(&archive);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::TarLz);
+    let data = lzma(&archive);
+    assert_eq!(
+        ArchiveFormat::detect(&data).unwrap(),
+        ArchiveFormat::TarLzma
+    );
+    let mut data = Vec::new();
+    lzma_rs::xz_compress(&mut Cursor::new(&archive), &mut data).unwrap();
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::TarXz);
+}
+
+#[test]
+fn test_lzip_trailer_mismatch() {
+    let member = lzip_member(b"Hello, World!\n");
+    let trailer = member.len() - 20;
+
+    for (offset, message) in [(0, "CRC"), (4, "holds"), (12, "corrupt")] {
+        let mut data = member.clone();
+        data[trailer + offset] ^= 0x01;
+
+        let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Lz);
+        assert!(
+            matches!(&result, Err(ArchiveError::InvalidArchive(e)) if e.contains(message)),
+            "Expected a '{}' error, got {:?}",
+            message,
+            result
+        );
+
+        let report = ArchiveExtractor::new()
+            .verify(&data, ArchiveFormat::Lz)
+            .expect("Failed to verify lzip file");
+        assert_eq!(report.failures().count(), 1);
+    }
+}
+
+#[test]
+fn test_lzip_truncated() {
+    let data = lzip_member(b"Hello, World!\n");
+    for length in [4, data.len() - 10] {
+        let result = ArchiveExtractor::new().extract(&data[..length], ArchiveFormat::Lz);
+        assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+    }
+
+    let mut data = data;
+    data[4] = 2;
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Lz);
+    assert!(matches!(result, Err(ArchiveError::UnsupportedFormat(_))));
+}
+
+#[test]
+fn test_lzip_window_limit() {
+    let mut data = lzip_member(b"Hello, World!\n");
+    // A second member declaring a 256 MiB dictionary
+    let mut second = lzip_member(b"more");
+    second[5] = 28;
+    data.extend(second);
+
+    for format in [ArchiveFormat::Lz, ArchiveFormat::TarLz] {
+        let result = ArchiveExtractor::new()
+            .with_max_window_size(1024 * 1024)
+            .extract(&data, format);
+        assert!(matches!(
+            result,
+            Err(ArchiveError::WindowTooLarge { size: 8388608, .. })
+        ));
+    }
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Lz);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::WindowTooLarge {
+            size: 268435456,
+            ..
+        })
+    ));
+
+    let result = ArchiveExtractor::new()
+        .with_max_window_size(1024 * 1024)
+        .extract(&lzma(b"Hello"), ArchiveFormat::Lzma);
+    assert!(matches!(result, Err(ArchiveError::WindowTooLarge { .. })));
+}
+
+#[test]
+fn test_lzip_size_limits() {
+    let data = lzip_member(&[0u8; 1000]);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Lz);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .verify(&data, ArchiveFormat::Lz);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let data = lzip_member(&tar(&[("a.bin", &[0u8; 1000]), ("b.bin", &[0u8; 1000])]));
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .verify(&data, ArchiveFormat::TarLz);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
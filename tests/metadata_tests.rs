@@ -0,0 +1,234 @@
+//! Tests for per-entry metadata (compressed size, CRC, method, encryption)
+
+mod common;
+
+use archive::{ArchiveExtractor, ArchiveFormat, SevenZBuilder};
+use common::{assert_contains_file, read_test_archive};
+
+const HELLO_CRC32: u32 = 0xb4e8_9e84;
+
+#[test]
+fn test_zip_entry_metadata() {
+    let data = read_test_archive("basic.zip");
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Zip)
+        .expect("Failed to extract basic.zip");
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    assert_eq!(hello.metadata.crc32, Some(HELLO_CRC32));
+    assert!(hello.metadata.compressed_size.is_some());
+    assert!(hello.metadata.compression_method.is_some());
+    assert!(!hello.metadata.encrypted);
+
+    // Info-ZIP stores extended timestamps (0x5455) for every entry
+    assert!(hello.metadata.extra_field_ids.contains(&0x5455));
+
+    let large = assert_contains_file(&files, "large-file.bin");
+    assert_eq!(
+        large.metadata.compression_method.as_deref(),
+        Some("Deflated")
+    );
+}
+
+#[test]
+fn test_zip_stored_entry_metadata() {
+    let data = read_test_archive("no-compression.zip");
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Zip)
+        .expect("Failed to extract no-compression.zip");
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    assert_eq!(hello.metadata.compression_method.as_deref(), Some("Stored"));
+    assert_eq!(
+        hello.metadata.compressed_size,
+        Some(hello.data.len() as u64)
+    );
+}
+
+#[test]
+fn test_7z_entry_metadata() {
+    let extractor = ArchiveExtractor::new();
+
+    let mut builder = SevenZBuilder::new();
+    builder
+        .add_file("hello.txt", b"Hello, World!\n".to_vec())
+        .unwrap();
+    let data = builder.finish().unwrap();
+    let files = extractor.extract(&data, ArchiveFormat::SevenZ).unwrap();
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    assert_eq!(hello.metadata.crc32, Some(HELLO_CRC32));
+    assert_eq!(hello.metadata.compression_method.as_deref(), Some("LZMA2"));
+    assert!(hello.metadata.compressed_size.is_some());
+    assert!(!hello.metadata.encrypted);
+
+    // Entries sharing a solid block have no individual compressed size
+    let mut builder = SevenZBuilder::new().with_solid(true);
+    builder.add_file("a.txt", b"a".to_vec()).unwrap();
+    builder.add_file("b.txt", b"b".to_vec()).unwrap();
+    let data = builder.finish().unwrap();
+    let files = extractor.extract(&data, ArchiveFormat::SevenZ).unwrap();
+
+    assert_eq!(files.len(), 2);
+    for file in &files {
+        assert!(file.metadata.compressed_size.is_none());
+        assert!(file.metadata.crc32.is_some());
+    }
+}
+
+#[test]
+fn test_single_file_metadata() {
+    let data = read_test_archive("hello.txt.gz");
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Gz)
+        .expect("Failed to decompress hello.txt.gz");
+
+    assert_eq!(files[0].metadata.compressed_size, Some(data.len() as u64));
+    assert_eq!(
+        files[0].metadata.compression_method.as_deref(),
+        Some("GZIP")
+    );
+}
+
+#[test]
+fn test_tar_entries_have_no_compression_metadata() {
+    let data = read_test_archive("archive.tar.gz");
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::TarGz)
+        .expect("Failed to extract archive.tar.gz");
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    assert_eq!(hello.metadata.compressed_size, None);
+    assert_eq!(hello.metadata.crc32, None);
+    assert_eq!(hello.metadata.compression_method, None);
+}
+
+/// `hello.txt` stored with ZipCrypto and the password "secret", by Info-ZIP's
+/// `zip -P secret`
+const ZIPCRYPTO_ZIP: &[u8] = &[
+    0x50, 0x4b, 0x03, 0x04, 0x0a, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x58, 0x84, 0x9e,
+    0xe8, 0xb4, 0x1a, 0x00, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x68, 0x65,
+    0x6c, 0x6c, 0x6f, 0x2e, 0x74, 0x78, 0x74, 0x5f, 0xd3, 0x36, 0x3c, 0xa3, 0x1e, 0x34, 0xd7, 0x4d,
+    0x55, 0x31, 0xb3, 0x62, 0x17, 0x86, 0x8c, 0xa5, 0xa4, 0xd4, 0xb0, 0x25, 0x74, 0x09, 0xed, 0xc5,
+    0x24, 0x50, 0x4b, 0x07, 0x08, 0x84, 0x9e, 0xe8, 0xb4, 0x1a, 0x00, 0x00, 0x00, 0x0e, 0x00, 0x00,
+    0x00, 0x50, 0x4b, 0x01, 0x02, 0x1e, 0x03, 0x0a, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21,
+    0x58, 0x84, 0x9e, 0xe8, 0xb4, 0x1a, 0x00, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xa4, 0x81, 0x00, 0x00, 0x00, 0x00, 0x68,
+    0x65, 0x6c, 0x6c, 0x6f, 0x2e, 0x74, 0x78, 0x74, 0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x37, 0x00, 0x00, 0x00, 0x51, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn test_zip_encrypted_entry_metadata() {
+    let files = ArchiveExtractor::new()
+        .with_password("secret")
+        .extract(ZIPCRYPTO_ZIP, ArchiveFormat::Zip)
+        .expect("Failed to extract encrypted zip");
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    assert_eq!(hello.data, b"Hello, World!\n");
+    assert!(hello.metadata.encrypted);
+
+    let result = ArchiveExtractor::new().extract(ZIPCRYPTO_ZIP, ArchiveFormat::Zip);
+    assert!(
+        result.is_err(),
+        "Expected encrypted entry to need a password"
+    );
+}
+
+#[test]
+fn test_zip_password_verify_and_inspect() {
+    let report = ArchiveExtractor::new()
+        .with_password("secret")
+        .verify(ZIPCRYPTO_ZIP, ArchiveFormat::Zip)
+        .expect("Failed to verify encrypted zip");
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!(report.entries[0].size, 14);
+
+    for extractor in [
+        ArchiveExtractor::new(),
+        ArchiveExtractor::new().with_password("wrong"),
+    ] {
+        let report = extractor
+            .verify(ZIPCRYPTO_ZIP, ArchiveFormat::Zip)
+            .expect("Failed to verify encrypted zip");
+        assert!(report.failures().any(|entry| entry.path == "hello.txt"));
+    }
+
+    let info = ArchiveExtractor::new()
+        .with_password("secret")
+        .inspect(ZIPCRYPTO_ZIP, ArchiveFormat::Zip)
+        .expect("Failed to inspect encrypted zip");
+    assert_eq!(info.zip.unwrap().entry_count, 1);
+}
+
+#[test]
+fn test_7z_encrypted_entry_metadata() {
+    let mut builder = SevenZBuilder::new()
+        .with_password("secret")
+        .with_header_encryption(false);
+    builder
+        .add_file("hello.txt", b"Hello, World!\n".to_vec())
+        .unwrap();
+    let data = builder.finish().unwrap();
+
+    let files = ArchiveExtractor::new()
+        .with_password("secret")
+        .extract(&data, ArchiveFormat::SevenZ)
+        .expect("Failed to extract encrypted 7z archive");
+
+    let hello = assert_contains_file(&files, "hello.txt");
+    assert_eq!(hello.data, b"Hello, World!\n");
+    assert_eq!(hello.metadata.crc32, Some(HELLO_CRC32));
+    assert!(hello.metadata.encrypted);
+}
+
+#[test]
+fn test_7z_password_verify_and_inspect() {
+    let mut builder = SevenZBuilder::new()
+        .with_password("secret")
+        .with_header_encryption(true);
+    // Enough names for the header to be worth compressing and encrypting
+    for i in 0..32 {
+        builder
+            .add_file(&format!("file-{:02}.txt", i), b"Hello, World!\n".to_vec())
+            .unwrap();
+    }
+    let data = builder.finish().unwrap();
+    let extractor = ArchiveExtractor::new().with_password("secret");
+
+    let files = extractor
+        .extract(&data, ArchiveFormat::SevenZ)
+        .expect("Failed to extract encrypted 7z archive");
+    assert_eq!(files.len(), 32);
+    assert!(files.iter().all(|file| file.metadata.encrypted));
+
+    let report = extractor
+        .verify(&data, ArchiveFormat::SevenZ)
+        .expect("Failed to verify encrypted 7z archive");
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!(report.entries.len(), 32);
+
+    let info = extractor
+        .inspect(&data, ArchiveFormat::SevenZ)
+        .expect("Failed to inspect encrypted 7z archive")
+        .sevenz
+        .unwrap();
+    assert!(info.encrypted);
+    assert!(info.header_encrypted);
+    assert_eq!(info.entry_count, 32);
+
+    // Without the password, the header can't be read
+    assert!(
+        ArchiveExtractor::new()
+            .verify(&data, ArchiveFormat::SevenZ)
+            .is_err()
+    );
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::SevenZ)
+        .unwrap()
+        .sevenz
+        .unwrap();
+    assert!(info.header_encrypted);
+    assert_eq!(info.entry_count, 0);
+}
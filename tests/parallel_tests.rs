@@ -0,0 +1,260 @@
+//! Tests for parallel extraction with the `rayon` feature
+#![cfg(feature = "rayon")]
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, HashAlgorithm, SevenZBuilder};
+use common::read_test_archive;
+use std::io::{Cursor, Write};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Creates a TAR archive with `count` distinct files.
+fn many_file_tar(count: usize) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for i in 0..count {
+        let contents = format!("contents of file {}\n", i).repeat(100);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("file-{:03}.txt", i),
+                contents.as_bytes(),
+            )
+            .unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+/// Compresses `data` in `parts` pieces, as parallel compressors do.
+fn compress_in_parts(data: &[u8], parts: usize, format: ArchiveFormat) -> Vec<u8> {
+    if matches!(format, ArchiveFormat::Xz | ArchiveFormat::TarXz) {
+        return multi_block_xz(data, parts);
+    }
+
+    let mut output = Vec::new();
+    for (i, chunk) in data.chunks(data.len().div_ceil(parts)).enumerate() {
+        match format {
+            ArchiveFormat::Zst | ArchiveFormat::TarZst => {
+                output.extend(zstd::encode_all(chunk, 3).unwrap());
+            }
+            ArchiveFormat::Xz | ArchiveFormat::TarXz => unreachable!(),
+            ArchiveFormat::Gz | ArchiveFormat::TarGz => {
+                // A BGZF member stores its size less one in a `BC` subfield
+                let mut builder = flate2::GzBuilder::new().extra(vec![b'B', b'C', 2, 0, 0, 0]);
+                if i == 0 {
+                    builder = builder.filename("data.txt");
+                }
+                let mut encoder = builder.write(Vec::new(), flate2::Compression::default());
+                encoder.write_all(chunk).unwrap();
+                let mut member = encoder.finish().unwrap();
+                let size = (member.len() as u16 - 1).to_le_bytes();
+                member[16..18].copy_from_slice(&size);
+                output.extend(member);
+            }
+            _ => unreachable!(),
+        }
+    }
+    output
+}
+
+/// Compresses `data` into one XZ stream of `parts` blocks, as `xz -T` does,
+/// by joining the blocks of single-block streams under a combined index.
+fn multi_block_xz(data: &[u8], parts: usize) -> Vec<u8> {
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = flate2::Crc::new();
+        crc.update(data);
+        crc.sum()
+    }
+
+    let mut header = Vec::new();
+    let mut blocks = Vec::new();
+    let mut records = Vec::new();
+    for chunk in data.chunks(data.len().div_ceil(parts)) {
+        let mut stream = Vec::new();
+        lzma_rs::xz_compress(&mut &chunk[..], &mut stream).unwrap();
+        let footer = &stream[stream.len() - 12..];
+        let index_size = (u32::from_le_bytes(footer[4..8].try_into().unwrap()) as usize + 1) * 4;
+        let index_start = stream.len() - 12 - index_size;
+
+        header = stream[..12].to_vec();
+        blocks.extend_from_slice(&stream[12..index_start]);
+        // The record follows the index indicator and the record count of
+        // one, and holds two variable-length integers
+        let index = &stream[index_start + 2..];
+        let second_end = index
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| *byte & 0x80 == 0)
+            .nth(1)
+            .unwrap()
+            .0;
+        records.extend_from_slice(&index[..=second_end]);
+    }
+
+    let mut index = vec![0, parts as u8];
+    index.extend(records);
+    index.resize(index.len().next_multiple_of(4), 0);
+    index.extend(crc32(&index).to_le_bytes());
+
+    let mut fields = ((index.len() / 4 - 1) as u32).to_le_bytes().to_vec();
+    fields.extend_from_slice(&header[6..8]);
+    let mut output = header;
+    output.extend(blocks);
+    output.extend(index);
+    output.extend(crc32(&fields).to_le_bytes());
+    output.extend(fields);
+    output.extend(b"YZ");
+    output
+}
+
+/// Creates a ZIP archive with `count` distinct, compressible files.
+fn many_file_zip(count: usize) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for i in 0..count {
+        writer
+            .start_file(format!("file-{:03}.txt", i), options)
+            .unwrap();
+        writer
+            .write_all(format!("contents of file {}\n", i).repeat(100).as_bytes())
+            .unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_parallel_matches_serial() {
+    let many = many_file_zip(64);
+    let mut builder = SevenZBuilder::new();
+    for i in 0..16 {
+        builder
+            .add_file(&format!("dir/file-{:02}.txt", i), vec![i as u8; 1000 + i])
+            .unwrap();
+    }
+    builder.add_directory("empty").unwrap();
+    let sevenz = builder.finish().unwrap();
+
+    let archives = [
+        (read_test_archive("basic.zip"), ArchiveFormat::Zip),
+        (many, ArchiveFormat::Zip),
+        (read_test_archive("archive.7z"), ArchiveFormat::SevenZ),
+        (sevenz, ArchiveFormat::SevenZ),
+    ];
+    let serial = ArchiveExtractor::new().with_hashes([HashAlgorithm::Sha256]);
+    let parallel = serial.clone().with_parallel(true);
+
+    for (data, format) in archives {
+        let expected = serial.extract(&data, format).unwrap();
+        let files = parallel.extract(&data, format).unwrap();
+
+        assert_eq!(files.len(), expected.len());
+        for (file, expected) in files.iter().zip(&expected) {
+            assert_eq!(file.path, expected.path);
+            assert_eq!(file.data, expected.data);
+            assert_eq!(file.digests, expected.digests);
+            assert_eq!(file.metadata, expected.metadata);
+        }
+    }
+}
+
+#[test]
+fn test_parallel_enforces_total_size() {
+    // 64 files of about 2 KB each
+    let data = many_file_zip(64);
+    let extractor = ArchiveExtractor::new()
+        .with_parallel(true)
+        .with_max_total_size(100 * 1024);
+
+    let result = extractor.extract(&data, ArchiveFormat::Zip);
+
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { size, .. }) if size > 100 * 1024
+    ));
+}
+
+#[test]
+fn test_parallel_progress_and_cancellation() {
+    let data = many_file_zip(64);
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&calls);
+    let extractor = ArchiveExtractor::new()
+        .with_parallel(true)
+        .with_progress(move |progress| {
+            counted.fetch_add(1, Ordering::Relaxed);
+            if progress.entries_processed >= 10 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+    let result = extractor.extract(&data, ArchiveFormat::Zip);
+
+    assert!(matches!(result, Err(ArchiveError::Cancelled)));
+    assert!(calls.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn test_parallel_streams_match_serial() {
+    let tar = many_file_tar(64);
+    let contents = "some text to compress\n".repeat(5000);
+    let serial = ArchiveExtractor::new().with_hashes([HashAlgorithm::Sha256]);
+    let parallel = serial.clone().with_parallel(true);
+
+    for format in [
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarXz,
+        ArchiveFormat::TarZst,
+        ArchiveFormat::Gz,
+        ArchiveFormat::Xz,
+        ArchiveFormat::Zst,
+    ] {
+        let is_tar = matches!(
+            format,
+            ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::TarZst
+        );
+        let input = if is_tar { &tar } else { contents.as_bytes() };
+        let data = compress_in_parts(input, 4, format);
+
+        let expected = serial.extract(&data, format).unwrap();
+        let files = parallel.extract(&data, format).unwrap();
+
+        assert_eq!(files.len(), expected.len(), "{}", format);
+        for (file, expected) in files.iter().zip(&expected) {
+            assert_eq!(file.path, expected.path, "{}", format);
+            assert_eq!(file.data, expected.data, "{}: {}", format, file.path);
+            assert_eq!(file.digests, expected.digests, "{}", format);
+            assert_eq!(file.metadata, expected.metadata, "{}", format);
+        }
+        if !is_tar {
+            assert_eq!(files[0].data, contents.as_bytes(), "{}", format);
+        }
+    }
+}
+
+#[test]
+fn test_parallel_streams_enforce_limits() {
+    let contents = vec![b'a'; 64 * 1024];
+    let extractor = ArchiveExtractor::new()
+        .with_parallel(true)
+        .with_max_file_size(16 * 1024)
+        .with_max_total_size(16 * 1024);
+
+    let data = compress_in_parts(&contents, 4, ArchiveFormat::Zst);
+    let result = extractor.extract(&data, ArchiveFormat::Zst);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let data = compress_in_parts(&many_file_tar(64), 4, ArchiveFormat::TarXz);
+    let result = extractor.extract(&data, ArchiveFormat::TarXz);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { size, .. }) if size > 16 * 1024
+    ));
+}
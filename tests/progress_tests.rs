@@ -0,0 +1,139 @@
+//! Tests for progress reporting and cancellation
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, CancellationToken, Progress};
+use common::read_test_archive;
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+
+/// A progress update with an owned path, so it can outlive the callback
+#[derive(Debug, Clone)]
+struct Update {
+    entries_processed: usize,
+    bytes_in: u64,
+    bytes_out: u64,
+    current_path: String,
+}
+
+impl From<&Progress<'_>> for Update {
+    fn from(progress: &Progress<'_>) -> Self {
+        Self {
+            entries_processed: progress.entries_processed,
+            bytes_in: progress.bytes_in,
+            bytes_out: progress.bytes_out,
+            current_path: progress.current_path.to_string(),
+        }
+    }
+}
+
+fn recording_extractor() -> (ArchiveExtractor, Arc<Mutex<Vec<Update>>>) {
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&updates);
+    let extractor = ArchiveExtractor::new().with_progress(move |progress| {
+        recorded.lock().unwrap().push(progress.into());
+        ControlFlow::Continue(())
+    });
+    (extractor, updates)
+}
+
+#[test]
+fn test_progress_across_formats() {
+    let fixtures = [
+        ("basic.zip", ArchiveFormat::Zip),
+        ("archive.tar", ArchiveFormat::Tar),
+        ("archive.tar.gz", ArchiveFormat::TarGz),
+        ("archive.tar.xz", ArchiveFormat::TarXz),
+        ("archive.7z", ArchiveFormat::SevenZ),
+        ("archive.ar", ArchiveFormat::Ar),
+        ("hello.txt.gz", ArchiveFormat::Gz),
+        ("hello.txt.zst", ArchiveFormat::Zst),
+    ];
+
+    for (name, format) in fixtures {
+        let (extractor, updates) = recording_extractor();
+        let data = read_test_archive(name);
+        let files = extractor
+            .extract(&data, format)
+            .unwrap_or_else(|e| panic!("Failed to extract {}: {}", name, e));
+
+        let updates = updates.lock().unwrap();
+        let last = updates.last().expect("No progress reported");
+        assert_eq!(last.entries_processed, files.len(), "{}", name);
+        let total: usize = files.iter().map(|f| f.data.len()).sum();
+        assert_eq!(last.bytes_out, total as u64, "{}", name);
+        assert!(last.bytes_in > 0, "{}", name);
+
+        // Counters never go backwards
+        for pair in updates.windows(2) {
+            assert!(pair[0].entries_processed <= pair[1].entries_processed);
+            assert!(pair[0].bytes_in <= pair[1].bytes_in);
+            assert!(pair[0].bytes_out <= pair[1].bytes_out);
+        }
+
+        for file in &files {
+            assert!(
+                updates.iter().any(|u| u.current_path == file.path),
+                "{}: {} never reported",
+                name,
+                file.path
+            );
+        }
+    }
+}
+
+#[test]
+fn test_callback_can_stop_extraction() {
+    let data = read_test_archive("archive.tar.gz");
+    let extractor = ArchiveExtractor::new().with_progress(|progress| {
+        if progress.entries_processed >= 1 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+
+    let result = extractor.extract(&data, ArchiveFormat::TarGz);
+
+    assert!(matches!(result, Err(ArchiveError::Cancelled)));
+}
+
+#[test]
+fn test_cancelled_token_stops_extraction() {
+    let token = CancellationToken::new();
+    let extractor = ArchiveExtractor::new().with_cancellation(token.clone());
+    let data = read_test_archive("archive.7z");
+
+    assert!(extractor.extract(&data, ArchiveFormat::SevenZ).is_ok());
+
+    token.cancel();
+    assert!(token.is_cancelled());
+    for (name, format) in [
+        ("archive.7z", ArchiveFormat::SevenZ),
+        ("basic.zip", ArchiveFormat::Zip),
+        ("hello.txt.xz", ArchiveFormat::Xz),
+    ] {
+        let data = read_test_archive(name);
+        let result = extractor.extract(&data, format);
+        assert!(matches!(result, Err(ArchiveError::Cancelled)), "{}", name);
+    }
+}
+
+#[test]
+fn test_cancel_during_extraction() {
+    let token = CancellationToken::new();
+    let trigger = token.clone();
+    let extractor = ArchiveExtractor::new()
+        .with_cancellation(token)
+        .with_progress(move |progress| {
+            if progress.bytes_out > 0 {
+                trigger.cancel();
+            }
+            ControlFlow::Continue(())
+        });
+    let data = read_test_archive("basic.zip");
+
+    let result = extractor.extract(&data, ArchiveFormat::Zip);
+
+    assert!(matches!(result, Err(ArchiveError::Cancelled)));
+}
@@ -0,0 +1,363 @@
+//! Tests for RAR archive extraction
+//!
+//! No RAR archiver is available to generate fixtures, so archives are
+//! written with the `rars` crate.
+#![cfg(feature = "rar")]
+
+mod common;
+
+use archive::info::RarVersion;
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, EntryLink};
+use common::find;
+use rars::{ArchiveVersion, Builder};
+
+const MTIME: u32 = 1_700_000_000;
+
+/// 2023-11-14 22:13:20 as an MS-DOS timestamp, which RAR versions before 5.0
+/// store in local time.
+const DOS_MTIME: u32 = 0x576E_B1AA;
+
+fn binary_data() -> Vec<u8> {
+    (0..=255u8).cycle().take(100_000).collect()
+}
+
+fn build_sample(builder: Builder) -> Vec<u8> {
+    let mut builder = builder;
+    let mtime = if builder.format() == ArchiveVersion::Rar50 {
+        MTIME
+    } else {
+        DOS_MTIME
+    };
+    builder
+        .add_directory(b"docs".to_vec(), Some(mtime), Some(0o755))
+        .unwrap();
+    builder
+        .add_bytes(
+            b"docs/hello.txt".to_vec(),
+            b"Hello, World!\n".to_vec(),
+            Some(mtime),
+            Some(0o644),
+        )
+        .unwrap();
+    builder
+        .add_bytes(
+            b"binary.bin".to_vec(),
+            binary_data(),
+            Some(mtime),
+            Some(0o600),
+        )
+        .unwrap();
+    builder.to_bytes().expect("Failed to build RAR archive")
+}
+
+#[test]
+fn test_rar_round_trip() {
+    for version in [ArchiveVersion::Rar29, ArchiveVersion::Rar50] {
+        for solid in [false, true] {
+            let data = build_sample(Builder::new(version).solid(solid));
+            let files = ArchiveExtractor::new()
+                .extract(&data, ArchiveFormat::Rar)
+                .unwrap_or_else(|e| panic!("Failed to extract {:?} archive: {}", version, e));
+
+            assert_eq!(files.len(), 3);
+            assert!(files.iter().any(|f| f.path == "docs" && f.is_directory));
+            let hello = find(&files, "docs/hello.txt");
+            assert_eq!(hello.data, b"Hello, World!\n");
+            assert_eq!(hello.metadata.mode, Some(0o644));
+            if version == ArchiveVersion::Rar50 {
+                assert_eq!(hello.metadata.mtime, Some(u64::from(MTIME)));
+            } else {
+                assert!(hello.metadata.mtime.is_some());
+            }
+            assert_eq!(hello.metadata.crc32, Some(crc32(b"Hello, World!\n")));
+            let binary = find(&files, "binary.bin");
+            assert_eq!(binary.data, binary_data());
+            assert!(binary.metadata.compressed_size.unwrap() < 100_000);
+        }
+    }
+}
+
+#[test]
+fn test_rar_symlinks() {
+    for version in [ArchiveVersion::Rar29, ArchiveVersion::Rar50] {
+        let mut builder = Builder::new(version);
+        builder
+            .add_bytes(
+                b"target.txt".to_vec(),
+                b"data".to_vec(),
+                Some(MTIME),
+                Some(0o644),
+            )
+            .unwrap();
+        builder
+            .add_unix_symlink(
+                b"link".to_vec(),
+                b"target.txt".to_vec(),
+                false,
+                Some(MTIME),
+                Some(0o777),
+            )
+            .unwrap();
+        let data = builder.to_bytes().unwrap();
+
+        let files = ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::Rar)
+            .expect("Failed to extract RAR archive with a symlink");
+        let link = find(&files, "link");
+        assert_eq!(
+            link.metadata.link,
+            Some(EntryLink::Symbolic("target.txt".to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_rar_encrypted_entry() {
+    for version in [ArchiveVersion::Rar29, ArchiveVersion::Rar50] {
+        let builder = Builder::new(version).password(Some(b"secret".to_vec()));
+        let data = build_sample(builder);
+
+        let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Rar);
+        match result {
+            Err(ArchiveError::UnsupportedFormat(message)) => {
+                assert!(message.contains("encrypted"), "{}", message);
+            }
+            other => panic!("Expected UnsupportedFormat, got {:?}", other),
+        }
+
+        let info = ArchiveExtractor::new()
+            .inspect(&data, ArchiveFormat::Rar)
+            .expect("Failed to inspect encrypted RAR archive");
+        let rar = info.rar.expect("Missing RAR info");
+        assert!(rar.encrypted);
+        assert!(!rar.header_encrypted);
+        assert_eq!(rar.entry_count, 3);
+
+        let report = ArchiveExtractor::new()
+            .verify(&data, ArchiveFormat::Rar)
+            .expect("Failed to verify encrypted RAR archive");
+        assert!(!report.is_ok());
+        assert!(report.failures().any(|entry| entry.path == "binary.bin"));
+    }
+}
+
+#[test]
+fn test_rar_encrypted_headers() {
+    let builder = Builder::new(ArchiveVersion::Rar50)
+        .password(Some(b"secret".to_vec()))
+        .header_encryption(true);
+    let data = build_sample(builder);
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Rar);
+    assert!(
+        matches!(result, Err(ArchiveError::UnsupportedFormat(_))),
+        "Expected UnsupportedFormat, got {:?}",
+        result
+    );
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Rar)
+        .expect("Failed to inspect RAR archive with encrypted headers");
+    let rar = info.rar.expect("Missing RAR info");
+    assert_eq!(rar.version, RarVersion::Rar5);
+    assert!(rar.encrypted);
+    assert!(rar.header_encrypted);
+}
+
+#[test]
+fn test_rar_password() {
+    let cases = [
+        (ArchiveVersion::Rar29, false),
+        (ArchiveVersion::Rar50, false),
+        (ArchiveVersion::Rar50, true),
+    ];
+    for (version, header_encrypted) in cases {
+        let builder = Builder::new(version)
+            .password(Some(b"secret".to_vec()))
+            .header_encryption(header_encrypted);
+        let data = build_sample(builder);
+        let extractor = ArchiveExtractor::new().with_password("secret");
+
+        let files = extractor
+            .extract(&data, ArchiveFormat::Rar)
+            .expect("Failed to extract encrypted RAR archive");
+        assert_eq!(files.len(), 3);
+        let hello = find(&files, "docs/hello.txt");
+        assert_eq!(hello.data, b"Hello, World!\n");
+        assert!(hello.metadata.encrypted);
+        assert_eq!(find(&files, "binary.bin").data, binary_data());
+
+        let report = extractor
+            .verify(&data, ArchiveFormat::Rar)
+            .expect("Failed to verify encrypted RAR archive");
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.entries.len(), 3);
+
+        let rar = extractor
+            .inspect(&data, ArchiveFormat::Rar)
+            .expect("Failed to inspect encrypted RAR archive")
+            .rar
+            .expect("Missing RAR info");
+        assert!(rar.encrypted);
+        assert_eq!(rar.header_encrypted, header_encrypted);
+        assert_eq!(rar.entry_count, 3);
+
+        let wrong = ArchiveExtractor::new().with_password("wrong");
+        assert!(wrong.extract(&data, ArchiveFormat::Rar).is_err());
+        assert!(
+            !wrong
+                .verify(&data, ArchiveFormat::Rar)
+                .is_ok_and(|report| report.is_ok())
+        );
+    }
+}
+
+#[test]
+fn test_rar_multi_volume() {
+    let mut builder = Builder::new(ArchiveVersion::Rar50).volume_size(Some(20_000));
+    builder
+        .add_bytes(b"big.bin".to_vec(), random_data(60_000), Some(MTIME), None)
+        .unwrap();
+    let volumes = builder
+        .build_volumes(None)
+        .expect("Failed to build volumes");
+    assert!(volumes.len() > 1);
+
+    let info = ArchiveExtractor::new()
+        .inspect(&volumes[0], ArchiveFormat::Rar)
+        .expect("Failed to inspect first volume");
+    let rar = info.rar.expect("Missing RAR info");
+    assert!(rar.multi_volume);
+    assert_eq!(rar.volume_number, Some(0));
+
+    let info = ArchiveExtractor::new()
+        .inspect(&volumes[1], ArchiveFormat::Rar)
+        .expect("Failed to inspect second volume");
+    assert_eq!(info.rar.unwrap().volume_number, Some(1));
+
+    let result = ArchiveExtractor::new().extract(&volumes[0], ArchiveFormat::Rar);
+    match result {
+        Err(ArchiveError::UnsupportedFormat(message)) => {
+            assert!(message.contains("multi-volume"), "{}", message);
+        }
+        other => panic!("Expected UnsupportedFormat, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_rar_inspect() {
+    let data = build_sample(Builder::new(ArchiveVersion::Rar40).solid(true));
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Rar)
+        .expect("Failed to inspect RAR archive");
+
+    let rar = info.rar.expect("Missing RAR info");
+    assert_eq!(rar.version, RarVersion::Rar4);
+    assert!(rar.solid);
+    assert!(!rar.multi_volume);
+    assert_eq!(rar.volume_number, None);
+    assert!(!rar.encrypted);
+    assert_eq!(rar.entry_count, 3);
+}
+
+#[test]
+fn test_rar_size_limits() {
+    for solid in [false, true] {
+        let data = build_sample(Builder::new(ArchiveVersion::Rar50).solid(solid));
+
+        let result = ArchiveExtractor::new()
+            .with_max_file_size(50_000)
+            .extract(&data, ArchiveFormat::Rar);
+        assert!(
+            matches!(result, Err(ArchiveError::FileTooLarge { .. })),
+            "Expected FileTooLarge, got {:?}",
+            result
+        );
+
+        let result = ArchiveExtractor::new()
+            .with_max_total_size(100_005)
+            .extract(&data, ArchiveFormat::Rar);
+        assert!(
+            matches!(result, Err(ArchiveError::TotalSizeTooLarge { .. })),
+            "Expected TotalSizeTooLarge, got {:?}",
+            result
+        );
+
+        let result = ArchiveExtractor::new()
+            .with_max_file_size(50_000)
+            .verify(&data, ArchiveFormat::Rar);
+        assert!(
+            matches!(result, Err(ArchiveError::FileTooLarge { .. })),
+            "Expected FileTooLarge, got {:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn test_rar_verify() {
+    let data = build_sample(Builder::new(ArchiveVersion::Rar50));
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Rar)
+        .expect("Failed to verify RAR archive");
+    assert!(report.is_ok(), "{:?}", report);
+    assert_eq!(report.entries.len(), 3);
+
+    // Flip a byte of the stored data of the last entry
+    let mut corrupt = build_sample(Builder::new(ArchiveVersion::Rar50).store(true));
+    let position = corrupt
+        .windows(4)
+        .rposition(|window| window == [0, 1, 2, 3])
+        .unwrap();
+    corrupt[position + 4] ^= 0xFF;
+
+    let report = ArchiveExtractor::new()
+        .verify(&corrupt, ArchiveFormat::Rar)
+        .expect("Failed to verify corrupt RAR archive");
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, "binary.bin");
+
+    let result = ArchiveExtractor::new().extract(&corrupt, ArchiveFormat::Rar);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+}
+
+#[test]
+fn test_rar_detect() {
+    for version in [
+        ArchiveVersion::Rar14,
+        ArchiveVersion::Rar29,
+        ArchiveVersion::Rar50,
+    ] {
+        let mut builder = Builder::new(version);
+        builder
+            .add_bytes(b"hello.txt".to_vec(), b"Hello".to_vec(), Some(MTIME), None)
+            .unwrap();
+        let data = builder.to_bytes().unwrap();
+
+        assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Rar);
+        let files = ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::Rar)
+            .unwrap_or_else(|e| panic!("Failed to extract {:?} archive: {}", version, e));
+        assert_eq!(find(&files, "hello.txt").data, b"Hello");
+    }
+}
+
+/// Incompressible data, so that volumes split it at the expected sizes.
+fn random_data(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
@@ -0,0 +1,320 @@
+//! Tests for RPM package extraction and inspection
+
+use archive::info::{RpmDependency, RpmInfo};
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, EntryLink, HashAlgorithm};
+use std::io::Write;
+
+const HELLO: &[u8] = b"#!/bin/sh\necho hello\n";
+const HELLO_SHA256: &str = "bfdeaeb08cffb6a36438bcd12dda25417e3cdd36f1e7e482a2849d539225288b";
+
+/// A tag value in a test header.
+enum Value {
+    Int16(Vec<u16>),
+    Int32(Vec<u32>),
+    String(&'static str),
+    StringArray(Vec<&'static str>),
+}
+
+/// Writes a header structure, aligning integers as `rpmbuild` does.
+fn header(tags: &[(u32, Value)]) -> Vec<u8> {
+    let mut index = Vec::new();
+    let mut store = Vec::new();
+    for (tag, value) in tags {
+        let (kind, alignment, count) = match value {
+            Value::Int16(values) => (3u32, 2, values.len()),
+            Value::Int32(values) => (4, 4, values.len()),
+            Value::String(_) => (6, 1, 1),
+            Value::StringArray(values) => (8, 1, values.len()),
+        };
+        store.resize(store.len().next_multiple_of(alignment), 0);
+        for field in [*tag, kind, store.len() as u32, count as u32] {
+            index.extend_from_slice(&field.to_be_bytes());
+        }
+        match value {
+            Value::Int16(values) => values
+                .iter()
+                .for_each(|v| store.extend_from_slice(&v.to_be_bytes())),
+            Value::Int32(values) => values
+                .iter()
+                .for_each(|v| store.extend_from_slice(&v.to_be_bytes())),
+            Value::String(value) => store.extend_from_slice(format!("{}\0", value).as_bytes()),
+            Value::StringArray(values) => values
+                .iter()
+                .for_each(|v| store.extend_from_slice(format!("{}\0", v).as_bytes())),
+        }
+    }
+
+    let mut header = vec![0x8E, 0xAD, 0xE8, 0x01, 0, 0, 0, 0];
+    header.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    header.extend_from_slice(&(store.len() as u32).to_be_bytes());
+    header.extend(index);
+    header.extend(store);
+    header
+}
+
+/// Writes a newc cpio archive of `(name, mode, data)` entries.
+fn newc(entries: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let trailer: (&str, u32, &[u8]) = ("TRAILER!!!", 0, b"");
+    for (ino, (name, mode, data)) in entries.iter().chain([&trailer]).enumerate() {
+        let fields = [
+            ino as u32,
+            *mode,
+            0,
+            0,
+            1,
+            1_700_000_000,
+            data.len() as u32,
+            0,
+            0,
+            0,
+            0,
+            name.len() as u32 + 1,
+            0,
+        ];
+        archive.extend_from_slice(b"070701");
+        for field in fields {
+            archive.extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        archive.extend_from_slice(name.as_bytes());
+        archive.push(0);
+        archive.resize(archive.len().next_multiple_of(4), 0);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len().next_multiple_of(4), 0);
+    }
+    archive
+}
+
+fn compress(payload: &[u8], compressor: &str) -> Vec<u8> {
+    match compressor {
+        "gzip" => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(payload).unwrap();
+            encoder.finish().unwrap()
+        }
+        "bzip2" => {
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(payload).unwrap();
+            encoder.finish().unwrap()
+        }
+        "xz" => {
+            let mut output = Vec::new();
+            lzma_rs::xz_compress(&mut &payload[..], &mut output).unwrap();
+            output
+        }
+        "lzma" => {
+            let mut output = Vec::new();
+            lzma_rs::lzma_compress(&mut &payload[..], &mut output).unwrap();
+            output
+        }
+        "zstd" => zstd::encode_all(payload, 3).unwrap(),
+        _ => payload.to_vec(),
+    }
+}
+
+/// Builds a package holding `/usr/bin/hello` and a symbolic link to it.
+fn package(compressor: &'static str, payload_format: &'static str) -> Vec<u8> {
+    let mut lead = vec![0xED, 0xAB, 0xEE, 0xDB, 3, 0, 0, 0, 0, 1];
+    lead.extend_from_slice(b"hello-1.0-1");
+    lead.resize(78, 0);
+    lead.extend_from_slice(&[0, 1, 0, 5]);
+    lead.resize(96, 0);
+
+    let mut rpm = lead;
+    rpm.extend(header(&[(1000, Value::Int32(vec![0]))]));
+    rpm.resize(rpm.len().next_multiple_of(8), 0);
+
+    rpm.extend(header(&[
+        (1000, Value::String("hello")),
+        (1001, Value::String("1.0")),
+        (1002, Value::String("1.fc40")),
+        (1003, Value::Int32(vec![2])),
+        (1004, Value::String("Prints a greeting")),
+        (1014, Value::String("MIT")),
+        (1022, Value::String("noarch")),
+        (1028, Value::Int32(vec![0, HELLO.len() as u32, 5])),
+        (1030, Value::Int16(vec![0o040755, 0o100755, 0o120777])),
+        (1035, Value::StringArray(vec!["", HELLO_SHA256, ""])),
+        (1036, Value::StringArray(vec!["", "", "hello"])),
+        (1047, Value::StringArray(vec!["hello", "greeting"])),
+        (
+            1048,
+            Value::Int32(vec![0, 0x08 | 0x04, 0x01000000 | 0x08 | 0x02]),
+        ),
+        (
+            1049,
+            Value::StringArray(vec!["/bin/sh", "bash", "rpmlib(CompressedFileNames)"]),
+        ),
+        (1050, Value::StringArray(vec!["", "5.0", "3.0.4-1"])),
+        (1112, Value::Int32(vec![0x08, 0])),
+        (1113, Value::StringArray(vec!["2:1.0-1.fc40", ""])),
+        (1116, Value::Int32(vec![0, 1, 1])),
+        (1117, Value::StringArray(vec!["bin", "hello", "hi"])),
+        (1118, Value::StringArray(vec!["/usr/", "/usr/bin/"])),
+        (1124, Value::String(payload_format)),
+        (1125, Value::String(compressor)),
+        (5011, Value::Int32(vec![8])),
+    ]));
+
+    let payload = newc(&[
+        ("./usr/bin", 0o040755, b""),
+        ("./usr/bin/hello", 0o100755, HELLO),
+        ("./usr/bin/hi", 0o120777, b"hello"),
+    ]);
+    rpm.extend(compress(&payload, compressor));
+    rpm
+}
+
+#[test]
+fn test_rpm_extract() {
+    for compressor in ["gzip", "bzip2", "xz", "lzma", "zstd", "identity"] {
+        let data = package(compressor, "cpio");
+        assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Rpm);
+
+        let files = ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::Rpm)
+            .unwrap_or_else(|e| panic!("Failed to extract {} payload: {}", compressor, e));
+
+        let names: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(names, ["./usr/bin", "./usr/bin/hello", "./usr/bin/hi"]);
+        assert!(files[0].is_directory);
+        assert_eq!(files[1].data, HELLO);
+        assert_eq!(files[1].metadata.mode, Some(0o755));
+        assert_eq!(
+            files[2].metadata.link,
+            Some(EntryLink::Symbolic("hello".to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_rpm_inspect() {
+    let data = package("zstd", "cpio");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Rpm)
+        .expect("Failed to inspect RPM package");
+    let rpm: RpmInfo = info.rpm.expect("Missing RPM information");
+
+    assert_eq!(rpm.name, "hello");
+    assert_eq!(rpm.version, "1.0");
+    assert_eq!(rpm.release, "1.fc40");
+    assert_eq!(rpm.epoch, Some(2));
+    assert_eq!(rpm.arch.as_deref(), Some("noarch"));
+    assert_eq!(rpm.summary.as_deref(), Some("Prints a greeting"));
+    assert_eq!(rpm.license.as_deref(), Some("MIT"));
+    assert_eq!(rpm.payload_compressor, "zstd");
+    assert_eq!(rpm.file_digest_algorithm, Some(HashAlgorithm::Sha256));
+
+    let dependency = |name: &str, constraint: Option<&str>| RpmDependency {
+        name: name.to_string(),
+        constraint: constraint.map(str::to_string),
+    };
+    assert_eq!(
+        rpm.requires,
+        [
+            dependency("/bin/sh", None),
+            dependency("bash", Some(">= 5.0")),
+            dependency("rpmlib(CompressedFileNames)", Some("<= 3.0.4-1")),
+        ]
+    );
+    assert_eq!(
+        rpm.provides,
+        [
+            dependency("hello", Some("= 2:1.0-1.fc40")),
+            dependency("greeting", None),
+        ]
+    );
+
+    let paths: Vec<_> = rpm.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["/usr/bin", "/usr/bin/hello", "/usr/bin/hi"]);
+    let hello = &rpm.files[1];
+    assert_eq!(hello.size, HELLO.len() as u64);
+    assert_eq!(hello.mode, 0o100755);
+    assert_eq!(hello.digest.as_deref(), Some(HELLO_SHA256));
+    assert!(rpm.files[0].digest.is_none());
+    assert_eq!(rpm.files[2].link_target.as_deref(), Some("hello"));
+}
+
+#[test]
+fn test_rpm_verify() {
+    let data = package("gzip", "cpio");
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Rpm)
+        .expect("Failed to verify RPM package");
+    assert!(report.is_ok());
+    assert_eq!(report.entries.len(), 3);
+
+    // A truncated payload ends in the middle of an entry
+    let mut corrupt = package("identity", "cpio");
+    let position = corrupt.windows(4).position(|w| w == b"echo").unwrap();
+    corrupt.truncate(position);
+    let report = ArchiveExtractor::new()
+        .verify(&corrupt, ArchiveFormat::Rpm)
+        .expect("Failed to verify RPM package");
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn test_rpm_unsupported_payload() {
+    let result = ArchiveExtractor::new().extract(&package("gzip", "drpm"), ArchiveFormat::Rpm);
+    assert!(matches!(result, Err(ArchiveError::UnsupportedFormat(_))));
+
+    let result = ArchiveExtractor::new().extract(&package("lzip", "cpio"), ArchiveFormat::Rpm);
+    assert!(matches!(result, Err(ArchiveError::UnsupportedFormat(_))));
+}
+
+#[test]
+fn test_rpm_invalid_header() {
+    let mut data = package("gzip", "cpio");
+    data[96] = 0;
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Rpm);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+
+    let result = ArchiveExtractor::new().inspect(&data[..120], ArchiveFormat::Rpm);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_rpm_size_limits() {
+    let data = package("gzip", "cpio");
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(HELLO.len() - 1)
+        .extract(&data, ArchiveFormat::Rpm);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+}
+
+#[test]
+fn test_rpm_buffered_payload_limits() {
+    for compressor in ["xz", "lzma"] {
+        let data = package(compressor, "cpio");
+        let extractor = ArchiveExtractor::new().with_max_total_size(64);
+
+        // The payload is decompressed before any entry is seen
+        let result = extractor.extract(&data, ArchiveFormat::Rpm);
+        assert!(
+            matches!(result, Err(ArchiveError::TotalSizeTooLarge { .. })),
+            "{}: {:?}",
+            compressor,
+            result
+        );
+        let result = extractor.verify(&data, ArchiveFormat::Rpm);
+        assert!(matches!(
+            result,
+            Err(ArchiveError::TotalSizeTooLarge { .. })
+        ));
+
+        // lzma-rs declares an 8 MB dictionary
+        let extractor = ArchiveExtractor::new().with_max_window_size(1024 * 1024);
+        let result = extractor.extract(&data, ArchiveFormat::Rpm);
+        assert!(
+            matches!(result, Err(ArchiveError::WindowTooLarge { .. })),
+            "{}: {:?}",
+            compressor,
+            result
+        );
+        let result = extractor.verify(&data, ArchiveFormat::Rpm);
+        assert!(matches!(result, Err(ArchiveError::WindowTooLarge { .. })));
+    }
+}
@@ -0,0 +1,74 @@
+//! Tests for serializing entries, listings and reports with the `serde` feature
+#![cfg(feature = "serde")]
+
+mod common;
+
+use archive::info::ArchiveInfo;
+use archive::{ArchiveExtractor, ArchiveFormat, ExtractedFile, HashAlgorithm, VerifyReport};
+use common::{assert_contains_file, read_test_archive};
+
+#[test]
+fn test_extracted_file_round_trip() {
+    let data = read_test_archive("archive.tar.gz");
+    let files = ArchiveExtractor::new()
+        .with_hashes([HashAlgorithm::Sha256])
+        .extract(&data, ArchiveFormat::TarGz)
+        .unwrap();
+
+    let json = serde_json::to_value(&files).unwrap();
+    let hello = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|file| file["path"] == "test-data/hello.txt")
+        .unwrap();
+    // Contents are base64 and digests hexadecimal
+    assert_eq!(hello["data"], "SGVsbG8sIFdvcmxkIQo=");
+    assert_eq!(
+        hello["digests"][0]["bytes"],
+        "c98c24b677eff44860afea6f493bbaec5bb1c4cbb209c6fc2bbb47f66ff2ad31"
+    );
+    assert_eq!(hello["digests"][0]["algorithm"], "Sha256");
+    assert_eq!(hello["metadata"]["mode"], 0o644);
+
+    let parsed: Vec<ExtractedFile> = serde_json::from_value(json).unwrap();
+    let original = assert_contains_file(&files, "hello.txt");
+    let restored = assert_contains_file(&parsed, "hello.txt");
+    assert_eq!(restored.data, original.data);
+    assert_eq!(restored.digests, original.digests);
+    assert_eq!(restored.metadata, original.metadata);
+}
+
+#[test]
+fn test_verify_report_round_trip() {
+    let data = read_test_archive("basic.zip");
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Zip)
+        .unwrap();
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains("\"format\":\"ZIP\""));
+    let parsed: VerifyReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, report);
+}
+
+#[test]
+fn test_archive_info_round_trip() {
+    let data = read_test_archive("hello.txt.gz");
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Gz)
+        .unwrap();
+
+    let json = serde_json::to_string(&info).unwrap();
+    let parsed: ArchiveInfo = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, info);
+}
+
+#[test]
+fn test_rejects_invalid_bytes() {
+    let json = r#"{"path":"a","data":"not base64!","digests":[],"is_directory":false,
+        "metadata":{"compressed_size":null,"crc32":null,"compression_method":null,
+        "encrypted":false,"comment":null,"extra_field_ids":[],"mode":null,"mtime":null,
+        "link":null}}"#;
+    assert!(serde_json::from_str::<ExtractedFile>(json).is_err());
+}
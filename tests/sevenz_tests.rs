@@ -2,7 +2,7 @@
 
 mod common;
 
-use archive::{ArchiveExtractor, ArchiveFormat};
+use archive::{ArchiveExtractor, ArchiveFormat, SevenZBuilder};
 use common::{assert_contains_file, read_test_archive};
 
 #[test]
@@ -18,3 +18,56 @@ fn test_7z_archive() {
     assert_contains_file(&files, "hello.txt");
     assert_contains_file(&files, "test.txt");
 }
+
+fn build_sample(builder: SevenZBuilder) -> Vec<u8> {
+    let mut builder = builder;
+    builder.add_directory("docs").unwrap();
+    builder
+        .add_file("docs/hello.txt", b"Hello, World!\n".to_vec())
+        .unwrap();
+    builder.add_file("docs/empty.txt", Vec::new()).unwrap();
+    builder
+        .add_file(
+            "binary.bin",
+            (0..=255u8).cycle().take(10_000).collect::<Vec<_>>(),
+        )
+        .unwrap();
+    builder.finish().expect("Failed to build 7z archive")
+}
+
+#[test]
+fn test_7z_builder_round_trip() {
+    for builder in [SevenZBuilder::new(), SevenZBuilder::new().with_solid(true)] {
+        let data = build_sample(builder);
+        let files = ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::SevenZ)
+            .expect("Failed to extract built 7z archive");
+
+        assert_eq!(files.len(), 4);
+        assert!(files.iter().any(|f| f.path == "docs" && f.is_directory));
+        assert_eq!(
+            assert_contains_file(&files, "hello.txt").data,
+            b"Hello, World!\n"
+        );
+        assert!(assert_contains_file(&files, "empty.txt").data.is_empty());
+        assert_eq!(
+            assert_contains_file(&files, "binary.bin").data.len(),
+            10_000
+        );
+    }
+}
+
+#[test]
+fn test_7z_builder_encrypted_requires_password() {
+    // The sample's header is too small to be encrypted
+    let data = build_sample(
+        SevenZBuilder::new()
+            .with_password("secret")
+            .with_header_encryption(false),
+    );
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::SevenZ);
+    assert!(
+        result.is_err(),
+        "Expected encrypted archive to fail without password"
+    );
+}
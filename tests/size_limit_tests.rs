@@ -1,9 +1,10 @@
-//! Tests for size limit enforcement (max file size, max total size)
+//! Tests for resource limit enforcement (file size, total size, duration, decoder window)
 
 mod common;
 
-use archive::{ArchiveExtractor, ArchiveFormat};
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat};
 use common::read_test_archive;
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_max_file_size_limit() {
@@ -30,3 +31,116 @@ fn test_max_total_size_limit() {
     // Should fail because total is > 1MB
     assert!(result.is_err(), "Expected to hit total size limit");
 }
+
+#[test]
+fn test_max_duration_limit() {
+    let fixtures = [
+        ("basic.zip", ArchiveFormat::Zip),
+        ("archive.tar.bz2", ArchiveFormat::TarBz2),
+        ("archive.tar.xz", ArchiveFormat::TarXz),
+        ("archive.7z", ArchiveFormat::SevenZ),
+        ("hello.txt.bz2", ArchiveFormat::Bz2),
+        ("hello.txt.xz", ArchiveFormat::Xz),
+    ];
+
+    // A budget that is already used up before the first read
+    let extractor = ArchiveExtractor::new().with_max_duration(Duration::ZERO);
+    for (name, format) in fixtures {
+        let data = read_test_archive(name);
+        let result = extractor.extract(&data, format);
+        assert!(
+            matches!(result, Err(ArchiveError::Timeout { limit }) if limit == Duration::ZERO),
+            "{}: expected timeout, got {:?}",
+            name,
+            result.map(|files| files.len())
+        );
+    }
+
+    let extractor = ArchiveExtractor::new().with_max_duration(Duration::from_secs(60));
+    for (name, format) in fixtures {
+        let data = read_test_archive(name);
+        assert!(extractor.extract(&data, format).is_ok(), "{}", name);
+    }
+}
+
+#[test]
+fn test_max_duration_stops_lzma_decoding() {
+    // 256 MiB of zeros in a single XZ block, made with `xz -9 -T1`. lzma-rs
+    // writes nothing out until the whole block is decoded.
+    let data = include_bytes!("fixtures/zeros-256m.xz");
+    let extractor = ArchiveExtractor::new()
+        .with_max_file_size(usize::MAX)
+        .with_max_total_size(usize::MAX)
+        .with_max_duration(Duration::from_millis(10));
+
+    let start = Instant::now();
+    let result = extractor.extract(data, ArchiveFormat::Xz);
+    let elapsed = start.elapsed();
+    assert!(
+        matches!(result, Err(ArchiveError::Timeout { .. })),
+        "expected timeout, got {:?}",
+        result.map(|files| files.len())
+    );
+    assert!(
+        elapsed < Duration::from_secs(1),
+        "stopped after {:?}",
+        elapsed
+    );
+}
+
+#[test]
+fn test_max_window_size_limit() {
+    // The fixtures are made with the default presets of `xz` (8 MB dictionary)
+    // and `zstd` (2 MB window for the TAR archive)
+    let fixtures = [
+        ("hello.txt.xz", ArchiveFormat::Xz, 8 * 1024 * 1024),
+        ("archive.tar.xz", ArchiveFormat::TarXz, 8 * 1024 * 1024),
+        ("archive.tar.zst", ArchiveFormat::TarZst, 2 * 1024 * 1024),
+    ];
+
+    for (name, format, window) in fixtures {
+        let data = read_test_archive(name);
+
+        let extractor = ArchiveExtractor::new().with_max_window_size(window - 1);
+        let result = extractor.extract(&data, format);
+        assert!(
+            matches!(result, Err(ArchiveError::WindowTooLarge { size, .. }) if size == window as u64),
+            "{}: expected window limit error, got {:?}",
+            name,
+            result.map(|files| files.len())
+        );
+        assert!(matches!(
+            extractor.verify(&data, format),
+            Err(ArchiveError::WindowTooLarge { .. })
+        ));
+
+        let extractor = ArchiveExtractor::new().with_max_window_size(window);
+        assert!(extractor.extract(&data, format).is_ok(), "{}", name);
+    }
+}
+
+#[test]
+fn test_huge_xz_dictionary_rejected_by_default() {
+    let mut data = read_test_archive("hello.txt.xz");
+
+    // Declare the largest possible LZMA2 dictionary in the first block header
+    let header = &data[12..];
+    let header_size = (header[0] as usize + 1) * 4;
+    let filter = header[..header_size]
+        .windows(2)
+        .position(|bytes| bytes == [0x21, 0x01])
+        .expect("No LZMA2 filter in block header");
+    data[12 + filter + 2] = 40;
+
+    let extractor = ArchiveExtractor::new();
+    let result = extractor.extract(&data, ArchiveFormat::Xz);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::WindowTooLarge { size, .. }) if size == u32::MAX as u64
+    ));
+
+    // Streams after the first are found through their indexes
+    let concatenated = [read_test_archive("hello.txt.xz"), data].concat();
+    let result = extractor.extract(&concatenated, ArchiveFormat::Xz);
+    assert!(matches!(result, Err(ArchiveError::WindowTooLarge { .. })));
+}
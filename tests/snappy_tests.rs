@@ -0,0 +1,82 @@
+//! Tests for framed Snappy extraction
+#![cfg(feature = "snappy")]
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat};
+use std::io::Write;
+
+fn snappy(data: &[u8]) -> Vec<u8> {
+    let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+    encoder.write_all(data).unwrap();
+    encoder.into_inner().unwrap()
+}
+
+#[test]
+fn test_snappy_single_file() {
+    // Several chunks of at most 64 KiB each
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let data = snappy(&content);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Snappy);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Snappy)
+        .expect("Failed to extract Snappy file");
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "data");
+    assert_eq!(files[0].data, content);
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Snappy)
+        .expect("Failed to verify Snappy file");
+    assert!(report.is_ok());
+}
+
+#[test]
+fn test_snappy_checksum_mismatch() {
+    let mut data = snappy(b"Hello, World!");
+    // The text is stored uncompressed at the end of the only data chunk
+    let position = data.windows(5).position(|w| w == b"World").unwrap();
+    data[position] = b'w';
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Snappy);
+    assert!(
+        matches!(&result, Err(ArchiveError::Io(e)) if e.to_string().contains("checksum")),
+        "Expected a checksum error, got {:?}",
+        result
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Snappy)
+        .expect("Failed to verify Snappy file");
+    assert_eq!(report.failures().count(), 1);
+}
+
+#[test]
+fn test_snappy_truncated() {
+    let data = snappy(b"Hello, World!");
+    let result = ArchiveExtractor::new().extract(&data[..data.len() - 4], ArchiveFormat::Snappy);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_snappy_size_limit() {
+    let data = snappy(&[0u8; 1000]);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Snappy);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .verify(&data, ArchiveFormat::Snappy);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    // Decoding stops as soon as the limit is exceeded
+    let data = snappy(&vec![0u8; 1024 * 1024]);
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(1024)
+        .extract(&data, ArchiveFormat::Snappy);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::FileTooLarge { size: 1025, .. })
+    ));
+}
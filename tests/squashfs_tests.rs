@@ -0,0 +1,701 @@
+//! Tests for SquashFS image extraction
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, EntryLink, SpecialFile};
+use common::find;
+use std::collections::HashMap;
+use std::io::Write;
+
+const MTIME: u32 = 1680350400;
+const BLOCK_SIZE: usize = 4096;
+const METADATA_SIZE: usize = 8192;
+const DATA_UNCOMPRESSED: u32 = 1 << 24;
+
+#[derive(Debug, Clone, Copy)]
+enum Compressor {
+    Gzip,
+    Lzma,
+    Xz,
+    Lz4,
+    Zstd,
+}
+
+impl Compressor {
+    fn id(self) -> u16 {
+        match self {
+            Self::Gzip => 1,
+            Self::Lzma => 2,
+            Self::Xz => 4,
+            Self::Lz4 => 5,
+            Self::Zstd => 6,
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+            Self::Lzma => {
+                let mut output = Vec::new();
+                lzma_rs::lzma_compress(&mut &data[..], &mut output).unwrap();
+                output
+            }
+            Self::Xz => {
+                let mut output = Vec::new();
+                lzma_rs::xz_compress(&mut &data[..], &mut output).unwrap();
+                output
+            }
+            Self::Lz4 => lz4::block::compress(data, None, false).unwrap(),
+            Self::Zstd => zstd::bulk::compress(data, 3).unwrap(),
+        }
+    }
+}
+
+enum Node {
+    Directory(Vec<(&'static str, Node)>),
+    File(Vec<u8>),
+    Symlink(&'static str),
+    /// Another name for the inode of an earlier entry in the same directory.
+    Hardlink(&'static str),
+    /// A directory inode of its own, sharing the listing of an earlier
+    /// directory in the same directory.
+    SharedListing(&'static str),
+    BlockDevice(u32, u32),
+    CharacterDevice(u32, u32),
+    Fifo,
+    Socket,
+}
+
+/// A table of metadata blocks, compressed as each block fills up.
+struct Metadata {
+    compressor: Compressor,
+    blocks: Vec<u8>,
+    starts: Vec<usize>,
+    pending: Vec<u8>,
+}
+
+impl Metadata {
+    fn new(compressor: Compressor) -> Self {
+        Self {
+            compressor,
+            blocks: Vec::new(),
+            starts: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// The position of the next byte: the start of its block in the table,
+    /// and its offset in the block.
+    fn position(&self) -> (u32, u16) {
+        (self.blocks.len() as u32, self.pending.len() as u16)
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        while self.pending.len() >= METADATA_SIZE {
+            let block: Vec<u8> = self.pending.drain(..METADATA_SIZE).collect();
+            self.flush(&block);
+        }
+    }
+
+    fn flush(&mut self, block: &[u8]) {
+        let compressed = self.compressor.compress(block);
+        self.starts.push(self.blocks.len());
+        self.blocks
+            .extend_from_slice(&(compressed.len() as u16).to_le_bytes());
+        self.blocks.extend_from_slice(&compressed);
+    }
+
+    fn finish(mut self) -> (Vec<u8>, Vec<usize>) {
+        if !self.pending.is_empty() {
+            let block = std::mem::take(&mut self.pending);
+            self.flush(&block);
+        }
+        (self.blocks, self.starts)
+    }
+}
+
+/// Writes a SquashFS 4.0 image the way `mksquashfs` lays it out: data and
+/// fragment blocks, then the inode, directory, fragment and ID tables, with
+/// every directory's inode written after those of its children.
+struct SquashFsBuilder {
+    compressor: Compressor,
+    /// Store data blocks uncompressed and without fragments.
+    stored: bool,
+    image: Vec<u8>,
+    inodes: Metadata,
+    directories: Metadata,
+    fragments: Vec<(u64, u32)>,
+    fragment: Vec<u8>,
+    inode_count: u32,
+    /// The inode body of each directory, by reference.
+    listings: HashMap<u64, Vec<u8>>,
+}
+
+impl SquashFsBuilder {
+    fn new(compressor: Compressor) -> Self {
+        Self {
+            compressor,
+            stored: false,
+            image: vec![0; 96],
+            inodes: Metadata::new(compressor),
+            directories: Metadata::new(compressor),
+            fragments: Vec::new(),
+            fragment: Vec::new(),
+            inode_count: 0,
+            listings: HashMap::new(),
+        }
+    }
+
+    fn stored(mut self) -> Self {
+        self.stored = true;
+        self
+    }
+
+    /// Writes an inode and returns its reference and number.
+    fn inode(&mut self, kind: u16, mode: u16, body: &[u8]) -> (u64, u32) {
+        self.inode_count += 1;
+        let (block, offset) = self.inodes.position();
+        let mut inode = Vec::new();
+        inode.extend_from_slice(&kind.to_le_bytes());
+        inode.extend_from_slice(&mode.to_le_bytes());
+        inode.extend_from_slice(&[0; 4]);
+        inode.extend_from_slice(&MTIME.to_le_bytes());
+        inode.extend_from_slice(&self.inode_count.to_le_bytes());
+        inode.extend_from_slice(body);
+        self.inodes.write(&inode);
+        (
+            (u64::from(block) << 16) | u64::from(offset),
+            self.inode_count,
+        )
+    }
+
+    fn flush_fragment(&mut self) {
+        if self.fragment.is_empty() {
+            return;
+        }
+        let fragment = std::mem::take(&mut self.fragment);
+        let compressed = self.compressor.compress(&fragment);
+        self.fragments
+            .push((self.image.len() as u64, compressed.len() as u32));
+        self.image.extend_from_slice(&compressed);
+    }
+
+    /// Writes the data blocks of a file and its inode, with the tail packed
+    /// into a fragment and blocks of zeros left out.
+    fn file(&mut self, data: &[u8]) -> (u64, u32) {
+        let start = self.image.len() as u64;
+        let tail_size = if self.stored {
+            0
+        } else {
+            data.len() % BLOCK_SIZE
+        };
+        let (blocks, tail) = data.split_at(data.len() - tail_size);
+
+        let mut sizes = Vec::new();
+        let mut sparse = 0u64;
+        for block in blocks.chunks(BLOCK_SIZE) {
+            let word = if block.iter().all(|&byte| byte == 0) {
+                sparse += block.len() as u64;
+                0
+            } else if self.stored {
+                self.image.extend_from_slice(block);
+                block.len() as u32 | DATA_UNCOMPRESSED
+            } else {
+                let compressed = self.compressor.compress(block);
+                self.image.extend_from_slice(&compressed);
+                compressed.len() as u32
+            };
+            sizes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        let (fragment, offset) = if tail.is_empty() {
+            (u32::MAX, 0)
+        } else {
+            if self.fragment.len() + tail.len() > BLOCK_SIZE {
+                self.flush_fragment();
+            }
+            let offset = self.fragment.len() as u32;
+            self.fragment.extend_from_slice(tail);
+            (self.fragments.len() as u32, offset)
+        };
+
+        let mut body = Vec::new();
+        if sparse == 0 {
+            body.extend_from_slice(&(start as u32).to_le_bytes());
+            body.extend_from_slice(&fragment.to_le_bytes());
+            body.extend_from_slice(&offset.to_le_bytes());
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(&sizes);
+            self.inode(2, 0o644, &body)
+        } else {
+            body.extend_from_slice(&start.to_le_bytes());
+            body.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            body.extend_from_slice(&sparse.to_le_bytes());
+            body.extend_from_slice(&1u32.to_le_bytes());
+            body.extend_from_slice(&fragment.to_le_bytes());
+            body.extend_from_slice(&offset.to_le_bytes());
+            body.extend_from_slice(&u32::MAX.to_le_bytes());
+            body.extend_from_slice(&sizes);
+            self.inode(9, 0o644, &body)
+        }
+    }
+
+    /// Writes the children of a directory, its listing and its inode.
+    fn directory(&mut self, entries: Vec<(&'static str, Node)>) -> (u64, u32) {
+        // Name, basic inode type, reference and number of each child
+        let mut children: Vec<(&str, u16, u64, u32)> = Vec::new();
+        for (name, node) in entries {
+            let (kind, (reference, number)) = match node {
+                Node::Directory(entries) => (1, self.directory(entries)),
+                Node::File(data) => (2, self.file(&data)),
+                Node::Symlink(target) => {
+                    let mut body = 1u32.to_le_bytes().to_vec();
+                    body.extend_from_slice(&(target.len() as u32).to_le_bytes());
+                    body.extend_from_slice(target.as_bytes());
+                    (3, self.inode(3, 0o777, &body))
+                }
+                Node::Hardlink(target) => {
+                    let &(_, kind, reference, number) =
+                        children.iter().find(|child| child.0 == target).unwrap();
+                    (kind, (reference, number))
+                }
+                Node::SharedListing(target) => {
+                    let reference = children.iter().find(|child| child.0 == target).unwrap().2;
+                    let body = self.listings[&reference].clone();
+                    (1, self.inode(1, 0o755, &body))
+                }
+                Node::BlockDevice(major, minor) => (4, self.device(4, major, minor)),
+                Node::CharacterDevice(major, minor) => (5, self.device(5, major, minor)),
+                Node::Fifo => (6, self.inode(6, 0o644, &1u32.to_le_bytes())),
+                Node::Socket => (7, self.inode(7, 0o755, &1u32.to_le_bytes())),
+            };
+            children.push((name, kind, reference, number));
+        }
+
+        let mut listing = Vec::new();
+        let mut rest = &children[..];
+        while !rest.is_empty() {
+            let block = rest[0].2 >> 16;
+            let count = rest
+                .iter()
+                .take(256)
+                .take_while(|child| child.2 >> 16 == block)
+                .count();
+            let base = rest[0].3;
+            listing.extend_from_slice(&(count as u32 - 1).to_le_bytes());
+            listing.extend_from_slice(&(block as u32).to_le_bytes());
+            listing.extend_from_slice(&base.to_le_bytes());
+            for &(name, kind, reference, number) in &rest[..count] {
+                listing.extend_from_slice(&(reference as u16).to_le_bytes());
+                listing.extend_from_slice(&((number as i32 - base as i32) as i16).to_le_bytes());
+                listing.extend_from_slice(&kind.to_le_bytes());
+                listing.extend_from_slice(&(name.len() as u16 - 1).to_le_bytes());
+                listing.extend_from_slice(name.as_bytes());
+            }
+            rest = &rest[count..];
+        }
+        let (block, offset) = self.directories.position();
+        self.directories.write(&listing);
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&block.to_le_bytes());
+        body.extend_from_slice(&2u32.to_le_bytes());
+        body.extend_from_slice(&(listing.len() as u16 + 3).to_le_bytes());
+        body.extend_from_slice(&offset.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        let (reference, number) = self.inode(1, 0o755, &body);
+        self.listings.insert(reference, body);
+        (reference, number)
+    }
+
+    fn device(&mut self, kind: u16, major: u32, minor: u32) -> (u64, u32) {
+        let device = (minor & 0xFF) | (major << 8) | ((minor & !0xFF) << 12);
+        let mut body = 1u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&device.to_le_bytes());
+        self.inode(kind, 0o600, &body)
+    }
+
+    /// Appends a table of metadata blocks and the index of pointers to its
+    /// blocks, returning the position of the index.
+    fn lookup_table(&mut self, table: Metadata) -> u64 {
+        let (blocks, starts) = table.finish();
+        let position = self.image.len();
+        self.image.extend_from_slice(&blocks);
+        let index = self.image.len() as u64;
+        for start in starts {
+            self.image
+                .extend_from_slice(&((position + start) as u64).to_le_bytes());
+        }
+        index
+    }
+
+    fn build(mut self, root: Vec<(&'static str, Node)>) -> Vec<u8> {
+        let (root, _) = self.directory(root);
+        self.flush_fragment();
+
+        let inode_table = self.image.len() as u64;
+        let inodes = std::mem::replace(&mut self.inodes, Metadata::new(self.compressor));
+        self.image.extend_from_slice(&inodes.finish().0);
+        let directory_table = self.image.len() as u64;
+        let directories = std::mem::replace(&mut self.directories, Metadata::new(self.compressor));
+        self.image.extend_from_slice(&directories.finish().0);
+
+        let mut fragments = Metadata::new(self.compressor);
+        for &(start, size) in &self.fragments {
+            fragments.write(&start.to_le_bytes());
+            fragments.write(&size.to_le_bytes());
+            fragments.write(&0u32.to_le_bytes());
+        }
+        let fragment_table = self.lookup_table(fragments);
+        let mut ids = Metadata::new(self.compressor);
+        ids.write(&0u32.to_le_bytes());
+        let id_table = self.lookup_table(ids);
+
+        let mut superblock = b"hsqs".to_vec();
+        superblock.extend_from_slice(&self.inode_count.to_le_bytes());
+        superblock.extend_from_slice(&MTIME.to_le_bytes());
+        superblock.extend_from_slice(&(BLOCK_SIZE as u32).to_le_bytes());
+        superblock.extend_from_slice(&(self.fragments.len() as u32).to_le_bytes());
+        superblock.extend_from_slice(&self.compressor.id().to_le_bytes());
+        superblock.extend_from_slice(&(BLOCK_SIZE.trailing_zeros() as u16).to_le_bytes());
+        superblock.extend_from_slice(&0u16.to_le_bytes());
+        superblock.extend_from_slice(&1u16.to_le_bytes());
+        superblock.extend_from_slice(&4u16.to_le_bytes());
+        superblock.extend_from_slice(&0u16.to_le_bytes());
+        superblock.extend_from_slice(&root.to_le_bytes());
+        superblock.extend_from_slice(&(self.image.len() as u64).to_le_bytes());
+        superblock.extend_from_slice(&id_table.to_le_bytes());
+        superblock.extend_from_slice(&u64::MAX.to_le_bytes());
+        superblock.extend_from_slice(&inode_table.to_le_bytes());
+        superblock.extend_from_slice(&directory_table.to_le_bytes());
+        superblock.extend_from_slice(&fragment_table.to_le_bytes());
+        superblock.extend_from_slice(&u64::MAX.to_le_bytes());
+        self.image[..96].copy_from_slice(&superblock);
+        self.image
+    }
+}
+
+/// Data that doesn't repeat within a block, so that it spans several blocks
+/// when compressed.
+fn pattern(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i * 7 + i / 251) as u8).collect()
+}
+
+fn sample_tree() -> Vec<(&'static str, Node)> {
+    vec![
+        (
+            "bin",
+            Node::Directory(vec![("tool", Node::File(pattern(3 * BLOCK_SIZE + 100)))]),
+        ),
+        (
+            "etc",
+            Node::Directory(vec![
+                ("config", Node::File(b"key = value\n".to_vec())),
+                ("empty", Node::File(Vec::new())),
+                ("link", Node::Symlink("config")),
+            ]),
+        ),
+        ("readme", Node::File(b"Hello, SquashFS!\n".to_vec())),
+    ]
+}
+
+#[test]
+fn test_squashfs_compressors() {
+    for (compressor, name) in [
+        (Compressor::Gzip, "gzip"),
+        (Compressor::Lzma, "lzma"),
+        (Compressor::Xz, "xz"),
+        (Compressor::Lz4, "lz4"),
+        (Compressor::Zstd, "zstd"),
+    ] {
+        let data = SquashFsBuilder::new(compressor).build(sample_tree());
+        assert_eq!(
+            ArchiveFormat::detect(&data).unwrap(),
+            ArchiveFormat::SquashFs
+        );
+
+        let files = ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::SquashFs)
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "bin",
+                "bin/tool",
+                "etc",
+                "etc/config",
+                "etc/empty",
+                "etc/link",
+                "readme"
+            ]
+        );
+
+        let bin = find(&files, "bin");
+        assert!(bin.is_directory);
+        assert_eq!(bin.metadata.mode, Some(0o755));
+
+        let tool = find(&files, "bin/tool");
+        assert_eq!(tool.data, pattern(3 * BLOCK_SIZE + 100), "{}", name);
+        assert_eq!(tool.metadata.mode, Some(0o644));
+        assert_eq!(tool.metadata.mtime, Some(u64::from(MTIME)));
+        assert_eq!(tool.metadata.compression_method.as_deref(), Some(name));
+
+        assert_eq!(find(&files, "etc/config").data, b"key = value\n");
+        assert!(find(&files, "etc/empty").data.is_empty());
+        assert_eq!(find(&files, "readme").data, b"Hello, SquashFS!\n");
+        assert_eq!(
+            find(&files, "etc/link").metadata.link,
+            Some(EntryLink::Symbolic("config".to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_squashfs_special_files() {
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(vec![(
+        "dev",
+        Node::Directory(vec![
+            ("console", Node::CharacterDevice(5, 1)),
+            ("initctl", Node::Fifo),
+            ("log", Node::Socket),
+            ("null", Node::CharacterDevice(1, 3)),
+            ("sda1", Node::BlockDevice(8, 1)),
+            ("vdb300", Node::BlockDevice(254, 300)),
+        ]),
+    )]);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::SquashFs)
+        .unwrap();
+    let special = |path| find(&files, path).metadata.special;
+    assert_eq!(
+        special("dev/null"),
+        Some(SpecialFile::CharacterDevice { major: 1, minor: 3 })
+    );
+    assert_eq!(
+        special("dev/console"),
+        Some(SpecialFile::CharacterDevice { major: 5, minor: 1 })
+    );
+    assert_eq!(
+        special("dev/sda1"),
+        Some(SpecialFile::BlockDevice { major: 8, minor: 1 })
+    );
+    assert_eq!(
+        special("dev/vdb300"),
+        Some(SpecialFile::BlockDevice {
+            major: 254,
+            minor: 300
+        })
+    );
+    assert_eq!(special("dev/initctl"), Some(SpecialFile::Fifo));
+    assert_eq!(special("dev/log"), Some(SpecialFile::Socket));
+    assert_eq!(special("dev"), None);
+    assert!(find(&files, "dev/null").data.is_empty());
+    assert_eq!(find(&files, "dev/null").metadata.mode, Some(0o600));
+
+    // Special files are skipped when extracting to disk
+    #[cfg(feature = "unpack")]
+    {
+        let dir = tempfile::tempdir().unwrap();
+        ArchiveExtractor::new()
+            .extract_to_dir(
+                &data,
+                ArchiveFormat::SquashFs,
+                dir.path(),
+                &archive::ExtractOptions::new(),
+            )
+            .unwrap();
+        assert!(dir.path().join("dev").is_dir());
+        assert!(!dir.path().join("dev/null").exists());
+    }
+}
+
+#[test]
+fn test_squashfs_hardlink() {
+    let data = SquashFsBuilder::new(Compressor::Zstd).build(vec![
+        ("a", Node::File(b"shared".to_vec())),
+        ("b", Node::Hardlink("a")),
+    ]);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::SquashFs)
+        .unwrap();
+    assert_eq!(find(&files, "a").data, b"shared");
+    assert_eq!(find(&files, "a").metadata.link, None);
+    assert_eq!(
+        find(&files, "b").metadata.link,
+        Some(EntryLink::Hard("a".to_string()))
+    );
+    assert!(find(&files, "b").data.is_empty());
+}
+
+#[test]
+fn test_squashfs_sparse_and_stored_blocks() {
+    let mut sparse = vec![0u8; 2 * BLOCK_SIZE];
+    sparse.extend_from_slice(&pattern(BLOCK_SIZE + 10));
+    sparse.extend_from_slice(&[0; BLOCK_SIZE]);
+
+    for builder in [
+        SquashFsBuilder::new(Compressor::Xz),
+        SquashFsBuilder::new(Compressor::Lz4).stored(),
+    ] {
+        let data = builder.build(vec![
+            ("sparse", Node::File(sparse.clone())),
+            ("small", Node::File(b"tail".to_vec())),
+        ]);
+        let files = ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::SquashFs)
+            .unwrap();
+        assert_eq!(find(&files, "sparse").data, sparse);
+        assert_eq!(find(&files, "small").data, b"tail");
+    }
+}
+
+#[test]
+fn test_squashfs_many_entries() {
+    // Enough inodes and listings to span several metadata blocks
+    let names: Vec<&'static str> = (0..600)
+        .map(|i| &*Box::leak(format!("file{:04}", i).into_boxed_str()))
+        .collect();
+    let tree = names
+        .iter()
+        .map(|&name| (name, Node::File(name.as_bytes().to_vec())))
+        .collect();
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(vec![("dir", Node::Directory(tree))]);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::SquashFs)
+        .unwrap();
+    assert_eq!(files.len(), 601);
+    for name in names {
+        assert_eq!(find(&files, &format!("dir/{}", name)).data, name.as_bytes());
+    }
+}
+
+#[test]
+fn test_squashfs_verify() {
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(sample_tree());
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::SquashFs)
+        .unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.entries.len(), 7);
+
+    // The first data block belongs to `bin/tool`
+    let mut corrupt = data.clone();
+    corrupt[96 + 20] ^= 0xFF;
+    let report = ArchiveExtractor::new()
+        .verify(&corrupt, ArchiveFormat::SquashFs)
+        .unwrap();
+    let failures: Vec<_> = report.failures().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(failures, ["bin/tool"]);
+
+    assert!(
+        ArchiveExtractor::new()
+            .extract(&corrupt, ArchiveFormat::SquashFs)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_squashfs_inspect() {
+    let data = SquashFsBuilder::new(Compressor::Xz).build(sample_tree());
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::SquashFs)
+        .unwrap();
+    let squashfs = info.squashfs.unwrap();
+    assert_eq!(squashfs.compression, "xz");
+    assert_eq!(squashfs.block_size, BLOCK_SIZE as u32);
+    assert_eq!(squashfs.inode_count, 8);
+    assert_eq!(squashfs.fragment_count, 1);
+    assert_eq!(squashfs.mtime, u64::from(MTIME));
+    assert_eq!(squashfs.bytes_used, data.len() as u64);
+}
+
+#[test]
+fn test_squashfs_limits() {
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(sample_tree());
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(BLOCK_SIZE)
+        .extract(&data, ArchiveFormat::SquashFs);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(3 * BLOCK_SIZE + 100)
+        .extract(&data, ArchiveFormat::SquashFs);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+
+    let result = ArchiveExtractor::new()
+        .with_max_window_size(1024)
+        .extract(&data, ArchiveFormat::SquashFs);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::WindowTooLarge { size: 4096, .. })
+    ));
+}
+
+#[test]
+fn test_squashfs_truncated() {
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(sample_tree());
+    for length in [50, 96, data.len() / 2, data.len() - 1] {
+        let result = ArchiveExtractor::new().extract(&data[..length], ArchiveFormat::SquashFs);
+        assert!(
+            matches!(result, Err(ArchiveError::InvalidArchive(_))),
+            "{} bytes: {:?}",
+            length,
+            result.map(|files| files.len())
+        );
+    }
+
+    // Other versions are recognized but not read
+    let mut version3 = data.clone();
+    version3[28] = 3;
+    let result = ArchiveExtractor::new().extract(&version3, ArchiveFormat::SquashFs);
+    assert!(matches!(result, Err(ArchiveError::UnsupportedFormat(_))));
+}
+
+#[test]
+fn test_squashfs_nested_too_deeply() {
+    let mut tree = vec![("file", Node::File(b"deep\n".to_vec()))];
+    for _ in 0..300 {
+        tree = vec![("d", Node::Directory(tree))];
+    }
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(tree);
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::SquashFs);
+    assert!(
+        matches!(&result, Err(ArchiveError::InvalidArchive(message)) if message.contains("nested")),
+        "{:?}",
+        result.map(|files| files.len())
+    );
+}
+
+#[test]
+fn test_squashfs_shared_listing() {
+    let tree = vec![
+        (
+            "a",
+            Node::Directory(vec![("file", Node::File(b"data\n".to_vec()))]),
+        ),
+        ("b", Node::SharedListing("a")),
+    ];
+    let data = SquashFsBuilder::new(Compressor::Gzip).build(tree);
+
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::SquashFs);
+    assert!(
+        matches!(&result, Err(ArchiveError::InvalidArchive(message)) if message.contains("shared")),
+        "{:?}",
+        result.map(|files| files.len())
+    );
+}
@@ -0,0 +1,386 @@
+//! Tests for safe extraction to a directory with `ArchiveExtractor::extract_to_dir`
+#![cfg(feature = "unpack")]
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, ExtractOptions, OverwritePolicy};
+use common::read_test_archive;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+#[test]
+fn test_extract_tar_gz_to_dir() {
+    let data = read_test_archive("archive.tar.gz");
+    let dir = tempfile::tempdir().unwrap();
+
+    let written = ArchiveExtractor::new()
+        .extract_to_dir(
+            &data,
+            ArchiveFormat::TarGz,
+            dir.path(),
+            &ExtractOptions::new(),
+        )
+        .expect("Failed to extract archive.tar.gz");
+
+    let hello = dir.path().join("test-data/hello.txt");
+    assert_eq!(fs::read(&hello).unwrap(), b"Hello, World!\n");
+    assert!(written.contains(&hello.canonicalize().unwrap()));
+
+    // No temporary files are left behind
+    for entry in walk(dir.path()) {
+        let name = entry.file_name().unwrap().to_string_lossy().to_string();
+        assert!(!name.starts_with(".archive-"), "leftover {}", name);
+    }
+}
+
+#[test]
+fn test_extract_creates_missing_root() {
+    let data = read_test_archive("basic.zip");
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("nested/root");
+
+    ArchiveExtractor::new()
+        .extract_to_dir(&data, ArchiveFormat::Zip, &root, &ExtractOptions::new())
+        .expect("Failed to extract basic.zip");
+
+    assert!(root.join("test-data/hello.txt").is_file());
+}
+
+#[test]
+fn test_rejects_parent_dir_paths() {
+    let data = build_tar(&[
+        TarEntry::file("safe.txt", b"safe"),
+        TarEntry::file("../evil.txt", b"evil"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+
+    let result = ArchiveExtractor::new().extract_to_dir(
+        &data,
+        ArchiveFormat::Tar,
+        &root,
+        &ExtractOptions::new(),
+    );
+
+    assert!(matches!(result, Err(ArchiveError::InvalidPath(_))));
+    // Validation happens before anything is written
+    assert!(!root.exists());
+    assert!(!dir.path().join("evil.txt").exists());
+}
+
+#[test]
+fn test_rejects_absolute_paths() {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file(
+            "/tmp/absolute.txt",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+    writer.write_all(b"absolute").unwrap();
+    let data = writer.finish().unwrap().into_inner();
+    let dir = tempfile::tempdir().unwrap();
+
+    let result = ArchiveExtractor::new().extract_to_dir(
+        &data,
+        ArchiveFormat::Zip,
+        dir.path(),
+        &ExtractOptions::new(),
+    );
+
+    assert!(matches!(result, Err(ArchiveError::InvalidPath(_))));
+}
+
+#[test]
+fn test_rejects_escaping_symlinks() {
+    let extractor = ArchiveExtractor::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    for target in ["../../outside", "/etc/passwd", "sub/../../outside", "a/../b"] {
+        let data = build_tar(&[TarEntry::symlink("sub/link", target)]);
+        let result = extractor.extract_to_dir(
+            &data,
+            ArchiveFormat::Tar,
+            dir.path(),
+            &ExtractOptions::new(),
+        );
+        assert!(
+            matches!(result, Err(ArchiveError::InvalidPath(_))),
+            "{} was accepted",
+            target
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_creates_contained_links() {
+    let data = build_tar(&[
+        TarEntry::file("docs/readme.txt", b"read me"),
+        TarEntry::symlink("docs/current", "readme.txt"),
+        TarEntry::symlink("bin/docs", "../docs"),
+        TarEntry::hard_link("docs/copy.txt", "docs/readme.txt"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+
+    ArchiveExtractor::new()
+        .extract_to_dir(
+            &data,
+            ArchiveFormat::Tar,
+            dir.path(),
+            &ExtractOptions::new(),
+        )
+        .expect("Failed to extract links");
+
+    let link = dir.path().join("docs/current");
+    assert!(
+        fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("readme.txt"));
+    assert_eq!(
+        fs::read(dir.path().join("bin/docs/readme.txt")).unwrap(),
+        b"read me"
+    );
+    assert_eq!(
+        fs::read(dir.path().join("docs/copy.txt")).unwrap(),
+        b"read me"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_does_not_write_through_archive_symlinks() {
+    let data = build_tar(&[
+        TarEntry::symlink("escape", "."),
+        TarEntry::file("escape/file.txt", b"through the link"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+
+    let result = ArchiveExtractor::new().extract_to_dir(
+        &data,
+        ArchiveFormat::Tar,
+        dir.path(),
+        &ExtractOptions::new(),
+    );
+
+    assert!(matches!(result, Err(ArchiveError::InvalidPath(_))));
+    assert!(!dir.path().join("file.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_does_not_write_through_existing_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let outside = dir.path().join("outside");
+    let root = dir.path().join("root");
+    fs::create_dir_all(&outside).unwrap();
+    fs::create_dir_all(&root).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("docs")).unwrap();
+
+    let data = build_tar(&[TarEntry::file("docs/readme.txt", b"read me")]);
+    let result = ArchiveExtractor::new().extract_to_dir(
+        &data,
+        ArchiveFormat::Tar,
+        &root,
+        &ExtractOptions::new(),
+    );
+
+    assert!(matches!(result, Err(ArchiveError::InvalidPath(_))));
+    assert!(fs::read_dir(&outside).unwrap().next().is_none());
+}
+
+#[test]
+fn test_overwrite_policies() {
+    let data = build_tar(&[
+        TarEntry::file("a.txt", b"new a"),
+        TarEntry::file("b.txt", b"new b"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+    let extractor = ArchiveExtractor::new();
+    let extract = |policy| {
+        extractor.extract_to_dir(
+            &data,
+            ArchiveFormat::Tar,
+            dir.path(),
+            &ExtractOptions::new().with_overwrite(policy),
+        )
+    };
+    fs::write(dir.path().join("a.txt"), b"old a").unwrap();
+
+    match extract(OverwritePolicy::Error) {
+        Err(ArchiveError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists),
+        other => panic!("Expected AlreadyExists, got {:?}", other),
+    }
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"old a");
+
+    let written = extract(OverwritePolicy::Skip).unwrap();
+    assert_eq!(written.len(), 1);
+    assert!(written[0].ends_with("b.txt"));
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"old a");
+
+    let written = extract(OverwritePolicy::Replace).unwrap();
+    assert_eq!(written.len(), 2);
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"new a");
+    assert_eq!(fs::read(dir.path().join("b.txt")).unwrap(), b"new b");
+}
+
+#[test]
+fn test_filter_entries() {
+    let data = build_tar(&[
+        TarEntry::file("keep.txt", b"keep"),
+        TarEntry::file("../skipped.bin", b"skip"),
+        TarEntry::file("docs/keep.txt", b"keep"),
+    ]);
+    let dir = tempfile::tempdir().unwrap();
+
+    let options = ExtractOptions::new().with_filter(|file| file.path.ends_with(".txt"));
+    let written = ArchiveExtractor::new()
+        .extract_to_dir(&data, ArchiveFormat::Tar, dir.path(), &options)
+        .expect("Filtered out entries should not be validated");
+
+    assert_eq!(written.len(), 2);
+    assert!(dir.path().join("keep.txt").is_file());
+    assert!(dir.path().join("docs/keep.txt").is_file());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_restores_permissions_and_mtime() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut script = TarEntry::file("bin/run.sh", b"#!/bin/sh\n");
+    script.mode = 0o4750;
+    let mut bin = TarEntry::directory("bin");
+    bin.mode = 0o700;
+    let data = build_tar(&[bin, script]);
+    let dir = tempfile::tempdir().unwrap();
+
+    let options = ExtractOptions::new()
+        .with_permissions(true)
+        .with_mtime(true);
+    ArchiveExtractor::new()
+        .extract_to_dir(&data, ArchiveFormat::Tar, dir.path(), &options)
+        .expect("Failed to extract");
+
+    let script = fs::metadata(dir.path().join("bin/run.sh")).unwrap();
+    // The setuid bit is dropped
+    assert_eq!(script.permissions().mode() & 0o7777, 0o750);
+    assert_eq!(
+        filetime::FileTime::from_last_modification_time(&script).unix_seconds(),
+        MTIME as i64
+    );
+
+    let bin = fs::metadata(dir.path().join("bin")).unwrap();
+    assert_eq!(bin.permissions().mode() & 0o7777, 0o700);
+    assert_eq!(
+        filetime::FileTime::from_last_modification_time(&bin).unix_seconds(),
+        MTIME as i64
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_default_permissions_ignore_archive_modes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut private = TarEntry::file("private.txt", b"secret");
+    private.mode = 0o000;
+    let data = build_tar(&[private]);
+    let dir = tempfile::tempdir().unwrap();
+
+    ArchiveExtractor::new()
+        .extract_to_dir(
+            &data,
+            ArchiveFormat::Tar,
+            dir.path(),
+            &ExtractOptions::new(),
+        )
+        .unwrap();
+
+    let metadata = fs::metadata(dir.path().join("private.txt")).unwrap();
+    // Created with regular default permissions, not the 0o600 of temporary files
+    assert_ne!(metadata.permissions().mode() & 0o044, 0);
+}
+
+const MTIME: u64 = 1_000_000_000;
+
+struct TarEntry {
+    path: &'static str,
+    kind: tar::EntryType,
+    data: &'static [u8],
+    link: Option<&'static str>,
+    mode: u32,
+}
+
+impl TarEntry {
+    fn file(path: &'static str, data: &'static [u8]) -> Self {
+        Self {
+            path,
+            kind: tar::EntryType::Regular,
+            data,
+            link: None,
+            mode: 0o644,
+        }
+    }
+
+    fn directory(path: &'static str) -> Self {
+        Self {
+            kind: tar::EntryType::Directory,
+            mode: 0o755,
+            ..Self::file(path, b"")
+        }
+    }
+
+    fn symlink(path: &'static str, target: &'static str) -> Self {
+        Self {
+            kind: tar::EntryType::Symlink,
+            link: Some(target),
+            mode: 0o777,
+            ..Self::file(path, b"")
+        }
+    }
+
+    fn hard_link(path: &'static str, target: &'static str) -> Self {
+        Self {
+            kind: tar::EntryType::Link,
+            link: Some(target),
+            ..Self::file(path, b"")
+        }
+    }
+}
+
+/// Builds a TAR archive, writing names directly into the headers so that
+/// unsafe paths can be stored.
+fn build_tar(entries: &[TarEntry]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..entry.path.len()].copy_from_slice(entry.path.as_bytes());
+        if let Some(link) = entry.link {
+            header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        }
+        header.set_entry_type(entry.kind);
+        header.set_mode(entry.mode);
+        header.set_mtime(MTIME);
+        header.set_size(entry.data.len() as u64);
+        header.set_cksum();
+        builder.append(&header, entry.data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn walk(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            paths.extend(walk(&path));
+        }
+        paths.push(path);
+    }
+    paths
+}
@@ -0,0 +1,214 @@
+//! Tests for integrity verification with `ArchiveExtractor::verify`
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, SevenZBuilder};
+use common::read_test_archive;
+use std::io::{Cursor, Write};
+
+#[test]
+fn test_verify_valid_fixtures() {
+    let fixtures = [
+        ("basic.zip", ArchiveFormat::Zip),
+        ("archive.tar", ArchiveFormat::Tar),
+        ("archive.ar", ArchiveFormat::Ar),
+        ("archive.tar.gz", ArchiveFormat::TarGz),
+        ("archive.tar.bz2", ArchiveFormat::TarBz2),
+        ("archive.tar.xz", ArchiveFormat::TarXz),
+        ("archive.tar.zst", ArchiveFormat::TarZst),
+        ("archive.7z", ArchiveFormat::SevenZ),
+        ("hello.txt.gz", ArchiveFormat::Gz),
+        ("hello.txt.bz2", ArchiveFormat::Bz2),
+        ("hello.txt.xz", ArchiveFormat::Xz),
+        ("hello.txt.lz4", ArchiveFormat::Lz4),
+        ("hello.txt.zst", ArchiveFormat::Zst),
+    ];
+    let extractor = ArchiveExtractor::new();
+
+    for (name, format) in fixtures {
+        let data = read_test_archive(name);
+        let report = extractor
+            .verify(&data, format)
+            .unwrap_or_else(|e| panic!("Failed to verify {}: {}", name, e));
+        assert!(report.is_ok(), "{} failed verification: {:?}", name, report);
+
+        // Verification sees the same entries as extraction
+        let files = extractor.extract(&data, format).unwrap();
+        assert_eq!(report.entries.len(), files.len(), "{}", name);
+        for (entry, file) in report.entries.iter().zip(&files) {
+            assert_eq!(entry.path, file.path);
+            assert_eq!(entry.size, file.data.len() as u64);
+        }
+    }
+}
+
+#[test]
+fn test_verify_zip_crc_mismatch() {
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("first.txt", options).unwrap();
+    writer.write_all(b"first file contents").unwrap();
+    writer.start_file("second.txt", options).unwrap();
+    writer.write_all(b"second file contents").unwrap();
+    let mut data = writer.finish().unwrap().into_inner();
+
+    let offset = find(&data, b"first file contents");
+    data[offset] ^= 0xFF;
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Zip)
+        .expect("Failed to verify zip");
+
+    assert!(!report.is_ok());
+    let failures: Vec<_> = report.failures().map(|e| e.path.as_str()).collect();
+    assert_eq!(failures, ["first.txt"]);
+    assert!(report.entries[1].is_ok());
+}
+
+#[test]
+fn test_verify_gzip_trailer_mismatch() {
+    let mut data = read_test_archive("hello.txt.gz");
+    let crc_offset = data.len() - 8;
+    data[crc_offset] ^= 0xFF;
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Gz)
+        .expect("Failed to verify gzip");
+
+    assert!(!report.is_ok());
+    assert_eq!(report.entries.len(), 1);
+    assert!(report.entries[0].error.is_some());
+}
+
+#[test]
+fn test_verify_tar_header_checksum() {
+    let mut data = read_test_archive("archive.tar");
+    // Corrupt the name of the second header without updating its checksum
+    let second_header = 512 * (1 + (tar_entry_size(&data[..512]) as usize).div_ceil(512));
+    data[second_header] ^= 0x01;
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Tar)
+        .expect("Failed to verify tar");
+
+    assert!(!report.is_ok());
+    assert_eq!(report.entries.len(), 1);
+    assert!(report.archive_error.is_some());
+}
+
+#[test]
+fn test_verify_tar_gz_trailer_mismatch() {
+    let mut data = read_test_archive("archive.tar.gz");
+    let crc_offset = data.len() - 8;
+    data[crc_offset] ^= 0xFF;
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::TarGz)
+        .expect("Failed to verify tar.gz");
+
+    // All entries decompress, but the stream checksum doesn't match
+    assert!(!report.is_ok());
+    assert!(report.entries.iter().all(|entry| entry.is_ok()));
+    assert!(report.archive_error.is_some());
+}
+
+#[test]
+fn test_verify_corrupt_single_file_streams() {
+    let extractor = ArchiveExtractor::new();
+
+    let mut xz = read_test_archive("hello.txt.xz");
+    let middle = xz.len() / 2;
+    xz[middle] ^= 0xFF;
+    let report = extractor.verify(&xz, ArchiveFormat::Xz).unwrap();
+    assert!(!report.is_ok());
+
+    let zst = read_test_archive("hello.txt.zst");
+    let truncated = &zst[..zst.len() - 4];
+    let report = extractor.verify(truncated, ArchiveFormat::Zst).unwrap();
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn test_verify_7z_corrupt_block() {
+    let noise = noise(4096);
+
+    for solid in [false, true] {
+        let mut builder = SevenZBuilder::new().with_solid(solid);
+        builder.add_file("noise.bin", noise.clone()).unwrap();
+        builder
+            .add_file("hello.txt", b"Hello, World!\n".to_vec())
+            .unwrap();
+        builder.add_file("empty.txt", Vec::new()).unwrap();
+        let mut data = builder.finish().unwrap();
+
+        // Packed streams start right after the 32-byte signature header, and
+        // incompressible data is stored as-is by LZMA2
+        data[32 + 1000] ^= 0xFF;
+
+        let report = ArchiveExtractor::new()
+            .verify(&data, ArchiveFormat::SevenZ)
+            .expect("Failed to verify 7z");
+
+        let failures: Vec<_> = report.failures().map(|e| e.path.as_str()).collect();
+        if solid {
+            // The rest of a solid block can't be decoded after the error
+            assert_eq!(failures, ["noise.bin", "hello.txt"]);
+        } else {
+            assert_eq!(failures, ["noise.bin"]);
+        }
+        assert!(
+            report
+                .entries
+                .iter()
+                .any(|e| e.path == "empty.txt" && e.is_ok())
+        );
+    }
+}
+
+#[test]
+fn test_verify_respects_size_limits() {
+    let data = read_test_archive("archive.tar.gz");
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(16)
+        .verify(&data, ArchiveFormat::TarGz);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let data = read_test_archive("hello.txt.xz");
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(4)
+        .verify(&data, ArchiveFormat::Xz);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+}
+
+#[test]
+fn test_verify_invalid_archive() {
+    let result = ArchiveExtractor::new().verify(b"not a zip", ArchiveFormat::Zip);
+    assert!(result.is_err());
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> usize {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .expect("needle not found")
+}
+
+fn tar_entry_size(header: &[u8]) -> u64 {
+    let size = std::str::from_utf8(&header[124..136]).unwrap();
+    u64::from_str_radix(size.trim_matches(|c: char| c == '\0' || c == ' '), 8).unwrap()
+}
+
+/// Incompressible data from a xorshift generator.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
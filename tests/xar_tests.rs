@@ -0,0 +1,611 @@
+//! Tests for XAR archive and macOS installer package extraction
+#![cfg(feature = "xar")]
+
+mod common;
+
+use archive::{ArchiveError, ArchiveExtractor, ArchiveFormat, EntryLink};
+use common::find;
+use sha1::{Digest, Sha1};
+use std::io::{Cursor, Write};
+
+/// Size of the SHA-1 checksum of the table of contents at the start of the
+/// heap.
+const TOC_CHECKSUM_SIZE: usize = 20;
+const MTIME: &str = "2023-04-01T12:00:00Z";
+const MTIME_SECONDS: u64 = 1680350400;
+
+fn sha1_hex(data: &[u8]) -> String {
+    Sha1::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Builds a XAR archive with SHA-1 checksums, adding file contents to the
+/// heap as their `<file>` elements are written.
+struct XarBuilder {
+    heap: Vec<u8>,
+    next_id: usize,
+}
+
+impl XarBuilder {
+    fn new() -> Self {
+        Self {
+            heap: vec![0; TOC_CHECKSUM_SIZE],
+            next_id: 1,
+        }
+    }
+
+    fn id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// A regular file, with its contents encoded as `encoding`.
+    fn file(&mut self, name: &str, contents: &[u8], encoding: &str) -> String {
+        self.file_with_checksum(name, contents, encoding, &sha1_hex(contents))
+    }
+
+    fn file_with_checksum(
+        &mut self,
+        name: &str,
+        contents: &[u8],
+        encoding: &str,
+        extracted_checksum: &str,
+    ) -> String {
+        let stored = match encoding {
+            "application/octet-stream" => contents.to_vec(),
+            "application/x-gzip" => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            }
+            "application/x-bzip2" => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            }
+            "application/x-xz" => {
+                let mut output = Vec::new();
+                lzma_rs::xz_compress(&mut Cursor::new(contents), &mut output).unwrap();
+                output
+            }
+            _ => panic!("Unknown encoding {}", encoding),
+        };
+        let offset = self.heap.len();
+        self.heap.extend_from_slice(&stored);
+
+        format!(
+            "<file id=\"{}\"><name>{}</name><type>file</type><mode>0755</mode>\
+             <mtime>{}</mtime><data><length>{}</length><offset>{}</offset>\
+             <size>{}</size><encoding style=\"{}\"/>\
+             <archived-checksum style=\"sha1\">{}</archived-checksum>\
+             <extracted-checksum style=\"sha1\">{}</extracted-checksum></data></file>",
+            self.id(),
+            name,
+            MTIME,
+            stored.len(),
+            offset,
+            contents.len(),
+            encoding,
+            sha1_hex(&stored),
+            extracted_checksum
+        )
+    }
+
+    fn directory(&mut self, name: &str, children: &[String]) -> String {
+        format!(
+            "<file id=\"{}\"><name>{}</name><type>directory</type><mode>0755</mode>{}</file>",
+            self.id(),
+            name,
+            children.concat()
+        )
+    }
+
+    fn symlink(&mut self, name: &str, target: &str) -> String {
+        format!(
+            "<file id=\"{}\"><name>{}</name><type>symlink</type>\
+             <link type=\"file\">{}</link></file>",
+            self.id(),
+            name,
+            target
+        )
+    }
+
+    fn finish(mut self, files: &[String]) -> Vec<u8> {
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xar><toc>\
+             <checksum style=\"sha1\"><offset>0</offset><size>{}</size></checksum>\
+             <creation-time>{}</creation-time>{}</toc></xar>",
+            TOC_CHECKSUM_SIZE,
+            MTIME,
+            files.concat()
+        );
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        let toc = encoder.finish().unwrap();
+        self.heap[..TOC_CHECKSUM_SIZE].copy_from_slice(&Sha1::digest(&toc));
+
+        let mut archive = b"xar!".to_vec();
+        archive.extend_from_slice(&28u16.to_be_bytes());
+        archive.extend_from_slice(&1u16.to_be_bytes());
+        archive.extend_from_slice(&(toc.len() as u64).to_be_bytes());
+        archive.extend_from_slice(&(xml.len() as u64).to_be_bytes());
+        archive.extend_from_slice(&1u32.to_be_bytes());
+        archive.extend_from_slice(&toc);
+        archive.extend_from_slice(&self.heap);
+        archive
+    }
+}
+
+/// Writes an odc cpio archive, as `mkbom` and `pkgbuild` do.
+fn odc(entries: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for &(name, mode, data) in entries.iter().chain([&("TRAILER!!!", 0, &[][..])]) {
+        let header = format!(
+            "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+            0,
+            0,
+            mode,
+            0,
+            80,
+            1,
+            0,
+            MTIME_SECONDS,
+            name.len() + 1,
+            data.len()
+        );
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.push(0);
+        archive.extend_from_slice(data);
+    }
+    archive
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Wraps `data` in a pbzx stream, compressing every other chunk with XZ.
+fn pbzx(data: &[u8], chunk_size: usize) -> Vec<u8> {
+    let mut stream = b"pbzx".to_vec();
+    stream.extend_from_slice(&(1u64 << 24 | chunk_size as u64).to_be_bytes());
+    let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let flags = if index + 1 < chunks.len() { 1 << 24 } else { 0 } | chunk.len() as u64;
+        let stored = if index % 2 == 0 {
+            let mut output = Vec::new();
+            lzma_rs::xz_compress(&mut Cursor::new(chunk), &mut output).unwrap();
+            output
+        } else {
+            chunk.to_vec()
+        };
+        stream.extend_from_slice(&flags.to_be_bytes());
+        stream.extend_from_slice(&(stored.len() as u64).to_be_bytes());
+        stream.extend_from_slice(&stored);
+    }
+    stream
+}
+
+fn sample_xar() -> Vec<u8> {
+    let mut builder = XarBuilder::new();
+    let readme = builder.file("README", b"Read me first\n", "application/octet-stream");
+    let tool = builder.file("tool", &[0x7Fu8; 5000], "application/x-gzip");
+    let config = builder.file("config.plist", b"<plist/>\n", "application/x-bzip2");
+    let data = builder.file("data.bin", &[1u8, 2, 3].repeat(1000), "application/x-xz");
+    let link = builder.symlink("current", "bin/tool");
+    let bin = builder.directory("bin", &[tool]);
+    builder.finish(&[readme, bin, config, data, link])
+}
+
+#[test]
+fn test_xar_encodings() {
+    let data = sample_xar();
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Xar);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Xar)
+        .expect("Failed to extract XAR archive");
+    let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "README",
+            "bin",
+            "bin/tool",
+            "config.plist",
+            "data.bin",
+            "current"
+        ]
+    );
+
+    assert_eq!(find(&files, "README").data, b"Read me first\n");
+    assert_eq!(find(&files, "bin/tool").data, [0x7Fu8; 5000]);
+    assert_eq!(find(&files, "config.plist").data, b"<plist/>\n");
+    assert_eq!(find(&files, "data.bin").data, [1u8, 2, 3].repeat(1000));
+    assert!(find(&files, "bin").is_directory);
+
+    let tool = &find(&files, "bin/tool").metadata;
+    assert_eq!(tool.mode, Some(0o755));
+    assert_eq!(tool.mtime, Some(MTIME_SECONDS));
+    assert_eq!(tool.compression_method.as_deref(), Some("ZLIB"));
+    assert!(tool.compressed_size.unwrap() < 5000);
+    assert_eq!(
+        find(&files, "current").metadata.link,
+        Some(EntryLink::Symbolic("bin/tool".to_string()))
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Xar)
+        .expect("Failed to verify XAR archive");
+    assert!(report.is_ok());
+    assert_eq!(report.entries.len(), 6);
+
+    let info = ArchiveExtractor::new()
+        .inspect(&data, ArchiveFormat::Xar)
+        .expect("Failed to inspect XAR archive");
+    let xar = info.xar.expect("Missing XAR information");
+    assert_eq!(xar.checksum_algorithm, "sha1");
+    assert_eq!(xar.entry_count, 6);
+}
+
+#[test]
+fn test_xar_hardlink() {
+    let mut builder = XarBuilder::new();
+    let original = builder
+        .file("a", b"shared", "application/octet-stream")
+        .replace(
+            "<type>file</type>",
+            "<type link=\"original\">hardlink</type>",
+        );
+    let link = "<file id=\"9\"><name>b</name><type link=\"1\">hardlink</type></file>".to_string();
+    let data = builder.finish(&[original, link]);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Xar)
+        .expect("Failed to extract XAR archive");
+    assert_eq!(find(&files, "a").data, b"shared");
+    assert_eq!(
+        find(&files, "b").metadata.link,
+        Some(EntryLink::Hard("a".to_string()))
+    );
+}
+
+#[test]
+fn test_xar_checksum_mismatch() {
+    let mut builder = XarBuilder::new();
+    let good = builder.file("good", b"intact", "application/octet-stream");
+    let bad = builder.file("bad", b"tampered", "application/octet-stream");
+    let data = builder.finish(&[good, bad]);
+
+    // Corrupt the stored contents of "bad", which end the heap
+    let mut corrupt = data.clone();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 0x01;
+    let result = ArchiveExtractor::new().extract(&corrupt, ArchiveFormat::Xar);
+    assert!(
+        matches!(&result, Err(ArchiveError::InvalidArchive(e)) if e.contains("checksum")),
+        "Expected a checksum error, got {:?}",
+        result
+    );
+    let report = ArchiveExtractor::new()
+        .verify(&corrupt, ArchiveFormat::Xar)
+        .expect("Failed to verify XAR archive");
+    let failures: Vec<_> = report.failures().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(failures, ["bad"]);
+
+    // The extracted checksum is checked once the contents are decoded
+    let mut builder = XarBuilder::new();
+    let wrong = builder.file_with_checksum(
+        "wrong",
+        b"contents",
+        "application/x-gzip",
+        &sha1_hex(b"other contents"),
+    );
+    let data = builder.finish(&[wrong]);
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Xar);
+    assert!(
+        matches!(&result, Err(ArchiveError::Io(e)) if e.to_string().contains("checksum")),
+        "Expected a checksum error, got {:?}",
+        result
+    );
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Xar)
+        .expect("Failed to verify XAR archive");
+    assert_eq!(report.failures().count(), 1);
+
+    // A corrupt table of contents checksum
+    let mut builder = XarBuilder::new();
+    let files = [builder.file("good", b"intact", "application/octet-stream")];
+    let mut data = builder.finish(&files);
+    let heap = data.len() - 6 - TOC_CHECKSUM_SIZE;
+    data[heap] ^= 0x01;
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Xar);
+    assert!(
+        matches!(&result, Err(ArchiveError::InvalidArchive(e)) if e.contains("table of contents")),
+        "Expected a table of contents error, got {:?}",
+        result
+    );
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Xar)
+        .expect("Failed to verify XAR archive");
+    assert!(report.archive_error.is_some());
+}
+
+#[test]
+fn test_pkg_product_archive() {
+    let payload = gzip(&odc(&[
+        (".", 0o40755, b""),
+        ("./Applications", 0o40755, b""),
+        (
+            "./Applications/Tool.app",
+            0o100755,
+            b"#!/bin/sh\necho tool\n",
+        ),
+        ("./usr/local/bin/tool", 0o120755, b"/Applications/Tool.app"),
+    ]));
+    let scripts = gzip(&odc(&[(
+        "./postinstall",
+        0o100755,
+        b"#!/bin/sh\nlaunchctl load /Library/LaunchDaemons/tool.plist\n",
+    )]));
+
+    let mut builder = XarBuilder::new();
+    let distribution = builder.file(
+        "Distribution",
+        b"<installer-gui-script/>",
+        "application/x-gzip",
+    );
+    let package_info = builder.file("PackageInfo", b"<pkg-info/>", "application/x-gzip");
+    let payload = builder.file("Payload", &payload, "application/octet-stream");
+    let scripts = builder.file("Scripts", &scripts, "application/octet-stream");
+    let component = builder.directory("tool.pkg", &[package_info, payload, scripts]);
+    let data = builder.finish(&[distribution, component]);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Pkg);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Pkg)
+        .expect("Failed to extract pkg");
+    let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "Distribution",
+            "tool.pkg",
+            "tool.pkg/PackageInfo",
+            "tool.pkg/Payload",
+            "tool.pkg/Payload/Applications",
+            "tool.pkg/Payload/Applications/Tool.app",
+            "tool.pkg/Payload/usr/local/bin/tool",
+            "tool.pkg/Scripts",
+            "tool.pkg/Scripts/postinstall",
+        ]
+    );
+    assert!(find(&files, "tool.pkg/Payload").is_directory);
+    assert_eq!(
+        find(&files, "tool.pkg/Payload/Applications/Tool.app").data,
+        b"#!/bin/sh\necho tool\n"
+    );
+    assert_eq!(
+        find(&files, "tool.pkg/Payload/usr/local/bin/tool")
+            .metadata
+            .link,
+        Some(EntryLink::Symbolic("/Applications/Tool.app".to_string()))
+    );
+    let postinstall = find(&files, "tool.pkg/Scripts/postinstall");
+    assert!(postinstall.data.starts_with(b"#!/bin/sh\nlaunchctl"));
+    assert_eq!(postinstall.metadata.mode, Some(0o755));
+
+    // As a plain XAR archive, the payload is a single file
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Xar)
+        .expect("Failed to extract pkg as XAR");
+    assert!(
+        find(&files, "tool.pkg/Payload")
+            .data
+            .starts_with(&[0x1F, 0x8B])
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Pkg)
+        .expect("Failed to verify pkg");
+    assert!(report.is_ok());
+    let paths: Vec<_> = report
+        .entries
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    assert!(paths.contains(&"tool.pkg/Payload/Applications/Tool.app"));
+    assert!(!paths.contains(&"tool.pkg/Payload/."));
+}
+
+#[test]
+fn test_pkg_pbzx_payload() {
+    let contents: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    let payload = pbzx(
+        &odc(&[
+            ("./Library/Tool/data.bin", 0o100644, &contents),
+            ("./Library/Tool/notes.txt", 0o100644, b"notes\n"),
+        ]),
+        4096,
+    );
+
+    let mut builder = XarBuilder::new();
+    let package_info = builder.file("PackageInfo", b"<pkg-info/>", "application/octet-stream");
+    let payload = builder.file("Payload", &payload, "application/octet-stream");
+    let data = builder.finish(&[package_info, payload]);
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Pkg);
+
+    let files = ArchiveExtractor::new()
+        .extract(&data, ArchiveFormat::Pkg)
+        .expect("Failed to extract pkg with a pbzx payload");
+    assert_eq!(find(&files, "Payload/Library/Tool/data.bin").data, contents);
+    assert_eq!(
+        find(&files, "Payload/Library/Tool/notes.txt").data,
+        b"notes\n"
+    );
+
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Pkg)
+        .expect("Failed to verify pkg");
+    assert!(report.is_ok());
+}
+
+#[test]
+fn test_pkg_corrupt_payload() {
+    let mut payload = gzip(&odc(&[("./file", 0o100644, b"contents")]));
+    let length = payload.len();
+    payload.truncate(length - 12);
+
+    let mut builder = XarBuilder::new();
+    let payload = builder.file("Payload", &payload, "application/octet-stream");
+    let data = builder.finish(&[payload]);
+
+    assert!(
+        ArchiveExtractor::new()
+            .extract(&data, ArchiveFormat::Pkg)
+            .is_err()
+    );
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Pkg)
+        .expect("Failed to verify pkg");
+    assert!(report.archive_error.is_none());
+    assert!(report.failures().any(|entry| entry.path == "Payload"));
+}
+
+#[test]
+fn test_xar_size_limits() {
+    let mut builder = XarBuilder::new();
+    let files = [
+        builder.file("a.bin", &[0u8; 1000], "application/x-gzip"),
+        builder.file("b.bin", &[0u8; 1000], "application/x-gzip"),
+    ];
+    let data = builder.finish(&files);
+
+    let result = ArchiveExtractor::new()
+        .with_max_file_size(999)
+        .extract(&data, ArchiveFormat::Xar);
+    assert!(matches!(result, Err(ArchiveError::FileTooLarge { .. })));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .extract(&data, ArchiveFormat::Xar);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .verify(&data, ArchiveFormat::Xar);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+
+    // The files of a payload count towards the total size
+    let payload = gzip(&odc(&[
+        ("./a.bin", 0o100644, &[0u8; 1000]),
+        ("./b.bin", 0o100644, &[0u8; 1000]),
+    ]));
+    let mut builder = XarBuilder::new();
+    let files = [builder.file("Payload", &payload, "application/octet-stream")];
+    let data = builder.finish(&files);
+    let result = ArchiveExtractor::new()
+        .with_max_total_size(1999)
+        .extract(&data, ArchiveFormat::Pkg);
+    assert!(matches!(
+        result,
+        Err(ArchiveError::TotalSizeTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_xar_truncated() {
+    let data = sample_xar();
+    for length in [4, 40, data.len() - 10] {
+        let result = ArchiveExtractor::new().extract(&data[..length], ArchiveFormat::Xar);
+        assert!(
+            matches!(result, Err(ArchiveError::InvalidArchive(_))),
+            "Expected an invalid archive error at {} bytes, got {:?}",
+            length,
+            result
+        );
+    }
+}
+
+#[test]
+fn test_xar_xz_contents_larger_than_declared() {
+    let mut builder = XarBuilder::new();
+    let file = builder
+        .file("zeros.bin", &[0u8; 100_000], "application/x-xz")
+        .replace("<size>100000</size>", "<size>10</size>");
+    let data = builder.finish(&[file]);
+
+    // Decoding stops at the declared size rather than after the whole stream
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Xar);
+    assert!(
+        matches!(&result, Err(ArchiveError::InvalidArchive(e)) if e.contains("more than 10 bytes")),
+        "Expected a size error, got {:?}",
+        result
+    );
+    let report = ArchiveExtractor::new()
+        .verify(&data, ArchiveFormat::Xar)
+        .expect("Failed to verify XAR archive");
+    assert_eq!(report.failures().count(), 1);
+}
+
+#[test]
+fn test_pkg_pbzx_payload_limit() {
+    let cpio = odc(&[("./zeros.bin", 0o100644, &[0u8; 100_000])]);
+    let mut builder = XarBuilder::new();
+    let files = [builder.file(
+        "Payload",
+        &pbzx(&cpio, 64 * 1024),
+        "application/octet-stream",
+    )];
+    let data = builder.finish(&files);
+
+    for result in [
+        ArchiveExtractor::new()
+            .with_max_total_size(50_000)
+            .extract(&data, ArchiveFormat::Pkg)
+            .map(drop),
+        ArchiveExtractor::new()
+            .with_max_total_size(50_000)
+            .verify(&data, ArchiveFormat::Pkg)
+            .map(drop),
+    ] {
+        assert!(
+            matches!(
+                result,
+                Err(ArchiveError::TotalSizeTooLarge { limit: 50_000, .. })
+            ),
+            "Expected a total size error, got {:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn test_xar_deeply_nested_files() {
+    let depth = 100_000;
+    let nested = format!(
+        "{}{}",
+        "<file><name>d</name><type>directory</type>".repeat(depth),
+        "</file>".repeat(depth)
+    );
+    let data = XarBuilder::new().finish(&[nested]);
+
+    assert_eq!(ArchiveFormat::detect(&data).unwrap(), ArchiveFormat::Xar);
+    let result = ArchiveExtractor::new().extract(&data, ArchiveFormat::Xar);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+    let result = ArchiveExtractor::new().inspect(&data, ArchiveFormat::Xar);
+    assert!(matches!(result, Err(ArchiveError::InvalidArchive(_))));
+}